@@ -0,0 +1,104 @@
+// Local Modules Use //
+use super::display::ChipDisplay;
+use super::ChipSystem;
+
+/// Columns of the grid used by `describe_screen`
+const GRID_COLUMNS: usize = 8;
+
+/// Rows of the grid used by `describe_screen`
+const GRID_ROWS: usize = 4;
+
+/// Turns what happens in a game into short text announcements, for
+/// screen readers and low-vision players
+///
+/// Call `update` after every frame and pass the lines it returns to a
+/// text channel such as stderr, which screen readers read out.
+#[derive(Debug, Clone, Default)]
+pub struct Announcer {
+    /// Sound timer was running after the last frame
+    sound: bool,
+    /// Screen was cleared during the last frame
+    cleared: bool,
+    /// Program was waiting for a key after the last frame
+    waiting: bool,
+    /// Exit has been announced
+    exited: bool
+}
+
+impl Announcer {
+    /// Initialize with nothing announced
+    pub fn init() -> Self {
+        Announcer::default()
+    }
+
+    /// Get the announcements for the frame that just finished: sound
+    /// starting, the screen being cleared, the program starting to wait
+    /// for a key and the program exiting. Each is announced once when
+    /// it starts, not on every frame it lasts.
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system after running a frame
+    pub fn update(&mut self, sys: &ChipSystem) -> Vec<String> {
+        let mut lines = Vec::new();
+        let sound = sys.registers.get_s() > 0;
+        if sound && !self.sound {
+            lines.push(String::from("sound started"));
+        }
+        self.sound = sound;
+        let cleared = sys.stats.get_last().clears > 0;
+        if cleared && !self.cleared {
+            lines.push(String::from("screen cleared"));
+        }
+        self.cleared = cleared;
+        let waiting = sys.keyboard.get_waiting().is_some();
+        if waiting && !self.waiting {
+            lines.push(String::from("waiting for a key"));
+        }
+        self.waiting = waiting;
+        if sys.get_exit().is_some() && !self.exited {
+            lines.push(String::from("program exited"));
+            self.exited = true;
+        }
+        lines
+    }
+}
+
+/// Describe the screen in words as a grid of 8 by 4 blocks of 8 by 8
+/// pixels, naming the blocks on each row that have lit pixels. This
+/// reads better through a screen reader than text art.
+///
+/// # Arguments
+///
+/// * `display` - display to describe
+pub fn describe_screen(display: &ChipDisplay) -> String {
+    let pixels = display.get_display();
+    let (width, height) = (display.get_width(), display.get_height());
+    let (block_w, block_h) = (width / GRID_COLUMNS, height / GRID_ROWS);
+    let lit = pixels.iter().filter(|pixel| **pixel).count();
+    let mut out = format!(
+        "Screen: {} of {} pixels lit, described as {} rows of {} columns\n",
+        lit, pixels.len(), GRID_ROWS, GRID_COLUMNS
+    );
+    for row in 0..GRID_ROWS {
+        let columns: Vec<String> = (0..GRID_COLUMNS)
+            .filter(|col| {
+                (0..block_h).any(|y| (0..block_w).any(|x| {
+                    pixels[(row * block_h + y) * width + col * block_w + x]
+                }))
+            })
+            .map(|col| (col + 1).to_string())
+            .collect();
+        let name = match row {
+            0 => " (top)",
+            r if r == GRID_ROWS - 1 => " (bottom)",
+            _ => ""
+        };
+        match columns.len() {
+            0 => out.push_str(&format!("Row {}{}: empty\n", row + 1, name)),
+            1 => out.push_str(&format!("Row {}{}: column {} lit\n", row + 1, name, columns[0])),
+            _ => out.push_str(&format!("Row {}{}: columns {} lit\n", row + 1, name, columns.join(", ")))
+        }
+    }
+    out
+}
@@ -0,0 +1,201 @@
+// Standard Library Modules //
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::fonts::Fontset;
+use super::json::JsonValue;
+use super::palette::Palette;
+use super::quirks::Quirks;
+
+/// Name of the metadata file of the CHIP-8 Archive
+pub const ARCHIVE_FILE: &str = "programs.json";
+
+/// Settings the CHIP-8 Archive gives a program, the `options` object
+/// Octo configures each title with
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveOptions {
+    /// Opcodes run per frame
+    pub tickrate: Option<u32>,
+    /// Color of lit pixels
+    pub fill_color: Option<[u8; 3]>,
+    /// Color of unlit pixels
+    pub background_color: Option<[u8; 3]>,
+    /// Interpreter behaviour, from Octo's quirk flags
+    pub quirks: Quirks,
+    /// Style of the built in font
+    pub font: Option<Fontset>
+}
+
+impl ArchiveOptions {
+    /// Read an options object, flags that are not given take Octo's
+    /// defaults, which are the COSMAC VIP behaviour
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - parsed options object
+    pub fn from_json(options: &JsonValue) -> Self {
+        let flag = |name: &str| options.get(name).and_then(JsonValue::as_bool).unwrap_or(false);
+        let color = |name: &str| options.get(name).and_then(JsonValue::as_str).and_then(Palette::parse_hex);
+        ArchiveOptions {
+            tickrate: options.get("tickrate").and_then(JsonValue::as_f64)
+                .filter(|rate| *rate >= 1.0)
+                .map(|rate| rate as u32),
+            fill_color: color("fillColor"),
+            background_color: color("backgroundColor"),
+            quirks: Quirks {
+                // Octo's flags name the CHIP-48 behaviour, ours mostly
+                // name the VIP one
+                shift_uses_vy: !flag("shiftQuirks"),
+                load_store_incr_i: !flag("loadStoreQuirks"),
+                jump_uses_vx: flag("jumpQuirks"),
+                vf_reset: flag("logicQuirks"),
+                clip_sprites: flag("clipQuirks"),
+                add_i_sets_vf: false
+            },
+            font: options.get("fontStyle").and_then(JsonValue::as_str).and_then(Fontset::from_name)
+        }
+    }
+
+    /// Set up a system with the quirks and font, call before the ROM
+    /// runs. The tick rate and colors are left to the frontend.
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to set up
+    pub fn apply(&self, sys: &mut ChipSystem) {
+        sys.quirks = self.quirks;
+        if let Some(font) = self.font {
+            sys.ram.set_fontset(font);
+        }
+    }
+
+    /// Get the colors as a palette, using `base` for colors not given
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - palette to start from
+    pub fn palette(&self, base: Palette) -> Palette {
+        Palette::init(self.fill_color.unwrap_or(base.on), self.background_color.unwrap_or(base.off))
+    }
+}
+
+/// A program listed in the CHIP-8 Archive
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveProgram {
+    /// Key of the program, also the name of its ROM file
+    pub key: String,
+    /// Title shown to players
+    pub title: String,
+    /// Authors of the program
+    pub authors: Vec<String>,
+    /// Platform it was written for, such as chip8, schip or xochip
+    pub platform: Option<String>,
+    /// Settings to run it with
+    pub options: ArchiveOptions
+}
+
+impl ArchiveProgram {
+    /// Read one program entry
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of the entry
+    /// * `entry` - parsed entry
+    fn from_json(key: &str, entry: &JsonValue) -> Self {
+        let text = |name: &str| entry.get(name).and_then(JsonValue::as_str).map(str::to_string);
+        let options = match entry.get("options") {
+            Some(options) => ArchiveOptions::from_json(options),
+            // A bare options object, as kept next to a single ROM
+            None => ArchiveOptions::from_json(entry)
+        };
+        ArchiveProgram {
+            key: key.to_string(),
+            title: text("title").unwrap_or_else(|| key.to_string()),
+            authors: entry.get("authors").and_then(JsonValue::as_array).unwrap_or(&[]).iter()
+                .filter_map(|author| author.as_str().map(str::to_string))
+                .collect(),
+            platform: text("platform"),
+            options
+        }
+    }
+}
+
+/// Programs listed in a CHIP-8 Archive style `programs.json`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArchiveCatalog {
+    /// Programs in the order listed
+    programs: Vec<ArchiveProgram>
+}
+
+impl ArchiveCatalog {
+    /// Parse the metadata of a single program, with or without its
+    /// `options` wrapper, or a whole `programs.json` keyed by program
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - JSON text
+    /// * `key` - key given to a single program
+    pub fn parse(text: &str, key: &str) -> Result<Self, String> {
+        let root = JsonValue::parse(text)?;
+        let members = root.as_object().ok_or("expected a JSON object")?;
+        let single = ["options", "title", "tickrate"].iter().any(|name| root.get(name).is_some());
+        let programs = match single {
+            true => vec![ArchiveProgram::from_json(key, &root)],
+            false => members.iter()
+                .filter(|(_, entry)| entry.as_object().is_some())
+                .map(|(key, entry)| ArchiveProgram::from_json(key, entry))
+                .collect()
+        };
+        Ok(ArchiveCatalog { programs })
+    }
+
+    /// Get every program in the catalog
+    pub fn get_programs(&self) -> &[ArchiveProgram] {
+        &self.programs
+    }
+
+    /// Find a program by key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - key of the program
+    pub fn find(&self, key: &str) -> Option<&ArchiveProgram> {
+        self.programs.iter().find(|program| program.key == key)
+    }
+
+    /// Look for the metadata of a ROM and return its entry, None if
+    /// there is none. `game.json` next to `game.ch8` is used first,
+    /// then a `programs.json` in the ROM's directory or the one above
+    /// it, the layout of the archive with its ROMs in `roms/`.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - path of the ROM
+    pub fn lookup(rom: &Path) -> io::Result<Option<(PathBuf, ArchiveProgram)>> {
+        let key = match rom.file_stem() {
+            Some(stem) => stem.to_string_lossy().to_string(),
+            None => return Ok(None)
+        };
+        let dir = rom.parent().unwrap_or_else(|| Path::new(""));
+        let mut candidates = vec![rom.with_extension("json"), dir.join(ARCHIVE_FILE)];
+        if let Some(parent) = dir.parent() {
+            candidates.push(parent.join(ARCHIVE_FILE));
+        }
+        for path in candidates {
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e)
+            };
+            let catalog = ArchiveCatalog::parse(&text, &key)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+            if let Some(program) = catalog.find(&key) {
+                return Ok(Some((path, program.clone())));
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,1470 @@
+// Standard Library Modules //
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Address programs are loaded at, and where output starts without ORG
+const START: u16 = 0x200;
+
+/// Deepest macros and includes can be nested, to catch a macro that
+/// uses itself or a file that includes itself
+const MAX_EXPANSION_DEPTH: usize = 16;
+
+/// A problem in assembler source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// File the problem is in, none for source given as a string
+    pub file: Option<PathBuf>,
+    /// Line number starting at 1, 0 when the whole file is the problem
+    pub line: usize,
+    /// Column starting at 1, 0 when the whole line is the problem
+    pub column: usize,
+    /// What is wrong with the line
+    pub message: String
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.file, self.line, self.column) {
+            (Some(file), 0, _) => write!(f, "{}: {}", file.display(), self.message),
+            (Some(file), line, 0) => write!(f, "{}:{}: {}", file.display(), line, self.message),
+            (Some(file), line, column) => write!(f, "{}:{}:{}: {}", file.display(), line, column, self.message),
+            (None, line, 0) => write!(f, "line {}: {}", line, self.message),
+            (None, line, column) => write!(f, "line {}, column {}: {}", line, column, self.message)
+        }
+    }
+}
+
+impl error::Error for AsmError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Source line an address was assembled from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// File the line is in, none for source given as a string
+    pub file: Option<PathBuf>,
+    /// Line number starting at 1
+    pub line: usize
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}", file.display(), self.line),
+            None => write!(f, "line {}", self.line)
+        }
+    }
+}
+
+/// Bytes assembled from one source line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    /// Address of the first byte
+    pub addr: u16,
+    /// Number of bytes
+    pub len: u16,
+    /// Line the bytes came from
+    pub location: SourceLocation
+}
+
+/// Map from the addresses of an assembled ROM back to the source lines
+/// they came from, so a debugger can show source instead of addresses
+///
+/// Written as text with one entry per line, `0x0204 2 game.asm:7`
+/// meaning the 2 bytes at 0x204 came from line 7 of game.asm. Code from
+/// a macro maps to the line that used the macro.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Entries in address order
+    entries: Vec<MapEntry>
+}
+
+impl SourceMap {
+    /// Get every entry in address order
+    pub fn get_entries(&self) -> &[MapEntry] {
+        &self.entries
+    }
+
+    /// Find the source line an address was assembled from
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - address of any byte of an instruction or data
+    pub fn lookup(&self, addr: u16) -> Option<&SourceLocation> {
+        let index = self.entries.partition_point(|entry| entry.addr <= addr);
+        let entry = self.entries.get(index.checked_sub(1)?)?;
+        match (addr as u32) < entry.addr as u32 + entry.len as u32 {
+            true => Some(&entry.location),
+            false => None
+        }
+    }
+
+    /// Find the first address assembled from a source line
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - file name, matched against the end of the mapped path
+    ///   so `game.asm` finds `src/game.asm`
+    /// * `line` - line number starting at 1
+    pub fn address_of(&self, file: &str, line: usize) -> Option<u16> {
+        let file = Path::new(file);
+        self.entries.iter()
+            .find(|entry| entry.location.line == line && match &entry.location.file {
+                Some(path) => path.ends_with(file),
+                None => file.as_os_str().is_empty()
+            })
+            .map(|entry| entry.addr)
+    }
+
+    /// Write the map as text
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for entry in self.entries.iter() {
+            let file = entry.location.file.as_ref().map(|file| file.display().to_string()).unwrap_or_default();
+            text.push_str(&format!("0x{:04X} {} {}:{}\n", entry.addr, entry.len, file, entry.location.line));
+        }
+        text
+    }
+
+    /// Read a map written by `to_text`
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - map text
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = || format!("line {}: expected 'ADDR LEN FILE:LINE', found '{}'", index + 1, line);
+            let mut parts = line.trim().splitn(3, ' ');
+            let addr = parts.next()
+                .and_then(|addr| addr.strip_prefix("0x"))
+                .and_then(|addr| u16::from_str_radix(addr, 16).ok())
+                .ok_or_else(invalid)?;
+            let len = parts.next().and_then(|len| len.parse::<u16>().ok()).ok_or_else(invalid)?;
+            let (file, number) = parts.next().and_then(|location| location.rsplit_once(':')).ok_or_else(invalid)?;
+            let number = number.parse::<usize>().map_err(|_| invalid())?;
+            let file = match file.is_empty() {
+                true => None,
+                false => Some(PathBuf::from(file))
+            };
+            entries.push(MapEntry { addr, len, location: SourceLocation { file, line: number } });
+        }
+        entries.sort_by_key(|entry| entry.addr);
+        Ok(SourceMap { entries })
+    }
+}
+
+/// An operand of an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    /// General purpose register V0-VF
+    Reg(u16),
+    /// Number, label or constant expression
+    Value(String),
+    /// A named operand such as I, DT, K or [I]
+    Name(String)
+}
+
+/// A line of source after macros and includes are expanded, with where
+/// it came from for error messages
+#[derive(Debug, Clone)]
+struct SourceLine {
+    /// File the line is in
+    file: Option<PathBuf>,
+    /// Line number starting at 1
+    number: usize,
+    /// Characters in the file before `text` starts, none once a macro
+    /// has rewritten the text
+    indent: Option<usize>,
+    /// Text without its comment
+    text: String
+}
+
+impl SourceLine {
+    /// Error pointing at this line
+    fn error(&self, message: String) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.number, column: 0, message }
+    }
+
+    /// Error pointing at part of this line, such as an operand, or at
+    /// the whole line when the part can not be found
+    fn error_at(&self, part: &str, message: String) -> AsmError {
+        // Upper casing ASCII keeps byte offsets, so mnemonics are found
+        // whatever case they were written in. Only whole words count, so
+        // the 0 of `LD V0, 0` is not found inside V0
+        let (text, part) = (self.text.to_ascii_uppercase(), part.trim().to_ascii_uppercase());
+        let word = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+        let found = text.match_indices(&part).map(|(offset, _)| offset).find(|offset| {
+            !word(text[..*offset].chars().next_back()) && !word(text[offset + part.len()..].chars().next())
+        });
+        let column = match (self.indent, found) {
+            (Some(indent), Some(offset)) if !part.trim().is_empty() => indent + self.text[..offset].chars().count() + 1,
+            _ => 0
+        };
+        AsmError { column, ..self.error(message) }
+    }
+}
+
+/// A macro defined with MACRO and ENDM
+#[derive(Debug, Clone)]
+struct Macro {
+    /// Parameter names
+    params: Vec<String>,
+    /// Lines between MACRO and ENDM
+    body: Vec<SourceLine>
+}
+
+/// A line of source split into its parts
+#[derive(Debug, Clone)]
+struct Line {
+    /// Where the line came from
+    source: SourceLine,
+    /// Label defined by the line, or the name of an EQU constant
+    label: Option<String>,
+    /// Upper case mnemonic or directive, empty for a line with only a
+    /// label
+    op: String,
+    /// Operands in order
+    args: Vec<Operand>
+}
+
+impl Line {
+    /// Error pointing at this line
+    fn error(&self, message: String) -> AsmError {
+        self.source.error(message)
+    }
+
+    /// Error pointing at part of this line
+    fn error_at(&self, part: &str, message: String) -> AsmError {
+        self.source.error_at(part, message)
+    }
+}
+
+/// Expands macros, repeat blocks and includes
+struct Preprocessor {
+    /// Macros defined so far, by upper case name
+    macros: HashMap<String, Macro>,
+    /// Constants whose value is known while expanding, for REPT counts
+    constants: HashMap<String, u16>,
+    /// Expansions done so far, used for `@`
+    expansions: usize
+}
+
+/// Assemble source written with the mnemonics of Cowgod's Chip-8
+/// technical reference into a ROM loaded at 0x200
+///
+/// Each line holds an optional `label:`, an optional instruction and an
+/// optional `; comment`. Numbers are decimal, `0x` hex or `0b` binary
+/// and a label can be used anywhere an address or number is expected.
+/// Besides the instructions there are four directives: `ORG addr`
+/// moves to an address at or after the current one, padding with
+/// zeros, `DB` writes bytes and `DW` writes 16 bit words, both taking a
+/// comma separated list, and `SPRITE` writes sprite rows drawn with `#`
+/// for lit and `.` for dark pixels, a byte for rows up to 8 pixels wide
+/// and two for the 16 pixel rows of SCHIP sprites:
+///
+/// ```text
+/// ball:
+///     SPRITE .##.
+///     SPRITE #..#, #..#
+///     SPRITE .##.
+/// ```
+///
+/// `LD I, long addr` is the XO-CHIP `F000 NNNN` long address load.
+/// Errors give the line and, when it is known, the column of the
+/// operand or mnemonic at fault.
+///
+/// `NAME EQU value` defines a constant. Anywhere a number is expected
+/// a constant expression can be used instead, such as `SCREEN_W / 2`
+/// or `(sprite + 5) & 0xFFF`, with the operators `+ - * / % & | ^ ~`,
+/// `<<` and `>>` and parentheses. A negative value is stored as its two's
+/// complement, so `ADD V0, -1` subtracts one. `INCLUDE "file"` inserts
+/// another file, found relative to the file including it. `GLOBAL`
+/// is accepted and ignored, so a file written to be linked as a
+/// [`Fragment`] can also be assembled on its own.
+///
+/// Repeated code can be written once as a macro:
+///
+/// ```text
+/// MACRO draw_digit reg, x, y
+///     LD F, reg
+///     DRW x, y, 5
+/// ENDM
+///     draw_digit V3, V0, V1
+/// ```
+///
+/// Parameters are replaced wherever they appear as a whole word. Lines
+/// between `REPT count` and `ENDR` are repeated `count` times. A `@`
+/// in a macro or repeat body becomes a number unique to each expansion,
+/// so `loop@:` gives every copy its own label.
+///
+/// # Arguments
+///
+/// * `source` - assembler source, includes are found relative to the
+///   current directory
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_lines(&source_lines(source, None)).map(|(rom, _)| rom)
+}
+
+/// Assemble source like [`assemble`], also returning where each address
+/// came from
+///
+/// # Arguments
+///
+/// * `source` - assembler source
+pub fn assemble_with_map(source: &str) -> Result<(Vec<u8>, SourceMap), AsmError> {
+    assemble_lines(&source_lines(source, None))
+}
+
+/// Assemble a source file, see [`assemble`] for the syntax
+///
+/// # Arguments
+///
+/// * `path` - path to the source
+pub fn assemble_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, AsmError> {
+    assemble_file_with_map(path).map(|(rom, _)| rom)
+}
+
+/// Assemble a source file, also returning where each address came from
+///
+/// # Arguments
+///
+/// * `path` - path to the source
+pub fn assemble_file_with_map<P: AsRef<Path>>(path: P) -> Result<(Vec<u8>, SourceMap), AsmError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| AsmError { file: Some(path.to_path_buf()), line: 0, column: 0, message: format!("could not read: {}", e) })?;
+    assemble_lines(&source_lines(&source, Some(path.to_path_buf())))
+}
+
+/// Split source into lines without comments
+fn source_lines(source: &str, file: Option<PathBuf>) -> Vec<SourceLine> {
+    source.lines()
+        .enumerate()
+        .map(|(index, text)| {
+            let code = text.split(';').next().unwrap_or("");
+            SourceLine {
+                file: file.clone(),
+                number: index + 1,
+                indent: Some(code.chars().take_while(|c| c.is_whitespace()).count()),
+                text: code.trim().to_string()
+            }
+        })
+        .collect()
+}
+
+/// Assemble source split into lines
+fn assemble_lines(source: &[SourceLine]) -> Result<(Vec<u8>, SourceMap), AsmError> {
+    let mut preprocessor = Preprocessor { macros: HashMap::new(), constants: HashMap::new(), expansions: 0 };
+    let mut expanded = Vec::new();
+    preprocessor.expand(source, 0, &mut expanded)?;
+    let lines = parse(&expanded)?;
+
+    // First pass, find the address of every label and the value of
+    // every constant
+    let mut symbols = HashMap::new();
+    let mut addr = START as usize;
+    for line in lines.iter() {
+        let value = match line.op.as_str() {
+            "ORG" => {
+                addr = org_target(line, &symbols, addr)?;
+                addr as u16
+            },
+            "EQU" => match line.args.as_slice() {
+                [Operand::Value(value)] => value_of(value, &symbols, line, 0xFFFF)?,
+                _ => return Err(line.error(String::from("expected EQU value")))
+            },
+            _ => addr as u16
+        };
+        if let Some(name) = &line.label {
+            if symbols.insert(name.clone(), value).is_some() {
+                return Err(line.error(format!("'{}' is defined twice", name)));
+            }
+        }
+        if line.op != "ORG" {
+            addr += size(line);
+        }
+    }
+
+    // Second pass, encode
+    let mut out: Vec<u8> = Vec::new();
+    let mut map = SourceMap::default();
+    let mut addr = START as usize;
+    for line in lines.iter() {
+        if line.op == "ORG" {
+            addr = org_target(line, &symbols, addr)?;
+            out.resize(addr - START as usize, 0);
+            continue;
+        }
+        let bytes = encode(line, &mut |text, field| value_of(text, &symbols, line, field.mask))?;
+        if addr + bytes.len() > 0x10000 {
+            return Err(line.error(String::from("program runs past the end of memory")));
+        }
+        if !bytes.is_empty() {
+            map.entries.push(MapEntry {
+                addr: addr as u16,
+                len: bytes.len() as u16,
+                location: SourceLocation { file: line.source.file.clone(), line: line.source.number }
+            });
+        }
+        addr += bytes.len();
+        out.extend(bytes);
+    }
+    Ok((out, map))
+}
+
+/// Value of a symbol defined in a fragment
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SymbolValue {
+    /// Label, an offset from the start of the fragment
+    Offset(u16),
+    /// Constant known while assembling
+    Constant(u16),
+    /// Constant that depends on labels, worked out when linking
+    Expression(String)
+}
+
+/// Symbol defined in a fragment
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FragmentSymbol {
+    /// Name of the symbol
+    name: String,
+    /// Value of the symbol
+    value: SymbolValue,
+    /// True when other fragments can use it, declared with GLOBAL
+    global: bool,
+    /// Where the symbol is defined
+    location: SourceLocation
+}
+
+/// Value that can only be worked out once fragments are placed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Fixup {
+    /// Offset of the encoded line in the fragment
+    offset: u16,
+    /// Field of the line the value goes in
+    field: Field,
+    /// Expression giving the value
+    expression: String,
+    /// Line the expression is on
+    location: SourceLocation
+}
+
+/// Relocatable piece of a program, assembled without knowing where it
+/// will be placed, see [`link`]
+///
+/// Labels are kept as offsets from the start of the fragment and every
+/// operand that uses a label is left for the linker to fill in. Only
+/// symbols named by a `GLOBAL name` directive can be used by other
+/// fragments, the rest are private. Fragments are saved as text, see
+/// `to_text`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fragment {
+    /// File the fragment was assembled from
+    source: Option<PathBuf>,
+    /// Encoded bytes, with zeros where fixups go
+    code: Vec<u8>,
+    /// Labels and constants
+    symbols: Vec<FragmentSymbol>,
+    /// Operands left for the linker
+    fixups: Vec<Fixup>,
+    /// Source lines of the code, addresses are offsets
+    map: Vec<MapEntry>
+}
+
+/// First line of a saved fragment
+const FRAGMENT_HEADER: &str = "CLIP8 FRAGMENT 1";
+
+impl Fragment {
+    /// Get the size of the fragment in bytes
+    pub fn get_size(&self) -> usize {
+        self.code.len()
+    }
+
+    /// Get the names of the symbols other fragments can use
+    pub fn get_globals(&self) -> Vec<&str> {
+        self.symbols.iter().filter(|symbol| symbol.global).map(|symbol| symbol.name.as_str()).collect()
+    }
+
+    /// Write the fragment as text, one tab separated record per line
+    pub fn to_text(&self) -> String {
+        let location = |location: &SourceLocation| {
+            let file = location.file.as_ref().map(|file| file.display().to_string()).unwrap_or_default();
+            format!("{}:{}", file, location.line)
+        };
+        let mut text = format!("{}\n", FRAGMENT_HEADER);
+        if let Some(source) = &self.source {
+            text.push_str(&format!("SOURCE\t{}\n", source.display()));
+        }
+        for chunk in self.code.chunks(32) {
+            let hex: String = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            text.push_str(&format!("CODE\t{}\n", hex));
+        }
+        for symbol in self.symbols.iter() {
+            let scope = if symbol.global { "global" } else { "local" };
+            let (kind, value) = match &symbol.value {
+                SymbolValue::Offset(offset) => ("LABEL", format!("0x{:04X}", offset)),
+                SymbolValue::Constant(value) => ("CONST", format!("0x{:04X}", value)),
+                SymbolValue::Expression(expression) => ("EQU", expression.clone())
+            };
+            text.push_str(&format!("{}\t{}\t{}\t{}\t{}\n", kind, symbol.name, scope, location(&symbol.location), value));
+        }
+        for fixup in self.fixups.iter() {
+            text.push_str(&format!(
+                "FIXUP\t0x{:04X}\t{}\t{}\t0x{:04X}\t{}\t{}\n",
+                fixup.offset, fixup.field.offset, fixup.field.width, fixup.field.mask,
+                location(&fixup.location), fixup.expression
+            ));
+        }
+        for entry in self.map.iter() {
+            text.push_str(&format!("MAP\t0x{:04X}\t{}\t{}\n", entry.addr, entry.len, location(&entry.location)));
+        }
+        text
+    }
+
+    /// Read a fragment written by `to_text`
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - fragment text
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.trim() == FRAGMENT_HEADER => {},
+            _ => return Err(String::from("not a clip8 fragment"))
+        }
+        let mut fragment = Fragment::default();
+        for (index, line) in lines.filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = || format!("line {}: invalid record '{}'", index + 1, line);
+            let number = |text: &str| -> Result<u16, String> {
+                match text.strip_prefix("0x") {
+                    Some(hex) => u16::from_str_radix(hex, 16).ok(),
+                    None => text.parse::<u16>().ok()
+                }.ok_or_else(invalid)
+            };
+            let location = |text: &str| -> Result<SourceLocation, String> {
+                let (file, line) = text.rsplit_once(':').ok_or_else(invalid)?;
+                Ok(SourceLocation {
+                    file: if file.is_empty() { None } else { Some(PathBuf::from(file)) },
+                    line: line.parse().map_err(|_| invalid())?
+                })
+            };
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["SOURCE", path] => fragment.source = Some(PathBuf::from(path)),
+                ["CODE", hex] => {
+                    if hex.len() % 2 != 0 {
+                        return Err(invalid());
+                    }
+                    for index in (0..hex.len()).step_by(2) {
+                        let byte = hex.get(index..index + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()).ok_or_else(invalid)?;
+                        fragment.code.push(byte);
+                    }
+                },
+                [kind @ ("LABEL" | "CONST" | "EQU"), name, scope, at, value] => {
+                    let value = match *kind {
+                        "LABEL" => SymbolValue::Offset(number(value)?),
+                        "CONST" => SymbolValue::Constant(number(value)?),
+                        _ => SymbolValue::Expression(value.to_string())
+                    };
+                    let global = match *scope {
+                        "global" => true,
+                        "local" => false,
+                        _ => return Err(invalid())
+                    };
+                    fragment.symbols.push(FragmentSymbol { name: name.to_string(), value, global, location: location(at)? });
+                },
+                ["FIXUP", offset, field_offset, width, mask, at, expression] => {
+                    let field = Field {
+                        offset: field_offset.parse().map_err(|_| invalid())?,
+                        width: width.parse().map_err(|_| invalid())?,
+                        mask: number(mask)?
+                    };
+                    if field.width == 0 || field.width > 2 {
+                        return Err(invalid());
+                    }
+                    fragment.fixups.push(Fixup { offset: number(offset)?, field, expression: expression.to_string(), location: location(at)? });
+                },
+                ["MAP", addr, len, at] => {
+                    fragment.map.push(MapEntry { addr: number(addr)?, len: number(len)?, location: location(at)? });
+                },
+                _ => return Err(invalid())
+            }
+        }
+        let size = fragment.code.len();
+        if let Some(fixup) = fragment.fixups.iter().find(|fixup| fixup.offset as usize + fixup.field.offset + fixup.field.width > size) {
+            return Err(format!("fixup at 0x{:04X} is past the end of the code", fixup.offset));
+        }
+        Ok(fragment)
+    }
+}
+
+/// Assemble source into a relocatable fragment, see [`Fragment`]
+///
+/// The syntax is the same as [`assemble`] except that ORG can not be
+/// used, fragments are placed by the linker.
+///
+/// # Arguments
+///
+/// * `source` - assembler source
+pub fn assemble_fragment(source: &str) -> Result<Fragment, AsmError> {
+    fragment_lines(&source_lines(source, None), None)
+}
+
+/// Assemble a source file into a relocatable fragment
+///
+/// # Arguments
+///
+/// * `path` - path to the source
+pub fn assemble_fragment_file<P: AsRef<Path>>(path: P) -> Result<Fragment, AsmError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|e| AsmError { file: Some(path.to_path_buf()), line: 0, column: 0, message: format!("could not read: {}", e) })?;
+    fragment_lines(&source_lines(&source, Some(path.to_path_buf())), Some(path.to_path_buf()))
+}
+
+/// Assemble source split into lines into a fragment
+fn fragment_lines(source: &[SourceLine], path: Option<PathBuf>) -> Result<Fragment, AsmError> {
+    let mut preprocessor = Preprocessor { macros: HashMap::new(), constants: HashMap::new(), expansions: 0 };
+    let mut expanded = Vec::new();
+    preprocessor.expand(source, 0, &mut expanded)?;
+    let lines = parse(&expanded)?;
+    let mut fragment = Fragment { source: path, ..Fragment::default() };
+
+    // First pass, find the offset of every label and the value of every
+    // constant that does not depend on a label
+    let mut constants = HashMap::new();
+    let mut globals = Vec::new();
+    let mut offset = 0;
+    for line in lines.iter() {
+        let location = SourceLocation { file: line.source.file.clone(), line: line.source.number };
+        let value = match line.op.as_str() {
+            "ORG" => return Err(line.error(String::from("ORG can not be used in a fragment, the linker places it"))),
+            "GLOBAL" => {
+                for arg in line.args.iter() {
+                    match arg {
+                        Operand::Value(name) if is_identifier(name) => globals.push((name.clone(), line)),
+                        _ => return Err(line.error(String::from("GLOBAL takes symbol names")))
+                    }
+                }
+                None
+            },
+            "EQU" => match line.args.as_slice() {
+                [Operand::Value(value)] => match value_of(value, &constants, line, 0xFFFF) {
+                    Ok(value) => Some(SymbolValue::Constant(value)),
+                    Err(e) => {
+                        deferrable(value, &constants, line, e)?;
+                        Some(SymbolValue::Expression(value.clone()))
+                    }
+                },
+                _ => return Err(line.error(String::from("expected EQU value")))
+            },
+            _ => Some(SymbolValue::Offset(offset as u16))
+        };
+        if let (Some(name), Some(value)) = (&line.label, value) {
+            if fragment.symbols.iter().any(|symbol| &symbol.name == name) {
+                return Err(line.error(format!("'{}' is defined twice", name)));
+            }
+            if let SymbolValue::Constant(value) = value {
+                constants.insert(name.clone(), value);
+            }
+            fragment.symbols.push(FragmentSymbol { name: name.clone(), value, global: false, location });
+        }
+        offset += size(line);
+        if offset > 0x10000 - START as usize {
+            return Err(line.error(String::from("fragment is larger than memory")));
+        }
+    }
+    for (name, line) in globals {
+        match fragment.symbols.iter_mut().find(|symbol| symbol.name == name) {
+            Some(symbol) => symbol.global = true,
+            None => return Err(line.error(format!("GLOBAL '{}' is not defined in this file", name)))
+        }
+    }
+
+    // Second pass, encode with zeros for values the linker fills in
+    for line in lines.iter() {
+        let offset = fragment.code.len() as u16;
+        let location = SourceLocation { file: line.source.file.clone(), line: line.source.number };
+        let mut fixups = Vec::new();
+        let bytes = encode(line, &mut |text, field| match value_of(text, &constants, line, field.mask) {
+            Ok(value) => Ok(value),
+            Err(e) => {
+                deferrable(text, &constants, line, e)?;
+                fixups.push(Fixup { offset, field, expression: text.to_string(), location: location.clone() });
+                Ok(0)
+            }
+        })?;
+        fragment.fixups.extend(fixups);
+        if !bytes.is_empty() {
+            fragment.map.push(MapEntry { addr: offset, len: bytes.len() as u16, location });
+        }
+        fragment.code.extend(bytes);
+    }
+    Ok(fragment)
+}
+
+/// Check an expression that could not be worked out while assembling a
+/// fragment can be left to the linker: it has to be well formed and use
+/// a name that is not a known constant, otherwise `error` is returned
+fn deferrable(text: &str, constants: &HashMap<String, u16>, line: &Line, error: AsmError) -> Result<(), AsmError> {
+    let tokens = tokenize(text).map_err(|e| line.error(e))?;
+    let names: HashMap<String, u16> = tokens.iter()
+        .filter_map(|token| match token {
+            Token::Word(word) if is_identifier(word) && !constants.contains_key(word) => Some((word.clone(), 1)),
+            _ => None
+        })
+        .collect();
+    if names.is_empty() {
+        return Err(error);
+    }
+    let mut symbols = constants.clone();
+    symbols.extend(names);
+    Expression { tokens, position: 0, symbols: &symbols }.evaluate().map_err(|e| line.error(e))?;
+    Ok(())
+}
+
+/// Link fragments into one ROM, placing them one after another from
+/// 0x200 in the order given so the first fragment holds the entry point
+///
+/// # Arguments
+///
+/// * `fragments` - fragments to link
+pub fn link(fragments: &[Fragment]) -> Result<(Vec<u8>, SourceMap), AsmError> {
+    let fragment_error = |fragment: &Fragment, message: String| AsmError { file: fragment.source.clone(), line: 0, column: 0, message };
+
+    // Place the fragments
+    let mut bases = Vec::new();
+    let mut addr = START as usize;
+    for fragment in fragments {
+        bases.push(addr as u16);
+        addr += fragment.code.len();
+        if addr > 0x10000 {
+            return Err(fragment_error(fragment, String::from("linked program runs past the end of memory")));
+        }
+    }
+
+    // Symbols of every fragment, globals are shared by all
+    let mut globals: HashMap<String, (u16, SourceLocation)> = HashMap::new();
+    let mut locals: Vec<HashMap<String, u16>> = vec![HashMap::new(); fragments.len()];
+    let mut pending = Vec::new();
+    for (index, fragment) in fragments.iter().enumerate() {
+        for symbol in fragment.symbols.iter() {
+            let value = match &symbol.value {
+                SymbolValue::Offset(offset) => bases[index].wrapping_add(*offset),
+                SymbolValue::Constant(value) => *value,
+                SymbolValue::Expression(expression) => {
+                    pending.push((index, symbol, expression));
+                    continue;
+                }
+            };
+            define(&mut globals, &mut locals[index], symbol, value)?;
+        }
+    }
+    // Constants built from labels can use each other, so keep working
+    // them out until none are left or none can be
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut unresolved = Vec::new();
+        let mut last_error = None;
+        for (index, symbol, expression) in pending {
+            match value_of(expression, &scope(&globals, &locals[index]), &symbol.location, 0xFFFF) {
+                Ok(value) => define(&mut globals, &mut locals[index], symbol, value)?,
+                Err(e) => {
+                    last_error = Some(e);
+                    unresolved.push((index, symbol, expression));
+                }
+            }
+        }
+        match last_error {
+            Some(e) if unresolved.len() == before => return Err(e),
+            _ => pending = unresolved
+        }
+    }
+
+    // Fill in the fixups
+    let mut rom = Vec::new();
+    let mut map = SourceMap::default();
+    for (index, fragment) in fragments.iter().enumerate() {
+        let mut code = fragment.code.clone();
+        let symbols = scope(&globals, &locals[index]);
+        for fixup in fragment.fixups.iter() {
+            let value = value_of(&fixup.expression, &symbols, &fixup.location, fixup.field.mask)?;
+            let at = fixup.offset as usize + fixup.field.offset;
+            match fixup.field.width {
+                1 => code[at] |= value as u8,
+                _ => {
+                    let word = u16::from_be_bytes([code[at], code[at + 1]]) | value;
+                    code[at..at + 2].copy_from_slice(&word.to_be_bytes());
+                }
+            }
+        }
+        map.entries.extend(fragment.map.iter().map(|entry| MapEntry { addr: bases[index].wrapping_add(entry.addr), ..entry.clone() }));
+        rom.extend(code);
+    }
+    Ok((rom, map))
+}
+
+/// Add a symbol of a fragment being linked to its scope, and to the
+/// globals when it is global
+fn define(globals: &mut HashMap<String, (u16, SourceLocation)>, locals: &mut HashMap<String, u16>, symbol: &FragmentSymbol, value: u16) -> Result<(), AsmError> {
+    locals.insert(symbol.name.clone(), value);
+    if symbol.global {
+        if let Some((_, first)) = globals.get(&symbol.name) {
+            return Err(symbol.location.locate(format!("global '{}' is also defined at {}", symbol.name, first)));
+        }
+        globals.insert(symbol.name.clone(), (value, symbol.location.clone()));
+    }
+    Ok(())
+}
+
+/// Symbols visible to a fragment, its own symbols hide globals of the
+/// same name
+fn scope(globals: &HashMap<String, (u16, SourceLocation)>, locals: &HashMap<String, u16>) -> HashMap<String, u16> {
+    let mut symbols: HashMap<String, u16> = globals.iter().map(|(name, (value, _))| (name.clone(), *value)).collect();
+    symbols.extend(locals.iter().map(|(name, value)| (name.clone(), *value)));
+    symbols
+}
+
+impl Preprocessor {
+    /// Expand macros, repeat blocks and includes, defining macros as they
+    /// are found
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - lines to expand
+    /// * `depth` - how deeply nested this expansion is
+    /// * `out` - where expanded lines go
+    fn expand(&mut self, source: &[SourceLine], depth: usize, out: &mut Vec<SourceLine>) -> Result<(), AsmError> {
+        if depth > MAX_EXPANSION_DEPTH {
+            return Err(match source.first() {
+                Some(line) => line.error(String::from("macros or includes are nested too deeply")),
+                None => AsmError { file: None, line: 0, column: 0, message: String::from("macros or includes are nested too deeply") }
+            });
+        }
+        let mut index = 0;
+        while index < source.len() {
+            let line = &source[index];
+            let (label, text) = split_label(&line.text);
+            let (op, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let rest = rest.trim();
+            let upper = op.to_ascii_uppercase();
+            match upper.as_str() {
+                "MACRO" => {
+                    if label.is_some() {
+                        return Err(line.error(String::from("a macro definition cannot have a label")));
+                    }
+                    let (name, params) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                    if !is_identifier(name) {
+                        return Err(line.error(format!("invalid macro name '{}'", name)));
+                    }
+                    let params: Vec<String> = params.split(',')
+                        .map(|param| param.trim().to_string())
+                        .filter(|param| !param.is_empty())
+                        .collect();
+                    if let Some(param) = params.iter().find(|param| !is_identifier(param)) {
+                        return Err(line.error(format!("invalid macro parameter '{}'", param)));
+                    }
+                    let body = block(source, index, "MACRO", "ENDM")?;
+                    index += body.len() + 2;
+                    self.macros.insert(name.to_ascii_uppercase(), Macro { params, body });
+                    continue;
+                },
+                "REPT" => {
+                    let count = value_of(rest, &self.constants, line, 0xFFFF)?;
+                    let body = block(source, index, "REPT", "ENDR")?;
+                    if let Some(label) = label {
+                        out.push(SourceLine { text: format!("{}:", label), ..line.clone() });
+                    }
+                    for _ in 0..count {
+                        self.expansions += 1;
+                        let copy: Vec<SourceLine> = body.iter()
+                            .map(|body_line| {
+                                let text = substitute(&body_line.text, &[], &[], self.expansions);
+                                // An @ replaced by a number moves the columns after it
+                                let indent = body_line.indent.filter(|_| text == body_line.text);
+                                SourceLine { text, indent, ..body_line.clone() }
+                            })
+                            .collect();
+                        self.expand(&copy, depth + 1, out)?;
+                    }
+                    index += body.len() + 2;
+                    continue;
+                },
+                "INCLUDE" => {
+                    let name = rest.strip_prefix('"').and_then(|name| name.strip_suffix('"'))
+                        .ok_or_else(|| line.error(String::from("expected INCLUDE \"file\"")))?;
+                    let path = match &line.file {
+                        Some(file) => file.parent().unwrap_or_else(|| Path::new("")).join(name),
+                        None => PathBuf::from(name)
+                    };
+                    let included = fs::read_to_string(&path).map_err(|e| line.error(format!("could not include {}: {}", path.display(), e)))?;
+                    if let Some(label) = label {
+                        out.push(SourceLine { text: format!("{}:", label), ..line.clone() });
+                    }
+                    self.expand(&source_lines(&included, Some(path)), depth + 1, out)?;
+                    index += 1;
+                    continue;
+                },
+                "ENDM" | "ENDR" => {
+                    let open = if upper == "ENDM" { "MACRO" } else { "REPT" };
+                    return Err(line.error(format!("{} without a matching {}", upper, open)));
+                },
+                _ => ()
+            }
+            if let Some(definition) = self.macros.get(&upper).cloned() {
+                let args: Vec<String> = rest.split(',')
+                    .map(|arg| arg.trim().to_string())
+                    .filter(|arg| !arg.is_empty())
+                    .collect();
+                if args.len() != definition.params.len() {
+                    return Err(line.error(format!("macro '{}' takes {} arguments, got {}", op, definition.params.len(), args.len())));
+                }
+                if let Some(label) = label {
+                    out.push(SourceLine { text: format!("{}:", label), ..line.clone() });
+                }
+                self.expansions += 1;
+                // Errors inside the expansion point at the line that used the macro
+                let copy: Vec<SourceLine> = definition.body.iter()
+                    .map(|body_line| SourceLine { text: substitute(&body_line.text, &definition.params, &args, self.expansions), indent: None, ..line.clone() })
+                    .collect();
+                self.expand(&copy, depth + 1, out)?;
+            } else {
+                // Remember constants that can already be worked out so
+                // REPT counts can use them
+                if let Some((directive, value)) = rest.split_once(char::is_whitespace) {
+                    if directive.eq_ignore_ascii_case("EQU") && label.is_none() {
+                        if let Ok(value) = value_of(value.trim(), &self.constants, line, 0xFFFF) {
+                            self.constants.insert(op.to_string(), value);
+                        }
+                    }
+                }
+                out.push(line.clone());
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Lines between an opening directive at `start` and its matching
+/// closing directive, counting nested blocks of the same kind
+fn block(source: &[SourceLine], start: usize, open: &str, close: &str) -> Result<Vec<SourceLine>, AsmError> {
+    let mut nesting = 0;
+    for (offset, line) in source[start + 1..].iter().enumerate() {
+        let (_, text) = split_label(&line.text);
+        let op = text.split_whitespace().next().unwrap_or("").to_ascii_uppercase();
+        if op == open {
+            nesting += 1;
+        } else if op == close {
+            if nesting == 0 {
+                return Ok(source[start + 1..start + 1 + offset].to_vec());
+            }
+            nesting -= 1;
+        }
+    }
+    Err(source[start].error(format!("{} without a matching {}", open, close)))
+}
+
+/// Replace whole word parameters with their arguments and `@` with the
+/// expansion number
+fn substitute(text: &str, params: &[String], args: &[String], expansion: usize) -> String {
+    let mut out = String::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut String| {
+        match params.iter().position(|param| param == word) {
+            Some(index) => out.push_str(&args[index]),
+            None => out.push_str(word)
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        flush(&mut word, &mut out);
+        match c {
+            '@' => out.push_str(&expansion.to_string()),
+            _ => out.push(c)
+        }
+    }
+    flush(&mut word, &mut out);
+    out
+}
+
+/// Whether text is a valid label, constant, macro or parameter name
+fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split a leading `label:` off a line
+fn split_label(text: &str) -> (Option<&str>, &str) {
+    match text.split_once(':') {
+        Some((name, rest)) => (Some(name.trim()), rest.trim()),
+        None => (None, text)
+    }
+}
+
+/// Split expanded source into labels and instructions, dropping blank
+/// lines
+fn parse(source: &[SourceLine]) -> Result<Vec<Line>, AsmError> {
+    let mut lines = Vec::new();
+    for source_line in source {
+        let (mut label, text) = split_label(&source_line.text);
+        if let Some(name) = label {
+            if !is_identifier(name) {
+                return Err(source_line.error_at(name, format!("invalid label '{}'", name)));
+            }
+        }
+        if label.is_none() && text.is_empty() {
+            continue;
+        }
+        let (mut op, mut rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        // NAME EQU value, the name takes the place of a label
+        if let Some((directive, value)) = rest.trim().split_once(char::is_whitespace) {
+            if directive.eq_ignore_ascii_case("EQU") && label.is_none() {
+                if !is_identifier(op) {
+                    return Err(source_line.error_at(op, format!("invalid constant name '{}'", op)));
+                }
+                label = Some(op);
+                op = directive;
+                rest = value;
+            }
+        }
+        let args = rest.split(',')
+            .map(|arg| arg.trim())
+            .filter(|arg| !arg.is_empty())
+            .map(operand)
+            .collect();
+        lines.push(Line {
+            source: source_line.clone(),
+            label: label.map(String::from),
+            op: op.to_ascii_uppercase(),
+            args
+        });
+    }
+    Ok(lines)
+}
+
+/// Parse one operand
+fn operand(text: &str) -> Operand {
+    let upper = text.to_ascii_uppercase();
+    let bytes = upper.as_bytes();
+    if bytes.len() == 2 && bytes[0] == b'V' && bytes[1].is_ascii_hexdigit() {
+        return Operand::Reg(u16::from_str_radix(&upper[1..], 16).unwrap_or(0));
+    }
+    match upper.as_str() {
+        "I" | "DT" | "ST" | "K" | "F" | "HF" | "B" | "R" | "[I]" => Operand::Name(upper),
+        _ if upper.starts_with("LONG ") => Operand::Name(format!("LONG {}", text[5..].trim())),
+        _ => Operand::Value(text.to_string())
+    }
+}
+
+/// Bytes an instruction or directive takes
+fn size(line: &Line) -> usize {
+    match line.op.as_str() {
+        "" | "EQU" | "GLOBAL" => 0,
+        "DB" => line.args.len(),
+        "DW" => line.args.len() * 2,
+        "SPRITE" => line.args.iter().map(|arg| match arg {
+            Operand::Value(row) if row.len() > 8 => 2,
+            _ => 1
+        }).sum(),
+        "LD" if matches!(line.args.get(1), Some(Operand::Name(name)) if name.starts_with("LONG ")) => 4,
+        _ => 2
+    }
+}
+
+/// Address an ORG line moves to
+fn org_target(line: &Line, symbols: &HashMap<String, u16>, addr: usize) -> Result<usize, AsmError> {
+    let target = match line.args.as_slice() {
+        [Operand::Value(value)] => value_of(value, symbols, line, 0xFFFF)? as usize,
+        _ => return Err(line.error(String::from("expected ORG address")))
+    };
+    if target < addr {
+        return Err(line.error(format!("ORG 0x{:03X} is before the current address 0x{:03X}", target, addr)));
+    }
+    Ok(target)
+}
+
+/// Value of a constant expression that has to fit in `max`, negative
+/// values are stored as two's complement
+fn value_of<L: Locate>(text: &str, symbols: &HashMap<String, u16>, line: &L, max: u16) -> Result<u16, AsmError> {
+    let value = Expression { tokens: tokenize(text).map_err(|e| line.locate_at(text, e))?, position: 0, symbols }
+        .evaluate()
+        .map_err(|e| line.locate_at(text, e))?;
+    let max = max as i64;
+    match value {
+        0..=0xFFFF if value <= max => Ok(value as u16),
+        _ if value < 0 && value >= -(max + 1) => Ok((value + max + 1) as u16),
+        _ => Err(line.locate_at(text, format!("{} does not fit in 0x{:X}", text, max)))
+    }
+}
+
+/// Something errors can point at
+trait Locate {
+    /// Error pointing here
+    fn locate(&self, message: String) -> AsmError;
+
+    /// Error pointing at part of the text here, where that is known
+    fn locate_at(&self, _part: &str, message: String) -> AsmError {
+        self.locate(message)
+    }
+}
+
+impl Locate for SourceLine {
+    fn locate(&self, message: String) -> AsmError {
+        self.error(message)
+    }
+
+    fn locate_at(&self, part: &str, message: String) -> AsmError {
+        self.error_at(part, message)
+    }
+}
+
+impl Locate for SourceLocation {
+    fn locate(&self, message: String) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.line, column: 0, message }
+    }
+}
+
+impl Locate for Line {
+    fn locate(&self, message: String) -> AsmError {
+        self.error(message)
+    }
+
+    fn locate_at(&self, part: &str, message: String) -> AsmError {
+        self.error_at(part, message)
+    }
+}
+
+/// Where a value goes in the bytes of an encoded line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    /// Offset of the first byte
+    offset: usize,
+    /// 1 or 2 bytes, a 2 byte field is big endian
+    width: usize,
+    /// Bits of the field the value is stored in, also the largest value
+    mask: u16
+}
+
+/// A piece of a constant expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Number or name
+    Word(String),
+    /// Operator or parenthesis
+    Symbol(&'static str)
+}
+
+/// Split a constant expression into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 13] = ["<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "(", ")"];
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = rest[symbol.len()..].trim_start();
+            continue;
+        }
+        let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if end == 0 {
+            return Err(format!("unexpected '{}' in '{}'", rest.chars().next().unwrap_or(' '), text));
+        }
+        tokens.push(Token::Word(rest[..end].to_string()));
+        rest = rest[end..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent evaluation of a constant expression, binding from
+/// loosest to tightest `|`, `^`, `&`, shifts, `+ -`, `* / %` then unary
+/// `- ~`
+struct Expression<'a> {
+    /// Tokens of the expression
+    tokens: Vec<Token>,
+    /// Next token to read
+    position: usize,
+    /// Labels and constants by name
+    symbols: &'a HashMap<String, u16>
+}
+
+impl<'a> Expression<'a> {
+    /// Evaluate the whole expression
+    fn evaluate(&mut self) -> Result<i64, String> {
+        if self.tokens.is_empty() {
+            return Err(String::from("expected a value"));
+        }
+        let value = self.binary(0)?;
+        match self.tokens.get(self.position) {
+            None => Ok(value),
+            Some(token) => Err(format!("unexpected {} in expression", describe(token)))
+        }
+    }
+
+    /// Evaluate operators binding at least as tightly as `level`
+    fn binary(&mut self, level: usize) -> Result<i64, String> {
+        const LEVELS: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+        let mut value = self.binary(level + 1)?;
+        while let Some(Token::Symbol(symbol)) = self.tokens.get(self.position) {
+            let symbol = *symbol;
+            if !LEVELS[level].contains(&symbol) {
+                break;
+            }
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            value = match symbol {
+                "|" => value | right,
+                "^" => value ^ right,
+                "&" => value & right,
+                "<<" => value.checked_shl(right as u32).ok_or("shift is too large")?,
+                ">>" => value.checked_shr(right as u32).ok_or("shift is too large")?,
+                "+" => value + right,
+                "-" => value - right,
+                "*" => value * right,
+                "/" => value.checked_div(right).ok_or("division by zero")?,
+                _ => value.checked_rem(right).ok_or("division by zero")?
+            };
+        }
+        Ok(value)
+    }
+
+    /// Evaluate a number, name, parenthesised expression or unary
+    /// operator
+    fn unary(&mut self) -> Result<i64, String> {
+        let token = self.tokens.get(self.position).cloned().ok_or("expression ends early")?;
+        self.position += 1;
+        match token {
+            Token::Symbol("-") => Ok(-self.unary()?),
+            Token::Symbol("~") => Ok(!self.unary()? & 0xFFFF),
+            Token::Symbol("(") => {
+                let value = self.binary(0)?;
+                match self.tokens.get(self.position) {
+                    Some(Token::Symbol(")")) => {
+                        self.position += 1;
+                        Ok(value)
+                    },
+                    _ => Err(String::from("missing ')'"))
+                }
+            },
+            Token::Word(word) => number(&word, self.symbols).map(|value| value as i64)
+                .ok_or_else(|| format!("unknown label or invalid number '{}'", word)),
+            Token::Symbol(_) => Err(format!("unexpected {} in expression", describe(&token)))
+        }
+    }
+}
+
+/// How a token is named in errors
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{}'", word),
+        Token::Symbol(symbol) => format!("'{}'", symbol)
+    }
+}
+
+/// Value of a number or name
+fn number(text: &str, symbols: &HashMap<String, u16>) -> Option<u16> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        u16::from_str_radix(bin, 2).ok()
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        text.parse::<u16>().ok()
+    } else {
+        symbols.get(text).copied()
+    }
+}
+
+/// Encode an instruction or data directive
+///
+/// # Arguments
+///
+/// * `line` - line to encode
+/// * `resolve` - gives the value of an operand expression and the field
+///   of the encoded bytes it goes in
+fn encode(line: &Line, resolve: &mut dyn FnMut(&str, Field) -> Result<u16, AsmError>) -> Result<Vec<u8>, AsmError> {
+    use Operand::*;
+    match (line.op.as_str(), line.args.as_slice()) {
+        ("", []) | ("EQU", _) | ("GLOBAL", _) => Ok(Vec::new()),
+        ("DB", args) => {
+            args.iter().enumerate().map(|(offset, arg)| match arg {
+                Value(text) => resolve(text, Field { offset, width: 1, mask: 0xFF }).map(|byte| byte as u8),
+                _ => Err(line.error(String::from("DB takes numbers")))
+            }).collect()
+        },
+        ("DW", args) => {
+            let mut bytes = Vec::new();
+            for arg in args {
+                match arg {
+                    Value(text) => bytes.extend_from_slice(&resolve(text, Field { offset: bytes.len(), width: 2, mask: 0xFFFF })?.to_be_bytes()),
+                    _ => return Err(line.error(String::from("DW takes numbers")))
+                }
+            }
+            Ok(bytes)
+        },
+        ("SPRITE", args) if !args.is_empty() => {
+            let mut bytes = Vec::new();
+            for arg in args {
+                match arg {
+                    Value(row) if row.len() <= 16 && row.chars().all(|c| c == '#' || c == '.') => {
+                        // Rows are drawn from the top bit, short rows
+                        // are dark on the right
+                        let width = if row.len() > 8 { 16 } else { 8 };
+                        let bits = row.chars().enumerate().fold(0u16, |bits, (x, c)| bits | ((c == '#') as u16) << (width - 1 - x));
+                        match width {
+                            16 => bytes.extend_from_slice(&bits.to_be_bytes()),
+                            _ => bytes.push(bits as u8)
+                        }
+                    },
+                    Value(row) => return Err(line.error_at(row, format!("sprite row '{}' should be up to 16 '#' and '.' pixels", row))),
+                    _ => return Err(line.error(String::from("SPRITE takes rows of '#' and '.' pixels")))
+                }
+            }
+            Ok(bytes)
+        },
+        ("LD", [Name(i), Name(long)]) if i == "I" && long.starts_with("LONG ") => {
+            let addr = resolve(&long[5..], Field { offset: 2, width: 2, mask: 0xFFFF })?;
+            Ok(vec![0xF0, 0x00, (addr >> 8) as u8, addr as u8])
+        },
+        _ => encode_opcode(line, &mut |text, mask| resolve(text, Field { offset: 0, width: 2, mask }))
+    }
+}
+
+/// Encode a two byte instruction
+fn encode_opcode(line: &Line, value: &mut dyn FnMut(&str, u16) -> Result<u16, AsmError>) -> Result<Vec<u8>, AsmError> {
+    use Operand::*;
+    let args = line.args.as_slice();
+    let op: u16 = match (line.op.as_str(), args) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("EXIT", []) => 0x00FD,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("AUDIO", []) => 0xF002,
+        ("SCD", [Value(n)]) => 0x00C0 | value(n, 0xF)?,
+        ("SCU", [Value(n)]) => 0x00D0 | value(n, 0xF)?,
+        ("PLANE", [Value(n)]) => 0xF001 | value(n, 0x3)? << 8,
+        ("SAVE", [Reg(x), Reg(y)]) => 0x5002 | x << 8 | y << 4,
+        ("LOAD", [Reg(x), Reg(y)]) => 0x5003 | x << 8 | y << 4,
+        ("SYS", [Value(nnn)]) => value(nnn, 0xFFF)?,
+        ("JP", [Value(nnn)]) => 0x1000 | value(nnn, 0xFFF)?,
+        ("JP", [Reg(0), Value(nnn)]) => 0xB000 | value(nnn, 0xFFF)?,
+        ("CALL", [Value(nnn)]) => 0x2000 | value(nnn, 0xFFF)?,
+        ("SE", [Reg(x), Value(kk)]) => 0x3000 | x << 8 | value(kk, 0xFF)?,
+        ("SNE", [Reg(x), Value(kk)]) => 0x4000 | x << 8 | value(kk, 0xFF)?,
+        ("SE", [Reg(x), Reg(y)]) => 0x5000 | x << 8 | y << 4,
+        ("SNE", [Reg(x), Reg(y)]) => 0x9000 | x << 8 | y << 4,
+        ("LD", [Reg(x), Value(kk)]) => 0x6000 | x << 8 | value(kk, 0xFF)?,
+        ("ADD", [Reg(x), Value(kk)]) => 0x7000 | x << 8 | value(kk, 0xFF)?,
+        ("LD", [Reg(x), Reg(y)]) => 0x8000 | x << 8 | y << 4,
+        ("OR", [Reg(x), Reg(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", [Reg(x), Reg(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", [Reg(x), Reg(y)]) => 0x8003 | x << 8 | y << 4,
+        ("ADD", [Reg(x), Reg(y)]) => 0x8004 | x << 8 | y << 4,
+        ("SUB", [Reg(x), Reg(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SHR", [Reg(x)]) => 0x8006 | x << 8,
+        ("SHR", [Reg(x), Reg(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SUBN", [Reg(x), Reg(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHL", [Reg(x)]) => 0x800E | x << 8,
+        ("SHL", [Reg(x), Reg(y)]) => 0x800E | x << 8 | y << 4,
+        ("LD", [Name(i), Value(nnn)]) if i == "I" => 0xA000 | value(nnn, 0xFFF)?,
+        ("RND", [Reg(x), Value(kk)]) => 0xC000 | x << 8 | value(kk, 0xFF)?,
+        ("DRW", [Reg(x), Reg(y), Value(n)]) => 0xD000 | x << 8 | y << 4 | value(n, 0xF)?,
+        ("SKP", [Reg(x)]) => 0xE09E | x << 8,
+        ("SKNP", [Reg(x)]) => 0xE0A1 | x << 8,
+        ("LD", [Reg(x), Name(name)]) if name == "DT" => 0xF007 | x << 8,
+        ("LD", [Reg(x), Name(name)]) if name == "K" => 0xF00A | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "DT" => 0xF015 | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "ST" => 0xF018 | x << 8,
+        ("ADD", [Name(name), Reg(x)]) if name == "I" => 0xF01E | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "F" => 0xF029 | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "HF" => 0xF030 | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "B" => 0xF033 | x << 8,
+        ("PITCH", [Reg(x)]) => 0xF03A | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "[I]" => 0xF055 | x << 8,
+        ("LD", [Reg(x), Name(name)]) if name == "[I]" => 0xF065 | x << 8,
+        ("LD", [Name(name), Reg(x)]) if name == "R" => 0xF075 | x << 8,
+        ("LD", [Reg(x), Name(name)]) if name == "R" => 0xF085 | x << 8,
+        _ => return Err(line.error_at(&line.op, format!("unknown instruction '{}' with {} operands", line.op, args.len())))
+    };
+    Ok(op.to_be_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruction::Instruction;
+
+    #[test]
+    fn every_instruction_assembles_from_its_mnemonic() {
+        for opcode in 0..=0xFFFF {
+            let instruction = Instruction::decode(opcode);
+            // F000 takes the address in the next word and only planes
+            // 0-3 exist
+            match instruction {
+                Instruction::Invalid(_) | Instruction::LoadILong => continue,
+                Instruction::Plane(planes) if planes > 3 => continue,
+                _ => {}
+            }
+            let source = instruction.to_string();
+            assert_eq!(assemble(&source), Ok(opcode.to_be_bytes().to_vec()), "{:04X} as {}", opcode, source);
+        }
+    }
+
+    #[test]
+    fn labels_constants_and_sprites() {
+        let source = "
+SCREEN_W EQU 64
+start:
+    LD V0, SCREEN_W / 2 - 4
+    LD I, sprite
+    JP start
+sprite:
+    SPRITE ..####..
+    DB 0x81
+";
+        assert_eq!(assemble(source), Ok(vec![0x60, 0x1C, 0xA2, 0x06, 0x12, 0x00, 0x3C, 0x81]));
+    }
+
+    #[test]
+    fn macros_and_repeats_expand_with_unique_labels() {
+        let source = "
+MACRO wait reg
+loop@:
+    SE reg, 0
+    JP loop@
+ENDM
+    wait V1
+    wait V2
+REPT 2
+    ADD V0, 1
+ENDR
+";
+        assert_eq!(assemble(source), Ok(vec![
+            0x31, 0x00, 0x12, 0x00,
+            0x32, 0x00, 0x12, 0x04,
+            0x70, 0x01, 0x70, 0x01
+        ]));
+    }
+
+    #[test]
+    fn errors_point_at_the_line() {
+        let error = assemble("CLS\n    LD V0, 0x100\n").unwrap_err();
+        assert_eq!((error.file, error.line), (None, 2));
+        assert_eq!(assemble("CLS\nFROB V0\n").unwrap_err().line, 2);
+        assert_eq!(assemble("JP nowhere").unwrap_err().line, 1);
+    }
+}
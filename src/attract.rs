@@ -0,0 +1,141 @@
+// Standard Library Modules //
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::keyboard::ChipKeyboard;
+
+/// File extensions recognised as ROMs when scanning a directory
+pub const ROM_EXTENSIONS: [&str; 5] = ["ch8", "c8", "sc8", "xo8", "8o"];
+
+/// Extension of the demo input file kept next to a ROM, `pong.ch8`
+/// is played with the inputs in `pong.ch8.demo`
+pub const DEMO_EXTENSION: &str = "demo";
+
+/// Find the ROMs in a directory, sorted by file name
+///
+/// # Arguments
+///
+/// * `dir` - directory to scan, sub directories are not searched
+pub fn find_roms(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_rom = path.extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if is_rom && path.is_file() {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+/// A key press or release at a given frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemoEvent {
+    /// Frame the event happens before
+    pub frame: u64,
+    /// Keypad key, 0-F
+    pub key: u8,
+    /// True for a press, false for a release
+    pub down: bool
+}
+
+/// Recorded inputs played back while a ROM runs in attract mode
+///
+/// Demo files have one `frame key down|up` event per line, for
+/// example `120 5 down`, with the key in hex. Blank lines and lines
+/// starting with `#` are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct DemoInput {
+    /// Events sorted by frame
+    events: Vec<DemoEvent>,
+    /// Index of the next event to apply
+    next: usize
+}
+
+impl DemoInput {
+    /// Parse the text of a demo file
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of the demo file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut events = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || format!("line {}: expected 'frame key down|up', found '{}'", index + 1, line);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(error());
+            }
+            let frame = fields[0].parse::<u64>().map_err(|_| error())?;
+            let key = u8::from_str_radix(fields[1], 16).ok().filter(|key| *key < 16).ok_or_else(error)?;
+            let down = match fields[2] {
+                "down" => true,
+                "up" => false,
+                _ => return Err(error())
+            };
+            events.push(DemoEvent { frame, key, down });
+        }
+        // A stable sort keeps events on the same frame in file order
+        events.sort_by_key(|event| event.frame);
+        Ok(DemoInput { events, next: 0 })
+    }
+
+    /// Play back events already known, such as a recorded replay
+    ///
+    /// # Arguments
+    ///
+    /// * `events` - events to play, sorted by frame if they are not
+    pub fn from_events(mut events: Vec<DemoEvent>) -> Self {
+        events.sort_by_key(|event| event.frame);
+        DemoInput { events, next: 0 }
+    }
+
+    /// Load the demo file kept next to a ROM, None if there is none
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - path of the ROM
+    pub fn load_for(rom: &Path) -> io::Result<Option<Self>> {
+        let mut name = rom.as_os_str().to_owned();
+        name.push(".");
+        name.push(DEMO_EXTENSION);
+        match fs::read_to_string(PathBuf::from(name)) {
+            Ok(text) => DemoInput::parse(&text)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e)
+        }
+    }
+
+    /// Get the events in the order they are played
+    pub fn get_events(&self) -> &[DemoEvent] {
+        &self.events
+    }
+
+    /// Press and release keys for every event due by a frame, call
+    /// once before running each frame
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - number of frames run so far
+    /// * `keyboard` - keypad to press keys on
+    pub fn apply(&mut self, frame: u64, keyboard: &mut ChipKeyboard) {
+        while let Some(event) = self.events.get(self.next) {
+            if event.frame > frame {
+                break;
+            }
+            keyboard.set_key(event.key, event.down);
+            self.next += 1;
+        }
+    }
+}
@@ -0,0 +1,2786 @@
+// Standard Library Modules //
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, IsTerminal};
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{self, Command};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Local Modules Use //
+use chip8emu::{ChipSystem, ExitReason};
+use chip8emu::{assembler, differential, disasm, heatmap, ihex, multicart, romdiff, stress, teach, tickrate, trim};
+use chip8emu::accessibility::{self, Announcer};
+use chip8emu::archive::ArchiveCatalog;
+use chip8emu::assembler::SourceMap;
+use chip8emu::attract::{self, DemoInput};
+use chip8emu::callgraph::CallGraph;
+use chip8emu::compare::ChipComparison;
+use chip8emu::corrupt::{self, CorruptMode, Corruptor, RunOutcome};
+use chip8emu::crashreport::{self, CrashReport};
+use chip8emu::export::{self, FrameDumper, TextStyle};
+use chip8emu::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use chip8emu::focus::FocusPause;
+use chip8emu::fonts::{Fontset, FONTSETS};
+use chip8emu::frames::FrameBudget;
+use chip8emu::frontend::{AnsiFrontend, DebugFrontend, Frontend, PrintFrontend};
+use chip8emu::hotkeys::{Hotkey, HotkeyMap};
+use chip8emu::image::RgbImage;
+use chip8emu::keyboard::ChipKeyboard;
+use chip8emu::kiosk::Kiosk;
+use chip8emu::keymap::KeyMap;
+use chip8emu::memory::WriteGuard;
+use chip8emu::memwatch::{MemoryWatch, WatchRange};
+use chip8emu::menu::{MenuAction, MenuInput, MenuSettings, PauseMenu};
+use chip8emu::monitor::{Monitor, MonitorRequest};
+use chip8emu::overlay::GhostReplay;
+use chip8emu::netplay::{self, Netplay, NetplaySettings, DEFAULT_INPUT_DELAY, LEFT_PLAYER_KEYS, MAX_INPUT_DELAY};
+use chip8emu::palette::{Palette, PRESETS};
+use chip8emu::quirks::{Quirks, QUIRK_NAMES};
+use chip8emu::recent::{self, RecentRom, RecentRoms};
+use chip8emu::speed::{Speed, SpeedControl, DEFAULT_SLOW_PERCENT};
+use chip8emu::sprite::{self, SpriteOptions, SPRITE_FORMATS};
+use chip8emu::registers::TimerMode;
+use chip8emu::replay::{InputRecorder, Replay};
+use chip8emu::rewind::RewindBuffer;
+use chip8emu::savestate;
+use chip8emu::sound::{PlayerAudio, TerminalBell, ToneSettings};
+use chip8emu::statedump::{self, DumpSignal};
+use chip8emu::stats::{CsvTelemetry, FrameTiming};
+use chip8emu::stream::MjpegServer;
+use chip8emu::termkeys::TerminalKeys;
+use chip8emu::testscript::TestScript;
+use chip8emu::timing::{TimerClock, TimingMode, TIMING_MODES};
+use chip8emu::trace::{OctoTrace, TraceLogger};
+use chip8emu::profile::{Profile, PROFILES};
+use chip8emu::variant::{Variant, VARIANTS};
+use chip8emu::watchpoint::WatchHit;
+use chip8emu::tutorial::{self, Annotations};
+
+// Modules From Crates.io //
+use log::LevelFilter;
+
+const USAGE: &str = "Usage: clip8 <command> [options]
+
+Commands:
+  run <rom> [--frames N] [--cycles N|auto] [--speed IPS] [--headless]
+            [--dump-frames DIR] [--dump-scale N] [--fg COLOR]
+            [--bg COLOR]
+            [--bell] [--mute] [--sound-config FILE] [--exit-code N]
+            [--exit-on-jump-self] [--notify] [--stats-csv FILE]
+            [--write-guard off|warn|block] [--extended-memory]
+            [--monitor] [--monitor-tty PATH] [--realtime-timers]
+            [--font vip|schip|dream6800|octo] [--jitter] [--kiosk]
+            [--serve-mjpeg ADDR] [--stream-scale N] [--palette NAME]
+            [--announce] [--source-map FILE] [--watch-mem RANGE]...
+            [--ghost FILE] [--no-archive] [--resolution WxH]
+            [--no-runaway-check] [--state-dump FILE] [--crash-dir DIR]
+            [--input-polls N] [--backend print|ansi|debug]
+            [--keyboard] [--keymap FILE] [--map-key KEY=NAME]...
+            [--mouse-keypad] [--keypad-overlay] [--onion N]
+            [--pause-unfocused]
+            [--variant chip8|schip|xochip] [--quirks-config FILE]
+            [--quirk NAME]... [--no-quirk NAME]...
+            [--profile vip|chip48|schip|xochip] [--state-file FILE]
+            [--rewind SECONDS] [--record-input FILE] [--replay FILE]
+            [--seed N] [--trace] [--trace-file FILE]
+            [--timing instructions|vip] [--slow-motion PERCENT]
+      Run a ROM, drawing the display in the terminal. Unless --cycles
+      is given the opcodes run per frame are guessed from the ROM,
+      --speed gives the rate as instructions per second instead, frames
+      alternating lengths when it is not a multiple of 60 so every
+      second runs exactly that many. --headless runs as fast as
+      possible without drawing, --dump-frames writes every frame
+      to DIR as frame_000001.png, frame_000002.png, ... The sound timer
+      beeps through aplay, paplay or SoX's play, whichever is installed,
+      unless --mute is given, with the waveform (square, sine or
+      triangle), frequency and volume set in the sound config file
+      (clip8/sound in the configuration directory or --sound-config).
+      --bell rings the terminal bell when the sound timer starts. The
+      session ends when the ROM exits with 00FD, or also when it jumps
+      to itself with --exit-on-jump-self, the process then exits with
+      --exit-code (0-255, default 0) and --notify shows a desktop
+      notification. --stats-csv logs per frame instruction counts,
+      frame duration, timer values and draw counts to a CSV file.
+      --write-guard reports (warn) or drops (block) writes below 0x200
+      and --extended-memory gives the ROM the 64 KB XO-CHIP address
+      space. --monitor reads commands (regs, stack, peek, poke, hex,
+      find, key, watch, pause, continue, speed, reset, quit, help) from
+      stdin while the game runs, --monitor-tty reads and answers them
+      on another terminal such as /dev/pts/3.
+      --realtime-timers counts the delay and sound timers down at 60 Hz
+      of real time instead of once per frame. --font picks the style
+      of the built in font (default schip) and --jitter prints frame
+      pacing statistics with a histogram at exit. --kiosk is for
+      exhibition builds: the ROM restarts instead of exiting and the
+      monitor, frame dumps and telemetry files are refused. With
+      --keyboard Ctrl+C and the hotkeys are ignored, only Ctrl+Alt+Q
+      quits.
+      --serve-mjpeg streams the display over HTTP on ADDR (such as
+      127.0.0.1:8080) for browsers and OBS, each chip8 pixel drawn as
+      N by N pixels (default 8); with --headless it still runs at 60
+      fps.
+      --palette picks a named color preset (white, green, amber, lcd,
+      paper or contrast for high contrast) and --announce writes game
+      events (sound started, screen cleared, waiting for a key) to
+      stderr as text for screen readers. --source-map loads a map
+      written by asm --map so the monitor shows source lines (where)
+      and takes addresses as FILE:LINE. --watch-mem logs changes to a
+      range of memory (0x300..0x310, 0x300..=0x30f or 0x300) to stderr
+      once per frame with the old and new values and the writing opcode.
+      --ghost plays a recording (a demo file of 'frame key down|up'
+      lines) of an earlier run faintly behind the live game to race it.
+      Settings from CHIP-8 Archive metadata (game.json next to the ROM
+      or a programs.json listing it) are applied unless --no-archive
+      is given, options given on the command line take precedence.
+      --resolution sets the size of the display, such as 64x64 (hi-res
+      CHIP-8), instead of the original 64x32, SCHIP and XO-CHIP
+      programs switch sizes themselves and refuse it. The run
+      stops with the recent program counters if execution leaves the
+      ROM or reaches a 0000 word, --no-runaway-check allows it for
+      programs that run code they write past their own end. On Unix
+      sending SIGUSR1 (kill -USR1 PID) writes the registers, stack,
+      code around PC and the display to stderr, or appended to
+      --state-dump FILE, while the game keeps running. A run stopped by
+      an error writes a crash report (ROM hash, settings, machine state
+      and the last instructions) to --crash-dir (default the current
+      directory) as clip8-crash-TIME.txt to attach to bug reports.
+      --input-polls reads input (the monitor) N times a frame (default
+      1) between even slices of the frame's opcodes, so fast ROMs see
+      keys sooner. --backend ansi draws on the terminal's alternate
+      screen and rewrites only the cells that changed, instead of
+      printing every changed frame (print, the default). --backend
+      debug shows the registers, timers, call stack and disassembly
+      around PC beside the display, updated every frame. --keyboard
+      plays with the keyboard of the terminal (1234/QWER/ASDF/ZXCV by
+      default, Ctrl+C quits), --keymap loads another mapping and
+      --map-key changes one keypad key, such as 5=up. --mouse-keypad
+      draws the hex keypad below the display of --backend ansi, where
+      it can be played with the mouse. --keypad-overlay draws the
+      keypad over the corner of the --backend ansi display in color,
+      held keys lit and the border red while FX0A waits for a key;
+      with --keyboard the keypad hotkey (F1) shows and hides it and
+      its keys can be clicked with the mouse. --onion draws the last N
+      frames (1 to 60) faded behind the --backend ansi display, older
+      ones fainter, to follow movement with --slow-motion.
+      --pause-unfocused stops the game and its sound while the
+      terminal is in the background, for terminals that report focus.
+      --variant schip runs SUPER-CHIP programs: hi-res 128x64 mode,
+      scrolling, 16x16 sprites and the RPL flags, xochip adds two
+      display planes, register ranges and the 64 KB address space,
+      chip8 (the default) decodes only the original instruction set.
+      --quirk and --no-quirk turn one interpreter quirk on or off
+      (shift_uses_vy, load_store_incr_i, jump_uses_vx, vf_reset,
+      clip_sprites, add_i_sets_vf), on top of clip8/quirks in the
+      configuration directory or --quirks-config.
+      --profile runs the ROM like a historical platform, setting the
+      quirks, speed, variant, memory size and font all at once. With
+      --keyboard the save_state and load_state hotkeys (F5 and F9 by
+      default) save and restore the machine in --state-file, the ROM
+      path with .state added unless given. Holding the rewind hotkey
+      (backspace) steps back through the last --rewind seconds of play
+      (default 10, 0 turns it off). --record-input writes every keypad
+      press and release with its frame, the speed and the random seed
+      to FILE when the run ends, --replay plays such a file back
+      exactly and prints the final display hash for regression tests.
+      --seed starts the random numbers of CXKK from N so runs repeat,
+      otherwise the seed is picked at random and a crash report has it.
+      --trace logs every instruction run (address, opcode, mnemonic and
+      the registers it changed) to stderr, --trace-file to FILE.
+      --timing vip runs each frame for the machine cycles a COSMAC VIP
+      had, each instruction taking as long as it did there and DXYN
+      waiting for the display interrupt, so music and timing sensitive
+      ROMs run at the original speed (pair it with --profile vip).
+      It replaces --cycles and --speed, instructions (the default)
+      runs the same number of opcodes every frame. With --keyboard the
+      turbo hotkey (tab) runs frames as fast as possible, drawing the
+      display 60 times a second, slow_motion (F3) runs them at
+      --slow-motion percent of normal speed (default 10) and
+      normal_speed (F4) goes back; each hotkey pressed again also
+      returns to normal speed. The menu hotkey (escape) pauses the
+      game under a menu drawn over --backend ansi or debug, the arrow
+      keys and enter pick resume, reset, a recent ROM, save or load
+      state, settings (speed, quirks, pausing in the background) or
+      quit, escape goes back. The menu is off while recording or
+      replaying input and in kiosk mode.
+  netplay <rom> (--host ADDR | --join ADDR) [--keys LIST] [--delay N]
+          [--cycles N] [--seed N] [--frames N] [--demo FILE] [--monitor]
+          [--headless]
+      Play a ROM with someone on another machine, both running it in
+      lockstep. --host waits on ADDR (such as 0.0.0.0:7777) and --join
+      connects to it, the host's settings are used by both. The host
+      owns the keys in LIST (default 1,4, the left paddle of Pong) and
+      the other player the rest. Keys reach the game --delay frames
+      (default 3, at most 180) after they are pressed to hide network
+      lag. Keys are pressed with the monitor's key command or played
+      from a demo file. The session stops if the two machines stop
+      matching
+  tutorial [<rom> --notes FILE] [--source]
+      Step through a ROM one instruction at a time, explaining each one
+      and what it changed. Without a ROM a small bundled program is
+      walked through with notes for every instruction, --source prints
+      its assembly. Notes for other ROMs are written one per address
+      ('0x200 text', indented lines continue the text)
+  explain <rom> [--delay MS] [--variant chip8|schip|xochip]
+          [--quirk NAME]... [--no-quirk NAME]...
+      Run a ROM one opcode at a time, printing each one before it runs
+      with a plain english explanation using the registers of the
+      moment, such as 'V3 = V3 + 0x05 (0x10 -> 0x15)'. --delay waits
+      MS milliseconds between opcodes (default 500), the variant and
+      quirks change the explanations the way they change the program
+  teach <rom> [--variant chip8|schip|xochip] [--quirk NAME]...
+          [--no-quirk NAME]...
+      Walk through a ROM one cycle at a time, showing the fetch, decode
+      and execute stages of each opcode in turn with the registers and
+      memory they change. Enter moves to the next stage, q quits
+  corrupt <rom> [--seed N] [--range RANGE]... [--count N]
+          [--mode flip|random] [--resets N] [--frames N] [--cycles N]
+          [--headless] [-o FILE]
+      Glitch a ROM by changing --count bytes (default 4) before each
+      run, flipping a bit of each (default) or randomizing them, only
+      within the ranges given (such as 0x300..0x340) if any. The seed
+      and run number pick the bytes so a glitch can be found again.
+      The ROM is run --resets times (default 1) for N frames (default
+      600) each and how every run ended is reported, crashes included.
+      -o writes the first run's corrupted ROM instead
+  test-script <script>... [--rom FILE]
+      Run end to end acceptance tests for a ROM. Scripts are statements
+      separated by lines or ';' such as 'rom game.ch8', 'cycles 10',
+      'press 5', 'run 120 frames', 'release 5', 'assert pixel 10,4 on',
+      'assert V3 == 2', 'assert mem 0x300 != 0', 'assert screen-hash
+      3fa2c1', 'assert exited' and 'print screen-hash'. The ROM is the
+      script's rom line, relative to the script, unless --rom is given.
+      Exits with 1 if any assertion fails
+  svg <rom> [--frames N] [--cycles N] [--scale N] [--fg COLOR]
+      [--bg COLOR] [-o FILE]
+      Run a ROM for N frames (default 60) then export the display as an
+      SVG image, colors are given as #rrggbb
+  text <rom> [--frames N] [--cycles N] [--charset ascii|blocks|half]
+       [--on CHAR] [--off CHAR] [--no-border] [--scale N] [--describe]
+       [-o FILE]
+      Run a ROM for N frames (default 60) then export the display as
+      text for bug reports and documentation. --describe lists which
+      parts of the screen are lit in words instead, for screen readers
+  trim <rom> [--align N] [--pad-to N] [-o FILE] [--in-place]
+      Strip trailing zero padding from a ROM and optionally pad it to a
+      multiple of N bytes or a minimum size, so copies of a game padded
+      differently hash the same. Without -o or --in-place only reports
+      what would change
+  multicart <rom> <rom>... -o FILE
+      Combine up to 5 small ROMs into one with a menu listing them by
+      file name, 5 and 8 move the cursor and 6 starts a game. Games are
+      moved to new addresses, ROMs that can not be moved safely are
+      refused with the reason
+  disasm <rom> [--entry ADDR]... [--check] [--range RANGE] [-o FILE]
+      Disassemble a ROM, following the code from 0x200 so sprites and
+      other data are listed as bytes instead of nonsense instructions.
+      --entry adds an address known to hold code, it can be repeated.
+      The output can be fed back to asm, --check verifies that it
+      assembles back to the same ROM. --range lists a range of memory
+      (0x200..0x220, 0x000..=0x04f) opcode by opcode with the raw bytes
+      instead, the font included, without telling code from data
+  asm <source>... [-o FILE] [--map FILE] [-c]
+      Assemble a program written with Cowgod's mnemonics (CLS, LD V0,
+      0x05, DRW V0, V1, 5, ...), labels and the ORG, DB and DW
+      directives into a ROM (default output is the source name with
+      .ch8). SPRITE ..####.. writes a sprite row, # lit and . dark.
+      MACRO name params ... ENDM defines a macro and REPT count ...
+      ENDR repeats lines, @ gives each copy unique labels.
+      NAME EQU value defines a constant, operands can be expressions
+      such as SCREEN_W / 2 and INCLUDE \"file\" inserts another file.
+      --map writes which source line each address came from. Several
+      sources are linked into one ROM in the order given, each file's
+      labels are private unless exported with GLOBAL name. -c writes
+      each source as a relocatable fragment (source.o8) instead
+  link <fragment>... -o FILE [--map FILE]
+      Link fragments written by asm -c into one ROM, the first one
+      given is placed at 0x200
+  sprite-from-image <png> [--width 8|16] [--height N] [--threshold N]
+                    [--dither] [--invert] [--format hex|asm|octo]
+                    [--name NAME] [-o FILE]
+      Turn an image into sprites, lighting pixels at least as bright as
+      the threshold (default halfway between the darkest and brightest
+      pixel) or dark ones with --invert. --dither
+      suits photos. Images larger than one sprite (8 by up to 15 rows,
+      or 16x16) are cut into several. Output is hex bytes, DB lines for
+      asm (default) or Octo, labelled with NAME (default the file name)
+  diff <a> <b>
+      Compare two ROMs byte by byte, showing each changed range with
+      the instructions around it disassembled side by side
+  callgraph <rom> [--frames N] [--cycles N] [--json] [-o FILE]
+      Export which subroutines call which as a Graphviz graph, or JSON
+      with --json. Calls are found by following the code, with
+      --frames the ROM is also run and each call counted
+  gen-stress --seed N [--blocks N] [--depth N] [-o FILE]
+      Generate an adversarial stress ROM (default output stress_N.ch8)
+  quirks <rom> [--frames N] [--cycles N]
+      Run a ROM under every quirk combination and report which change it
+  compare <rom> --a-quirks LIST --b-quirks LIST [--frames N]
+          [--cycles N]
+      Run a ROM on two systems in lockstep, A with the quirks in LIST
+      turned on (comma separated, none for all off) and B with its own,
+      until their displays differ or N frames (default 600) have run.
+      Both displays are printed side by side, differing pixels as * and
+      ., with the first frame they diverged on
+  heatmap <rom> [--frames N] [--cycles N] [--png FILE] [--scale N]
+          [--pixels]
+      Run a ROM and show how often each memory address is read,
+      written and executed, in the terminal or as a PNG. With --pixels
+      show how often each display pixel flickers instead
+  trace <rom> [--frames N] [--cycles N] [-o FILE]
+      Run a ROM for N frames (default 60) writing an execution trace in
+      the register format of Octo's debugger, one line per opcode
+  memory-dump <rom> -o FILE [--frames N] [--cycles N] [--range RANGE]
+              [--format bin|ihex]
+      Run a ROM for N frames (default 0) then write memory to FILE, all
+      of it or a range such as 0x200..0x300, as raw binary (default) or
+      Intel HEX for EEPROM programmers. ROMs ending in .hex, .ihex or
+      .ihx are read as Intel HEX by every command
+  stats <rom> [--frames N] [--cycles N] [--every N]
+      Run a ROM and print drawing statistics every N frames (default 1)
+  recent [N] [run options]
+      List the ROMs recently opened with run and pick one to relaunch
+      with the same settings, or relaunch entry N directly. Other
+      options are passed on to run. Also available as clip8 --recent
+  attract <dir> [--seconds N] [--cycles N] [--once]
+      Attract mode for idle screens: run each ROM in a directory for N
+      seconds (default 30) then move on to the next, starting over at
+      the end unless --once is given. A ROM with a demo file next to
+      it (game.ch8.demo, lines of 'frame key down|up') plays it back
+  hotkeys
+      Print the hotkey bindings used by the graphical frontends, in the
+      format of the hotkeys config file they are loaded from
+  keymap [--keymap FILE]
+      Print the keys pressing each keypad key with run --keyboard, in
+      the format of the keypad config file they are loaded from
+";
+
+/// Error ending a command
+#[derive(Debug)]
+enum CliError {
+    /// The command line could not be parsed, the usage is printed
+    /// after it
+    Usage(String),
+    /// The command failed while running
+    Runtime(Box<dyn Error>)
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Runtime(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Runtime(message.into())
+    }
+}
+
+/// Command line arguments left after the command name
+struct Args {
+    args: Vec<String>
+}
+
+impl Args {
+    /// Remove `--name value` from the arguments, returning the value
+    fn value(&mut self, names: &[&str]) -> Result<Option<String>, CliError> {
+        match self.args.iter().position(|a| names.contains(&a.as_str())) {
+            Some(pos) => {
+                if pos + 1 >= self.args.len() {
+                    return Err(CliError::Usage(format!("missing value for {}", self.args[pos])));
+                }
+                let value = self.args.remove(pos + 1);
+                self.args.remove(pos);
+                Ok(Some(value))
+            },
+            None => Ok(None)
+        }
+    }
+
+    /// Remove `--name value` and parse the value as a number
+    fn number(&mut self, names: &[&str]) -> Result<Option<u64>, CliError> {
+        match self.value(names)? {
+            Some(v) => parse_number(&v).map(Some).map_err(CliError::Usage),
+            None => Ok(None)
+        }
+    }
+
+    /// Remove `--cycles N` and check the opcodes per frame it gives
+    fn cycles(&mut self) -> Result<Option<u32>, CliError> {
+        match self.value(&["--cycles"])? {
+            Some(v) => parse_cycles(&v).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    /// Remove a `--name` flag, returning true if it was present
+    fn flag(&mut self, names: &[&str]) -> bool {
+        match self.args.iter().position(|a| names.contains(&a.as_str())) {
+            Some(pos) => {
+                self.args.remove(pos);
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Remove the first positional argument
+    fn positional(&mut self, what: &str) -> Result<String, CliError> {
+        match self.args.iter().position(|a| !a.starts_with('-')) {
+            Some(pos) => Ok(self.args.remove(pos)),
+            None => Err(CliError::Usage(format!("missing {}", what)))
+        }
+    }
+
+    /// Fail if any arguments were not used
+    fn finish(&self) -> Result<(), CliError> {
+        match self.args.first() {
+            Some(arg) => Err(CliError::Usage(format!("unexpected argument: {}", arg))),
+            None => Ok(())
+        }
+    }
+}
+
+/// Parse a decimal or 0x prefixed hex number
+fn parse_number(value: &str) -> Result<u64, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse::<u64>()
+    };
+    parsed.map_err(|_| format!("invalid number: {}", value))
+}
+
+/// Parse the opcodes per frame given with --cycles, at least 1 and at
+/// most u32::MAX
+fn parse_cycles(value: &str) -> Result<u32, CliError> {
+    match parse_number(value).map_err(CliError::Usage)? {
+        0 => Err(CliError::Usage(String::from("--cycles must be at least 1"))),
+        cycles => u32::try_from(cycles).map_err(|_| CliError::Usage(format!("--cycles {} is too large, at most {}", cycles, u32::MAX)))
+    }
+}
+
+/// Read a ROM file from disk
+fn read_rom(path: &str) -> Result<Vec<u8>, String> {
+    match ihex::is_ihex_path(Path::new(path)) {
+        true => fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {}", path, e))
+            .and_then(|text| ihex::decode_rom(&text).map_err(|e| format!("{}: {}", path, e))),
+        false => fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))
+    }
+}
+
+/// Read a ROM from disk and load it, printing any warnings about it
+fn load_rom(sys: &mut ChipSystem, path: &str) -> Result<(), String> {
+    let warnings = sys.load_rom(read_rom(path)?).map_err(|e| format!("could not load {}: {}", path, e))?;
+    for warning in warnings {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    Ok(())
+}
+
+/// Options of clip8 run, all checked against each other by
+/// `run_options` before anything is opened
+struct RunOptions {
+    /// ROM to run
+    path: String,
+    /// --frames, None runs until the ROM exits
+    frames: Option<u64>,
+    /// --cycles, a number or auto, also set from --speed
+    cycles_arg: Option<String>,
+    /// --speed in instructions per second
+    speed_ips: Option<u32>,
+    /// --headless
+    headless: bool,
+    /// --backend, one of print, ansi or debug
+    backend: Option<String>,
+    /// --dump-frames
+    dump_dir: Option<String>,
+    /// --dump-scale
+    dump_scale: usize,
+    /// --palette, --fg and --bg
+    palette: Palette,
+    /// True if any of them were given, they then win over the archive
+    /// metadata
+    palette_given: bool,
+    /// --bell
+    bell: bool,
+    /// --mute
+    mute: bool,
+    /// --sound-config
+    sound_config: Option<String>,
+    /// --exit-code
+    exit_code: u8,
+    /// --notify
+    notify_exit: bool,
+    /// --stats-csv
+    csv_path: Option<String>,
+    /// --write-guard
+    guard: WriteGuard,
+    /// --extended-memory
+    extended: bool,
+    /// --monitor
+    monitor_stdin: bool,
+    /// --monitor-tty
+    monitor_tty: Option<String>,
+    /// --keyboard
+    keyboard_input: bool,
+    /// --mouse-keypad
+    mouse_keypad: bool,
+    /// --pause-unfocused
+    pause_unfocused: bool,
+    /// --keypad-overlay
+    keypad_overlay: bool,
+    /// --onion, 1 to 60 frames
+    onion: Option<u64>,
+    /// --keymap
+    keymap_path: Option<String>,
+    /// Every --map-key
+    key_bindings: Vec<String>,
+    /// --quirks-config
+    quirks_config: Option<String>,
+    /// Every --quirk (true) and --no-quirk (false)
+    quirk_changes: Vec<(String, bool)>,
+    /// --source-map
+    source_map: Option<String>,
+    /// Every --watch-mem
+    watch_ranges: Vec<WatchRange>,
+    /// --ghost
+    ghost_path: Option<String>,
+    /// False with --no-runaway-check
+    runaway_check: bool,
+    /// --exit-on-jump-self
+    exit_on_jump_self: bool,
+    /// --state-dump
+    state_dump_path: Option<String>,
+    /// --crash-dir
+    crash_dir: Option<String>,
+    /// --state-file
+    state_file: Option<String>,
+    /// --rewind
+    rewind_seconds: Option<u64>,
+    /// --record-input
+    record_path: Option<String>,
+    /// --replay
+    replay_path: Option<String>,
+    /// --seed
+    seed_arg: Option<u64>,
+    /// --trace
+    trace_stderr: bool,
+    /// --trace-file
+    trace_path: Option<String>,
+    /// --input-polls, at least 1
+    input_polls: u32,
+    /// --slow-motion, 1 to 99 percent
+    slow_percent: Option<u64>,
+    /// --timing
+    timing: TimingMode,
+    /// --resolution
+    resolution: Option<(usize, usize)>,
+    /// --realtime-timers
+    realtime: bool,
+    /// --jitter
+    jitter: bool,
+    /// --font
+    fontset: Option<Fontset>,
+    /// --profile
+    profile: Option<&'static Profile>,
+    /// --variant
+    variant: Option<Variant>,
+    /// False with --no-archive
+    use_archive: bool,
+    /// --kiosk
+    kiosk: bool,
+    /// --announce
+    announce: bool,
+    /// --serve-mjpeg
+    stream_addr: Option<String>,
+    /// --stream-scale
+    stream_scale: usize
+}
+
+/// Settings of the machine clip8 run emulates, from its options, the
+/// ROM's archive metadata and the profile
+struct RunMachine {
+    /// Interpreter quirks
+    quirks: Quirks,
+    /// Instruction set
+    variant: Variant,
+    /// Built in font
+    fontset: Fontset,
+    /// True for the 64 KB XO-CHIP address space
+    extended: bool,
+    /// Size of the display
+    resolution: (usize, usize),
+    /// Colors of the display
+    palette: Palette,
+    /// Opcodes per frame from the archive metadata or profile
+    default_cycles: Option<u32>
+}
+
+/// Parse the options of clip8 run, failing on values and combinations
+/// that can not be used
+fn run_options(args: &mut Args) -> Result<RunOptions, CliError> {
+    let frames = args.number(&["--frames"])?;
+    let mut cycles_arg = args.value(&["--cycles"])?;
+    let speed_ips = match args.number(&["--speed"])? {
+        Some(_) if cycles_arg.is_some() => return Err(CliError::Usage(String::from("give one of --cycles or --speed"))),
+        Some(speed) if speed < 60 => {
+            return Err(CliError::Usage(format!("--speed {} is too slow, at least 60 instructions per second (one per frame) are needed", speed)));
+        },
+        Some(speed) => Some(u32::try_from(speed).map_err(|_| CliError::Usage(format!("--speed {} is too fast, at most {}", speed, u32::MAX)))?),
+        None => None
+    };
+    if let Some(ips) = speed_ips {
+        // The nearest whole number of opcodes per frame stands for the
+        // speed where one is needed, frames still run `ips` a second
+        cycles_arg = Some(((ips + 30) / 60).to_string());
+    }
+    if let Some(value) = cycles_arg.as_deref().filter(|value| *value != "auto") {
+        parse_cycles(value)?;
+    }
+    let headless = args.flag(&["--headless"]);
+    let backend = args.value(&["--backend"])?;
+    if let Some(other) = backend.as_deref().filter(|backend| !["print", "ansi", "debug"].contains(backend)) {
+        return Err(CliError::Usage(format!("unknown backend '{}', expected print, ansi or debug", other)));
+    }
+    let dump_dir = args.value(&["--dump-frames"])?;
+    let dump_scale = args.number(&["--dump-scale"])?.unwrap_or(1) as usize;
+    let palette_given = args.args.iter().any(|a| ["--palette", "--fg", "--bg"].contains(&a.as_str()));
+    let palette = palette_args(args)?;
+    let bell = args.flag(&["--bell"]);
+    let mute = args.flag(&["--mute"]);
+    let sound_config = args.value(&["--sound-config"])?;
+    let exit_code = match args.number(&["--exit-code"])? {
+        Some(code) => u8::try_from(code).map_err(|_| CliError::Usage(format!("--exit-code {} is out of range, expected 0-255", code)))?,
+        None => 0
+    };
+    let notify_exit = args.flag(&["--notify"]);
+    let csv_path = args.value(&["--stats-csv"])?;
+    let guard = match args.value(&["--write-guard"])?.as_deref() {
+        None | Some("off") => WriteGuard::Off,
+        Some("warn") => WriteGuard::Warn,
+        Some("block") => WriteGuard::Block,
+        Some(other) => return Err(CliError::Usage(format!("unknown write guard '{}', expected off, warn or block", other)))
+    };
+    let extended = args.flag(&["--extended-memory"]);
+    let monitor_stdin = args.flag(&["--monitor"]);
+    let monitor_tty = args.value(&["--monitor-tty"])?;
+    let keyboard_input = args.flag(&["--keyboard"]);
+    let mouse_keypad = args.flag(&["--mouse-keypad"]);
+    let pause_unfocused = args.flag(&["--pause-unfocused"]);
+    let keypad_overlay = args.flag(&["--keypad-overlay"]);
+    let onion = args.number(&["--onion"])?;
+    let keymap_path = args.value(&["--keymap"])?;
+    let mut key_bindings = Vec::new();
+    while let Some(binding) = args.value(&["--map-key"])? {
+        key_bindings.push(binding);
+    }
+    let quirks_config = args.value(&["--quirks-config"])?;
+    let mut quirk_changes = Vec::new();
+    while let Some(name) = args.value(&["--quirk"])? {
+        quirk_changes.push((name, true));
+    }
+    while let Some(name) = args.value(&["--no-quirk"])? {
+        quirk_changes.push((name, false));
+    }
+    if let Some((name, _)) = quirk_changes.iter().find(|(name, _)| !QUIRK_NAMES.contains(&name.as_str())) {
+        return Err(CliError::Usage(format!("unknown quirk '{}', expected one of {}", name, QUIRK_NAMES.join(", "))));
+    }
+    let source_map = args.value(&["--source-map"])?;
+    let mut watch_ranges = Vec::new();
+    while let Some(range) = args.value(&["--watch-mem"])? {
+        watch_ranges.push(WatchRange::parse(&range).map_err(CliError::Usage)?);
+    }
+    let ghost_path = args.value(&["--ghost"])?;
+    let runaway_check = !args.flag(&["--no-runaway-check"]);
+    let exit_on_jump_self = args.flag(&["--exit-on-jump-self"]);
+    let state_dump_path = args.value(&["--state-dump"])?;
+    let crash_dir = args.value(&["--crash-dir"])?;
+    let state_file = args.value(&["--state-file"])?;
+    let rewind_seconds = args.number(&["--rewind"])?;
+    let record_path = args.value(&["--record-input"])?;
+    let replay_path = args.value(&["--replay"])?;
+    let seed_arg = args.number(&["--seed"])?;
+    let trace_stderr = args.flag(&["--trace"]);
+    let trace_path = args.value(&["--trace-file"])?;
+    let input_polls = args.number(&["--input-polls"])?.unwrap_or(1).max(1) as u32;
+    let slow_percent = args.number(&["--slow-motion"])?;
+    let timing = match args.value(&["--timing"])? {
+        Some(name) => TimingMode::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = TIMING_MODES.iter().map(|(_, name)| *name).collect();
+            CliError::Usage(format!("unknown timing '{}', expected one of {}", name, names.join(", ")))
+        })?,
+        None => TimingMode::default()
+    };
+    let resolution = match args.value(&["--resolution"])? {
+        Some(text) => Some(parse_resolution(&text).map_err(CliError::Usage)?),
+        None => None
+    };
+    let realtime = args.flag(&["--realtime-timers"]);
+    let jitter = args.flag(&["--jitter"]);
+    let fontset = match args.value(&["--font"])? {
+        Some(name) => Some(Fontset::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = FONTSETS.iter().map(|(_, name)| *name).collect();
+            CliError::Usage(format!("unknown font '{}', expected one of {}", name, names.join(", ")))
+        })?),
+        None => None
+    };
+    let profile = match args.value(&["--profile"])? {
+        Some(name) => Some(Profile::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = PROFILES.iter().map(|profile| profile.name).collect();
+            CliError::Usage(format!("unknown profile '{}', expected one of {}", name, names.join(", ")))
+        })?),
+        None => None
+    };
+    let variant = match args.value(&["--variant"])? {
+        Some(name) => Some(Variant::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = VARIANTS.iter().map(|(_, name)| *name).collect();
+            CliError::Usage(format!("unknown variant '{}', expected one of {}", name, names.join(", ")))
+        })?),
+        None => None
+    };
+    let use_archive = !args.flag(&["--no-archive"]);
+    let kiosk = args.flag(&["--kiosk"]);
+    let announce = args.flag(&["--announce"]);
+    let stream_addr = args.value(&["--serve-mjpeg"])?;
+    let stream_scale = args.number(&["--stream-scale"])?.unwrap_or(8) as usize;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+    if kiosk && (dump_dir.is_some() || csv_path.is_some() || state_dump_path.is_some() || crash_dir.is_some() || trace_path.is_some() || monitor_stdin || monitor_tty.is_some()) {
+        return Err(CliError::Usage(String::from("--kiosk can not be used with the monitor or options that write files")));
+    }
+    if source_map.is_some() && !monitor_stdin && monitor_tty.is_none() {
+        return Err(CliError::Usage(String::from("--source-map is used by the monitor, add --monitor or --monitor-tty")));
+    }
+    if (keymap_path.is_some() || !key_bindings.is_empty()) && !keyboard_input {
+        return Err(CliError::Usage(String::from("--keymap and --map-key are used by --keyboard")));
+    }
+    if mouse_keypad && (!keyboard_input || backend.as_deref() != Some("ansi")) {
+        return Err(CliError::Usage(String::from("--mouse-keypad needs --keyboard and --backend ansi")));
+    }
+    if keypad_overlay && backend.as_deref() != Some("ansi") {
+        return Err(CliError::Usage(String::from("--keypad-overlay is drawn by --backend ansi")));
+    }
+    match onion {
+        Some(_) if backend.as_deref() != Some("ansi") => return Err(CliError::Usage(String::from("--onion is drawn by --backend ansi"))),
+        Some(depth) if !(1..=60).contains(&depth) => return Err(CliError::Usage(format!("--onion {} must be between 1 and 60 frames", depth))),
+        _ => {}
+    }
+    if keyboard_input && monitor_stdin {
+        return Err(CliError::Usage(String::from("--keyboard and --monitor both read stdin, use --monitor-tty for the monitor")));
+    }
+    // Keys are recorded and replayed a frame at a time against timers
+    // counted in frames, anything else would not play back the same
+    if (record_path.is_some() || replay_path.is_some()) && (realtime || input_polls > 1 || kiosk) {
+        return Err(CliError::Usage(String::from("--record-input and --replay can not be used with --realtime-timers, --input-polls or --kiosk")));
+    }
+    if replay_path.is_some() && (record_path.is_some() || keyboard_input || cycles_arg.is_some() || seed_arg.is_some()) {
+        return Err(CliError::Usage(String::from("--replay presses the keys and sets the speed and seed, it can not be used with --record-input, --keyboard, --cycles or --seed")));
+    }
+    // VIP timing decides how much runs in a frame on its own and a
+    // recording plays back at a number of opcodes per frame
+    if timing == TimingMode::Vip && (cycles_arg.is_some() || input_polls > 1 || record_path.is_some() || replay_path.is_some()) {
+        return Err(CliError::Usage(String::from("--timing vip can not be used with --cycles, --speed, --input-polls, --record-input or --replay")));
+    }
+    if speed_ips.is_some_and(|ips| ips % 60 != 0) && record_path.is_some() {
+        return Err(CliError::Usage(String::from("--record-input needs the same opcodes every frame, give --speed as a multiple of 60 or --cycles")));
+    }
+    if trace_stderr && trace_path.is_some() {
+        return Err(CliError::Usage(String::from("give one of --trace or --trace-file")));
+    }
+    if rewind_seconds.is_some() && !keyboard_input {
+        return Err(CliError::Usage(String::from("--rewind is used by --keyboard")));
+    }
+    match slow_percent {
+        Some(percent) if !keyboard_input => return Err(CliError::Usage(format!("--slow-motion {} is used by --keyboard", percent))),
+        Some(percent) if !(1..100).contains(&percent) => return Err(CliError::Usage(format!("--slow-motion {} must be between 1 and 99 percent", percent))),
+        _ => {}
+    }
+    if pause_unfocused && !keyboard_input {
+        return Err(CliError::Usage(String::from("--pause-unfocused is used by --keyboard")));
+    }
+    Ok(RunOptions {
+        path, frames, cycles_arg, speed_ips, headless, backend, dump_dir, dump_scale, palette, palette_given,
+        bell, mute, sound_config, exit_code, notify_exit, csv_path, guard, extended, monitor_stdin, monitor_tty,
+        keyboard_input, mouse_keypad, pause_unfocused, keypad_overlay, onion, keymap_path, key_bindings,
+        quirks_config, quirk_changes, source_map, watch_ranges, ghost_path, runaway_check, exit_on_jump_self,
+        state_dump_path, crash_dir, state_file, rewind_seconds, record_path, replay_path, seed_arg, trace_stderr,
+        trace_path, input_polls, slow_percent, timing, resolution, realtime, jitter, fontset, profile, variant,
+        use_archive, kiosk, announce, stream_addr, stream_scale
+    })
+}
+
+/// Work out the machine clip8 run emulates from the options, the ROM's
+/// archive metadata and the profile
+fn run_machine(options: &RunOptions) -> Result<RunMachine, CliError> {
+    let archive = match options.use_archive {
+        // Broken metadata is not worth refusing to run the ROM over
+        true => ArchiveCatalog::lookup(Path::new(&options.path)).unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring archive metadata, {}", e);
+            None
+        }),
+        false => None
+    };
+    let mut quirks = load_quirks(options.quirks_config.clone())?;
+    let mut palette = options.palette;
+    let mut fontset = options.fontset;
+    let mut variant = options.variant;
+    let mut default_cycles = None;
+    if let Some((file, program)) = archive {
+        eprintln!("Using the settings for {} from {}", program.title, file.display());
+        if !options.palette_given {
+            palette = program.options.palette(palette);
+        }
+        fontset = fontset.or(program.options.font);
+        default_cycles = program.options.tickrate;
+        quirks = program.options.quirks;
+        variant = variant.or_else(|| program.platform.as_deref().and_then(Variant::from_name));
+    }
+    // A profile asked for wins over the archive metadata, options for
+    // single settings win over the profile
+    if let Some(profile) = options.profile {
+        fontset = fontset.or(Some(profile.fontset));
+        default_cycles = Some(profile.cycles);
+        quirks = profile.quirks;
+        variant = variant.or(Some(profile.variant));
+    }
+    for (name, enabled) in options.quirk_changes.iter() {
+        quirks.set(name, *enabled);
+    }
+    let variant = variant.unwrap_or_default();
+    // 00FE and 00FF switch between 64x32 and 128x64, which would throw
+    // away any other size
+    let resolution = match options.resolution {
+        Some(_) if variant.has_schip() => return Err(CliError::Usage(format!("--resolution can not be used with {} programs, they pick their own with 00FE and 00FF", variant.name()))),
+        Some(resolution) => resolution,
+        None => (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    };
+    Ok(RunMachine {
+        quirks,
+        variant,
+        fontset: fontset.unwrap_or_default(),
+        // XO-CHIP programs expect the whole 64 KB address space
+        extended: options.extended || variant.has_xochip() || options.profile.is_some_and(|profile| profile.extended_memory),
+        resolution,
+        palette,
+        default_cycles
+    })
+}
+
+/// Send the instruction trace of clip8 run where its options ask
+fn start_trace(options: &RunOptions) -> Result<(), String> {
+    let out: Box<dyn Write + Send> = match options.trace_path.as_ref() {
+        Some(trace_path) => Box::new(BufWriter::new(
+            File::create(trace_path).map_err(|e| format!("could not create {}: {}", trace_path, e))?
+        )),
+        None if options.trace_stderr => Box::new(io::stderr()),
+        None => return Ok(())
+    };
+    TraceLogger::install(out, LevelFilter::Trace).map_err(|e| format!("could not start the trace: {}", e))
+}
+
+/// Open the monitor of clip8 run on stdin or a terminal, if asked for
+///
+/// # Arguments
+///
+/// * `options` - options of the run
+/// * `source_map` - source lines the monitor shows addresses with
+fn open_monitor(options: &RunOptions, source_map: Option<SourceMap>) -> Result<Option<Monitor>, String> {
+    let mut monitor = match (options.monitor_tty.as_ref(), options.monitor_stdin) {
+        (Some(tty), _) => {
+            let input = File::open(tty).map_err(|e| format!("could not open {}: {}", tty, e))?;
+            let output = OpenOptions::new().write(true).open(tty).map_err(|e| format!("could not open {}: {}", tty, e))?;
+            Monitor::init(io::BufReader::new(input), output)
+        },
+        (None, true) => Monitor::stdin(),
+        (None, false) => return Ok(None)
+    };
+    if monitor.is_closed() {
+        return Err(String::from("the monitor needs threads, which this platform does not have"));
+    }
+    if let Some(source_map) = source_map {
+        monitor.set_source_map(source_map);
+    }
+    Ok(Some(monitor))
+}
+
+/// Start reading keys from the terminal for clip8 run --keyboard
+///
+/// # Arguments
+///
+/// * `options` - options of the run
+/// * `hotkeys` - hotkey bindings, dropped in kiosk mode
+fn open_keys(options: &RunOptions, hotkeys: HotkeyMap) -> Result<TerminalKeys, String> {
+    let mut input = TerminalKeys::init().map_err(|e| format!("could not read keys from the terminal: {}", e))?;
+    if options.mouse_keypad || options.keypad_overlay {
+        input.enable_mouse().map_err(|e| format!("could not turn on the mouse: {}", e))?;
+    }
+    // Kiosk mode allows no hotkeys, they save files and leave,
+    // the bindings are still needed to know which keys to drop
+    input.set_hotkeys(Some(hotkeys));
+    if options.kiosk {
+        input.set_kiosk(Some(Kiosk::terminal()));
+    }
+    Ok(input)
+}
+
+/// Create the frontend drawing the display for clip8 run
+fn open_frontend(options: &RunOptions, palette: Palette) -> Box<dyn Frontend> {
+    match options.backend.as_deref() {
+        Some("ansi") => {
+            let mut ansi = AnsiFrontend::init();
+            ansi.set_palette(palette);
+            if options.mouse_keypad {
+                ansi.show_keypad();
+            }
+            if options.keypad_overlay {
+                ansi.toggle_keypad();
+            }
+            if let Some(depth) = options.onion {
+                ansi.show_onion(depth as usize);
+            }
+            Box::new(ansi)
+        },
+        Some("debug") => Box::new(DebugFrontend::init()),
+        _ => Box::new(PrintFrontend)
+    }
+}
+
+/// clip8 run
+fn run(mut args: Args) -> Result<(), CliError> {
+    let options = run_options(&mut args)?;
+    let machine = run_machine(&options)?;
+    let mut palette = machine.palette;
+    // Every file is read and the ROM loaded before anything is created,
+    // listened on or changed in the terminal, so a mistake in them
+    // leaves nothing half done behind
+    let replay = match options.replay_path.as_ref() {
+        Some(replay_path) => {
+            let text = fs::read_to_string(replay_path).map_err(|e| format!("could not read {}: {}", replay_path, e))?;
+            Some(Replay::parse(&text).map_err(|e| format!("{}: {}", replay_path, e))?)
+        },
+        None => None
+    };
+    let seed = match replay.as_ref() {
+        Some(replay) => Some(replay.seed),
+        // A recording keeps one seed across resets
+        None => options.seed_arg.or_else(|| options.record_path.as_ref().map(|_| rand::random()))
+    };
+    let ghost_inputs = match options.ghost_path.as_ref() {
+        Some(demo_path) => {
+            let text = fs::read_to_string(demo_path).map_err(|e| format!("could not read {}: {}", demo_path, e))?;
+            Some(DemoInput::parse(&text).map_err(|e| format!("{}: {}", demo_path, e))?)
+        },
+        None => None
+    };
+    let source_map = match options.source_map.as_ref() {
+        Some(map_path) => {
+            let text = fs::read_to_string(map_path).map_err(|e| format!("could not read {}: {}", map_path, e))?;
+            Some(SourceMap::parse(&text).map_err(|e| format!("{}: {}", map_path, e))?)
+        },
+        None => None
+    };
+    let key_config = match options.keyboard_input {
+        true => {
+            let mut keymap = load_keymap(options.keymap_path.clone())?;
+            for binding in options.key_bindings.iter() {
+                keymap.apply(binding).map_err(|_| CliError::Usage(format!("invalid --map-key '{}', expected KEY=NAME such as 5=up", binding)))?;
+            }
+            Some((keymap, load_hotkeys()?))
+        },
+        false => None
+    };
+    let tone = match options.headless || options.mute {
+        true => None,
+        false => Some(load_tone(options.sound_config.clone())?)
+    };
+
+    let new_system = || -> Result<ChipSystem, String> {
+        let mut sys = ChipSystem::init();
+        sys.display.set_size(machine.resolution.0, machine.resolution.1);
+        sys.set_runaway_check(options.runaway_check);
+        sys.set_exit_on_jump_self(options.exit_on_jump_self);
+        sys.ram.set_guard(options.guard);
+        sys.ram.set_extended(machine.extended);
+        sys.ram.set_fontset(machine.fontset);
+        sys.quirks = machine.quirks;
+        sys.variant = machine.variant;
+        sys.set_timing(options.timing);
+        if options.realtime {
+            sys.registers.set_timer_mode(TimerMode::RealTime);
+        }
+        if let Some(seed) = seed {
+            sys.seed_random(seed);
+        }
+        load_rom(&mut sys, &options.path)?;
+        Ok(sys)
+    };
+    let mut sys = new_system()?;
+    let mut cycles = match (options.cycles_arg.as_deref(), machine.default_cycles) {
+        _ if replay.is_some() => replay.as_ref().map_or(1, |replay| replay.cycles),
+        // Not used, the cycles of each instruction set the pace
+        _ if options.timing == TimingMode::Vip => 1,
+        (None, Some(cycles)) => cycles,
+        (None, None) | (Some("auto"), _) => {
+            let rate = tickrate::guess(&read_rom(&options.path)?);
+            eprintln!("Running {} opcodes per frame, the ROM {}", rate.cycles, rate.reason);
+            rate.cycles
+        },
+        (Some(value), _) => parse_cycles(value)?
+    };
+    let new_ghost = |cycles: u32| -> Result<Option<GhostReplay>, String> {
+        let inputs = match ghost_inputs.as_ref() {
+            Some(inputs) => inputs.clone(),
+            None => return Ok(None)
+        };
+        let mut ghost = GhostReplay::init(read_rom(&options.path)?, inputs, cycles)
+            .map_err(|e| format!("could not load {}: {}", options.path, e))?;
+        ghost.system().ram.set_fontset(machine.fontset);
+        ghost.system().quirks = machine.quirks;
+        ghost.system().variant = machine.variant;
+        ghost.system().ram.set_extended(machine.extended);
+        ghost.system().display.set_size(machine.resolution.0, machine.resolution.1);
+        ghost.system().set_runaway_check(options.runaway_check);
+        ghost.system().set_exit_on_jump_self(options.exit_on_jump_self);
+        ghost.system().set_timing(options.timing);
+        Ok(Some(ghost))
+    };
+    let mut ghost = new_ghost(cycles)?;
+
+    start_trace(&options)?;
+    let mut dumper = match options.dump_dir.as_ref() {
+        Some(dir) => Some(
+            FrameDumper::init(Path::new(dir), options.dump_scale, palette)
+                .map_err(|e| format!("could not create {}: {}", dir, e))?
+        ),
+        None => None
+    };
+    let mut telemetry = match options.csv_path.as_ref() {
+        Some(csv) => Some(
+            File::create(csv)
+                .and_then(|file| CsvTelemetry::init(BufWriter::new(file)))
+                .map_err(|e| format!("could not create {}: {}", csv, e))?
+        ),
+        None => None
+    };
+    // Without the signal a hung instance can still be stopped, so a
+    // platform without it is not worth refusing to run over
+    let dump_signal = DumpSignal::install().ok();
+    let mut monitor = open_monitor(&options, source_map)?;
+    let mut stream = match options.stream_addr.as_ref() {
+        Some(addr) => {
+            let server = MjpegServer::init(addr).map_err(|e| format!("could not listen on {}: {}", addr, e))?;
+            eprintln!("Streaming the display at http://{}/stream", server.get_addr());
+            Some(server)
+        },
+        None => None
+    };
+    let mut keys = match key_config {
+        Some((keymap, hotkeys)) => Some((open_keys(&options, hotkeys)?, keymap)),
+        None => None
+    };
+
+    let mut watch = match options.watch_ranges.is_empty() {
+        true => None,
+        false => Some(MemoryWatch::init(options.watch_ranges.clone(), &sys))
+    };
+    // Rates that are not a multiple of 60 alternate frame lengths the
+    // way ChipSystem::run_at does, so each second runs `ips` opcodes
+    let mut clock = options.speed_ips.filter(|ips| ips % 60 != 0).map(TimerClock::init);
+    // The recent list reopens ROMs at a number of opcodes per frame
+    if !options.kiosk && options.timing == TimingMode::Instructions {
+        remember_rom(&options.path, cycles, palette, &sys);
+    }
+    let mut bell = TerminalBell::init(options.bell);
+    let mut player = match tone.map(PlayerAudio::start) {
+        Some(Ok(player)) => Some(player),
+        Some(Err(e)) => {
+            if !bell.enabled {
+                eprintln!("Running without sound, {} (--bell rings the terminal bell instead)", e);
+            }
+            None
+        },
+        None => None
+    };
+    let mut jitter = match options.jitter {
+        true => Some(FrameTiming::init()),
+        false => None
+    };
+    let mut announcer = match options.announce {
+        true => Some(Announcer::init()),
+        false => None
+    };
+    let mut frontend = open_frontend(&options, palette);
+    let state_path = PathBuf::from(options.state_file.clone().unwrap_or_else(|| format!("{}.state", options.path)));
+    let mut speed = SpeedControl::init(options.slow_percent.map_or(DEFAULT_SLOW_PERCENT, |percent| percent as u32));
+    // Stepping back in time would leave holes in a recording
+    let mut rewind = match options.rewind_seconds.unwrap_or(10) {
+        seconds if seconds > 0 && options.keyboard_input && !options.kiosk && options.record_path.is_none() => Some(RewindBuffer::init(seconds)),
+        _ => None
+    };
+    let mut recorder = options.record_path.as_ref().map(|_| InputRecorder::init());
+    let mut replay_input = replay.as_ref().map(Replay::input);
+    // The menu changes settings a recording or replay depends on, and
+    // the print backend scrolls so it has nowhere to draw it
+    let menu_backend = matches!(options.backend.as_deref(), Some("ansi" | "debug"));
+    let mut menu = match options.keyboard_input && menu_backend && !options.kiosk && recorder.is_none() && replay.is_none() {
+        true => Some(PauseMenu::init()),
+        false => None
+    };
+    let mut menu_settings = MenuSettings {
+        cycles,
+        palette: PRESETS.iter().position(|(_, preset)| *preset == palette).unwrap_or(0),
+        quirks: machine.quirks,
+        pause_unfocused: options.pause_unfocused
+    };
+    let recent_roms = RecentRoms::default_path()
+        .and_then(|file| RecentRoms::load(&file, recent::DEFAULT_LIMIT).ok())
+        .unwrap_or(RecentRoms::init(recent::DEFAULT_LIMIT));
+    if let Some(menu) = menu.as_mut() {
+        menu.set_recent(&recent_roms);
+    }
+    // The menu can turn pausing in the background on later
+    let mut focus = FocusPause::init(options.pause_unfocused);
+    if let Some((input, _)) = keys.as_mut().filter(|_| options.pause_unfocused || menu.is_some()) {
+        input.enable_focus().map_err(|e| format!("could not turn on focus reports: {}", e))?;
+    }
+    let mut relaunch = None;
+    let mut frame: u64 = 0;
+    let mut last_start: Option<Instant> = None;
+    // When the next frame is due, frames are paced against it so time
+    // spent drawing is not added on top of the frame time
+    let mut deadline = Instant::now();
+    'frames: while options.frames.is_none_or(|limit| frame < limit) {
+        let start = Instant::now();
+        // Time between frame starts, which includes drawing and sleeping
+        if let (Some(jitter), Some(last)) = (jitter.as_mut(), last_start) {
+            jitter.record(start - last);
+        }
+        last_start = Some(start);
+        // A crash of the emulator itself stops the run like an invalid
+        // opcode does, so it gets a crash report too
+        let frame_cycles = match clock.as_mut() {
+            Some(clock) => {
+                let frame_cycles = clock.until_tick();
+                clock.advance(frame_cycles);
+                frame_cycles
+            },
+            None => cycles
+        };
+        let mut budget = FrameBudget::init(frame_cycles);
+        budget.set_input_polls(options.input_polls);
+        // The frame's sleep is spread between the polls so input read
+        // part way through a frame arrived part way through it
+        let frame_time = speed.frame_time();
+        let pace = (!options.headless || stream.is_some()) && frame_time.is_some();
+        let frame_time = frame_time.unwrap_or_default();
+        let slice_time = frame_time / budget.get_slices().len() as u32;
+        // A frame that ran late, or a pause, does not make the following
+        // frames run fast to catch up
+        deadline = deadline.max(start);
+        let frame_start = deadline;
+        let mut requests = Vec::new();
+        let mut hotkeys = Vec::new();
+        let mut monitor_error = None;
+        let poll = |sys: &mut ChipSystem, slice: usize| {
+            if pace && slice > 0 {
+                sleep_until(frame_start + slice_time * slice as u32);
+            }
+            if let Some((input, keymap)) = keys.as_mut() {
+                if input.poll(keymap, &mut sys.keyboard) {
+                    requests.push(MonitorRequest::Quit);
+                }
+                hotkeys.extend(input.take_hotkeys());
+                for event in input.take_mouse() {
+                    frontend.mouse(event, &mut sys.keyboard);
+                }
+            }
+            if let Some(monitor) = monitor.as_mut() {
+                match monitor.poll(sys) {
+                    Ok(polled) => requests.extend(polled),
+                    Err(e) => monitor_error = Some(e)
+                }
+            }
+            if let Some(replay_input) = replay_input.as_mut() {
+                replay_input.apply(frame, &mut sys.keyboard);
+            }
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.record(frame, &sys.keyboard);
+            }
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match watch.as_mut() {
+            Some(watch) => sys.run_frame_polled(&budget, poll, |sys, opcode| watch.observe(sys, opcode)),
+            None => sys.run_frame_polled(&budget, poll, |_, _| {})
+        }));
+        if let Some(e) = monitor_error {
+            return Err(format!("monitor failed: {}", e).into());
+        }
+        let result = match result {
+            Ok(result) => result.map_err(|e| e.to_string()),
+            Err(payload) => Err(format!("the emulator crashed: {}", crashreport::panic_message(payload.as_ref())))
+        };
+        if let Err(e) = result {
+            if options.kiosk {
+                sys = new_system()?;
+                if let Some(watch) = watch.as_mut() {
+                    watch.resync(&sys);
+                }
+                ghost = new_ghost(cycles)?;
+                continue;
+            }
+            println!("Stopped after {} frames: {}", frame, e);
+            let mut report = CrashReport::init(&sys, &options.path, &read_rom(&options.path)?, &e);
+            match options.timing {
+                TimingMode::Instructions => report.add_config("cycles", &cycles.to_string()),
+                TimingMode::Vip => report.add_config("timing", options.timing.name())
+            }
+            report.add_config("font", machine.fontset.name());
+            report.add_config("frames run", &frame.to_string());
+            if let Some(seed) = sys.get_seed() {
+                report.add_config("seed", &seed.to_string());
+            }
+            let dir = options.crash_dir.as_deref().unwrap_or(".");
+            match report.write_to(Path::new(dir)) {
+                Ok(report_path) => eprintln!("Crash report written to {}", report_path.display()),
+                Err(e) => eprintln!("Could not write a crash report to {}: {}", dir, e)
+            }
+            break;
+        }
+        frame += 1;
+        if let Some(ghost) = ghost.as_mut() {
+            ghost.advance();
+        }
+        if let Some(watch) = watch.as_mut() {
+            for change in watch.end_frame(&sys) {
+                eprintln!("{}", change);
+            }
+        }
+        if dump_signal.as_ref().is_some_and(|signal| signal.take_request()) {
+            let dump = statedump::state_dump(&sys);
+            match options.state_dump_path.as_ref() {
+                Some(dump_path) => OpenOptions::new().create(true).append(true).open(dump_path)
+                    .and_then(|mut file| file.write_all(dump.as_bytes()))
+                    .map_err(|e| format!("could not write {}: {}", dump_path, e))?,
+                None => eprint!("{}", dump)
+            }
+        }
+        for violation in sys.ram.take_violations() {
+            eprintln!(
+                "frame {}: {} write of 0x{:02x} to protected address 0x{:03x}",
+                frame, if violation.blocked { "blocked" } else { "allowed" },
+                violation.value, violation.addr
+            );
+        }
+        if let Some(telemetry) = telemetry.as_mut() {
+            telemetry.record(&sys.stats, &sys.registers, start.elapsed())
+                .map_err(|e| format!("could not write telemetry: {}", e))?;
+        }
+        if let Some(dumper) = dumper.as_mut() {
+            dumper.dump(&sys.display).map_err(|e| format!("could not write frame {}: {}", frame, e))?;
+        }
+        if let Some(announcer) = announcer.as_mut() {
+            for line in announcer.update(&sys) {
+                eprintln!("{}", line);
+            }
+        }
+        // Turbo runs frames faster than they can be shown, the display
+        // is still drawn once a tick
+        let present = speed.should_present(Instant::now());
+        if let Some(stream) = stream.as_mut().filter(|_| present) {
+            let image = match ghost.as_ref() {
+                Some(ghost) => ghost.render(&sys.display, palette.on, palette.off),
+                None => sys.display.to_image(palette.on, palette.off)
+            };
+            let image = image.scaled(options.stream_scale.max(1));
+            stream.send(&image).map_err(|e| format!("could not stream frame {}: {}", frame, e))?;
+        }
+        if !options.headless {
+            // A failed bell is not worth stopping the game over
+            let _ = bell.update(&mut sys.registers);
+            let playing = sys.registers.get_s() > 0;
+            if let Some(Err(e)) = player.as_mut().map(|player| player.update(&mut sys.audio, playing)) {
+                eprintln!("Sound stopped, {}", e);
+                player = None;
+            }
+            match ghost.as_ref() {
+                _ if !present => {},
+                // The ghost moves even when the live display does not
+                Some(ghost) => print!("{}", ghost.render(&sys.display, palette.on, palette.off).render_ansi()),
+                None => frontend.present(&mut sys).map_err(|e| format!("could not draw frame {}: {}", frame, e))?
+            }
+        }
+        deadline = frame_start + frame_time;
+        if pace {
+            sleep_until(deadline);
+        }
+        // The game waits while the menu is open, it can queue state and
+        // monitor requests handled below like their hotkeys and commands
+        if let (Some(menu), Some((input, keymap)), true) = (menu.as_mut(), keys.as_mut(), hotkeys.contains(&Hotkey::Menu)) {
+            menu.open();
+            menu_settings.cycles = cycles;
+            menu_settings.quirks = sys.quirks;
+            input.set_capture(true);
+            let mut stdout = io::stdout();
+            let mut draw = |menu: &PauseMenu, settings: &MenuSettings, note: &str| -> Result<(), String> {
+                let mut text = menu.render_ansi(settings, 3, 2);
+                if !note.is_empty() {
+                    text.push_str(&format!("\x1b[1;3H{}", note));
+                }
+                stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush())
+                    .map_err(|e| format!("could not draw the menu: {}", e))
+            };
+            draw(menu, &menu_settings, "")?;
+            while menu.is_open() {
+                thread::sleep(Duration::from_millis(16));
+                if input.poll(keymap, &mut sys.keyboard) {
+                    requests.push(MonitorRequest::Quit);
+                    menu.close();
+                }
+                let captured = input.take_captured();
+                if captured.is_empty() {
+                    continue;
+                }
+                let mut note = "";
+                for combo in captured {
+                    let menu_input = match combo.key.as_str() {
+                        "up" => MenuInput::Up,
+                        "down" => MenuInput::Down,
+                        "left" => MenuInput::Left,
+                        "right" => MenuInput::Right,
+                        "enter" | "space" => MenuInput::Select,
+                        "escape" | "backspace" => MenuInput::Back,
+                        _ => continue
+                    };
+                    match menu.handle(menu_input, &mut menu_settings) {
+                        Some(MenuAction::Reset) => requests.push(MonitorRequest::Reset),
+                        Some(MenuAction::Quit) => requests.push(MonitorRequest::Quit),
+                        Some(MenuAction::SaveState) => hotkeys.push(Hotkey::SaveState),
+                        Some(MenuAction::LoadState) => hotkeys.push(Hotkey::LoadState),
+                        Some(MenuAction::LoadRecent(index)) => {
+                            relaunch = Some(index);
+                            requests.push(MonitorRequest::Quit);
+                        },
+                        Some(MenuAction::LoadRom) => {
+                            // There is no file picker on a terminal
+                            menu.open();
+                            note = "clip8 run ROM opens another rom";
+                        },
+                        Some(MenuAction::SettingsChanged) => {
+                            if cycles != menu_settings.cycles {
+                                cycles = menu_settings.cycles;
+                                clock = None;
+                            }
+                            palette = PRESETS[menu_settings.palette].1;
+                            sys.quirks = menu_settings.quirks;
+                            focus.set_enabled(menu_settings.pause_unfocused);
+                        },
+                        Some(MenuAction::Resume) | None => {}
+                    }
+                }
+                // Closing it or changing pages leaves parts of the last
+                // page on the screen, the game is drawn again under it
+                frontend.redraw();
+                frontend.present(&mut sys).map_err(|e| format!("could not draw frame {}: {}", frame, e))?;
+                draw(menu, &menu_settings, note)?;
+            }
+            input.set_capture(false);
+        }
+        let rewinding = hotkeys.contains(&Hotkey::Rewind);
+        for hotkey in hotkeys {
+            match hotkey {
+                Hotkey::Rewind => {
+                    if let Some(rewind) = rewind.as_mut() {
+                        rewind.rewind(&mut sys);
+                        if let Some(watch) = watch.as_mut() {
+                            watch.resync(&sys);
+                        }
+                    }
+                },
+                Hotkey::SaveState => match savestate::save_state_file(&sys, &state_path) {
+                    Ok(()) => eprintln!("Saved the state to {}", state_path.display()),
+                    Err(e) => eprintln!("Could not save the state to {}: {}", state_path.display(), e)
+                },
+                Hotkey::Keypad => frontend.toggle_keypad(),
+                Hotkey::Turbo => eprintln!("Speed: {}", speed.toggle_turbo()),
+                Hotkey::SlowMotion => eprintln!("Speed: {}", speed.toggle_slow()),
+                Hotkey::NormalSpeed => {
+                    speed.set_speed(Speed::Normal);
+                    eprintln!("Speed: {}", speed.get_speed());
+                },
+                Hotkey::LoadState if recorder.is_some() => eprintln!("Loading a state is off while recording input"),
+                Hotkey::LoadState => match savestate::load_state_file(&mut sys, &state_path) {
+                    Ok(()) => {
+                        if let Some(watch) = watch.as_mut() {
+                            watch.resync(&sys);
+                        }
+                        eprintln!("Loaded the state from {}", state_path.display());
+                    },
+                    Err(e) => eprintln!("Could not load the state from {}: {}", state_path.display(), e)
+                },
+                _ => {}
+            }
+        }
+        if let Some(rewind) = rewind.as_mut().filter(|_| !rewinding) {
+            rewind.record(&sys);
+        }
+        // The game and its sound wait while the terminal is in the
+        // background, the sound player catches up by skipping the gap
+        if let Some((input, keymap)) = keys.as_mut() {
+            input.update_focus(&mut focus);
+            while focus.is_paused() && !requests.contains(&MonitorRequest::Quit) {
+                thread::sleep(Duration::from_millis(16));
+                if input.poll(keymap, &mut sys.keyboard) {
+                    requests.push(MonitorRequest::Quit);
+                }
+                input.update_focus(&mut focus);
+            }
+        }
+        // A watchpoint hit or the pause command stops the game until the
+        // monitor asks to continue, a reset or quit ends the pause too
+        let hits = sys.take_watch_hits();
+        if let (true, Some(monitor)) = (!hits.is_empty() || requests.contains(&MonitorRequest::Pause), monitor.as_mut()) {
+            let report = |monitor: &mut Monitor, hits: Vec<WatchHit>, writer: &str| -> Result<(), String> {
+                for hit in hits {
+                    monitor.report(&format!("{} {}", hit, writer)).map_err(|e| format!("monitor failed: {}", e))?;
+                }
+                Ok(())
+            };
+            let (pc, opcode) = sys.get_instruction_history().last().copied().unwrap_or_default();
+            report(monitor, hits, &format!("at {:03x} {}", pc, disasm::mnemonic(opcode)))?;
+            monitor.report("Paused, continue runs on").map_err(|e| format!("monitor failed: {}", e))?;
+            let mut paused = true;
+            while paused {
+                thread::sleep(Duration::from_millis(16));
+                if let Some((input, keymap)) = keys.as_mut() {
+                    if input.poll(keymap, &mut sys.keyboard) {
+                        requests.push(MonitorRequest::Quit);
+                    }
+                }
+                requests.extend(monitor.poll(&mut sys).map_err(|e| format!("monitor failed: {}", e))?);
+                // Pokes from the monitor can hit watchpoints too
+                report(monitor, sys.take_watch_hits(), "by the monitor")?;
+                paused = !monitor.is_closed() && !requests.iter().any(|request| matches!(
+                    request, MonitorRequest::Continue | MonitorRequest::Reset | MonitorRequest::Quit
+                ));
+            }
+        }
+        for request in requests {
+            match request {
+                MonitorRequest::Speed(ips) => {
+                    cycles = ((ips + 30) / 60).max(1);
+                    clock = match ips % 60 {
+                        0 => None,
+                        _ if recorder.is_some() => None,
+                        _ => Some(TimerClock::init(ips))
+                    };
+                },
+                MonitorRequest::Pause | MonitorRequest::Continue => {},
+                MonitorRequest::Reset => {
+                    // Watchpoints are the debugger's, not the game's
+                    let points = sys.get_watchpoints();
+                    sys = new_system()?;
+                    // Quirks changed from the menu last until the run ends
+                    sys.quirks = menu_settings.quirks;
+                    for point in points {
+                        sys.add_watchpoint(point);
+                    }
+                    if let Some(rewind) = rewind.as_mut() {
+                        rewind.clear();
+                    }
+                    if let Some(watch) = watch.as_mut() {
+                        watch.resync(&sys);
+                    }
+                    ghost = new_ghost(cycles)?;
+                },
+                MonitorRequest::Quit => break 'frames
+            }
+        }
+        if sys.get_exit().is_some() {
+            if options.kiosk {
+                sys = new_system()?;
+                if let Some(watch) = watch.as_mut() {
+                    watch.resync(&sys);
+                }
+                ghost = new_ghost(cycles)?;
+                continue;
+            }
+            break;
+        }
+    }
+    frontend.finish().map_err(|e| format!("could not restore the terminal: {}", e))?;
+    if let Some((input, _)) = keys.as_mut() {
+        input.restore().map_err(|e| format!("could not restore the terminal: {}", e))?;
+    }
+    if let Some(dumper) = dumper {
+        println!("Wrote {} frames", dumper.get_count());
+    }
+    if let Some(jitter) = jitter {
+        print!("{}", jitter.report());
+    }
+    if let Some(mut telemetry) = telemetry {
+        telemetry.flush().map_err(|e| format!("could not write telemetry: {}", e))?;
+        println!("Logged {} frames of telemetry", telemetry.get_rows());
+    }
+    if let (Some(recorder), Some(record_path), Some(seed)) = (recorder, options.record_path.as_ref(), sys.get_seed()) {
+        let recording = recorder.finish(seed, cycles);
+        fs::write(record_path, recording.to_text()).map_err(|e| format!("could not write {}: {}", record_path, e))?;
+        println!("Recorded {} key events to {}", recording.events.len(), record_path);
+    }
+    if replay.is_some() {
+        println!("Replayed {} frames, display hash {:016x}", frame, sys.display.hash());
+    }
+    log::logger().flush();
+    // A recent ROM picked from the menu opens like clip8 recent opens
+    // it, in a fresh process so no thread is left reading the terminal
+    if let Some(index) = relaunch {
+        drop(player);
+        let mut command = Command::new(env::current_exe().map_err(|e| format!("could not find clip8: {}", e))?);
+        command.args(["recent", &(index + 1).to_string(), "--keyboard"]);
+        command.args(options.backend.iter().flat_map(|backend| ["--backend", backend]));
+        if options.mouse_keypad {
+            command.arg("--mouse-keypad");
+        }
+        if options.keypad_overlay {
+            command.arg("--keypad-overlay");
+        }
+        if let Some(depth) = options.onion {
+            command.args(["--onion", &depth.to_string()]);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            return Err(format!("could not open recent ROM {}: {}", index + 1, command.exec()).into());
+        }
+        #[cfg(not(unix))]
+        {
+            let status = command.status().map_err(|e| format!("could not open recent ROM {}: {}", index + 1, e))?;
+            process::exit(status.code().unwrap_or(1));
+        }
+    }
+    if let Some(reason) = sys.get_exit() {
+        let how = match reason {
+            ExitReason::ExitOpcode => "exit opcode 00FD",
+            ExitReason::JumpToSelf => "jump to itself"
+        };
+        println!("Program exited after {} frames ({})", frame, how);
+        if options.notify_exit {
+            notify("clip8", &format!("{} exited after {} frames ({})", options.path, frame, how));
+        }
+        if options.exit_code != 0 {
+            process::exit(i32::from(options.exit_code));
+        }
+    }
+    Ok(())
+}
+
+/// clip8 netplay
+fn netplay(mut args: Args) -> Result<(), CliError> {
+    let host = args.value(&["--host"])?;
+    let join = args.value(&["--join"])?;
+    let keys = args.value(&["--keys"])?;
+    let delay = args.number(&["--delay"])?.unwrap_or(DEFAULT_INPUT_DELAY);
+    if delay > MAX_INPUT_DELAY {
+        return Err(CliError::Usage(format!("--delay {} is too long, at most {} frames", delay, MAX_INPUT_DELAY)));
+    }
+    let cycles = args.cycles()?;
+    let seed = args.number(&["--seed"])?;
+    let frames = args.number(&["--frames"])?;
+    let demo_path = args.value(&["--demo"])?;
+    let mut monitor = match args.flag(&["--monitor"]) {
+        true => Some(Monitor::stdin()),
+        false => None
+    };
+    let headless = args.flag(&["--headless"]);
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    let mut demo = match demo_path {
+        Some(demo_path) => {
+            let text = fs::read_to_string(&demo_path).map_err(|e| format!("could not read {}: {}", demo_path, e))?;
+            Some(DemoInput::parse(&text).map_err(|e| format!("{}: {}", demo_path, e))?)
+        },
+        None => None
+    };
+    let mut session = match (host, join) {
+        (Some(addr), None) => {
+            let host_keys = match keys {
+                Some(list) => parse_keys(&list).map_err(CliError::Usage)?,
+                None => LEFT_PLAYER_KEYS
+            };
+            let settings = NetplaySettings {
+                rom_hash: netplay::rom_hash(&rom),
+                cycles: cycles.unwrap_or_else(|| tickrate::guess(&rom).cycles),
+                seed: seed.unwrap_or_else(rand::random),
+                delay,
+                host_keys
+            };
+            eprintln!("Waiting for the other player on {}", addr);
+            Netplay::host(&addr, settings).map_err(|e| e.to_string())?
+        },
+        (None, Some(addr)) => {
+            if keys.is_some() || cycles.is_some() || seed.is_some() {
+                return Err(CliError::Usage(String::from("--keys, --cycles and --seed are chosen by the host")));
+            }
+            Netplay::join(&addr, netplay::rom_hash(&rom)).map_err(|e| e.to_string())?
+        },
+        _ => return Err(CliError::Usage(String::from("give one of --host or --join")))
+    };
+    let settings = session.get_settings();
+    let owned: Vec<String> = (0..16).filter(|key| session.get_local_keys() & (1 << key) != 0)
+        .map(|key| format!("{:X}", key))
+        .collect();
+    eprintln!("Connected, running {} opcodes per frame, your keys are {}", settings.cycles, owned.join(" "));
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    session.prepare(&mut sys);
+    // Keys held by the local player, the system's keyboard holds the
+    // delayed keys of both players
+    let mut pad = ChipKeyboard::init();
+    let mut frame: u64 = 0;
+    'frames: while frames.is_none_or(|limit| frame < limit) {
+        if let Some(demo) = demo.as_mut() {
+            demo.apply(frame, &mut pad);
+        }
+        session.sync(&mut sys, held_keys(&pad)).map_err(|e| e.to_string())?;
+        if let Err(e) = sys.run_frame(settings.cycles) {
+            println!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+        frame += 1;
+        if !headless {
+            if sys.display.mod_check() {
+                sys.display.draw_display();
+            }
+            thread::sleep(Duration::from_millis(16));
+        }
+        if let Some(monitor) = monitor.as_mut() {
+            // Key commands change the system's keyboard, move them to the
+            // local player's keys so they go through the session
+            let before = held_keys(&sys.keyboard);
+            let requests = monitor.poll(&mut sys).map_err(|e| format!("monitor failed: {}", e))?;
+            let after = held_keys(&sys.keyboard);
+            for key in (0..16u8).filter(|key| (before ^ after) & (1 << key) != 0) {
+                pad.set_key(key, after & (1 << key) != 0);
+                sys.keyboard.set_key(key, before & (1 << key) != 0);
+            }
+            for request in requests {
+                match request {
+                    MonitorRequest::Speed(_) | MonitorRequest::Reset | MonitorRequest::Pause | MonitorRequest::Continue => {
+                        eprintln!("Speed, reset and pausing are not available during netplay");
+                    },
+                    MonitorRequest::Quit => break 'frames
+                }
+            }
+            // A frame cut short by a watchpoint would leave the other
+            // player behind
+            if !sys.get_watchpoints().is_empty() {
+                sys.clear_watchpoints();
+                eprintln!("Watchpoints are not available during netplay");
+            }
+        }
+        if sys.get_exit().is_some() {
+            break;
+        }
+    }
+    // The other player finds out either way, so a failed goodbye is fine
+    let _ = session.close();
+    println!("Played {} frames", frame);
+    Ok(())
+}
+
+/// clip8 corrupt
+fn corrupt_rom(mut args: Args) -> Result<(), CliError> {
+    let seed = args.number(&["--seed"])?.unwrap_or_else(rand::random);
+    let count = args.number(&["--count"])?.unwrap_or(4) as usize;
+    let mode = match args.value(&["--mode"])? {
+        Some(name) => CorruptMode::from_name(&name)
+            .ok_or_else(|| format!("unknown corruption mode '{}', expected flip or random", name))?,
+        None => CorruptMode::Flip
+    };
+    let mut corruptor = Corruptor::init(mode, count, seed);
+    while let Some(range) = args.value(&["--range"])? {
+        corruptor.add_range(WatchRange::parse(&range)?);
+    }
+    let resets = args.number(&["--resets"])?.unwrap_or(1);
+    let frames = args.number(&["--frames"])?.unwrap_or(600);
+    let cycles = args.cycles()?;
+    let headless = args.flag(&["--headless"]);
+    let out = args.value(&["-o"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    println!("Seed {}", seed);
+    if let Some(out) = out {
+        let (corrupted, changes) = corruptor.corrupt(&rom, 0);
+        for change in changes.iter() {
+            println!("  {}", change);
+        }
+        fs::write(&out, &corrupted).map_err(|e| format!("could not write {}: {}", out, e))?;
+        println!("Wrote {} bytes to {}", corrupted.len(), out);
+        return Ok(());
+    }
+    // Crashes are reported with each run, not as a panic message
+    panic::set_hook(Box::new(|_| {}));
+    let cycles = cycles.unwrap_or_else(|| tickrate::guess(&rom).cycles);
+    for reset in 0..resets {
+        let (corrupted, changes) = corruptor.corrupt(&rom, reset);
+        let mut sys = ChipSystem::init();
+        sys.set_exit_on_jump_self(true);
+        sys.load_rom(corrupted).map_err(|e| format!("could not load {}: {}", path, e))?;
+        let mut outcome = RunOutcome::Survived;
+        let mut frame = 0;
+        while frame < frames {
+            if let Some(end) = corrupt::run_frame_guarded(&mut sys, cycles) {
+                outcome = end;
+                break;
+            }
+            frame += 1;
+            if !headless {
+                if sys.display.mod_check() {
+                    sys.display.draw_display();
+                }
+                thread::sleep(Duration::from_millis(16));
+            }
+        }
+        let changes: Vec<String> = changes.iter().map(|change| change.to_string()).collect();
+        match changes.is_empty() {
+            true => println!("Run {}: {} after {} frames, nothing corrupted", reset, outcome, frame),
+            false => println!("Run {}: {} after {} frames ({})", reset, outcome, frame, changes.join(", "))
+        }
+    }
+    let _ = panic::take_hook();
+    Ok(())
+}
+
+/// clip8 test-script
+fn test_script(mut args: Args) -> Result<(), CliError> {
+    let rom_override = args.value(&["--rom"])?;
+    let mut scripts = Vec::new();
+    while args.args.iter().any(|a| !a.starts_with('-')) {
+        scripts.push(args.positional("test script")?);
+    }
+    args.finish()?;
+    if scripts.is_empty() {
+        return Err(CliError::Usage(String::from("missing test script")));
+    }
+
+    // Crashes are reported as script errors, not as a panic message
+    panic::set_hook(Box::new(|_| {}));
+    let mut failed = 0;
+    for path in scripts.iter() {
+        let text = fs::read_to_string(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        let script = TestScript::parse(&text).map_err(|e| format!("{}: {}", path, e))?;
+        let rom = match (rom_override.as_ref(), script.get_rom()) {
+            (Some(rom), _) => rom.clone(),
+            (None, Some(rom)) => Path::new(path).with_file_name(rom).display().to_string(),
+            (None, None) => return Err(format!("{} names no ROM, add a rom line or --rom", path).into())
+        };
+        println!("{} ({})", path, rom);
+        let mut sys = ChipSystem::init();
+        sys.set_exit_on_jump_self(true);
+        load_rom(&mut sys, &rom)?;
+        let summary = script.run(&mut sys, io::stdout()).map_err(|e| format!("could not write results: {}", e))?;
+        println!("{} passed, {} failed", summary.passed, summary.failed);
+        if !summary.is_success() {
+            failed += 1;
+        }
+    }
+    let _ = panic::take_hook();
+    if failed > 0 {
+        eprintln!("{} of {} scripts failed", failed, scripts.len());
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// clip8 tutorial
+fn tutorial(mut args: Args) -> Result<(), CliError> {
+    let notes_path = args.value(&["--notes"])?;
+    let source = args.flag(&["--source"]);
+    let path = args.positional("ROM path").ok();
+    args.finish()?;
+    if source {
+        print!("{}", tutorial::TUTORIAL_SOURCE);
+        return Ok(());
+    }
+
+    let (rom, notes) = match (path, notes_path) {
+        (None, None) => tutorial::bundled(),
+        (Some(path), notes_path) => {
+            let notes = match notes_path {
+                Some(notes_path) => Annotations::load(&notes_path)
+                    .map_err(|e| format!("could not read {}: {}", notes_path, e))?,
+                None => Annotations::default()
+            };
+            (read_rom(&path)?, notes)
+        },
+        (None, Some(_)) => return Err(CliError::Usage(String::from("--notes needs the ROM they are for")))
+    };
+    let mut sys = ChipSystem::init();
+    sys.set_exit_on_jump_self(true);
+    sys.load_rom(rom).map_err(|e| format!("could not load the ROM: {}", e))?;
+    let stdin = io::stdin();
+    tutorial::run_tutorial(&mut sys, &notes, stdin.lock(), io::stdout())
+        .map_err(|e| format!("tutorial failed: {}", e))?;
+    Ok(())
+}
+
+/// Read `--variant`, `--quirk` and `--no-quirk` into a system for the
+/// commands that step through a ROM, quirks start from the config file
+fn machine_args(args: &mut Args, sys: &mut ChipSystem) -> Result<(), CliError> {
+    if let Some(name) = args.value(&["--variant"])? {
+        sys.variant = Variant::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = VARIANTS.iter().map(|(_, name)| *name).collect();
+            format!("unknown variant '{}', expected one of {}", name, names.join(", "))
+        })?;
+    }
+    sys.quirks = load_quirks(None)?;
+    let mut changes = Vec::new();
+    while let Some(name) = args.value(&["--quirk"])? {
+        changes.push((name, true));
+    }
+    while let Some(name) = args.value(&["--no-quirk"])? {
+        changes.push((name, false));
+    }
+    for (name, enabled) in changes {
+        if !sys.quirks.set(&name, enabled) {
+            return Err(CliError::Usage(format!("unknown quirk '{}', expected one of {}", name, QUIRK_NAMES.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+/// clip8 explain
+fn explain(mut args: Args) -> Result<(), CliError> {
+    let delay = args.number(&["--delay"])?.unwrap_or(500);
+    let mut sys = ChipSystem::init();
+    sys.set_exit_on_jump_self(true);
+    machine_args(&mut args, &mut sys)?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    load_rom(&mut sys, &path)?;
+    sys.run_explain(delay);
+    Ok(())
+}
+
+/// clip8 teach
+fn teach(mut args: Args) -> Result<(), CliError> {
+    let mut sys = ChipSystem::init();
+    sys.set_exit_on_jump_self(true);
+    machine_args(&mut args, &mut sys)?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    load_rom(&mut sys, &path)?;
+    teach::run_teach(&mut sys);
+    Ok(())
+}
+
+/// Parse a display size given as WIDTHxHEIGHT, such as 128x64
+fn parse_resolution(text: &str) -> Result<(usize, usize), String> {
+    let size = text.split_once(['x', 'X'])
+        .and_then(|(width, height)| Some((width.trim().parse::<usize>().ok()?, height.trim().parse::<usize>().ok()?)));
+    match size {
+        Some((width, height)) if (1..=256).contains(&width) && (1..=256).contains(&height) => Ok((width, height)),
+        _ => Err(format!("invalid resolution '{}', expected WIDTHxHEIGHT such as 128x64", text))
+    }
+}
+
+/// Parse a comma separated list of hex keys into one bit per key
+fn parse_keys(list: &str) -> Result<u16, String> {
+    let mut keys = 0;
+    for key in list.split(',') {
+        match u8::from_str_radix(key.trim(), 16) {
+            Ok(key) if key < 16 => keys |= 1 << key,
+            _ => return Err(format!("invalid key '{}', expected 0-F", key))
+        }
+    }
+    Ok(keys)
+}
+
+/// Get the keys held on a keypad, one bit per key
+fn held_keys(keyboard: &ChipKeyboard) -> u16 {
+    (0..16u8).filter(|key| keyboard.get_key(*key)).fold(0, |keys, key| keys | 1 << key)
+}
+
+/// Add a ROM to the recent list, a list that can not be written is not
+/// worth failing the run over so errors are ignored
+fn remember_rom(path: &str, cycles: u32, palette: Palette, sys: &ChipSystem) {
+    let (file, rom) = match (RecentRoms::default_path(), fs::read(path)) {
+        (Some(file), Ok(rom)) => (file, rom),
+        _ => return
+    };
+    let mut list = RecentRoms::load(&file, recent::DEFAULT_LIMIT).unwrap_or(RecentRoms::init(recent::DEFAULT_LIMIT));
+    let path = fs::canonicalize(path).map(|full| full.display().to_string()).unwrap_or(path.to_string());
+    list.push(RecentRom {
+        path,
+        hash: recent::rom_hash(&rom),
+        cycles,
+        palette,
+        quirks: sys.quirks
+    });
+    let _ = list.save(&file);
+}
+
+/// Sleep until an instant, returning straight away once it has passed
+fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now {
+        thread::sleep(deadline - now);
+    }
+}
+
+/// Show a desktop notification, failing quietly apart from a warning
+fn notify(title: &str, body: &str) {
+    let status = if cfg!(target_os = "macos") {
+        Command::new("osascript")
+            .arg("-e")
+            .arg(format!("display notification {:?} with title {:?}", body, title))
+            .status()
+    } else {
+        Command::new("notify-send").arg(title).arg(body).status()
+    };
+    if status.is_err() {
+        eprintln!("Could not show a desktop notification");
+    }
+}
+
+/// Read `--palette` and `--fg`/`--bg` colors into a palette, the
+/// colors override the preset
+fn palette_args(args: &mut Args) -> Result<Palette, CliError> {
+    let mut palette = match args.value(&["--palette"])? {
+        Some(name) => Palette::preset(&name).ok_or(format!("unknown palette: {}", name))?,
+        None => Palette::default()
+    };
+    if let Some(fg) = args.value(&["--fg"])? {
+        palette.on = Palette::parse_hex(&fg).ok_or(format!("invalid color: {}", fg))?;
+    }
+    if let Some(bg) = args.value(&["--bg"])? {
+        palette.off = Palette::parse_hex(&bg).ok_or(format!("invalid color: {}", bg))?;
+    }
+    Ok(palette)
+}
+
+/// clip8 svg
+fn svg(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(60);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let scale = args.number(&["--scale"])?.unwrap_or(10) as usize;
+    let palette = palette_args(&mut args)?;
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    for frame in 0..frames {
+        if let Err(e) = sys.run_frame(cycles) {
+            eprintln!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+    }
+    let image = export::display_to_svg(&sys.display, &palette, scale);
+    match out {
+        Some(out) => {
+            fs::write(&out, image).map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote {}", out);
+        },
+        None => print!("{}", image)
+    }
+    Ok(())
+}
+
+/// clip8 memory-dump
+fn memory_dump(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(0);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let range = args.value(&["--range"])?.map(|range| WatchRange::parse(&range)).transpose()?;
+    let format = args.value(&["--format"])?.unwrap_or_else(|| String::from("bin"));
+    let out = args.value(&["-o", "--output"])?.ok_or_else(|| CliError::Usage(String::from("memory-dump needs an output file, -o FILE")))?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    for frame in 0..frames {
+        if let Err(e) = sys.run_frame(cycles) {
+            eprintln!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+    }
+    let (start, end) = match range {
+        Some(range) => (range.start as usize, range.end as usize),
+        None => (0, sys.ram.get_size() - 1)
+    };
+    if end >= sys.ram.get_size() {
+        return Err(CliError::Usage(format!("memory ends at 0x{:03x}", sys.ram.get_size() - 1)));
+    }
+    let bytes = sys.ram.peek_bytes(start as u16, end - start + 1);
+    let data = match format.as_str() {
+        "bin" => bytes,
+        "ihex" => ihex::encode(&bytes, start as u32).into_bytes(),
+        other => return Err(CliError::Usage(format!("unknown format '{}', expected bin or ihex", other)))
+    };
+    fs::write(&out, data).map_err(|e| format!("could not write {}: {}", out, e))?;
+    println!("Wrote 0x{:03x}..=0x{:03x} to {}", start, end, out);
+    Ok(())
+}
+
+/// clip8 trace
+fn trace(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(60);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let writer: Box<dyn Write> = match &out {
+        Some(out) => Box::new(BufWriter::new(File::create(out).map_err(|e| format!("could not create {}: {}", out, e))?)),
+        None => Box::new(BufWriter::new(io::stdout()))
+    };
+    let mut trace = OctoTrace::init(writer);
+    let mut sys = ChipSystem::init();
+    sys.set_exit_on_jump_self(true);
+    load_rom(&mut sys, &path)?;
+    let mut written = Ok(());
+    for frame in 0..frames {
+        let result = sys.run_frame_traced(cycles, |sys, opcode| {
+            if written.is_ok() {
+                written = trace.record(sys, opcode);
+            }
+        });
+        written.as_ref().map_err(|e| format!("could not write trace: {}", e))?;
+        if let Err(e) = result {
+            eprintln!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+        if sys.get_exit().is_some() {
+            break;
+        }
+    }
+    trace.flush().map_err(|e| format!("could not write trace: {}", e))?;
+    if let Some(out) = out {
+        println!("Wrote {} trace lines to {}", trace.get_lines(), out);
+    }
+    Ok(())
+}
+
+/// clip8 text
+fn text(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(60);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let mut style = match args.value(&["--charset"])? {
+        Some(name) => TextStyle::charset(&name)
+            .ok_or_else(|| format!("unknown charset '{}', expected one of {}", name, export::CHARSETS.join(", ")))?,
+        None => TextStyle::default()
+    };
+    let glyph = |value: String| -> Result<char, String> {
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(glyph), None) => Ok(glyph),
+            _ => Err(format!("expected a single character, found '{}'", value))
+        }
+    };
+    if let Some(on) = args.value(&["--on"])? {
+        style.on = glyph(on)?;
+    }
+    if let Some(off) = args.value(&["--off"])? {
+        style.off = glyph(off)?;
+    }
+    style.border = !args.flag(&["--no-border"]);
+    style.scale = args.number(&["--scale"])?.unwrap_or(1) as usize;
+    let describe = args.flag(&["--describe"]);
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    for frame in 0..frames {
+        if let Err(e) = sys.run_frame(cycles) {
+            eprintln!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+    }
+    let screen = match describe {
+        true => accessibility::describe_screen(&sys.display),
+        false => export::display_to_text(&sys.display, &style)
+    };
+    match out {
+        Some(out) => {
+            fs::write(&out, screen).map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote {}", out);
+        },
+        None => print!("{}", screen)
+    }
+    Ok(())
+}
+
+/// clip8 trim
+fn trim(mut args: Args) -> Result<(), CliError> {
+    let align = args.number(&["--align"])?.unwrap_or(1) as usize;
+    let pad_to = args.number(&["--pad-to"])?.map(|size| size as usize);
+    let out = args.value(&["-o", "--output"])?;
+    let in_place = args.flag(&["--in-place"]);
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    let (normalized, report) = trim::normalize(&rom, align, pad_to);
+    println!("{}: {}", path, report);
+    for warning in report.warnings.iter() {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    let target = match (out, in_place) {
+        (Some(out), _) => out,
+        (None, true) => path,
+        (None, false) => return Ok(())
+    };
+    fs::write(&target, &normalized).map_err(|e| format!("could not write {}: {}", target, e))?;
+    println!("Wrote {} (hash {:016x})", target, recent::rom_hash(&normalized));
+    Ok(())
+}
+
+/// clip8 multicart
+fn multicart(mut args: Args) -> Result<(), CliError> {
+    let out = args.value(&["-o", "--output"])?.ok_or_else(|| CliError::Usage(String::from("missing -o FILE")))?;
+    let mut games = Vec::new();
+    while args.args.iter().any(|a| !a.starts_with('-')) {
+        let path = args.positional("ROM path")?;
+        let name = Path::new(&path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or(path.clone());
+        games.push((name, read_rom(&path)?));
+    }
+    args.finish()?;
+
+    let cart = multicart::build(&games).map_err(|e| e.to_string())?;
+    for game in cart.games.iter() {
+        println!(
+            "{:<14} {:>4} bytes at 0x{:03X}, {} addresses relocated",
+            game.name, game.size, game.addr, game.relocations
+        );
+    }
+    fs::write(&out, &cart.rom).map_err(|e| format!("could not write {}: {}", out, e))?;
+    println!("Wrote {} ({} bytes)", out, cart.rom.len());
+    Ok(())
+}
+
+/// clip8 disasm
+fn disassemble(mut args: Args) -> Result<(), CliError> {
+    let mut entries = Vec::new();
+    while let Some(entry) = args.number(&["--entry"])? {
+        entries.push(entry as u16);
+    }
+    let check = args.flag(&["--check"]);
+    let range = args.value(&["--range"])?.map(|range| WatchRange::parse(&range)).transpose()?;
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    if let Some(range) = range {
+        if check || !entries.is_empty() {
+            return Err(CliError::Usage(String::from("--range lists memory as it is, it can not be used with --check or --entry")));
+        }
+        let mut sys = ChipSystem::init();
+        load_rom(&mut sys, &path)?;
+        if range.end as usize >= sys.ram.get_size() {
+            return Err(CliError::Usage(format!("memory ends at 0x{:03x}", sys.ram.get_size() - 1)));
+        }
+        let listing: String = disasm::disassemble_memory(&sys.ram, range.start, range.end).iter()
+            .map(|(addr, opcode, text)| format!("0x{:03X}  {:02X} {:02X}  {}\n", addr, opcode >> 8, opcode & 0xFF, text))
+            .collect();
+        return write_listing(listing, out);
+    }
+    if check {
+        disasm::round_trip(&rom, &entries)?;
+        println!("{} disassembles and assembles back to the same {} bytes", path, rom.len());
+        return Ok(());
+    }
+    write_listing(disasm::disassemble_rom(&rom, &entries), out)
+}
+
+/// Write a listing to a file, or print it without one
+fn write_listing(listing: String, out: Option<String>) -> Result<(), CliError> {
+    match out {
+        Some(out) => {
+            fs::write(&out, listing).map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote {}", out);
+        },
+        None => print!("{}", listing)
+    }
+    Ok(())
+}
+
+/// clip8 asm
+fn assemble(mut args: Args) -> Result<(), CliError> {
+    let out = args.value(&["-o", "--output"])?;
+    let map_out = args.value(&["--map"])?;
+    let compile = args.flag(&["-c", "--compile"]);
+    let mut paths = vec![args.positional("source path")?];
+    while args.args.iter().any(|a| !a.starts_with('-')) {
+        paths.push(args.positional("source path")?);
+    }
+    args.finish()?;
+
+    if compile {
+        if out.is_some() && paths.len() > 1 {
+            return Err(CliError::Usage(String::from("-o can only be used with -c for a single source")));
+        }
+        for path in paths.iter() {
+            let fragment = assembler::assemble_fragment_file(path).map_err(|e| e.to_string())?;
+            let out = out.clone().unwrap_or_else(|| Path::new(path).with_extension("o8").display().to_string());
+            fs::write(&out, fragment.to_text()).map_err(|e| format!("could not write {}: {}", out, e))?;
+            match fragment.get_globals().as_slice() {
+                [] => println!("Wrote {} ({} bytes)", out, fragment.get_size()),
+                globals => println!("Wrote {} ({} bytes, exports {})", out, fragment.get_size(), globals.join(", "))
+            }
+        }
+        return Ok(());
+    }
+    let (rom, map) = match paths.as_slice() {
+        [path] => assembler::assemble_file_with_map(path).map_err(|e| e.to_string())?,
+        _ => {
+            let fragments = paths.iter()
+                .map(|path| assembler::assemble_fragment_file(path).map_err(|e| e.to_string()))
+                .collect::<Result<Vec<_>, _>>()?;
+            assembler::link(&fragments).map_err(|e| e.to_string())?
+        }
+    };
+    let out = out.unwrap_or_else(|| Path::new(&paths[0]).with_extension("ch8").display().to_string());
+    write_linked(&out, map_out, &rom, &map)
+}
+
+/// clip8 sprite-from-image
+fn sprite_from_image(mut args: Args) -> Result<(), CliError> {
+    let defaults = SpriteOptions::default();
+    let options = SpriteOptions {
+        width: args.number(&["--width"])?.map(|width| width as usize).unwrap_or(defaults.width),
+        height: args.number(&["--height"])?.map(|height| height as usize),
+        threshold: match args.number(&["--threshold"])? {
+            Some(threshold) if threshold > 255 => return Err(CliError::Usage(format!("threshold {} is above 255", threshold))),
+            Some(threshold) => Some(threshold as u8),
+            None => defaults.threshold
+        },
+        dither: args.flag(&["--dither"]),
+        invert: args.flag(&["--invert"])
+    };
+    let format = args.value(&["--format"])?.unwrap_or_else(|| String::from("asm"));
+    let name = args.value(&["--name"])?;
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("image path")?;
+    args.finish()?;
+
+    let image = RgbImage::load_png(&path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    let sheet = sprite::image_to_sprites(&image, &options).map_err(|e| e.to_string())?;
+    let name = name.unwrap_or_else(|| {
+        let stem = Path::new(&path).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let name: String = stem.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        match name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            true => name,
+            false => format!("sprite_{}", name)
+        }
+    });
+    let text = sheet.format(&format, &name)
+        .ok_or_else(|| format!("unknown format '{}', expected one of {}", format, SPRITE_FORMATS.join(", ")))?;
+    match out {
+        Some(out) => {
+            fs::write(&out, text).map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote {} ({} sprites of {}x{})", out, sheet.sprites.len(), sheet.width, sheet.height);
+        },
+        None => print!("{}", text)
+    }
+    Ok(())
+}
+
+/// clip8 link
+fn link(mut args: Args) -> Result<(), CliError> {
+    let out = args.value(&["-o", "--output"])?.ok_or_else(|| CliError::Usage(String::from("missing -o FILE")))?;
+    let map_out = args.value(&["--map"])?;
+    let mut paths = vec![args.positional("fragment path")?];
+    while args.args.iter().any(|a| !a.starts_with('-')) {
+        paths.push(args.positional("fragment path")?);
+    }
+    args.finish()?;
+
+    let mut fragments = Vec::new();
+    for path in paths {
+        let text = fs::read_to_string(&path).map_err(|e| format!("could not read {}: {}", path, e))?;
+        fragments.push(assembler::Fragment::parse(&text).map_err(|e| format!("{}: {}", path, e))?);
+    }
+
+    let (rom, map) = assembler::link(&fragments).map_err(|e| e.to_string())?;
+    write_linked(&out, map_out, &rom, &map)
+}
+
+/// Write an assembled ROM and optionally its source map
+fn write_linked(out: &str, map_out: Option<String>, rom: &[u8], map: &SourceMap) -> Result<(), CliError> {
+    fs::write(out, rom).map_err(|e| format!("could not write {}: {}", out, e))?;
+    println!("Wrote {} ({} bytes)", out, rom.len());
+    if let Some(map_out) = map_out {
+        fs::write(&map_out, map.to_text()).map_err(|e| format!("could not write {}: {}", map_out, e))?;
+        println!("Wrote {} ({} entries)", map_out, map.get_entries().len());
+    }
+    Ok(())
+}
+
+/// clip8 diff
+fn diff(mut args: Args) -> Result<(), CliError> {
+    let first = args.positional("first ROM path")?;
+    let second = args.positional("second ROM path")?;
+    args.finish()?;
+
+    print!("{}", romdiff::render_diff(&read_rom(&first)?, &read_rom(&second)?));
+    Ok(())
+}
+
+/// clip8 callgraph
+fn callgraph(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(0);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let json = args.flag(&["--json"]);
+    let out = args.value(&["-o", "--output"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    let mut graph = CallGraph::from_rom(&rom);
+    if frames > 0 {
+        let mut sys = ChipSystem::init();
+        sys.set_exit_on_jump_self(true);
+        load_rom(&mut sys, &path)?;
+        for frame in 0..frames {
+            if let Err(e) = sys.run_frame_traced(cycles, |sys, opcode| graph.observe(sys, opcode)) {
+                eprintln!("Stopped after {} frames: {}", frame, e);
+                break;
+            }
+            if sys.get_exit().is_some() {
+                break;
+            }
+        }
+    }
+    let export = match json {
+        true => graph.to_json(),
+        false => graph.to_dot()
+    };
+    match out {
+        Some(out) => {
+            fs::write(&out, export).map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote {}", out);
+        },
+        None => print!("{}", export)
+    }
+    Ok(())
+}
+
+/// clip8 gen-stress
+fn gen_stress(mut args: Args) -> Result<(), CliError> {
+    let seed = match args.number(&["--seed"])? {
+        Some(seed) => seed,
+        None => return Err(CliError::Usage(String::from("gen-stress requires --seed N")))
+    };
+    let mut config = stress::StressConfig::init(seed);
+    if let Some(blocks) = args.number(&["--blocks"])? {
+        config.blocks = blocks as usize;
+    }
+    if let Some(depth) = args.number(&["--depth"])? {
+        config.max_depth = depth as usize;
+    }
+    let out = args.value(&["-o", "--output"])?
+        .unwrap_or(format!("stress_{}.ch8", seed));
+    args.finish()?;
+
+    let rom = stress::generate(&config);
+    fs::write(&out, &rom).map_err(|e| format!("could not write {}: {}", out, e))?;
+    println!("Wrote {} bytes to {}", rom.len(), out);
+    Ok(())
+}
+
+/// clip8 quirks
+fn quirks(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(600);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let rom = read_rom(&path)?;
+    let report = differential::run_all(&rom, frames, cycles);
+    print!("{}", report.render());
+    Ok(())
+}
+
+/// Read a comma separated list of quirks to turn on, none for all off
+fn quirk_list(option: &str, list: &str) -> Result<Quirks, String> {
+    let mut quirks = Quirks::from_bits(0);
+    for name in list.split(',').map(str::trim).filter(|name| !name.is_empty() && *name != "none") {
+        if !quirks.set(name, true) {
+            return Err(format!("unknown quirk '{}' in {}, expected one of {}", name, option, QUIRK_NAMES.join(", ")));
+        }
+    }
+    Ok(quirks)
+}
+
+/// clip8 compare
+fn compare(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(600);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let a_quirks = args.value(&["--a-quirks"])?;
+    let b_quirks = args.value(&["--b-quirks"])?;
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let (a_quirks, b_quirks) = match (a_quirks, b_quirks) {
+        (Some(a), Some(b)) => (
+            quirk_list("--a-quirks", &a).map_err(CliError::Usage)?,
+            quirk_list("--b-quirks", &b).map_err(CliError::Usage)?
+        ),
+        _ => return Err(CliError::Usage(String::from("compare needs --a-quirks and --b-quirks")))
+    };
+    let mut left = ChipSystem::init();
+    left.quirks = a_quirks;
+    let mut right = ChipSystem::init();
+    right.quirks = b_quirks;
+    let mut comparison = ChipComparison::init(left, right, cycles);
+    let warnings = comparison.load_rom(read_rom(&path)?).map_err(|e| format!("could not load {}: {}", path, e))?;
+    for warning in warnings {
+        eprintln!("Warning: {}: {}", path, warning);
+    }
+    let result = comparison.run_until_divergence(frames).map(|divergence| divergence.is_some());
+    let name = |quirks: Quirks| match quirks.enabled() {
+        enabled if enabled.is_empty() => String::from("none"),
+        enabled => enabled.join(", ")
+    };
+    println!("A (left): {}", name(a_quirks));
+    println!("B (right): {}", name(b_quirks));
+    print!("{}", comparison.render_side_by_side());
+    if let Err(e) = result {
+        println!("Stopped after {} frames: {}", comparison.get_frame(), e);
+    }
+    Ok(())
+}
+
+/// clip8 heatmap
+fn heatmap(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(600);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let png = args.value(&["--png"])?;
+    let scale = args.number(&["--scale"])?.unwrap_or(8) as usize;
+    let pixels = args.flag(&["--pixels"]);
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    sys.ram.enable_heat();
+    sys.display.enable_activity();
+    for frame in 0..frames {
+        if let Err(e) = sys.run_frame(cycles) {
+            println!("Stopped after {} frames: {}", frame, e);
+            break;
+        }
+    }
+
+    let heat = match sys.ram.get_heat() {
+        Some(heat) => heat,
+        None => return Err(String::from("memory counters were not enabled").into())
+    };
+    let toggles = match sys.display.get_activity() {
+        Some(toggles) => toggles,
+        None => return Err(String::from("pixel counters were not enabled").into())
+    };
+    let display = sys.display.get_display();
+    if pixels && png.is_none() {
+        print!("{}", heatmap::render_pixel_overlay_ansi(&display, toggles, sys.display.get_width()));
+        return Ok(());
+    }
+    match png {
+        Some(out) => {
+            let image = match pixels {
+                true => heatmap::pixel_overlay(&display, toggles, sys.display.get_width()),
+                false => heat.to_image()
+            };
+            image.scaled(scale)
+                .save_png(&out)
+                .map_err(|e| format!("could not write {}: {}", out, e))?;
+            println!("Wrote heatmap to {}", out);
+        },
+        None => print!("{}", heat.render_ansi())
+    }
+    Ok(())
+}
+
+/// clip8 stats
+fn stats(mut args: Args) -> Result<(), CliError> {
+    let frames = args.number(&["--frames"])?.unwrap_or(600);
+    let cycles = args.number(&["--cycles"])?.unwrap_or(10) as u32;
+    let every = args.number(&["--every"])?.unwrap_or(1).max(1);
+    let path = args.positional("ROM path")?;
+    args.finish()?;
+
+    let mut sys = ChipSystem::init();
+    load_rom(&mut sys, &path)?;
+    for frame in 1..=frames {
+        if let Err(e) = sys.run_frame(cycles) {
+            println!("Stopped after {} frames: {}", frame - 1, e);
+            break;
+        }
+        if frame % every == 0 {
+            println!("{:>6}  {}", frame, sys.stats.get_last().hud());
+        }
+    }
+
+    let total = sys.stats.get_total();
+    let count = sys.stats.get_frames().max(1) as f64;
+    println!("Totals over {} frames:", sys.stats.get_frames());
+    println!("  {}", total.hud());
+    println!(
+        "Per frame: {:.1} draws, {:.1} sprite rows, {:.1} collisions, {:.1} pixels toggled",
+        total.draws as f64 / count, total.sprite_rows as f64 / count,
+        total.collisions as f64 / count, total.pixels_toggled as f64 / count
+    );
+    Ok(())
+}
+
+/// clip8 recent
+fn recent(mut args: Args) -> Result<(), CliError> {
+    let choice = match args.args.iter().position(|a| !a.starts_with('-')) {
+        Some(_) => Some(parse_number(&args.positional("entry")?).map_err(CliError::Usage)?),
+        None => None
+    };
+
+    let file = RecentRoms::default_path().ok_or_else(|| String::from("could not find a configuration directory"))?;
+    let list = RecentRoms::load(&file, recent::DEFAULT_LIMIT)
+        .map_err(|e| format!("could not read {}: {}", file.display(), e))?;
+    if list.get_entries().is_empty() {
+        println!("No recent ROMs, they are remembered when opened with clip8 run");
+        return Ok(());
+    }
+    let choice = match choice {
+        Some(choice) => choice,
+        None => {
+            for (index, entry) in list.get_entries().iter().enumerate() {
+                println!(
+                    "{:>2}. {:<24} cycles {:<4} {}/{}  {}",
+                    index + 1, entry.name(), entry.cycles,
+                    Palette::hex(entry.palette.on), Palette::hex(entry.palette.off), entry.path
+                );
+            }
+            if !io::stdin().is_terminal() {
+                return Ok(());
+            }
+            print!("Relaunch [1-{}, enter to quit]: ", list.get_entries().len());
+            io::stdout().flush().map_err(|e| e.to_string())?;
+            let mut line = String::new();
+            io::stdin().read_line(&mut line).map_err(|e| e.to_string())?;
+            match line.trim() {
+                "" => return Ok(()),
+                number => parse_number(number).map_err(CliError::Usage)?
+            }
+        }
+    };
+    let entry = match (choice as usize).checked_sub(1).and_then(|index| list.get(index)) {
+        Some(entry) => entry.clone(),
+        None => return Err(CliError::Usage(format!("no recent ROM {}, pick 1-{}", choice, list.get_entries().len())))
+    };
+    if let Ok(rom) = fs::read(&entry.path) {
+        if recent::rom_hash(&rom) != entry.hash {
+            eprintln!("Warning: {} has changed since it was last opened", entry.path);
+        }
+    }
+    // Options left over are passed on to run
+    let mut run_args = vec![
+        entry.path,
+        String::from("--cycles"), entry.cycles.to_string(),
+        String::from("--fg"), Palette::hex(entry.palette.on),
+        String::from("--bg"), Palette::hex(entry.palette.off)
+    ];
+    run_args.append(&mut args.args);
+    run(Args { args: run_args })
+}
+
+/// clip8 attract
+fn attract(mut args: Args) -> Result<(), CliError> {
+    let seconds = args.number(&["--seconds"])?.unwrap_or(30);
+    let cycles = args.cycles()?;
+    let once = args.flag(&["--once"]);
+    let dir = args.positional("ROM directory")?;
+    args.finish()?;
+
+    let roms = attract::find_roms(Path::new(&dir)).map_err(|e| format!("could not read {}: {}", dir, e))?;
+    if roms.is_empty() {
+        return Err(format!("no ROMs found in {}", dir).into());
+    }
+    let frames_per_rom = seconds * 60;
+    loop {
+        for rom in roms.iter() {
+            let path = rom.display().to_string();
+            let mut sys = ChipSystem::init();
+            // A broken ROM should not stop the rest of the show
+            if let Err(e) = load_rom(&mut sys, &path) {
+                eprintln!("Skipping {}", e);
+                continue;
+            }
+            let cycles = match cycles {
+                Some(cycles) => cycles,
+                None => tickrate::guess(&read_rom(&path)?).cycles
+            };
+            let mut demo = match DemoInput::load_for(rom) {
+                Ok(demo) => demo,
+                Err(e) => {
+                    eprintln!("Warning: ignoring demo inputs for {}: {}", path, e);
+                    None
+                }
+            };
+            println!("Now playing: {}", path);
+            for frame in 0..frames_per_rom {
+                if let Some(demo) = demo.as_mut() {
+                    demo.apply(frame, &mut sys.keyboard);
+                }
+                if let Err(e) = sys.run_frame(cycles) {
+                    eprintln!("{} stopped after {} frames: {}", path, frame, e);
+                    break;
+                }
+                if sys.display.mod_check() {
+                    sys.display.draw_display();
+                }
+                if sys.get_exit().is_some() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(16));
+            }
+        }
+        if once {
+            return Ok(());
+        }
+    }
+}
+
+/// Load the beep settings from a file, or from the default config
+/// file when none is given
+fn load_tone(path: Option<String>) -> Result<ToneSettings, String> {
+    match path.map(PathBuf::from).or_else(ToneSettings::default_path) {
+        Some(file) => ToneSettings::load(&file).map_err(|e| format!("could not load {}: {}", file.display(), e)),
+        None => Ok(ToneSettings::default())
+    }
+}
+
+/// Load the interpreter quirks from a file, or from the default config
+/// file when none is given
+fn load_quirks(path: Option<String>) -> Result<Quirks, String> {
+    match path.map(PathBuf::from).or_else(Quirks::default_path) {
+        Some(file) => Quirks::load(&file).map_err(|e| format!("could not load {}: {}", file.display(), e)),
+        None => Ok(Quirks::default())
+    }
+}
+
+/// Load the hotkeys from the default config file
+fn load_hotkeys() -> Result<HotkeyMap, String> {
+    match HotkeyMap::default_path() {
+        Some(file) => HotkeyMap::load(&file).map_err(|e| format!("could not load {}: {}", file.display(), e)),
+        None => Ok(HotkeyMap::init())
+    }
+}
+
+/// Load the keypad mapping from a file, or from the default config
+/// file when none is given
+fn load_keymap(path: Option<String>) -> Result<KeyMap, String> {
+    match path.map(PathBuf::from).or_else(KeyMap::default_path) {
+        Some(file) => KeyMap::load(&file).map_err(|e| format!("could not load {}: {}", file.display(), e)),
+        None => Ok(KeyMap::init())
+    }
+}
+
+/// clip8 keymap
+fn keymap(mut args: Args) -> Result<(), CliError> {
+    let path = args.value(&["--keymap"])?;
+    args.finish()?;
+    if let Some(file) = path.clone().map(PathBuf::from).or_else(KeyMap::default_path) {
+        println!("# {}", file.display());
+    }
+    print!("{}", load_keymap(path)?.to_config());
+    Ok(())
+}
+
+/// clip8 hotkeys
+fn hotkeys(args: Args) -> Result<(), CliError> {
+    args.finish()?;
+    let map = match HotkeyMap::default_path() {
+        Some(file) => {
+            println!("# {}", file.display());
+            HotkeyMap::load(&file).map_err(|e| format!("could not load {}: {}", file.display(), e))?
+        },
+        None => HotkeyMap::init()
+    };
+    print!("{}", map.to_config());
+    Ok(())
+}
+
+fn main() {
+    let mut argv: Vec<String> = env::args().skip(1).collect();
+    if argv.is_empty() {
+        eprint!("{}", USAGE);
+        process::exit(2);
+    }
+    let command = argv.remove(0);
+    let args = Args { args: argv };
+
+    let result = match command.as_str() {
+        "run" => run(args),
+        "netplay" => netplay(args),
+        "tutorial" => tutorial(args),
+        "explain" => explain(args),
+        "teach" => teach(args),
+        "corrupt" => corrupt_rom(args),
+        "test-script" => test_script(args),
+        "svg" => svg(args),
+        "text" => text(args),
+        "trim" => trim(args),
+        "multicart" => multicart(args),
+        "disasm" => disassemble(args),
+        "asm" => assemble(args),
+        "link" => link(args),
+        "sprite-from-image" => sprite_from_image(args),
+        "diff" => diff(args),
+        "callgraph" => callgraph(args),
+        "gen-stress" => gen_stress(args),
+        "quirks" => quirks(args),
+        "compare" => compare(args),
+        "heatmap" => heatmap(args),
+        "stats" => stats(args),
+        "trace" => trace(args),
+        "memory-dump" => memory_dump(args),
+        "recent" | "--recent" => recent(args),
+        "attract" => attract(args),
+        "hotkeys" => hotkeys(args),
+        "keymap" => keymap(args),
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            Ok(())
+        },
+        _ => Err(CliError::Usage(format!("unknown command: {}", command)))
+    };
+    // Trace lines still buffered when a run stops on an error
+    log::logger().flush();
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        if let CliError::Usage(_) = e {
+            eprint!("{}", USAGE);
+        }
+        process::exit(1);
+    }
+}
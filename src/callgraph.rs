@@ -0,0 +1,140 @@
+// Standard Library Modules //
+use std::collections::BTreeMap;
+
+// Local Modules Use //
+use super::flow::{self, START};
+use super::ChipSystem;
+
+/// A subroutine call from one routine to another
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallEdge {
+    /// Found by following the code without running it
+    pub found_static: bool,
+    /// Times the call was made while running
+    pub calls: u64
+}
+
+/// Which routines call which, found by static analysis of a ROM and
+/// optionally counted while running it
+///
+/// Routines are named by their entry address, the program itself is
+/// the routine at 0x200.
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    /// Entry address of every routine and the number of opcodes found
+    /// in it, 0 for routines only seen while running
+    routines: BTreeMap<u16, usize>,
+    /// Calls by caller and callee
+    edges: BTreeMap<(u16, u16), CallEdge>,
+    /// Entry addresses of the routines being run, innermost last
+    stack: Vec<u16>
+}
+
+impl CallGraph {
+    /// Build the call graph of a ROM by following its code from 0x200
+    /// and into every routine it calls
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - rom contents
+    pub fn from_rom(rom: &[u8]) -> Self {
+        let mut graph = CallGraph::default();
+        let mut pending = vec![START];
+        while let Some(entry) = pending.pop() {
+            if graph.routines.contains_key(&entry) {
+                continue;
+            }
+            let (opcodes, calls) = flow::walk_routine(rom, entry);
+            graph.routines.insert(entry, opcodes.len());
+            for callee in calls {
+                graph.edges.entry((entry, callee)).or_default().found_static = true;
+                pending.push(callee);
+            }
+        }
+        graph
+    }
+
+    /// Count a call or return about to run, use as the trace function
+    /// of `ChipSystem::run_frame_traced`
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system about to run the opcode
+    /// * `opcode` - opcode at the program counter
+    pub fn observe(&mut self, _sys: &ChipSystem, opcode: u16) {
+        match flow::flow(opcode) {
+            flow::Flow::Call(callee) => {
+                let caller = self.stack.last().copied().unwrap_or(START);
+                self.routines.entry(callee).or_insert(0);
+                self.edges.entry((caller, callee)).or_default().calls += 1;
+                self.stack.push(callee);
+            },
+            flow::Flow::Return => {
+                self.stack.pop();
+            },
+            _ => {}
+        }
+    }
+
+    /// Get the entry address of every routine
+    pub fn get_routines(&self) -> Vec<u16> {
+        self.routines.keys().copied().collect()
+    }
+
+    /// Get every call as caller, callee and edge, sorted by caller
+    pub fn get_edges(&self) -> Vec<(u16, u16, CallEdge)> {
+        self.edges.iter().map(|((caller, callee), edge)| (*caller, *callee, *edge)).collect()
+    }
+
+    /// Name used for a routine in exports
+    fn name(addr: u16) -> String {
+        match addr {
+            START => String::from("main"),
+            _ => format!("sub_{:03X}", addr)
+        }
+    }
+
+    /// Export as a Graphviz digraph, edges are labeled with how often
+    /// they ran and calls only seen while running are dashed
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph calls {\n    node [shape=box, fontname=monospace];\n");
+        for (addr, size) in self.routines.iter() {
+            out.push_str(&format!(
+                "    \"{}\" [label=\"{}\\n0x{:03X}, {} opcodes\"];\n",
+                Self::name(*addr), Self::name(*addr), addr, size
+            ));
+        }
+        for ((caller, callee), edge) in self.edges.iter() {
+            let mut attrs = vec![format!("label=\"{}\"", edge.calls)];
+            if !edge.found_static {
+                attrs.push(String::from("style=dashed"));
+            }
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\" [{}];\n",
+                Self::name(*caller), Self::name(*callee), attrs.join(", ")
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Export as JSON with a `routines` and a `calls` list
+    pub fn to_json(&self) -> String {
+        let routines: Vec<String> = self.routines.iter()
+            .map(|(addr, size)| format!(
+                "    {{\"name\": \"{}\", \"address\": {}, \"opcodes\": {}}}",
+                Self::name(*addr), addr, size
+            ))
+            .collect();
+        let calls: Vec<String> = self.edges.iter()
+            .map(|((caller, callee), edge)| format!(
+                "    {{\"caller\": \"{}\", \"callee\": \"{}\", \"static\": {}, \"calls\": {}}}",
+                Self::name(*caller), Self::name(*callee), edge.found_static, edge.calls
+            ))
+            .collect();
+        format!(
+            "{{\n  \"routines\": [\n{}\n  ],\n  \"calls\": [\n{}\n  ]\n}}\n",
+            routines.join(",\n"), calls.join(",\n")
+        )
+    }
+}
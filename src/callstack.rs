@@ -0,0 +1,87 @@
+// Standard Library Modules //
+use std::fmt;
+
+// Local Modules Use //
+use super::ChipSystem;
+
+/// One subroutine call on the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallFrame {
+    /// Address of the CALL, execution returns to the opcode after it
+    pub call_site: u16,
+    /// Address the CALL jumped to, None if the word at the call site
+    /// is no longer a CALL because the program overwrote it
+    pub entry: Option<u16>
+}
+
+impl CallFrame {
+    /// Read the call made from an address on the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system holding the program
+    /// * `call_site` - address pushed by the CALL
+    pub fn read(sys: &ChipSystem, call_site: u16) -> Self {
+        // Peeking keeps the debugger out of the heatmap
+        let bytes = sys.ram.peek_bytes(call_site, 2);
+        let opcode = ((bytes[0] as u16) << 8) | bytes[1] as u16;
+        let entry = match opcode & 0xF000 {
+            0x2000 => Some(opcode & 0x0FFF),
+            _ => None
+        };
+        CallFrame { call_site, entry }
+    }
+}
+
+impl fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.entry {
+            Some(entry) => write!(f, "{:03x} -> {:03x}", self.call_site, entry),
+            None => write!(f, "{:03x} -> ???", self.call_site)
+        }
+    }
+}
+
+/// Get the calls on the stack, the oldest first
+///
+/// # Arguments
+///
+/// * `sys` - system to read
+pub fn call_frames(sys: &ChipSystem) -> Vec<CallFrame> {
+    sys.registers.get_stack().iter().map(|addr| CallFrame::read(sys, *addr)).collect()
+}
+
+/// Describe the call stack as a backtrace, one line per level with the
+/// innermost first, such as
+///
+/// ```text
+/// #0 pc 24a in 240
+/// #1 at 21c in 230
+/// #2 at 206 in main
+/// ```
+///
+/// The first line is where execution is now, each line after it the
+/// CALL that the line above returns to. A subroutine is named by its
+/// entry address, `main` is the code that is not in any call. Each
+/// line comes with the address it is at, for adding source lines.
+///
+/// # Arguments
+///
+/// * `sys` - system to read
+pub fn backtrace(sys: &ChipSystem) -> Vec<(u16, String)> {
+    let frames = call_frames(sys);
+    let name = |depth: usize| match depth {
+        0 => String::from("main"),
+        depth => match frames[depth - 1].entry {
+            Some(entry) => format!("{:03x}", entry),
+            None => String::from("???")
+        }
+    };
+    let pc = sys.registers.get_pc();
+    let mut lines = vec![(pc, format!("#0 pc {:03x} in {}", pc, name(frames.len())))];
+    for (level, depth) in (0..frames.len()).rev().enumerate() {
+        let site = frames[depth].call_site;
+        lines.push((site, format!("#{} at {:03x} in {}", level + 1, site, name(depth))));
+    }
+    lines
+}
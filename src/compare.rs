@@ -0,0 +1,177 @@
+// Local Modules Use //
+use super::{ChipSystem, ExResult};
+use super::memory::{RomError, RomWarning};
+
+/// Information about the first frame where two systems differ
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    /// Frame number (starting at 1) where the displays first differed
+    pub frame: u64,
+    /// Program counter of the left system at the end of the frame
+    pub left_pc: u16,
+    /// Program counter of the right system at the end of the frame
+    pub right_pc: u16,
+    /// How many pixels differ between the two displays
+    pub pixels: usize
+}
+
+/// Two chip8 systems running the same program in lockstep
+///
+/// Both systems receive the same ROM and key presses and are run one
+/// frame at a time, after each frame the displays are compared so the
+/// first frame where they diverge can be reported. Useful to check how
+/// a change to the core (or a different configuration) affects a ROM.
+pub struct ChipComparison {
+    /// System displayed on the left
+    pub left: ChipSystem,
+    /// System displayed on the right
+    pub right: ChipSystem,
+    /// Opcodes executed per frame on each system
+    cycles: u32,
+    /// Number of frames run so far
+    frame: u64,
+    /// First frame where the displays did not match
+    divergence: Option<Divergence>
+}
+
+impl ChipComparison {
+    /// Initialize a comparison between two systems
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - first system to run
+    /// * `right` - second system to run
+    /// * `cycles` - opcodes to execute on each system per frame
+    pub fn init(left: ChipSystem, right: ChipSystem, cycles: u32) -> Self {
+        ChipComparison {
+            left,
+            right,
+            cycles,
+            frame: 0,
+            divergence: None
+        }
+    }
+
+    /// Load the same ROM into both systems, returning any warnings
+    /// about it
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - a u8 vector representing the rom
+    pub fn load_rom(&mut self, rom: Vec<u8>) -> Result<Vec<RomWarning>, RomError> {
+        self.left.load_rom(rom.clone())?;
+        self.right.load_rom(rom)
+    }
+
+    /// Set a key on both systems to pressed (true) or not pressed (false)
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - index of the key (0-15)
+    /// * `value` - true or false
+    pub fn set_key(&mut self, index: u8, value: bool) {
+        self.left.keyboard.set_key(index, value);
+        self.right.keyboard.set_key(index, value);
+    }
+
+    /// Get the number of frames that have been run
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Get the first divergence found, if any
+    pub fn first_divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    /// Run a frame on both systems and compare their displays
+    ///
+    /// Returns true if the displays differ after this frame, the first
+    /// frame this happens on is kept and available via `first_divergence`
+    pub fn run_frame(&mut self) -> ExResult<bool> {
+        self.left.run_frame(self.cycles)?;
+        self.right.run_frame(self.cycles)?;
+        self.frame += 1;
+
+        let pixels = self.diff_count();
+        if pixels > 0 && self.divergence.is_none() {
+            self.divergence = Some(Divergence {
+                frame: self.frame,
+                left_pc: self.left.registers.get_pc(),
+                right_pc: self.right.registers.get_pc(),
+                pixels
+            });
+        }
+        Ok(pixels > 0)
+    }
+
+    /// Run frames until the displays diverge or `max_frames` have been run
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frames` - upper bound on frames to run
+    pub fn run_until_divergence(&mut self, max_frames: u64) -> ExResult<Option<&Divergence>> {
+        for _ in 0..max_frames {
+            if self.run_frame()? {
+                break;
+            }
+        }
+        Ok(self.divergence.as_ref())
+    }
+
+    /// Count the pixels that differ between the two displays
+    fn diff_count(&self) -> usize {
+        let left = self.left.display.get_display();
+        let right = self.right.display.get_display();
+        left.iter().zip(right.iter()).filter(|(l, r)| l != r).count()
+    }
+
+    /// Render both displays side by side into a string
+    ///
+    /// Pixels that match are drawn as `#` or a space, pixels that differ
+    /// are drawn as `*` on the side where they are set and `.` on the
+    /// side where they are clear.
+    pub fn render_side_by_side(&self) -> String {
+        let left = self.left.display.get_display();
+        let right = self.right.display.get_display();
+        let (width, height) = (self.left.display.get_width(), self.left.display.get_height());
+        let divider = "-".repeat(width);
+        let mut out = String::new();
+
+        out.push_str(&format!("|{}|{}|\n", divider, divider));
+        for y in 0..height {
+            let mut left_row = String::with_capacity(width);
+            let mut right_row = String::with_capacity(width);
+            for x in 0..width {
+                let pos: usize = y * width + x;
+                left_row.push(ChipComparison::cell(left[pos], right[pos]));
+                right_row.push(ChipComparison::cell(right[pos], left[pos]));
+            }
+            out.push_str(&format!("|{}|{}|\n", left_row, right_row));
+        }
+        out.push_str(&format!("|{}|{}|\n", divider, divider));
+        match &self.divergence {
+            Some(d) => out.push_str(&format!(
+                "Frame {}: diverged at frame {} ({} pixels, PC {:03x} / {:03x})\n",
+                self.frame, d.frame, d.pixels, d.left_pc, d.right_pc
+            )),
+            None => out.push_str(&format!("Frame {}: displays match\n", self.frame))
+        }
+        out
+    }
+
+    /// Draw both displays side by side in the terminal
+    pub fn draw_side_by_side(&self) {
+        print!("{}", self.render_side_by_side());
+    }
+
+    /// Pick the character for a pixel given its value on both systems
+    fn cell(this: bool, other: bool) -> char {
+        match (this, this == other) {
+            (true, true) => '#',
+            (false, true) => ' ',
+            (true, false) => '*',
+            (false, false) => '.'
+        }
+    }
+}
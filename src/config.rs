@@ -0,0 +1,17 @@
+// Standard Library Modules //
+use std::env;
+use std::path::PathBuf;
+
+/// Directory clip8 keeps its settings in, `clip8` inside the user's
+/// configuration directory (`$XDG_CONFIG_HOME`, `%APPDATA%` or
+/// `~/.config`), None if none of them are set
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("APPDATA") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config")
+        }
+    };
+    Some(base.join("clip8"))
+}
@@ -0,0 +1,186 @@
+// Standard Library Modules //
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+// Local Modules Use //
+use super::{ChipSystem, EmulatorError, ExitReason};
+use super::crashreport;
+use super::memwatch::WatchRange;
+
+// Modules From Crates.io //
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::index;
+
+/// Address ROMs are loaded at
+const ROM_START: u16 = 0x200;
+
+/// How a corrupted byte is changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorruptMode {
+    /// Flip one bit, small glitches that often keep the game running
+    Flip,
+    /// Replace the byte with a random value
+    Randomize
+}
+
+impl CorruptMode {
+    /// Get a mode from its name, `flip` or `random`
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the mode
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "flip" => Some(CorruptMode::Flip),
+            "random" => Some(CorruptMode::Randomize),
+            _ => None
+        }
+    }
+}
+
+/// A byte changed by the corruptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Corruption {
+    /// Address of the byte once loaded
+    pub addr: u16,
+    /// Value in the original ROM
+    pub old: u8,
+    /// Value in the corrupted ROM
+    pub new: u8
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:03x} {:02x} -> {:02x}", self.addr, self.old, self.new)
+    }
+}
+
+/// Corrupts bytes of a ROM for glitch art and robustness testing
+///
+/// Every reset gets its own corruption, picked from the seed and the
+/// reset number so the same seed always glitches a ROM the same way
+/// and an interesting glitch can be found again.
+#[derive(Debug, Clone)]
+pub struct Corruptor {
+    /// Addresses that can be corrupted, empty for the whole ROM
+    ranges: Vec<WatchRange>,
+    /// How bytes are changed
+    mode: CorruptMode,
+    /// Bytes changed per reset
+    count: usize,
+    /// Seed of the corruptions
+    seed: u64
+}
+
+impl Corruptor {
+    /// Initialize a corruptor for the whole ROM
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - how bytes are changed
+    /// * `count` - bytes changed per reset
+    /// * `seed` - seed of the corruptions
+    pub fn init(mode: CorruptMode, count: usize, seed: u64) -> Self {
+        Corruptor {
+            ranges: Vec::new(),
+            mode,
+            count,
+            seed
+        }
+    }
+
+    /// Only corrupt bytes in a range of addresses, call more than once
+    /// for several ranges
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - addresses the ROM is loaded to that can be changed
+    pub fn add_range(&mut self, range: WatchRange) {
+        self.ranges.push(range);
+    }
+
+    /// Get the ranges that can be corrupted, empty for the whole ROM
+    pub fn get_ranges(&self) -> &[WatchRange] {
+        &self.ranges
+    }
+
+    /// Corrupt a ROM for one reset, returning the corrupted ROM and
+    /// the bytes changed. Each byte is changed at most once and range
+    /// addresses outside the ROM are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - original ROM
+    /// * `reset` - number of the reset, 0 for the first run
+    pub fn corrupt(&self, rom: &[u8], reset: u64) -> (Vec<u8>, Vec<Corruption>) {
+        let end = ROM_START as usize + rom.len();
+        let addrs: Vec<u16> = match self.ranges.is_empty() {
+            true => (ROM_START as usize..end).map(|addr| addr as u16).collect(),
+            false => self.ranges.iter()
+                .flat_map(|range| range.start..=range.end)
+                .filter(|addr| *addr >= ROM_START && (*addr as usize) < end)
+                .collect()
+        };
+        let mut out = rom.to_vec();
+        let mut changes = Vec::new();
+        let mut rng = StdRng::seed_from_u64(self.seed ^ reset.wrapping_mul(0x9e3779b97f4a7c15));
+        for pick in index::sample(&mut rng, addrs.len(), self.count.min(addrs.len())).into_vec() {
+            let addr = addrs[pick];
+            let offset = (addr - ROM_START) as usize;
+            let old = out[offset];
+            out[offset] = match self.mode {
+                CorruptMode::Flip => old ^ (1 << rng.gen_range(0, 8)),
+                CorruptMode::Randomize => rng.gen()
+            };
+            changes.push(Corruption { addr, old, new: out[offset] });
+        }
+        changes.sort_by_key(|change| change.addr);
+        (out, changes)
+    }
+}
+
+/// How a run of a corrupted ROM ended
+#[derive(Debug, Clone)]
+pub enum RunOutcome {
+    /// Every frame ran
+    Survived,
+    /// The program stopped on its own
+    Exited(ExitReason),
+    /// An invalid opcode was run
+    Failed(EmulatorError),
+    /// The emulator gave up on the program, such as when the stack
+    /// overflowed, with the reason
+    Crashed(String)
+}
+
+impl fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunOutcome::Survived => write!(f, "survived"),
+            RunOutcome::Exited(ExitReason::ExitOpcode) => write!(f, "exited with 00FD"),
+            RunOutcome::Exited(ExitReason::JumpToSelf) => write!(f, "stopped with a jump to itself"),
+            RunOutcome::Failed(e) => write!(f, "failed: {}", e),
+            RunOutcome::Crashed(reason) => write!(f, "crashed: {}", reason)
+        }
+    }
+}
+
+/// Run a frame of a system that may hold a corrupted program, turning
+/// a crash of the emulator into a `RunOutcome` instead of ending the
+/// process. Returns None while the program keeps running.
+///
+/// A crash leaves the system part way through an opcode, it should be
+/// reset before running again.
+///
+/// # Arguments
+///
+/// * `sys` - system to run
+/// * `cycles` - opcodes run per frame
+pub fn run_frame_guarded(sys: &mut ChipSystem, cycles: u32) -> Option<RunOutcome> {
+    match panic::catch_unwind(AssertUnwindSafe(|| sys.run_frame(cycles))) {
+        Ok(Ok(())) => sys.get_exit().map(RunOutcome::Exited),
+        Ok(Err(e)) => Some(RunOutcome::Failed(e)),
+        Err(payload) => Some(RunOutcome::Crashed(crashreport::panic_message(payload.as_ref())))
+    }
+}
@@ -0,0 +1,117 @@
+// Standard Library Modules //
+use std::any::Any;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::disasm;
+use super::netplay::rom_hash;
+use super::statedump::state_dump;
+
+/// Get the message a panic was raised with
+///
+/// # Arguments
+///
+/// * `payload` - payload caught by `std::panic::catch_unwind`
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(reason), _) => reason.to_string(),
+        (None, Some(reason)) => reason.clone(),
+        (None, None) => String::from("unknown error")
+    }
+}
+
+/// Everything needed to act on a bug report about a run that stopped
+/// with an error: which ROM, how it was run, the machine at the time
+/// and the instructions that led there
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    /// Name of the ROM, usually its path
+    pub rom_name: String,
+    /// 64 bit FNV-1a hash of the ROM, the same as netplay checks
+    pub rom_hash: u64,
+    /// Size of the ROM in bytes
+    pub rom_size: usize,
+    /// Why the run stopped
+    pub error: String,
+    /// Settings the ROM was run with, as names and values
+    pub config: Vec<(String, String)>,
+    /// Machine state, see `statedump::state_dump`
+    pub state: String,
+    /// Last opcodes executed with their program counters, oldest first
+    pub instructions: Vec<(u16, u16)>
+}
+
+impl CrashReport {
+    /// Capture a report from a system that just stopped
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system that stopped
+    /// * `rom_name` - name of the ROM
+    /// * `rom` - ROM bytes as loaded
+    /// * `error` - why it stopped
+    pub fn init(sys: &ChipSystem, rom_name: &str, rom: &[u8], error: &str) -> Self {
+        CrashReport {
+            rom_name: rom_name.to_string(),
+            rom_hash: rom_hash(rom),
+            rom_size: rom.len(),
+            error: error.to_string(),
+            config: vec![
+                (String::from("quirks"), format!("{:?}", sys.quirks)),
+                (String::from("memory"), format!("{} bytes", sys.ram.get_size())),
+                (String::from("display"), format!("{}x{}", sys.display.get_width(), sys.display.get_height())),
+                (String::from("timers"), format!("{:?}", sys.registers.get_timer_mode())),
+                (String::from("runaway check"), sys.get_runaway_check().to_string())
+            ],
+            state: state_dump(sys),
+            instructions: sys.get_instruction_history()
+        }
+    }
+
+    /// Add a setting the system does not know about, such as the
+    /// opcodes run per frame
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the setting
+    /// * `value` - value it had
+    pub fn add_config(&mut self, name: &str, value: &str) {
+        self.config.push((name.to_string(), value.to_string()));
+    }
+
+    /// Write the report into a directory as `clip8-crash-<time>.txt`,
+    /// returning the path written
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - directory to write to
+    pub fn write_to(&self, dir: &Path) -> io::Result<PathBuf> {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let path = dir.join(format!("clip8-crash-{}.txt", time));
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "clip8 crash report, version {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(f, "Error: {}", self.error)?;
+        writeln!(f, "ROM: {} ({} bytes, hash {:016x})", self.rom_name, self.rom_size, self.rom_hash)?;
+        writeln!(f, "\nConfig:")?;
+        for (name, value) in self.config.iter() {
+            writeln!(f, "  {}: {}", name, value)?;
+        }
+        writeln!(f, "\nLast {} instructions, oldest first:", self.instructions.len())?;
+        for (pc, opcode) in self.instructions.iter() {
+            writeln!(f, "  {:03x}  {:04x}  {}", pc, opcode, disasm::mnemonic(*opcode))?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", self.state)
+    }
+}
@@ -0,0 +1,191 @@
+// Standard Library Modules //
+use std::panic;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::quirks::{Quirks, QUIRK_NAMES};
+
+/// Frame hashes from running a ROM under one set of quirks
+#[derive(Debug, Clone)]
+pub struct QuirkRun {
+    /// Quirks the ROM ran with
+    pub quirks: Quirks,
+    /// Display hash at the end of every frame
+    pub hashes: Vec<u64>,
+    /// Why the run stopped early, if it did
+    pub error: Option<String>
+}
+
+/// Results of running a ROM under every combination of quirks
+pub struct QuirkReport {
+    /// Frames each combination was run for
+    pub frames: u64,
+    /// Opcodes executed per frame
+    pub cycles: u32,
+    /// One run per combination, index is the `Quirks` bit mask
+    pub runs: Vec<QuirkRun>,
+    /// First frame where two runs with identical quirks disagreed,
+    /// caused by the ROM using random numbers. Frames from here on
+    /// can't be compared.
+    pub noise_frame: Option<u64>
+}
+
+/// Run a ROM under every combination of quirks and record a display
+/// hash for each frame so differences in behaviour can be found
+///
+/// # Arguments
+///
+/// * `rom` - the ROM to test
+/// * `frames` - how many frames to run each combination for
+/// * `cycles` - opcodes executed per frame
+pub fn run_all(rom: &[u8], frames: u64, cycles: u32) -> QuirkReport {
+    let combos = 1u8 << QUIRK_NAMES.len();
+    let runs: Vec<QuirkRun> = (0..combos)
+        .map(|bits| run_one(rom, Quirks::from_bits(bits), frames, cycles))
+        .collect();
+
+    // A second baseline run shows where randomness takes over
+    let control = run_one(rom, Quirks::default(), frames, cycles);
+    let noise_frame = first_difference(&runs[0].hashes, &control.hashes);
+    QuirkReport {
+        frames,
+        cycles,
+        runs,
+        noise_frame
+    }
+}
+
+/// Run a ROM under one set of quirks
+fn run_one(rom: &[u8], quirks: Quirks, frames: u64, cycles: u32) -> QuirkRun {
+    let mut hashes: Vec<u64> = Vec::with_capacity(frames as usize);
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let mut sys = ChipSystem::init();
+        sys.quirks = quirks;
+        if let Err(e) = sys.load_rom(rom.to_vec()) {
+            return Some(format!("{}", e));
+        }
+        for _ in 0..frames {
+            if let Err(e) = sys.run_frame(cycles) {
+                return Some(format!("{}", e));
+            }
+            hashes.push(sys.display.hash());
+        }
+        None
+    }));
+    let error = match result {
+        Ok(error) => error,
+        Err(_) => Some(String::from("emulator panicked"))
+    };
+    QuirkRun {
+        quirks,
+        hashes,
+        error
+    }
+}
+
+/// Find the first frame (starting at 1) where two lists of hashes differ
+fn first_difference(a: &[u64], b: &[u64]) -> Option<u64> {
+    for (index, (ha, hb)) in a.iter().zip(b.iter()).enumerate() {
+        if ha != hb {
+            return Some(index as u64 + 1);
+        }
+    }
+    match a.len() == b.len() {
+        true => None,
+        false => Some(a.len().min(b.len()) as u64 + 1)
+    }
+}
+
+impl QuirkReport {
+    /// Run with every quirk turned off
+    pub fn baseline(&self) -> &QuirkRun {
+        &self.runs[0]
+    }
+
+    /// First frame where a run differs from the baseline, ignoring
+    /// differences from the noise frame onwards
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - run to compare against the baseline
+    pub fn divergence(&self, run: &QuirkRun) -> Option<u64> {
+        let frame = first_difference(&self.baseline().hashes, &run.hashes)?;
+        match self.noise_frame {
+            Some(noise) if frame >= noise => None,
+            _ => Some(frame)
+        }
+    }
+
+    /// Every flag with the frame its change first showed up when it
+    /// is the only flag enabled, None if it made no difference
+    pub fn flag_effects(&self) -> Vec<(&'static str, Option<u64>)> {
+        QUIRK_NAMES.iter()
+            .enumerate()
+            .map(|(index, name)| (*name, self.divergence(&self.runs[1 << index])))
+            .collect()
+    }
+
+    /// Group the combinations that behaved the same way
+    pub fn behaviour_groups(&self) -> Vec<Vec<Quirks>> {
+        let mut groups: Vec<(usize, Vec<Quirks>)> = Vec::new();
+        for (index, run) in self.runs.iter().enumerate() {
+            let matching = groups.iter_mut().find(|(first, _)| {
+                let other = &self.runs[*first];
+                match first_difference(&other.hashes, &run.hashes) {
+                    None => true,
+                    Some(frame) => self.noise_frame.is_some_and(|noise| frame >= noise)
+                }
+            });
+            match matching {
+                Some((_, group)) => group.push(run.quirks),
+                None => groups.push((index, vec![run.quirks]))
+            }
+        }
+        groups.into_iter().map(|(_, group)| group).collect()
+    }
+
+    /// Render the report as readable text
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "Ran {} frames at {} opcodes per frame under {} quirk combinations\n",
+            self.frames, self.cycles, self.runs.len()
+        );
+        if let Some(noise) = self.noise_frame {
+            out.push_str(&format!(
+                "Note: the ROM uses random numbers, runs are only compared up to frame {}\n",
+                noise - 1
+            ));
+        }
+        if let Some(e) = &self.baseline().error {
+            out.push_str(&format!("Note: baseline run stopped early: {}\n", e));
+        }
+
+        out.push_str("\nFlags that change behaviour on their own:\n");
+        for (name, frame) in self.flag_effects() {
+            match frame {
+                Some(frame) => out.push_str(&format!("  {:<18} changes frame {}\n", name, frame)),
+                None => out.push_str(&format!("  {:<18} no change\n", name))
+            }
+        }
+
+        let groups = self.behaviour_groups();
+        out.push_str(&format!("\nDistinct behaviours: {}\n", groups.len()));
+        for (index, group) in groups.iter().enumerate() {
+            let run = &self.runs[group[0].to_bits() as usize];
+            let status = match &run.error {
+                Some(e) => format!("stopped: {}", e),
+                None => format!("final hash {:016x}", run.hashes.last().copied().unwrap_or(0))
+            };
+            out.push_str(&format!("  {}. {} combinations, {}\n", index + 1, group.len(), status));
+            let names: Vec<String> = group.iter()
+                .take(4)
+                .map(|q| match q.enabled() {
+                    flags if flags.is_empty() => String::from("[none]"),
+                    flags => format!("[{}]", flags.join(", "))
+                })
+                .collect();
+            out.push_str(&format!("     e.g. {}\n", names.join(" ")));
+        }
+        out
+    }
+}
@@ -0,0 +1,250 @@
+// Local Modules Use //
+use super::assembler;
+use super::flow::{self, Flow, START};
+use super::instruction::Instruction;
+use super::memory::ChipMemory;
+
+/// Disassemble an opcode into the mnemonics of Cowgod's Chip-8
+/// technical reference, e.g. `LD V3, 0x05` or `DRW V0, V1, 5`.
+/// Opcodes that are not instructions are written as `DW 0xNNNN`.
+///
+/// # Arguments
+///
+/// * `opcode` - two byte opcode to disassemble
+pub fn mnemonic(opcode: u16) -> String {
+    Instruction::decode(opcode).to_string()
+}
+
+/// Find which bytes of a ROM are code by following every path from
+/// 0x200 and from extra entry points, into every routine called and
+/// through the jump tables of BNNN jumps. Bytes never reached are data.
+///
+/// # Arguments
+///
+/// * `rom` - rom contents, loaded at 0x200
+/// * `entries` - other addresses known to hold code, such as jump
+///   targets seen while running
+pub fn code_map(rom: &[u8], entries: &[u16]) -> Vec<bool> {
+    let mut code = vec![false; rom.len()];
+    let mut walked = Vec::new();
+    let mut pending = vec![START];
+    pending.extend_from_slice(entries);
+    while let Some(entry) = pending.pop() {
+        if walked.contains(&entry) {
+            continue;
+        }
+        walked.push(entry);
+        let (opcodes, calls) = flow::walk_routine(rom, entry);
+        pending.extend(calls);
+        for addr in opcodes {
+            let offset = (addr - START) as usize;
+            let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+            let size = match flow::flow(opcode) {
+                Flow::Long => 4,
+                _ => 2
+            };
+            for byte in code.iter_mut().skip(offset).take(size) {
+                *byte = true;
+            }
+            // A computed jump usually lands in a table of jumps
+            if flow::flow(opcode) == Flow::Computed {
+                let mut entry = opcode & 0x0FFF;
+                while let Some(Instruction::Jump(target)) = opcode_at(rom, entry).map(Instruction::decode) {
+                    pending.push(entry);
+                    pending.push(target);
+                    entry += 2;
+                }
+            }
+        }
+    }
+    code
+}
+
+/// Get the opcode at an address, None outside the ROM
+fn opcode_at(rom: &[u8], addr: u16) -> Option<u16> {
+    let offset = (addr as usize).checked_sub(START as usize)?;
+    Some((*rom.get(offset)? as u16) << 8 | *rom.get(offset + 1)? as u16)
+}
+
+/// A line of a disassembly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    /// An opcode
+    Code(u16),
+    /// A long address load, F000 followed by the address
+    Long(u16),
+    /// A byte of data
+    Data(u8)
+}
+
+/// Disassemble a ROM into source the assembler turns back into the
+/// same ROM byte for byte. Code is listed as mnemonics and data as `DB`
+/// bytes with their pixels drawn in a comment, see `code_map` for how
+/// code is told apart from data. Addresses that start a line and are
+/// called, jumped to or loaded into `I` get `sub_NNN`, `label_NNN` and
+/// `data_NNN` labels, and every line notes its address and opcode in a
+/// comment.
+///
+/// # Arguments
+///
+/// * `rom` - rom contents, loaded at 0x200
+/// * `entries` - other addresses known to hold code
+pub fn disassemble_rom(rom: &[u8], entries: &[u16]) -> String {
+    let code = code_map(rom, entries);
+    let mut items = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let addr = offset as u16 + START;
+        match opcode_at(rom, addr).filter(|_| code[offset] && code[offset + 1]) {
+            Some(opcode) if flow::flow(opcode) == Flow::Long && opcode_at(rom, addr + 2).is_some() => {
+                items.push((addr, Item::Long(opcode_at(rom, addr + 2).unwrap_or(0))));
+                offset += 4;
+            },
+            Some(opcode) => {
+                items.push((addr, Item::Code(opcode)));
+                offset += 2;
+            },
+            None => {
+                items.push((addr, Item::Data(rom[offset])));
+                offset += 1;
+            }
+        }
+    }
+
+    // Only addresses a line starts at can be labeled, a routine that is
+    // also jumped to keeps its sub_ label
+    let mut labels: Vec<(u16, &str)> = Vec::new();
+    for (_, item) in items.iter() {
+        if let Item::Code(opcode) = item {
+            let (target, kind) = match Instruction::decode(*opcode) {
+                Instruction::Call(target) => (target, "sub"),
+                Instruction::Jump(target) | Instruction::JumpOffset(target) => (target, "label"),
+                Instruction::LoadI(target) => match items.iter().find(|(addr, _)| *addr == target) {
+                    Some((_, Item::Data(_))) => (target, "data"),
+                    _ => (target, "label")
+                },
+                _ => continue
+            };
+            if items.iter().any(|(addr, _)| *addr == target) {
+                labels.push((target, kind));
+            }
+        }
+    }
+    labels.sort_by_key(|(addr, kind)| (*addr, *kind != "sub"));
+    labels.dedup_by_key(|(addr, _)| *addr);
+    let label = |addr: u16| -> Option<String> {
+        labels.iter()
+            .find(|(label, _)| *label == addr)
+            .map(|(addr, kind)| format!("{}_{:03X}", kind, addr))
+    };
+
+    let mut out = format!("ORG 0x{:03X}\n", START);
+    for (addr, item) in items.iter() {
+        if let Some(name) = label(*addr) {
+            out.push_str(&format!("{}:\n", name));
+        }
+        let (text, comment) = match item {
+            Item::Code(opcode) => {
+                let instruction = Instruction::decode(*opcode);
+                let text = match (instruction, label(opcode & 0x0FFF)) {
+                    (Instruction::Jump(_), Some(name)) => format!("JP {}", name),
+                    (Instruction::Call(_), Some(name)) => format!("CALL {}", name),
+                    (Instruction::LoadI(_), Some(name)) => format!("LD I, {}", name),
+                    (Instruction::JumpOffset(_), Some(name)) => format!("JP V0, {}", name),
+                    _ => instruction.to_string()
+                };
+                (text, format!("{:04X}", opcode))
+            },
+            Item::Long(long) => (format!("LD I, long 0x{:04X}", long), format!("F000 {:04X}", long)),
+            Item::Data(byte) => {
+                let row: String = (0..8).rev().map(|bit| if byte >> bit & 1 == 1 { '#' } else { '.' }).collect();
+                (format!("DB 0x{:02X}", byte), row)
+            }
+        };
+        out.push_str(&format!("    {:<24}; 0x{:03X}  {}\n", text, addr, comment));
+    }
+    out
+}
+
+/// Disassemble memory an opcode at a time, as the interpreter would
+/// read it from each address, returning the address, opcode and
+/// mnemonic of every line. Unlike `disassemble_rom` nothing is told
+/// apart as data, which suits debugger views of a running program.
+///
+/// # Arguments
+///
+/// * `ram` - memory to read
+/// * `start` - address of the first opcode
+/// * `end` - last address to list, included
+pub fn disassemble_memory(ram: &ChipMemory, start: u16, end: u16) -> Vec<(u16, u16, String)> {
+    (start as u32..=end as u32).step_by(2).map(|addr| {
+        let opcode = ram.get_opcode(addr as u16);
+        (addr as u16, opcode, mnemonic(opcode))
+    }).collect()
+}
+
+/// List the opcodes around an address, the line at it marked with an
+/// arrow, as shown by the debugger frontend and state dumps
+///
+/// # Arguments
+///
+/// * `ram` - memory to read
+/// * `pc` - address to list around
+/// * `context` - opcodes to list before and after it
+pub fn listing_around(ram: &ChipMemory, pc: u16, context: u16) -> Vec<String> {
+    let start = pc.saturating_sub(context * 2);
+    disassemble_memory(ram, start, pc.saturating_add(context * 2)).into_iter().map(|(addr, opcode, text)| {
+        let marker = if addr == pc { "->" } else { "  " };
+        format!("{} {:03x}  {:04x}  {}", marker, addr, opcode, text)
+    }).collect()
+}
+
+/// Disassemble a ROM and assemble the result again, returning the
+/// first address that does not come back the same
+///
+/// # Arguments
+///
+/// * `rom` - rom contents, loaded at 0x200
+/// * `entries` - other addresses known to hold code
+pub fn round_trip(rom: &[u8], entries: &[u16]) -> Result<(), String> {
+    let source = disassemble_rom(rom, entries);
+    let assembled = assembler::assemble(&source).map_err(|e| format!("disassembly does not assemble, {}", e))?;
+    match rom.iter().zip(assembled.iter()).position(|(a, b)| a != b) {
+        Some(offset) => Err(format!(
+            "byte at 0x{:03X} assembles to 0x{:02X} instead of 0x{:02X}",
+            offset + START as usize, assembled[offset], rom[offset]
+        )),
+        None if rom.len() != assembled.len() => Err(format!(
+            "assembled ROM is {} bytes instead of {}", assembled.len(), rom.len()
+        )),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stress::{self, StressConfig};
+
+    #[test]
+    fn stress_roms_round_trip() {
+        for seed in 0..16 {
+            let rom = stress::generate(&StressConfig::init(seed));
+            assert_eq!(round_trip(&rom, &[]), Ok(()), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn data_and_odd_addresses_round_trip() {
+        let rom = [
+            0x12, 0x03,                     // 200: JP 0x203
+            0xFF,                           // 202: data between code
+            0xA2, 0x09,                     // 203: LD I, 0x209
+            0xD0, 0x15,                     // 205: DRW V0, V1, 5
+            0x12, 0x07,                     // 207: JP 0x207
+            0xF0, 0x90, 0x90, 0x90, 0xF0,   // 209: sprite
+            0x01                            // 20e: trailing odd byte
+        ];
+        assert_eq!(round_trip(&rom, &[]), Ok(()));
+    }
+}
@@ -1,98 +1,376 @@
+// Local Modules Use //
+use super::image::RgbImage;
 
 // Modules from crates.io //
 
+/// Width of the original chip8 display in pixels
+pub const DISPLAY_WIDTH: usize = 64;
+
+/// Height of the original chip8 display in pixels
+pub const DISPLAY_HEIGHT: usize = 32;
+
+/// Number of display planes, XO-CHIP draws on two to get four colors
+pub const PLANES: usize = 2;
+
 /// A struct representing the chip8 display
+///
+/// The display has two planes of pixels, plain chip8 and SCHIP programs
+/// only ever draw on the first one. XO-CHIP programs pick the planes
+/// drawn on with FN01, a pixel's color is then 0-3 with a bit for each
+/// plane lit. Monochrome consumers see a pixel as lit when any plane is.
 pub struct ChipDisplay {
-    /// A boolean vector representing the display
-    display: Vec<bool>,
+    /// A boolean vector per plane representing the display, row by row
+    planes: [Vec<bool>; PLANES],
+    /// Planes drawn on, scrolled and cleared, bit 0 for the first
+    selected: u8,
+    /// Width of the display in pixels
+    width: usize,
+    /// Height of the display in pixels
+    height: usize,
     /// String to divide display with
     divider: String,
     /// If display has been modified
-    modified: bool
+    modified: bool,
+    /// Times each pixel has changed state, only kept when enabled
+    toggles: Option<Vec<u32>>,
+    /// Total number of pixel changes since init
+    toggle_count: u64
 }
 
 impl ChipDisplay {
-    /// Initialize the chip8 display struct
+    /// Initialize the chip8 display struct at the original 64x32
     pub fn init() -> Self {
-        let divider = match String::from_utf8(vec![b'-'; 64]) {
-            Ok(s) => s,
-            Err(_) => String::from("ERROR")
-        };
+        ChipDisplay::init_size(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    }
+
+    /// Initialize a display of any size, such as 128x64 for SCHIP or
+    /// 64x64 for the hi-res CHIP-8 variant
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - width in pixels
+    /// * `height` - height in pixels
+    pub fn init_size(width: usize, height: usize) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
         ChipDisplay {
-            display: vec![false; 2048],
-            divider,
-            modified: false
+            planes: [vec![false; width * height], vec![false; width * height]],
+            selected: 1,
+            width,
+            height,
+            divider: "-".repeat(width),
+            modified: false,
+            toggles: None,
+            toggle_count: 0
         }
     }
 
+    /// Get the width of the display in pixels
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the display in pixels
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Change the size of the display, clearing it. Pixel activity is
+    /// restarted at the new size if it was being counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - width in pixels
+    /// * `height` - height in pixels
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        let (width, height) = (width.max(1), height.max(1));
+        self.planes = [vec![false; width * height], vec![false; width * height]];
+        self.width = width;
+        self.height = height;
+        self.divider = "-".repeat(width);
+        self.modified = true;
+        if self.toggles.is_some() {
+            self.enable_activity();
+        }
+    }
+
+    /// Check if a pixel is lit, false for pixels off the display
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - column from the left
+    /// * `y` - row from the top
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        self.get_color(x, y) != 0
+    }
+
+    /// Get the color of a pixel, 0-3 with bit 0 set when the first
+    /// plane is lit and bit 1 when the second is, 0 off the display
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - column from the left
+    /// * `y` - row from the top
+    pub fn get_color(&self, x: usize, y: usize) -> u8 {
+        match x < self.width && y < self.height {
+            true => self.color_at(y * self.width + x),
+            false => 0
+        }
+    }
+
+    fn color_at(&self, pos: usize) -> u8 {
+        self.planes[0][pos] as u8 | (self.planes[1][pos] as u8) << 1
+    }
+
+    /// Get the color of every pixel, row by row, see `get_color`
+    pub fn get_colors(&self) -> Vec<u8> {
+        (0..self.width * self.height).map(|pos| self.color_at(pos)).collect()
+    }
+
+    /// Get the planes drawn on, bit 0 for the first plane
+    pub fn get_planes(&self) -> u8 {
+        self.selected
+    }
+
+    /// Pick the planes sprites are drawn on and that are scrolled and
+    /// cleared, the XO-CHIP FN01 opcode
+    ///
+    /// # Arguments
+    ///
+    /// * `planes` - bit 0 for the first plane, bit 1 for the second
+    pub fn select_planes(&mut self, planes: u8) {
+        self.selected = planes & 0x3;
+    }
+
+    /// Get the pixels of one plane, row by row
+    ///
+    /// # Arguments
+    ///
+    /// * `plane` - 0 for the first plane, 1 for the second
+    pub fn get_plane(&self, plane: usize) -> &[bool] {
+        &self.planes[plane]
+    }
+
+    /// Replace the pixels of one plane, for restoring save states,
+    /// returns false and leaves the plane alone if the size is wrong
+    ///
+    /// # Arguments
+    ///
+    /// * `plane` - 0 for the first plane, 1 for the second
+    /// * `pixels` - width * height pixels, row by row
+    pub fn set_plane(&mut self, plane: usize, pixels: Vec<bool>) -> bool {
+        if plane >= PLANES || pixels.len() != self.width * self.height {
+            return false;
+        }
+        self.planes[plane] = pixels;
+        self.modified = true;
+        true
+    }
+
+    /// Planes picked by `select_planes`, in order
+    fn selected_planes(&self) -> Vec<usize> {
+        (0..PLANES).filter(|plane| self.selected & (1 << plane) != 0).collect()
+    }
+
     /// Check if the display has been modified
     pub fn mod_check(&mut self) -> bool {
-        let ret = match self.modified {
+        match self.modified {
             true => {
                 self.modified = false;
                 true
             }
             false => false
-        };
-        ret
+        }
     }
 
-    /// Get a copy of the display vector
+    /// Get a copy of the display vector, a pixel is lit when any plane
+    /// is lit there
     pub fn get_display(&self) -> Vec<bool> {
-        self.display.clone()
+        self.get_colors().iter().map(|color| *color != 0).collect()
+    }
+
+    /// Render the display into an image, one pixel per chip8 pixel.
+    /// Pixels lit only on the second plane are drawn halfway between
+    /// the two colors.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `on` - color of lit pixels
+    /// * `off` - color of unlit pixels
+    pub fn to_image(&self, on: [u8; 3], off: [u8; 3]) -> RgbImage {
+        let mut half = [0; 3];
+        for channel in 0..3 {
+            half[channel] = ((on[channel] as u16 + off[channel] as u16) / 2) as u8;
+        }
+        self.to_image_colors([off, on, half, on])
+    }
+
+    /// Render the display into an image with a color for each of the
+    /// four pixel colors, see `get_color`
+    ///
+    /// # Arguments
+    ///
+    /// * `colors` - colors of unlit pixels, the first plane, the second
+    ///   plane and both planes
+    pub fn to_image_colors(&self, colors: [[u8; 3]; 4]) -> RgbImage {
+        let mut image = RgbImage::init(self.width, self.height);
+        for (pos, color) in self.get_colors().iter().enumerate() {
+            image.set_pixel(pos % self.width, pos / self.width, colors[*color as usize]);
+        }
+        image
+    }
+
+    /// Hash the contents of the display, the same image always gives
+    /// the same hash (64 bit FNV-1a over the pixels)
+    pub fn hash(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for color in self.get_colors() {
+            hash ^= color as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        hash
     }
 
 
     /// Draw a sprite into the chip8 display buffer, returns true if
-    /// a cell has a deletion, false otherwise
+    /// a cell has a deletion, false otherwise. With both planes
+    /// selected the sprite holds the rows for the first plane followed
+    /// by the rows for the second.
     /// 
     /// # Arguments
     /// 
     /// * `x_loc` - x starting position
     /// * `y_loc` - y starting position
     /// * `sprite` - a vector of bytes representing the sprite
+    /// * `clip` - clip the sprite at the screen edges instead of wrapping
+    /// 
+    pub fn draw_sprite(&mut self, x_loc: u16, y_loc: u16, sprite: Vec<u8>, clip: bool) -> bool {
+        let rows: Vec<u16> = sprite.iter().map(|byte| (*byte as u16) << 8).collect();
+        self.draw_planes(x_loc, y_loc, &rows, 8, clip)
+    }
+
+    /// Draw a 16 by 16 sprite, the SCHIP DXY0 opcode, returns true if
+    /// a cell has a deletion, false otherwise
     /// 
-    pub fn draw_sprite(&mut self, x_loc: u16, y_loc: u16, sprite: Vec<u8>) -> bool {
-        let row_count = sprite.len();
-        let mut pos;
-        // let mut index;
-        let mut mask;
-        let mut init_val;
+    /// # Arguments
+    /// 
+    /// * `x_loc` - x starting position
+    /// * `y_loc` - y starting position
+    /// * `sprite` - 32 bytes, two per row with the left half first, for
+    ///   each selected plane
+    /// * `clip` - clip the sprite at the screen edges instead of wrapping
+    pub fn draw_sprite_16(&mut self, x_loc: u16, y_loc: u16, sprite: Vec<u8>, clip: bool) -> bool {
+        let rows: Vec<u16> = sprite.chunks(2)
+            .map(|pair| (pair[0] as u16) << 8 | *pair.get(1).unwrap_or(&0) as u16)
+            .collect();
+        self.draw_planes(x_loc, y_loc, &rows, 16, clip)
+    }
+
+    /// Split the rows between the selected planes and draw each part
+    fn draw_planes(&mut self, x_loc: u16, y_loc: u16, rows: &[u16], row_width: usize, clip: bool) -> bool {
+        let planes = self.selected_planes();
         let mut ret = false;
-        // println!("Sprite: {:#?}", sprite);
-        // for i in 0..sprite.len() {
-            // println!("{:#010b}", sprite[i]);
-        // }
-        for row in 0..row_count {
-            // index = row * 8;
-            mask = 0x80;
-            for i in 0..8 {
-                // Calculate bit position with wrap around
-                pos = (((y_loc + row as u16) % 32) * 64) + ((x_loc + i) % 64);
-                // println!("Pixel Index: {}", pos);
-                init_val = self.display[pos as usize];
-                match sprite[row] & mask == mask {
-                    true => self.display[pos as usize] ^= true,
-                    false => self.display[pos as usize] ^= false
+        if !planes.is_empty() {
+            let per_plane = rows.len() / planes.len();
+            for (part, plane) in planes.into_iter().enumerate() {
+                let part_rows = &rows[part * per_plane..(part + 1) * per_plane];
+                ret |= self.draw_rows(plane, x_loc, y_loc, part_rows, row_width, clip);
+            }
+        }
+        self.modified = true;
+        ret
+    }
+
+    /// XOR rows of pixels onto a plane, the leftmost pixel of each
+    /// row in its most significant bit
+    fn draw_rows(&mut self, plane: usize, x_loc: u16, y_loc: u16, rows: &[u16], row_width: usize, clip: bool) -> bool {
+        let (width, height) = (self.width, self.height);
+        let (x_start, y_start) = (x_loc as usize % width, y_loc as usize % height);
+        let mut ret = false;
+        for (row, bits) in rows.iter().enumerate() {
+            for i in 0..row_width {
+                // Starting position always wraps, the rest of the
+                // sprite is either clipped or wrapped around
+                if clip && (x_start + i >= width || y_start + row >= height) {
+                    continue;
+                }
+                if bits & (0x8000 >> i) == 0 {
+                    continue;
                 }
+                // Calculate bit position with wrap around
+                let pos = ((y_start + row) % height) * width + (x_start + i) % width;
                 // Check if deletion occured
-                if ret == false && init_val == true && self.display[pos as usize] == false {
-                    ret = true
+                ret |= self.planes[plane][pos];
+                self.planes[plane][pos] ^= true;
+                self.count_toggle(pos);
+            }
+        }
+        ret
+    }
+
+    /// Move the whole picture on the selected planes, pixels moved off
+    /// the display are lost and the ones uncovered are cleared
+    /// (SCHIP/XO-CHIP scrolling)
+    /// 
+    /// # Arguments
+    /// 
+    /// * `dx` - pixels to move right, negative to move left
+    /// * `dy` - pixels to move down, negative to move up
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.width as isize, self.height as isize);
+        for plane in self.selected_planes() {
+            let old = self.planes[plane].clone();
+            for y in 0..height {
+                for x in 0..width {
+                    let (from_x, from_y) = (x - dx, y - dy);
+                    let lit = from_x >= 0 && from_x < width && from_y >= 0 && from_y < height
+                        && old[(from_y * width + from_x) as usize];
+                    let pos = (y * width + x) as usize;
+                    if self.planes[plane][pos] != lit {
+                        self.planes[plane][pos] = lit;
+                        self.count_toggle(pos);
+                    }
                 }
-                mask >>= 1;
             }
         }
         self.modified = true;
-        // self.draw_display();
-        return ret
     }
 
-    /// Clear the display array
+    /// Start counting how often each pixel changes state, clearing
+    /// any previous counts
+    pub fn enable_activity(&mut self) {
+        self.toggles = Some(vec![0; self.width * self.height]);
+    }
+
+    /// Stop counting pixel changes
+    pub fn disable_activity(&mut self) {
+        self.toggles = None;
+    }
+
+    /// Get the number of times each pixel changed state, if enabled
+    pub fn get_activity(&self) -> Option<&[u32]> {
+        self.toggles.as_deref()
+    }
+
+    /// Get the total number of pixel changes since init
+    pub fn get_toggle_count(&self) -> u64 {
+        self.toggle_count
+    }
+
+    fn count_toggle(&mut self, pos: usize) {
+        self.toggle_count += 1;
+        if let Some(toggles) = self.toggles.as_mut() {
+            toggles[pos] = toggles[pos].saturating_add(1);
+        }
+    }
+
+    /// Clear the display array, only the selected planes
     pub fn clear_display(&mut self) {
-        for y in 0..32 {
-            for x in 0..64 {
-                let pos: usize = y * 64 + x;
-                self.display[pos] = false;
+        for plane in self.selected_planes() {
+            for pos in 0..self.width * self.height {
+                if self.planes[plane][pos] {
+                    self.count_toggle(pos);
+                }
+                self.planes[plane][pos] = false;
             }
         }
     }
@@ -100,11 +378,11 @@ impl ChipDisplay {
     /// Draw the chip8 display in the terminal
     pub fn draw_display(&self) {
         println!("|{}|", self.divider);
-        for x in 0..32 {
+        for x in 0..self.height {
             print!("|");
-            for y in 0..64 {
-                let pos: usize = x * 64 + y;
-                if self.display[pos] == true {
+            for y in 0..self.width {
+                let pos: usize = x * self.width + y;
+                if self.color_at(pos) != 0 {
                     print!("#")
                 }
                 else {
@@ -0,0 +1,187 @@
+// Local Modules Use //
+use super::ChipSystem;
+use super::instruction::Instruction;
+
+/// Explain an opcode in plain english using the current state of the
+/// system, e.g. `V3 = V3 + 0x05 (0x10 -> 0x15), no carry flag`
+///
+/// The explanation describes what the opcode is about to do, so it
+/// should be generated before the opcode is executed. It follows the
+/// quirks and variant of the system the way `execute` does.
+///
+/// # Arguments
+///
+/// * `opcode` - two byte opcode to explain
+/// * `sys` - system the opcode will be executed on
+pub fn explain_opcode(opcode: u16, sys: &ChipSystem) -> String {
+    let reg = &sys.registers;
+    let v = |reg_index: u8| reg.get_gp(reg_index as usize);
+    let quirks = &sys.quirks;
+    let vf_reset = match quirks.vf_reset {
+        true => ", VF = 0 (vf_reset quirk)",
+        false => ""
+    };
+    let incr_i = |x: u8| match quirks.load_store_incr_i {
+        true => format!(", then I = 0x{:03x}", reg.get_i().wrapping_add(x as u16 + 1)),
+        false => String::from(", I unchanged")
+    };
+
+    match Instruction::decode_for(opcode, sys.variant) {
+        Instruction::Clear => String::from("Clear the display, every pixel is turned off"),
+        Instruction::Exit => String::from("Exit the interpreter, the program is finished"),
+        Instruction::Return => match reg.peek_stack() {
+            Some(addr) => format!("Return from subroutine, pop 0x{:03x} off the stack and continue after it", addr),
+            None => String::from("Return from subroutine, but the stack is empty")
+        },
+        Instruction::Sys(nnn) => format!("Call machine code routine at 0x{:03x}, ignored by interpreters", nnn),
+        Instruction::ScrollDown(n) => format!("Scroll the display down {} rows", n),
+        Instruction::ScrollUp(n) => format!("Scroll the display up {} rows", n),
+        Instruction::ScrollRight => String::from("Scroll the display right 4 columns"),
+        Instruction::ScrollLeft => String::from("Scroll the display left 4 columns"),
+        Instruction::LowRes => String::from("Switch to the 64x32 display"),
+        Instruction::HighRes => String::from("Switch to the 128x64 display"),
+        Instruction::Jump(nnn) if nnn == reg.get_pc() => format!("Jump to 0x{:03x}, its own address, the program is finished", nnn),
+        Instruction::Jump(nnn) => format!("Jump to 0x{:03x}", nnn),
+        Instruction::Call(nnn) => format!(
+            "Call subroutine at 0x{:03x}, push 0x{:03x} onto the stack (depth {} -> {})",
+            nnn, reg.get_pc(), reg.get_sp(), reg.get_sp() + 1
+        ),
+        Instruction::SkipEqByte(x, kk) => format!(
+            "Skip next instruction if V{:X} (0x{:02x}) == 0x{:02x}: {}",
+            x, v(x), kk, skip_text(v(x) == kk)
+        ),
+        Instruction::SkipNeByte(x, kk) => format!(
+            "Skip next instruction if V{:X} (0x{:02x}) != 0x{:02x}: {}",
+            x, v(x), kk, skip_text(v(x) != kk)
+        ),
+        Instruction::SkipEqReg(x, y) => format!(
+            "Skip next instruction if V{:X} (0x{:02x}) == V{:X} (0x{:02x}): {}",
+            x, v(x), y, v(y), skip_text(v(x) == v(y))
+        ),
+        Instruction::StoreRange(x, y) => format!("Store V{:X} through V{:X} in memory starting at I (0x{:03x})", x, y, reg.get_i()),
+        Instruction::LoadRange(x, y) => format!("Load V{:X} through V{:X} from memory starting at I (0x{:03x})", x, y, reg.get_i()),
+        Instruction::LoadByte(x, kk) => format!("V{:X} = 0x{:02x}", x, kk),
+        Instruction::AddByte(x, kk) => format!(
+            "V{:X} = V{:X} + 0x{:02x} (0x{:02x} -> 0x{:02x}), no carry flag",
+            x, x, kk, v(x), v(x).wrapping_add(kk)
+        ),
+        Instruction::LoadReg(x, y) => format!("V{:X} = V{:X} (0x{:02x})", x, y, v(y)),
+        Instruction::Or(x, y) => format!("V{:X} = V{:X} | V{:X} (0x{:02x} | 0x{:02x} = 0x{:02x}){}", x, x, y, v(x), v(y), v(x) | v(y), vf_reset),
+        Instruction::And(x, y) => format!("V{:X} = V{:X} & V{:X} (0x{:02x} & 0x{:02x} = 0x{:02x}){}", x, x, y, v(x), v(y), v(x) & v(y), vf_reset),
+        Instruction::Xor(x, y) => format!("V{:X} = V{:X} ^ V{:X} (0x{:02x} ^ 0x{:02x} = 0x{:02x}){}", x, x, y, v(x), v(y), v(x) ^ v(y), vf_reset),
+        Instruction::AddReg(x, y) => {
+            let (vx, vy) = (v(x), v(y));
+            let carry = vx as u16 + vy as u16 > 255;
+            format!(
+                "V{:X} = V{:X} + V{:X} (0x{:02x} + 0x{:02x} = 0x{:02x}), VF = {} ({})",
+                x, x, y, vx, vy, vx.wrapping_add(vy), carry as u8,
+                if carry { "carry" } else { "no carry" }
+            )
+        },
+        Instruction::Sub(x, y) => {
+            let (vx, vy) = (v(x), v(y));
+            format!(
+                "V{:X} = V{:X} - V{:X} (0x{:02x} - 0x{:02x} = 0x{:02x}), VF = {} ({})",
+                x, x, y, vx, vy, vx.wrapping_sub(vy), (vx >= vy) as u8,
+                if vx >= vy { "no borrow" } else { "borrow" }
+            )
+        },
+        Instruction::ShiftRight(x, y) => {
+            let source = if quirks.shift_uses_vy { y } else { x };
+            format!(
+                "V{:X} = V{:X} >> 1 (0x{:02x} -> 0x{:02x}), VF = least significant bit ({})",
+                x, source, v(source), v(source) >> 1, v(source) & 0x01
+            )
+        },
+        Instruction::SubN(x, y) => {
+            let (vx, vy) = (v(x), v(y));
+            format!(
+                "V{:X} = V{:X} - V{:X} (0x{:02x} - 0x{:02x} = 0x{:02x}), VF = {} ({})",
+                x, y, x, vy, vx, vy.wrapping_sub(vx), (vy >= vx) as u8,
+                if vy >= vx { "no borrow" } else { "borrow" }
+            )
+        },
+        Instruction::ShiftLeft(x, y) => {
+            let source = if quirks.shift_uses_vy { y } else { x };
+            format!(
+                "V{:X} = V{:X} << 1 (0x{:02x} -> 0x{:02x}), VF = most significant bit ({})",
+                x, source, v(source), v(source) << 1, v(source) >> 7
+            )
+        },
+        Instruction::SkipNeReg(x, y) => format!(
+            "Skip next instruction if V{:X} (0x{:02x}) != V{:X} (0x{:02x}): {}",
+            x, v(x), y, v(y), skip_text(v(x) != v(y))
+        ),
+        Instruction::LoadI(nnn) => format!("I = 0x{:03x}", nnn),
+        Instruction::JumpOffset(nnn) => {
+            // The top nibble of the address picks the register with the
+            // jump_uses_vx quirk
+            let offset = if quirks.jump_uses_vx { (nnn >> 8) as u8 } else { 0 };
+            format!(
+                "Jump to 0x{:03x} + V{:X} (0x{:02x}) = 0x{:03x}",
+                nnn, offset, v(offset), nnn + v(offset) as u16
+            )
+        },
+        Instruction::Random(x, kk) => format!("V{:X} = random byte & 0x{:02x}", x, kk),
+        Instruction::Draw(x, y, n) => {
+            let size = match n == 0 && sys.variant.has_schip() {
+                true => String::from("16x16"),
+                false => format!("8x{}", n)
+            };
+            let edges = if quirks.clip_sprites { "clipped" } else { "wrapped" };
+            format!(
+                "Draw {} sprite from memory at I (0x{:03x}) at V{:X}, V{:X} ({}, {}), {} at the edges, VF = 1 if any pixel is erased",
+                size, reg.get_i(), x, y, v(x), v(y), edges
+            )
+        },
+        Instruction::SkipKey(x) => format!(
+            "Skip next instruction if key V{:X} (0x{:X}) is pressed: {}",
+            x, v(x) & 0xf, skip_text(sys.keyboard.get_key(v(x) & 0xf))
+        ),
+        Instruction::SkipNotKey(x) => format!(
+            "Skip next instruction if key V{:X} (0x{:X}) is not pressed: {}",
+            x, v(x) & 0xf, skip_text(!sys.keyboard.get_key(v(x) & 0xf))
+        ),
+        Instruction::LoadILong if sys.ram.is_extended() => format!(
+            "I = 0x{:04x}, the 16 bit address stored after this opcode",
+            sys.ram.get_opcode(reg.get_pc().wrapping_add(2))
+        ),
+        Instruction::Plane(n) => format!("Draw on the display planes in the bitmask {}", n),
+        Instruction::Audio => format!("Load the 16 byte audio pattern from memory at I (0x{:03x})", reg.get_i()),
+        Instruction::Pitch(x) => format!(
+            "Audio pitch = V{:X} ({}), the pattern plays at {:.0} bits per second",
+            x, v(x), 4000.0 * 2f64.powf((v(x) as f64 - 64.0) / 48.0)
+        ),
+        Instruction::ReadDelay(x) => format!("V{:X} = delay timer (0x{:02x})", x, reg.get_d()),
+        Instruction::WaitKey(x) => format!("Wait for a key press and store the key in V{:X}", x),
+        Instruction::SetDelay(x) => format!("Delay timer = V{:X} (0x{:02x})", x, v(x)),
+        Instruction::SetSound(x) => format!("Sound timer = V{:X} (0x{:02x}), a tone plays while it is above zero", x, v(x)),
+        Instruction::AddI(x) => {
+            let sum = reg.get_i().wrapping_add(v(x) as u16);
+            let flag = match sys.quirks.add_i_sets_vf {
+                true => format!(", VF = {} ({})", (sum > 0xFFF) as u8, if sum > 0xFFF { "past 0xFFF" } else { "within 0xFFF" }),
+                false => String::from(", VF unchanged")
+            };
+            format!("I = I + V{:X} (0x{:03x} + 0x{:02x} = 0x{:03x}){}", x, reg.get_i(), v(x), sum, flag)
+        },
+        Instruction::Font(x) => format!("I = address of the font sprite for digit V{:X} (0x{:X})", x, v(x) & 0xf),
+        Instruction::BigFont(x) => format!("I = address of the large font sprite for digit V{:X} (0x{:X})", x, v(x) & 0xf),
+        Instruction::Bcd(x) => format!(
+            "Store the decimal digits of V{:X} ({}) at I, I+1, I+2 ({}, {}, {})",
+            x, v(x), v(x) / 100, (v(x) / 10) % 10, v(x) % 10
+        ),
+        Instruction::StoreRegs(x) => format!("Store V0 through V{:X} in memory starting at I (0x{:03x}){}", x, reg.get_i(), incr_i(x)),
+        Instruction::LoadRegs(x) => format!("Load V0 through V{:X} from memory starting at I (0x{:03x}){}", x, reg.get_i(), incr_i(x)),
+        Instruction::SaveFlags(x) => format!("Save V0 through V{:X} in the RPL user flags", x),
+        Instruction::LoadFlags(x) => format!("Load V0 through V{:X} from the RPL user flags", x),
+        Instruction::LoadILong | Instruction::Invalid(_) => format!("Unknown opcode {:04x}, execution will halt", opcode)
+    }
+}
+
+/// Describe whether a skip opcode will skip
+fn skip_text(skip: bool) -> &'static str {
+    match skip {
+        true => "skipped",
+        false => "not skipped"
+    }
+}
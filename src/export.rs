@@ -0,0 +1,201 @@
+// Standard Library Modules //
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::display::ChipDisplay;
+use super::palette::Palette;
+
+/// Writes every frame it is given to a numbered PNG file
+///
+/// Files are named `frame_000001.png`, `frame_000002.png`, ... so they
+/// sort in order and can be assembled into a video by external tools,
+/// or compared pixel by pixel between runs.
+pub struct FrameDumper {
+    /// Directory frames are written to
+    dir: PathBuf,
+    /// Output pixels per chip8 pixel
+    scale: usize,
+    /// Colors frames are drawn with
+    palette: Palette,
+    /// Number of frames written so far
+    count: u64
+}
+
+impl FrameDumper {
+    /// Create a frame dumper, creating the output directory if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - directory to write frames to
+    /// * `scale` - output pixels per chip8 pixel
+    /// * `palette` - colors to draw frames with
+    pub fn init(dir: &Path, scale: usize, palette: Palette) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(FrameDumper {
+            dir: dir.to_path_buf(),
+            scale: scale.max(1),
+            palette,
+            count: 0
+        })
+    }
+
+    /// Get the number of frames written so far
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    /// Write the display as the next frame, returning the file written
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - display to write
+    pub fn dump(&mut self, display: &ChipDisplay) -> io::Result<PathBuf> {
+        self.count += 1;
+        let path = self.dir.join(format!("frame_{:06}.png", self.count));
+        let image = display.to_image(self.palette.on, self.palette.off).scaled(self.scale);
+        let name = match path.to_str() {
+            Some(name) => name,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame path is not valid utf-8"))
+        };
+        image.save_png(name)?;
+        Ok(path)
+    }
+}
+
+/// Render the display as an SVG image made of rectangles, lit pixels
+/// next to each other on a row are merged into one rectangle
+///
+/// # Arguments
+///
+/// * `display` - display to export
+/// * `palette` - colors to draw with
+/// * `scale` - size of each chip8 pixel in SVG units
+pub fn display_to_svg(display: &ChipDisplay, palette: &Palette, scale: usize) -> String {
+    let pixels = display.get_display();
+    let (width, height) = (display.get_width(), display.get_height());
+    let scale = scale.max(1);
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\n",
+        w = width * scale, h = height * scale
+    );
+    out.push_str(&format!(
+        "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        Palette::hex(palette.off)
+    ));
+    out.push_str(&format!("  <g fill=\"{}\">\n", Palette::hex(palette.on)));
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            if !pixels[y * width + x] {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && pixels[y * width + x] {
+                x += 1;
+            }
+            out.push_str(&format!(
+                "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                start * scale, y * scale, (x - start) * scale, scale
+            ));
+        }
+    }
+    out.push_str("  </g>\n</svg>\n");
+    out
+}
+
+/// How `display_to_text` draws the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStyle {
+    /// Character for lit pixels
+    pub on: char,
+    /// Character for unlit pixels
+    pub off: char,
+    /// Pack two rows of pixels into each line with half block
+    /// characters, `on` and `off` are ignored
+    pub half_blocks: bool,
+    /// Surround the display with a `+--+` border
+    pub border: bool,
+    /// Characters and lines per chip8 pixel
+    pub scale: usize
+}
+
+impl Default for TextStyle {
+    /// `#` and space with a border, like the terminal display
+    fn default() -> Self {
+        TextStyle {
+            on: '#',
+            off: ' ',
+            half_blocks: false,
+            border: true,
+            scale: 1
+        }
+    }
+}
+
+/// Names of the text export character sets, see `TextStyle::charset`
+pub const CHARSETS: [&str; 3] = ["ascii", "blocks", "half"];
+
+impl TextStyle {
+    /// Style for a named character set with a border, `ascii` uses `#`,
+    /// `blocks` uses full block characters and `half` packs two rows
+    /// into each line with half blocks
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the character set
+    pub fn charset(name: &str) -> Option<Self> {
+        let style = TextStyle::default();
+        match name {
+            "ascii" => Some(style),
+            "blocks" => Some(TextStyle { on: '\u{2588}', ..style }),
+            "half" => Some(TextStyle { half_blocks: true, ..style }),
+            _ => None
+        }
+    }
+}
+
+/// Render the display as text, for bug reports and documentation
+///
+/// # Arguments
+///
+/// * `display` - display to export
+/// * `style` - characters, border and scale to draw with
+pub fn display_to_text(display: &ChipDisplay, style: &TextStyle) -> String {
+    let pixels = display.get_display();
+    let scale = style.scale.max(1);
+    let (width, height) = (display.get_width() * scale, display.get_height() * scale);
+    let lit = |x: usize, y: usize| y < height && pixels[y / scale * display.get_width() + x / scale];
+    let mut out = String::new();
+    let border = format!("+{}+\n", "-".repeat(width));
+    if style.border {
+        out.push_str(&border);
+    }
+    let step = if style.half_blocks { 2 } else { 1 };
+    for y in (0..height).step_by(step) {
+        if style.border {
+            out.push('|');
+        }
+        for x in 0..width {
+            let glyph = match (style.half_blocks, lit(x, y), lit(x, y + 1)) {
+                (false, true, _) => style.on,
+                (false, false, _) => style.off,
+                (true, true, true) => '\u{2588}',
+                (true, true, false) => '\u{2580}',
+                (true, false, true) => '\u{2584}',
+                (true, false, false) => ' '
+            };
+            out.push(glyph);
+        }
+        if style.border {
+            out.push('|');
+        }
+        out.push('\n');
+    }
+    if style.border {
+        out.push_str(&border);
+    }
+    out
+}
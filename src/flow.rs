@@ -0,0 +1,92 @@
+// Local Modules Use //
+use super::instruction::Instruction;
+
+/// Address programs are loaded at
+pub const START: u16 = 0x200;
+
+/// Where execution can go after an opcode, for following code without
+/// running it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Continues with the next opcode
+    Next,
+    /// Continues with the next opcode or the one after it
+    Skip,
+    /// Jumps to an address (1NNN)
+    Jump(u16),
+    /// Calls a subroutine (2NNN) then continues with the next opcode
+    Call(u16),
+    /// Returns from a subroutine (00EE)
+    Return,
+    /// Ends the program (00FD)
+    Exit,
+    /// Jumps to an address only known when running (BNNN)
+    Computed,
+    /// Continues after a 16 bit address stored in the next two bytes
+    /// (F000 NNNN)
+    Long
+}
+
+/// Find where execution can go after an opcode
+///
+/// # Arguments
+///
+/// * `opcode` - two byte opcode
+pub fn flow(opcode: u16) -> Flow {
+    match Instruction::decode(opcode) {
+        Instruction::Return => Flow::Return,
+        Instruction::Exit => Flow::Exit,
+        Instruction::Jump(addr) => Flow::Jump(addr),
+        Instruction::Call(addr) => Flow::Call(addr),
+        Instruction::SkipEqByte(_, _) | Instruction::SkipNeByte(_, _)
+        | Instruction::SkipEqReg(_, _) | Instruction::SkipNeReg(_, _)
+        | Instruction::SkipKey(_) | Instruction::SkipNotKey(_) => Flow::Skip,
+        Instruction::JumpOffset(_) => Flow::Computed,
+        Instruction::LoadILong => Flow::Long,
+        _ => Flow::Next
+    }
+}
+
+/// Follow the code of a routine from its entry without entering the
+/// routines it calls, returning the address of every opcode reached in
+/// order and the routines called. Addresses outside the ROM are not
+/// followed.
+///
+/// # Arguments
+///
+/// * `rom` - rom contents, loaded at 0x200
+/// * `entry` - address of the first opcode of the routine
+pub fn walk_routine(rom: &[u8], entry: u16) -> (Vec<u16>, Vec<u16>) {
+    let end = START as usize + rom.len();
+    let mut seen = Vec::new();
+    let mut calls = Vec::new();
+    let mut pending = vec![entry];
+    while let Some(addr) = pending.pop() {
+        let at = addr as usize;
+        if at < START as usize || at + 1 >= end || seen.contains(&addr) {
+            continue;
+        }
+        seen.push(addr);
+        let offset = at - START as usize;
+        let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+        let next = addr.wrapping_add(2);
+        match flow(opcode) {
+            Flow::Next => pending.push(next),
+            Flow::Skip => {
+                pending.push(next.wrapping_add(2));
+                pending.push(next);
+            },
+            Flow::Jump(target) => pending.push(target),
+            Flow::Call(target) => {
+                if !calls.contains(&target) {
+                    calls.push(target);
+                }
+                pending.push(next);
+            },
+            Flow::Long => pending.push(next.wrapping_add(2)),
+            Flow::Return | Flow::Exit | Flow::Computed => {}
+        }
+    }
+    seen.sort_unstable();
+    (seen, calls)
+}
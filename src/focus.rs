@@ -0,0 +1,67 @@
+/// Pauses the core and mutes audio while the window is in the
+/// background
+///
+/// Frontends call `focus_lost`/`focus_gained` from their window
+/// events, or from the focus reports of `termkeys::TerminalKeys` on a
+/// terminal, and skip `ChipSystem::run_frame` and audio output while
+/// `is_paused` is true. This is separate from a pause asked for by the
+/// player, regaining focus never resumes a game the player paused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FocusPause {
+    /// Pause when focus is lost
+    enabled: bool,
+    /// True while the window does not have focus
+    unfocused: bool
+}
+
+impl Default for FocusPause {
+    /// Enabled, graphical frontends pause in the background by default
+    fn default() -> Self {
+        FocusPause::init(true)
+    }
+}
+
+impl FocusPause {
+    /// Initialize with the window focused
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true to pause when focus is lost
+    pub fn init(enabled: bool) -> Self {
+        FocusPause {
+            enabled,
+            unfocused: false
+        }
+    }
+
+    /// Returns true if losing focus pauses the core
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn pausing on focus loss on or off, turning it off while
+    /// unfocused resumes straight away
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true to pause when focus is lost
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// The window went to the background
+    pub fn focus_lost(&mut self) {
+        self.unfocused = true;
+    }
+
+    /// The window came back to the foreground
+    pub fn focus_gained(&mut self) {
+        self.unfocused = false;
+    }
+
+    /// Returns true if the core should not run and audio should be
+    /// muted
+    pub fn is_paused(&self) -> bool {
+        self.enabled && self.unfocused
+    }
+}
@@ -0,0 +1,221 @@
+// Local Modules Use //
+use super::ChipSystem;
+use super::memory::{BIG_FONT_START, FONT, FONT_START};
+
+/// Font of the COSMAC VIP interpreter
+pub const VIP_FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0x70, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+];
+
+/// Font of the DREAM 6800 interpreter, 3 pixels wide
+pub const DREAM_6800_FONT: [u8; 80] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80  // F
+];
+
+/// Large font of SUPER-CHIP, 10 bytes per digit, each byte a full 8
+/// pixel row. SUPER-CHIP only had the digits 0-9, A-F are Octo's.
+pub const SCHIP_BIG_FONT: [u8; 160] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+/// Large font of Octo, blocky 10 byte digits 0-F
+pub const OCTO_BIG_FONT: [u8; 160] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0  // F
+];
+
+/// Historical font styles that can be loaded into memory
+///
+/// Programs draw scores and text with the built in font, so which
+/// interpreter's font is loaded changes how they look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fontset {
+    /// COSMAC VIP, the original interpreter
+    Vip,
+    /// CHIP-48 and SUPER-CHIP, the default
+    #[default]
+    Schip,
+    /// DREAM 6800, narrow 3 pixel glyphs
+    Dream6800,
+    /// Octo, the same small glyphs as SUPER-CHIP
+    Octo
+}
+
+/// Every fontset with its name, in the order they are listed
+pub const FONTSETS: [(Fontset, &str); 4] = [
+    (Fontset::Vip, "vip"),
+    (Fontset::Schip, "schip"),
+    (Fontset::Dream6800, "dream6800"),
+    (Fontset::Octo, "octo")
+];
+
+impl Fontset {
+    /// Name of the fontset as used on the command line
+    pub fn name(&self) -> &'static str {
+        FONTSETS.iter()
+            .find(|(fontset, _)| fontset == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Find a fontset by name, case is ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the fontset
+    pub fn from_name(name: &str) -> Option<Fontset> {
+        let name = name.to_ascii_lowercase();
+        FONTSETS.iter()
+            .find(|(_, fontset_name)| *fontset_name == name)
+            .map(|(fontset, _)| *fontset)
+    }
+
+    /// Get the 10 byte high glyphs for the digits 0-F used by FX30,
+    /// interpreters without a large font get the SUPER-CHIP one
+    pub fn big(&self) -> &'static [u8; 160] {
+        match self {
+            Fontset::Octo => &OCTO_BIG_FONT,
+            _ => &SCHIP_BIG_FONT
+        }
+    }
+
+    /// Get the 5 byte high glyphs for the digits 0-F
+    pub fn small(&self) -> &'static [u8; 80] {
+        match self {
+            Fontset::Vip => &VIP_FONT,
+            Fontset::Schip | Fontset::Octo => &FONT,
+            Fontset::Dream6800 => &DREAM_6800_FONT
+        }
+    }
+}
+
+/// Glyph size used by `draw_text`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphSize {
+    /// 4x5 glyphs drawn by FX29
+    Small,
+    /// 8x10 SUPER-CHIP glyphs drawn by FX30
+    Big
+}
+
+impl GlyphSize {
+    /// Get the address of the first glyph and the bytes per glyph
+    fn layout(&self) -> (usize, usize) {
+        match self {
+            GlyphSize::Small => (FONT_START, 5),
+            GlyphSize::Big => (BIG_FONT_START, 10)
+        }
+    }
+
+    /// Get the pixels from the start of one character to the next
+    pub fn advance(&self) -> u16 {
+        match self {
+            GlyphSize::Small => 5,
+            GlyphSize::Big => 9
+        }
+    }
+}
+
+/// Draw hex digits onto the display with the font sprites in memory,
+/// the same way a program drawing its score would, returning the width
+/// drawn in pixels. Spaces leave a gap and other characters are refused
+/// before anything is drawn.
+///
+/// Sprites are XORed onto the display like DXYN, drawing the same text
+/// again erases it, so an overlay can be removed before the next frame.
+///
+/// # Arguments
+///
+/// * `sys` - system to draw on, its loaded fontset is used
+/// * `text` - hex digits and spaces, case is ignored
+/// * `x` - column of the left edge of the text
+/// * `y` - row of the top edge of the text
+/// * `size` - small or big glyphs
+pub fn draw_text(sys: &mut ChipSystem, text: &str, x: u16, y: u16, size: GlyphSize) -> Result<u16, String> {
+    if let Some(c) = text.chars().find(|c| *c != ' ' && !c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' has no glyph in the font, only hex digits can be drawn", c));
+    }
+    let (start, height) = size.layout();
+    let mut left = x;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(16) {
+            let glyph = sys.ram.peek_bytes((start + digit as usize * height) as u16, height);
+            sys.display.draw_sprite(left, y, glyph, true);
+        }
+        left += size.advance();
+    }
+    Ok(left - x)
+}
+
+/// Draw a number in decimal with the font sprites, see `draw_text`
+///
+/// # Arguments
+///
+/// * `sys` - system to draw on
+/// * `value` - number to draw
+/// * `x` - column of the left edge of the number
+/// * `y` - row of the top edge of the number
+/// * `size` - small or big glyphs
+pub fn draw_number(sys: &mut ChipSystem, value: u64, x: u16, y: u16, size: GlyphSize) -> u16 {
+    // Decimal digits always have a glyph
+    draw_text(sys, &value.to_string(), x, y, size).unwrap_or(0)
+}
@@ -0,0 +1,185 @@
+// Standard Library Modules //
+use std::time::Duration;
+
+// Local Modules Use //
+use super::{ChipSystem, EmulatorError};
+use super::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use super::image::RgbImage;
+
+/// Width of a frame of the original display in pixels
+pub const FRAME_WIDTH: usize = DISPLAY_WIDTH;
+
+/// Height of a frame of the original display in pixels
+pub const FRAME_HEIGHT: usize = DISPLAY_HEIGHT;
+
+/// The display at the end of one 60hz frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Frame number, 1 for the first frame run
+    pub number: u64,
+    /// Emulated time at the end of the frame, frame number / 60 seconds
+    pub timestamp: Duration,
+    /// Width of the display in pixels
+    pub width: usize,
+    /// Height of the display in pixels
+    pub height: usize,
+    /// Pixels in rows from the top left, true when lit
+    pub pixels: Vec<bool>
+}
+
+impl Frame {
+    /// Check if a pixel is lit, pixels outside the frame are unlit
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - column from the left
+    /// * `y` - row from the top
+    pub fn get_pixel(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.pixels[y * self.width + x]
+    }
+
+    /// Get the frame as bytes, 255 for a lit pixel and 0 for an unlit
+    /// one, the layout grayscale image and tensor libraries expect
+    pub fn to_luma(&self) -> Vec<u8> {
+        self.pixels.iter().map(|pixel| if *pixel { 255 } else { 0 }).collect()
+    }
+
+    /// Render the frame into an image, one pixel per chip8 pixel
+    ///
+    /// # Arguments
+    ///
+    /// * `on` - color of lit pixels
+    /// * `off` - color of unlit pixels
+    pub fn to_image(&self, on: [u8; 3], off: [u8; 3]) -> RgbImage {
+        let mut image = RgbImage::init(self.width, self.height);
+        for (pos, pixel) in self.pixels.iter().enumerate() {
+            image.set_pixel(pos % self.width, pos / self.width, if *pixel { on } else { off });
+        }
+        image
+    }
+}
+
+/// Opcodes run in one 60hz frame and how often input is read while
+/// they run, see `ChipSystem::run_frame_polled`
+///
+/// With one poll per frame a key pressed just after the poll waits a
+/// whole frame to be seen. Splitting the frame into slices with a poll
+/// before each lets programs running many opcodes per frame see input
+/// sooner, without changing how many opcodes a frame runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameBudget {
+    /// Opcodes run per frame
+    cycles: u32,
+    /// Times input is read per frame, at least 1
+    input_polls: u32
+}
+
+impl FrameBudget {
+    /// Initialize a budget reading input once per frame
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - opcodes run per frame
+    pub fn init(cycles: u32) -> Self {
+        FrameBudget { cycles, input_polls: 1 }
+    }
+
+    /// Get the opcodes run per frame
+    pub fn get_cycles(&self) -> u32 {
+        self.cycles
+    }
+
+    /// Set the opcodes run per frame
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - opcodes run per frame
+    pub fn set_cycles(&mut self, cycles: u32) {
+        self.cycles = cycles;
+    }
+
+    /// Get the times input is read per frame
+    pub fn get_input_polls(&self) -> u32 {
+        self.input_polls
+    }
+
+    /// Set the times input is read per frame, values below 1 are
+    /// taken as 1
+    ///
+    /// # Arguments
+    ///
+    /// * `polls` - times input is read per frame
+    pub fn set_input_polls(&mut self, polls: u32) {
+        self.input_polls = polls.max(1);
+    }
+
+    /// Get the opcodes run after each input poll, as even as possible
+    /// with the larger slices first. They add up to the opcodes run per
+    /// frame, a frame with more polls than opcodes polls once per
+    /// opcode.
+    pub fn get_slices(&self) -> Vec<u32> {
+        let polls = self.input_polls.min(self.cycles).max(1);
+        let (size, extra) = (self.cycles / polls, self.cycles % polls);
+        (0..polls).map(|slice| size + (slice < extra) as u32).collect()
+    }
+}
+
+/// Iterator running a system one frame at a time, see
+/// `ChipSystem::frames`
+///
+/// The iterator ends when the program exits or an invalid opcode is
+/// run, `get_error` tells the two apart.
+pub struct Frames<'a> {
+    /// System being run
+    sys: &'a mut ChipSystem,
+    /// Opcodes run per frame
+    cycles: u32,
+    /// Error that ended the iterator
+    error: Option<EmulatorError>
+}
+
+impl<'a> Frames<'a> {
+    /// Start running a system frame by frame
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to run, with a ROM loaded
+    /// * `cycles` - opcodes to run per frame
+    pub fn init(sys: &'a mut ChipSystem, cycles: u32) -> Self {
+        Frames { sys, cycles, error: None }
+    }
+
+    /// Get the error that ended the iterator, None while it is running
+    /// or when the program exited on its own
+    pub fn get_error(&self) -> Option<&EmulatorError> {
+        self.error.as_ref()
+    }
+
+    /// Get the system being run, for reading registers or pressing keys
+    /// between frames
+    pub fn system(&mut self) -> &mut ChipSystem {
+        self.sys
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.error.is_some() || self.sys.get_exit().is_some() {
+            return None;
+        }
+        if let Err(e) = self.sys.run_frame(self.cycles) {
+            self.error = Some(e);
+            return None;
+        }
+        let number = self.sys.get_frame();
+        Some(Frame {
+            number,
+            timestamp: Duration::from_secs_f64(number as f64 / 60.0),
+            width: self.sys.display.get_width(),
+            height: self.sys.display.get_height(),
+            pixels: self.sys.display.get_display()
+        })
+    }
+}
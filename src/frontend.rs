@@ -0,0 +1,381 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::disasm;
+use super::keyboard::ChipKeyboard;
+use super::overlay::{KeypadOverlay, OnionSkin};
+use super::palette::Palette;
+use super::termpad::{MouseEvent, TerminalKeypad};
+
+/// Something that shows the running system to the player, the run loop
+/// calls `present` after every frame and `finish` once when it stops
+pub trait Frontend {
+    /// Show the system as it is at the end of a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system that just ran a frame
+    fn present(&mut self, sys: &mut ChipSystem) -> io::Result<()>;
+
+    /// Put the terminal or window back the way it was found
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Draw everything again on the next `present`, after something
+    /// else such as a menu was drawn over the frontend
+    fn redraw(&mut self) {}
+
+    /// Show or hide the keypad overlay, for the keypad hotkey
+    fn toggle_keypad(&mut self) {}
+
+    /// Handle a mouse event, returning true if it was on something the
+    /// frontend draws, such as a clickable keypad
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - mouse event from the terminal
+    /// * `keyboard` - keypad the frontend can press keys on
+    fn mouse(&mut self, _event: MouseEvent, _keyboard: &mut ChipKeyboard) -> bool {
+        false
+    }
+}
+
+/// Prints the whole display with `ChipDisplay::draw_display` each time
+/// it changes, works on any terminal or when output is redirected
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintFrontend;
+
+impl Frontend for PrintFrontend {
+    fn present(&mut self, sys: &mut ChipSystem) -> io::Result<()> {
+        if sys.display.mod_check() {
+            sys.display.draw_display();
+        }
+        Ok(())
+    }
+}
+
+/// Switches to the alternate screen and hides the cursor
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+
+/// Shows the cursor and returns to the normal screen
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+/// Draws the display on the terminal's alternate screen, two pixel rows
+/// per line with half block characters, rewriting only the cells
+/// that changed since the last frame so it does not flicker
+///
+/// The terminal is switched over on the first frame and restored by
+/// `finish`, or when the frontend is dropped if the run ended early.
+/// With `show_keypad` a `TerminalKeypad` is drawn below the display,
+/// played by passing mouse events to `mouse`. While the keypad overlay
+/// of `toggle_keypad` is shown the whole display is drawn as a colored
+/// image with the overlay on top instead, and it can be clicked too.
+/// The same goes for the faded previous frames of `show_onion`.
+pub struct AnsiFrontend {
+    /// Pixels shown by the last frame, empty before the first one
+    shown: Vec<bool>,
+    /// Display size the last frame was drawn at
+    size: (usize, usize),
+    /// True while the alternate screen is in use
+    active: bool,
+    /// Clickable keypad drawn below the display
+    keypad: Option<TerminalKeypad>,
+    /// Keys held when the keypad was last drawn, one bit per key and
+    /// the top bit while a key is waited for
+    shown_keys: Option<u32>,
+    /// Keypad drawn over the display
+    overlay: KeypadOverlay,
+    /// Previous frames drawn faded behind the display
+    onion: OnionSkin,
+    /// Colors of the display while an overlay is drawn over it
+    palette: Palette,
+    /// True if the last frame was drawn as an image with an overlay
+    overlaid: bool
+}
+
+impl AnsiFrontend {
+    /// Initialize a frontend, the terminal is left alone until the
+    /// first frame is presented
+    pub fn init() -> Self {
+        AnsiFrontend {
+            shown: Vec::new(),
+            size: (0, 0),
+            active: false,
+            keypad: None,
+            shown_keys: None,
+            overlay: KeypadOverlay::init(6),
+            onion: OnionSkin::init(0),
+            palette: Palette::default(),
+            overlaid: false
+        }
+    }
+
+    /// Draw a keypad below the display that can be clicked with the
+    /// mouse, the terminal has to report the mouse for it to be played
+    pub fn show_keypad(&mut self) {
+        self.keypad = Some(TerminalKeypad::init(0, 0));
+        self.shown_keys = None;
+    }
+
+    /// Draw the last frames faded behind the display, older ones
+    /// fainter, to follow movement between frames
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - number of previous frames drawn
+    pub fn show_onion(&mut self, depth: usize) {
+        self.onion.set_depth(depth);
+        self.onion.enabled = depth > 0;
+    }
+
+    /// Set the colors of the display while an overlay is drawn over it,
+    /// otherwise it is drawn in the terminal's own colors
+    ///
+    /// # Arguments
+    ///
+    /// * `palette` - colors of lit and unlit pixels
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+}
+
+impl Default for AnsiFrontend {
+    fn default() -> Self {
+        Self::init()
+    }
+}
+
+/// Character showing a pixel with the one below it
+fn half_block(top: bool, bottom: bool) -> char {
+    match (top, bottom) {
+        (false, false) => ' ',
+        (true, false) => '\u{2580}',
+        (false, true) => '\u{2584}',
+        (true, true) => '\u{2588}'
+    }
+}
+
+impl Frontend for AnsiFrontend {
+    fn present(&mut self, sys: &mut ChipSystem) -> io::Result<()> {
+        let display = &mut sys.display;
+        let size = (display.get_width(), display.get_height());
+        let overlaid = self.overlay.visible || self.onion.enabled;
+        if !self.overlay.visible {
+            self.overlay.release_pointer(&mut sys.keyboard);
+        }
+        // Cells left colored by the overlay are cleared away with the
+        // rest of the screen once it is hidden
+        let resized = size != self.size || (self.overlaid && !overlaid);
+        let keyboard = &sys.keyboard;
+        let waiting = (keyboard.get_waiting().is_some() as u32) << 16;
+        let keys = (0..16).filter(|key| keyboard.get_key(*key)).fold(waiting, |mask, key| mask | 1 << key);
+        let keys_changed = (self.keypad.is_some() || overlaid) && self.shown_keys != Some(keys);
+        // Previous frames fade out while the display stays the same
+        let fading = self.onion.enabled;
+        if !display.mod_check() && !resized && !keys_changed && !fading && overlaid == self.overlaid {
+            return Ok(());
+        }
+        let mut out = String::new();
+        if !self.active {
+            out.push_str(ENTER_SCREEN);
+            self.active = true;
+        }
+        // A new size moves every cell, start over from a blank screen
+        if resized {
+            out.push_str("\x1b[2J");
+            self.shown = vec![false; size.0 * size.1];
+            self.size = size;
+            if let Some(keypad) = self.keypad.as_mut() {
+                keypad.set_position(0, size.1.div_ceil(2) as u16 + 1);
+            }
+        }
+        self.overlaid = overlaid;
+        if overlaid {
+            let mut image = self.onion.render(display, self.palette.on, self.palette.off);
+            self.overlay.draw(&mut image, keyboard);
+            for (line, text) in image.render_ansi().lines().enumerate() {
+                out.push_str(&format!("\x1b[{};1H{}", line + 1, text));
+            }
+        }
+        let pixels = display.get_display();
+        let (width, height) = size;
+        let pixel = |pixels: &[bool], x: usize, y: usize| y < height && pixels[y * width + x];
+        for line in (0..height.div_ceil(2)).filter(|_| !overlaid) {
+            let (top, bottom) = (line * 2, line * 2 + 1);
+            // Column the cursor is at on this line, None until moved here
+            let mut cursor: Option<usize> = None;
+            for x in 0..width {
+                let now = (pixel(&pixels, x, top), pixel(&pixels, x, bottom));
+                if now == (pixel(&self.shown, x, top), pixel(&self.shown, x, bottom)) {
+                    continue;
+                }
+                if cursor != Some(x) {
+                    out.push_str(&format!("\x1b[{};{}H", line + 1, x + 1));
+                }
+                out.push(half_block(now.0, now.1));
+                cursor = Some(x + 1);
+            }
+        }
+        self.shown = pixels;
+        if self.onion.enabled {
+            self.onion.record(display);
+        }
+        if let Some(keypad) = self.keypad.as_ref().filter(|_| keys_changed || resized) {
+            out.push_str(&keypad.render(keyboard));
+        }
+        self.shown_keys = Some(keys);
+        let mut stdout = io::stdout();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+        self.active = false;
+        let mut stdout = io::stdout();
+        stdout.write_all(LEAVE_SCREEN.as_bytes())?;
+        stdout.flush()
+    }
+
+    fn redraw(&mut self) {
+        // The size check then starts over from a blank screen
+        self.size = (0, 0);
+    }
+
+    fn toggle_keypad(&mut self) {
+        self.overlay.toggle();
+    }
+
+    fn mouse(&mut self, event: MouseEvent, keyboard: &mut ChipKeyboard) -> bool {
+        // A cell shows two pixel rows, the top one is clicked
+        let (width, height) = self.size;
+        let (px, py) = (event.col as usize, event.row as usize * 2);
+        if self.overlay.pointer(px, py, event.down, width, height, keyboard) {
+            return true;
+        }
+        match self.keypad.as_mut() {
+            Some(keypad) => keypad.mouse(event, keyboard),
+            None => false
+        }
+    }
+}
+
+impl Drop for AnsiFrontend {
+    fn drop(&mut self) {
+        // Nothing more can be done about a failure while dropping
+        let _ = self.finish();
+    }
+}
+
+/// Instructions disassembled before and after the program counter by
+/// `DebugFrontend`
+const DEBUG_CONTEXT: u16 = 6;
+
+/// A debugger view on the terminal's alternate screen: the display with
+/// the registers, timers, call stack and the disassembly around the
+/// program counter beside it, all redrawn every frame
+///
+/// The screen is overwritten in place rather than cleared so it does
+/// not flicker. Pair it with the monitor to poke at the game while
+/// watching it.
+pub struct DebugFrontend {
+    /// True while the alternate screen is in use
+    active: bool
+}
+
+impl DebugFrontend {
+    /// Initialize a frontend, the terminal is left alone until the
+    /// first frame is presented
+    pub fn init() -> Self {
+        DebugFrontend { active: false }
+    }
+}
+
+impl Default for DebugFrontend {
+    fn default() -> Self {
+        Self::init()
+    }
+}
+
+/// Lines of the debugger panel shown beside the display
+fn debug_panel(sys: &ChipSystem) -> Vec<String> {
+    let reg = &sys.registers;
+    let pc = reg.get_pc();
+    let mut lines = vec![
+        format!("PC {:03x}  I {:03x}  frame {}", pc, reg.get_i(), sys.get_frame()),
+        format!("DT {:02x}   ST {:02x}", reg.get_d(), reg.get_s())
+    ];
+    for row in 0..4 {
+        let gp: Vec<String> = (row * 4..row * 4 + 4).map(|i| format!("V{:X} {:02x}", i, reg.get_gp(i))).collect();
+        lines.push(gp.join("  "));
+    }
+    let stack: Vec<String> = reg.get_stack().iter().rev().map(|addr| format!("{:03x}", addr)).collect();
+    match stack.is_empty() {
+        true => lines.push(String::from("Stack: empty")),
+        false => lines.push(format!("Stack: {}", stack.join(" ")))
+    }
+    if let Some(reason) = sys.get_exit() {
+        lines.push(format!("Exited: {:?}", reason));
+    }
+    lines.push(String::new());
+    lines.extend(disasm::listing_around(&sys.ram, pc, DEBUG_CONTEXT));
+    lines
+}
+
+impl Frontend for DebugFrontend {
+    fn present(&mut self, sys: &mut ChipSystem) -> io::Result<()> {
+        let display = &mut sys.display;
+        display.mod_check();
+        let (width, height) = (display.get_width(), display.get_height());
+        let pixels = display.get_display();
+        let pixel = |x: usize, y: usize| y < height && pixels[y * width + x];
+        let mut left = vec![format!("+{}+", "-".repeat(width))];
+        for line in 0..height.div_ceil(2) {
+            let row: String = (0..width).map(|x| half_block(pixel(x, line * 2), pixel(x, line * 2 + 1))).collect();
+            left.push(format!("|{}|", row));
+        }
+        left.push(left[0].clone());
+        let right = debug_panel(sys);
+
+        let mut out = String::new();
+        if !self.active {
+            out.push_str(ENTER_SCREEN);
+            self.active = true;
+        }
+        out.push_str("\x1b[H");
+        let blank = " ".repeat(width + 2);
+        for line in 0..left.len().max(right.len()) {
+            let (display_part, panel_part) = (left.get(line).unwrap_or(&blank), right.get(line).map_or("", |text| text));
+            // Clearing to the end of the line removes longer text drawn
+            // there by the last frame
+            out.push_str(&format!("{}  {}\x1b[K\r\n", display_part, panel_part));
+        }
+        out.push_str("\x1b[J");
+        let mut stdout = io::stdout();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+        self.active = false;
+        let mut stdout = io::stdout();
+        stdout.write_all(LEAVE_SCREEN.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+impl Drop for DebugFrontend {
+    fn drop(&mut self) {
+        // Nothing more can be done about a failure while dropping
+        let _ = self.finish();
+    }
+}
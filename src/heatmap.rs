@@ -0,0 +1,183 @@
+// Standard Library Modules //
+use std::cell::Cell;
+
+// Local Modules Use //
+use super::image::RgbImage;
+
+/// Per address counters of how memory is used
+///
+/// Reads happen through `&self` methods on the memory, so the counters
+/// use `Cell` to be updated without needing a mutable borrow.
+#[derive(Debug, Clone)]
+pub struct MemoryHeat {
+    /// Times each address was read as data
+    reads: Vec<Cell<u32>>,
+    /// Times each address was written
+    writes: Vec<Cell<u32>>,
+    /// Times each address was fetched as part of an opcode
+    executes: Vec<Cell<u32>>
+}
+
+impl MemoryHeat {
+    /// Initialize empty counters
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - number of addresses to track
+    pub fn init(size: usize) -> Self {
+        MemoryHeat {
+            reads: vec![Cell::new(0); size],
+            writes: vec![Cell::new(0); size],
+            executes: vec![Cell::new(0); size]
+        }
+    }
+
+    /// Count a data read of an address
+    pub fn record_read(&self, addr: usize) {
+        MemoryHeat::bump(&self.reads, addr);
+    }
+
+    /// Count a write to an address
+    pub fn record_write(&self, addr: usize) {
+        MemoryHeat::bump(&self.writes, addr);
+    }
+
+    /// Count an opcode fetch of an address
+    pub fn record_execute(&self, addr: usize) {
+        MemoryHeat::bump(&self.executes, addr);
+    }
+
+    fn bump(counts: &[Cell<u32>], addr: usize) {
+        if let Some(count) = counts.get(addr) {
+            count.set(count.get().saturating_add(1));
+        }
+    }
+
+    /// Get the (read, write, execute) counts of an address
+    pub fn get_counts(&self, addr: usize) -> (u32, u32, u32) {
+        let get = |counts: &[Cell<u32>]| counts.get(addr).map_or(0, |c| c.get());
+        (get(&self.reads), get(&self.writes), get(&self.executes))
+    }
+
+    /// Number of tracked addresses
+    pub fn len(&self) -> usize {
+        self.reads.len()
+    }
+
+    /// True if no addresses are tracked
+    pub fn is_empty(&self) -> bool {
+        self.reads.is_empty()
+    }
+
+    /// Set every counter back to zero
+    pub fn clear(&self) {
+        for counts in [&self.reads, &self.writes, &self.executes].iter() {
+            for count in counts.iter() {
+                count.set(0);
+            }
+        }
+    }
+
+    fn scale(count: u32, max: u32) -> u8 {
+        if count == 0 || max == 0 {
+            return 0;
+        }
+        // Anything touched is at least dimly visible
+        let level = (1.0 + count as f64).ln() / (1.0 + max as f64).ln();
+        (64.0 + level * 191.0) as u8
+    }
+
+    /// Render the heatmap as an image, one pixel per address laid out
+    /// in rows of 64 bytes
+    pub fn to_image(&self) -> RgbImage {
+        let rows = self.len().div_ceil(64);
+        let mut image = RgbImage::init(64, rows);
+        let colors = self.colors();
+        for (addr, color) in colors.iter().enumerate() {
+            image.set_pixel(addr % 64, addr / 64, *color);
+        }
+        image
+    }
+
+    /// Render the heatmap for a terminal using ANSI true color, 64 bytes
+    /// per row with two rows of memory per line of text
+    pub fn render_ansi(&self) -> String {
+        let image = self.to_image();
+        let mut out = String::from("      R = write  G = read  B = execute\n");
+        for (row, line) in image.render_ansi().lines().enumerate() {
+            out.push_str(&format!("{:03x}  {}\n", row * 128, line));
+        }
+        out
+    }
+
+    /// Color of every address, red for writes, green for reads and
+    /// blue for executes. Each channel is log scaled against the busiest
+    /// address of that kind so rarely touched bytes still show up.
+    pub fn colors(&self) -> Vec<[u8; 3]> {
+        let max = |counts: &[Cell<u32>]| counts.iter().map(|c| c.get()).max().unwrap_or(0);
+        let (max_r, max_w, max_x) = (max(&self.reads), max(&self.writes), max(&self.executes));
+        (0..self.len())
+            .map(|addr| {
+                let (reads, writes, executes) = self.get_counts(addr);
+                [
+                    MemoryHeat::scale(writes, max_w),
+                    MemoryHeat::scale(reads, max_r),
+                    MemoryHeat::scale(executes, max_x)
+                ]
+            })
+            .collect()
+    }
+}
+
+/// Render a flicker map of the display, pixels that change state often
+/// glow from red to yellow on top of the current display contents which
+/// are drawn in grey
+///
+/// # Arguments
+///
+/// * `pixels` - current display contents
+/// * `toggles` - times each pixel changed state
+/// * `width` - display width in pixels
+pub fn pixel_overlay(pixels: &[bool], toggles: &[u32], width: usize) -> RgbImage {
+    let height = pixels.len() / width;
+    let max = toggles.iter().copied().max().unwrap_or(0);
+    let mut image = RgbImage::init(width, height);
+    for (pos, (on, count)) in pixels.iter().zip(toggles.iter()).enumerate() {
+        image.set_pixel(pos % width, pos / width, flicker_color(*on, *count, max));
+    }
+    image
+}
+
+/// Render a flicker map for a terminal using ANSI true color, two rows
+/// of pixels per line of text, see `pixel_overlay`
+///
+/// # Arguments
+///
+/// * `pixels` - current display contents
+/// * `toggles` - times each pixel changed state
+/// * `width` - display width in pixels
+pub fn render_pixel_overlay_ansi(pixels: &[bool], toggles: &[u32], width: usize) -> String {
+    let mut out = pixel_overlay(pixels, toggles, width).render_ansi();
+    let busiest = toggles.iter().enumerate().max_by_key(|(_, count)| **count);
+    if let Some((pos, count)) = busiest {
+        out.push_str(&format!(
+            "Busiest pixel ({}, {}) changed {} times\n",
+            pos % width, pos / width, count
+        ));
+    }
+    out
+}
+
+/// Color of one pixel in the flicker map
+fn flicker_color(on: bool, count: u32, max: u32) -> [u8; 3] {
+    let base: u8 = if on { 96 } else { 0 };
+    if count == 0 || max == 0 {
+        return [base, base, base];
+    }
+    let level = (1.0 + count as f64).ln() / (1.0 + max as f64).ln();
+    [
+        (96.0 + level * 159.0) as u8,
+        (level * level * 220.0) as u8,
+        base / 2
+    ]
+}
@@ -0,0 +1,138 @@
+// Local Modules Use //
+use super::memory::ChipMemory;
+
+/// Bytes shown on a row of the hex view
+pub const HEX_ROW: usize = 16;
+
+/// Rows shown on a page of the hex view
+pub const HEX_PAGE_ROWS: usize = 8;
+
+/// A paged hex view of memory for the debugger, remembering the page
+/// shown so it can be scrolled
+///
+/// Each row shows 16 bytes in hex and as text. Rows holding the opcode
+/// at PC or the byte at I get a line underneath pointing at them, `^^`
+/// for PC and `**` for I.
+#[derive(Debug, Clone)]
+pub struct HexView {
+    /// First address of the page shown
+    addr: usize,
+    /// Rows on a page
+    rows: usize,
+    /// Where the last search matched, the next one starts after it
+    found: Option<usize>
+}
+
+impl Default for HexView {
+    fn default() -> Self {
+        HexView::init()
+    }
+}
+
+impl HexView {
+    /// Initialize a view of the first program page, 0x200
+    pub fn init() -> Self {
+        HexView { addr: 0x200, rows: HEX_PAGE_ROWS, found: None }
+    }
+
+    /// Get the first address of the page shown
+    pub fn get_addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Move the page to the row holding an address
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory viewed, for its size
+    /// * `addr` - address to show
+    pub fn set_addr(&mut self, ram: &ChipMemory, addr: usize) {
+        self.addr = (addr % ram.get_size()) / HEX_ROW * HEX_ROW;
+        self.found = None;
+    }
+
+    /// Scroll a page forward, wrapping at the end of memory
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory viewed, for its size
+    pub fn next_page(&mut self, ram: &ChipMemory) {
+        self.set_addr(ram, self.addr + self.rows * HEX_ROW);
+    }
+
+    /// Scroll a page back, wrapping at the start of memory
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory viewed, for its size
+    pub fn prev_page(&mut self, ram: &ChipMemory) {
+        self.set_addr(ram, self.addr + ram.get_size() - self.rows * HEX_ROW);
+    }
+
+    /// Find bytes in memory, searching forward from an address and
+    /// wrapping around, and return where they start
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory to search
+    /// * `pattern` - bytes to find
+    /// * `from` - first address to look at
+    pub fn find(ram: &ChipMemory, pattern: &[u8], from: usize) -> Option<usize> {
+        let size = ram.get_size();
+        if pattern.is_empty() || pattern.len() > size {
+            return None;
+        }
+        // Reading without get_byte keeps searches out of the heatmap
+        let bytes = ram.peek_bytes(0, size + pattern.len() - 1);
+        (0..size).map(|offset| (from + offset) % size)
+            .find(|start| bytes[*start..*start + pattern.len()] == *pattern)
+    }
+
+    /// Find bytes after the last match, or from the page shown, and
+    /// move the page to them
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory to search
+    /// * `pattern` - bytes to find
+    pub fn find_next(&mut self, ram: &ChipMemory, pattern: &[u8]) -> Option<usize> {
+        let from = self.found.map_or(self.addr, |found| found + 1);
+        let addr = HexView::find(ram, pattern, from)?;
+        self.set_addr(ram, addr);
+        self.found = Some(addr);
+        Some(addr)
+    }
+
+    /// Draw the page as text
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - memory to show
+    /// * `pc` - program counter, its two bytes are pointed at
+    /// * `i` - index register, its byte is pointed at
+    pub fn render(&self, ram: &ChipMemory, pc: u16, i: u16) -> String {
+        let size = ram.get_size();
+        let (pc, i) = (pc as usize % size, i as usize % size);
+        let mut out = String::new();
+        for row in 0..self.rows {
+            let start = (self.addr + row * HEX_ROW) % size;
+            let bytes = ram.peek_bytes(start as u16, HEX_ROW);
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text: String = bytes.iter()
+                .map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' })
+                .collect();
+            out.push_str(&format!("{:03x}: {}  {}\n", start, hex.join(" "), text));
+
+            let marks: Vec<&str> = (start..start + HEX_ROW).map(|addr| match addr % size {
+                addr if addr == pc || addr == (pc + 1) % size => "^^",
+                addr if addr == i => "**",
+                _ => "  "
+            }).collect();
+            if marks.iter().any(|mark| *mark != "  ") {
+                out.push_str(&format!("     {}\n", marks.join(" ").trim_end()));
+            }
+        }
+        out.push_str(&format!("PC {:03x} ^^  I {:03x} **\n", pc, i));
+        out
+    }
+}
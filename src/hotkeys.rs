@@ -0,0 +1,324 @@
+// Standard Library Modules //
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::config::config_dir;
+
+/// Emulator actions that can be bound to a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hotkey {
+    /// Open or close the pause menu
+    Menu,
+    /// Pause or resume without the menu
+    Pause,
+    /// Restart the loaded ROM
+    Reset,
+    /// Switch between running as fast as possible and normal speed
+    Turbo,
+    /// Switch between slow motion and normal speed
+    SlowMotion,
+    /// Go back to normal speed from turbo or slow motion
+    NormalSpeed,
+    /// Step back in time while held
+    Rewind,
+    /// Save the display as an image
+    Screenshot,
+    /// Save the display as a text file, see `export::display_to_text`
+    TextScreenshot,
+    /// Save the machine state
+    SaveState,
+    /// Restore the saved machine state
+    LoadState,
+    /// Switch between windowed and fullscreen
+    Fullscreen,
+    /// Show or hide the keypad overlay
+    Keypad
+}
+
+/// Every action in the order they are listed, with their config name
+pub const HOTKEYS: [(Hotkey, &str); 13] = [
+    (Hotkey::Menu, "menu"),
+    (Hotkey::Pause, "pause"),
+    (Hotkey::Reset, "reset"),
+    (Hotkey::Turbo, "turbo"),
+    (Hotkey::SlowMotion, "slow_motion"),
+    (Hotkey::NormalSpeed, "normal_speed"),
+    (Hotkey::Rewind, "rewind"),
+    (Hotkey::Screenshot, "screenshot"),
+    (Hotkey::TextScreenshot, "text_screenshot"),
+    (Hotkey::SaveState, "save_state"),
+    (Hotkey::LoadState, "load_state"),
+    (Hotkey::Fullscreen, "fullscreen"),
+    (Hotkey::Keypad, "keypad")
+];
+
+impl Hotkey {
+    /// Name of the action used in config files
+    pub fn name(&self) -> &'static str {
+        HOTKEYS.iter()
+            .find(|(hotkey, _)| hotkey == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Find an action by its config name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - config name of the action
+    pub fn from_name(name: &str) -> Option<Hotkey> {
+        HOTKEYS.iter()
+            .find(|(_, hotkey_name)| *hotkey_name == name)
+            .map(|(hotkey, _)| *hotkey)
+    }
+}
+
+/// A key with the modifiers held with it
+///
+/// Keys are named the same way by every frontend: lowercase letters
+/// and digits, `f1`-`f12`, and names like `escape`, `space`, `tab`,
+/// `backspace` and `enter`. Frontends convert their key events with
+/// `init` before looking them up.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCombo {
+    /// Lowercase name of the key
+    pub key: String,
+    /// True if control is held
+    pub ctrl: bool,
+    /// True if shift is held
+    pub shift: bool,
+    /// True if alt is held
+    pub alt: bool
+}
+
+impl KeyCombo {
+    /// Create a key combination
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the key, case is ignored
+    /// * `ctrl` - true if control is held
+    /// * `shift` - true if shift is held
+    /// * `alt` - true if alt is held
+    pub fn init(key: &str, ctrl: bool, shift: bool, alt: bool) -> Self {
+        KeyCombo {
+            key: key.to_ascii_lowercase(),
+            ctrl,
+            shift,
+            alt
+        }
+    }
+
+    /// Parse a combination like `ctrl+shift+s` or `f5`
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - combination to parse
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut combo = KeyCombo::init("", false, false, false);
+        for part in text.split('+').map(|part| part.trim().to_ascii_lowercase()) {
+            match part.as_str() {
+                "ctrl" | "control" => combo.ctrl = true,
+                "shift" => combo.shift = true,
+                "alt" => combo.alt = true,
+                "" => return None,
+                _ if !combo.key.is_empty() => return None,
+                _ => combo.key = part
+            }
+        }
+        match combo.key.is_empty() {
+            true => None,
+            false => Some(combo)
+        }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "shift+")?;
+        }
+        if self.alt {
+            write!(f, "alt+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+/// A problem in a hotkey config file
+#[derive(Debug, Clone)]
+pub struct HotkeyError {
+    /// Line number starting at 1
+    line: usize,
+    /// What is wrong with the line
+    message: String
+}
+
+impl fmt::Display for HotkeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for HotkeyError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Table of key bindings shared by every frontend
+///
+/// Frontends look up each key press here instead of hard coding their
+/// own bindings. The table starts with the default bindings and can be
+/// changed from a config file with one `action = key, key` line per
+/// action, for example `save_state = f5, ctrl+s`. Actions not named in
+/// the file keep their default keys and `#` starts a comment.
+#[derive(Debug, Clone)]
+pub struct HotkeyMap {
+    /// Bound keys, in the order they were bound
+    bindings: Vec<(KeyCombo, Hotkey)>
+}
+
+impl Default for HotkeyMap {
+    /// Default bindings, chosen to stay clear of the keys the chip8
+    /// keypad is usually mapped to (1-4, q-r, a-f, z-v)
+    fn default() -> Self {
+        let mut map = HotkeyMap { bindings: Vec::new() };
+        let defaults = [
+            ("escape", Hotkey::Menu),
+            ("p", Hotkey::Pause),
+            ("f2", Hotkey::Reset),
+            ("tab", Hotkey::Turbo),
+            ("f3", Hotkey::SlowMotion),
+            ("f4", Hotkey::NormalSpeed),
+            ("backspace", Hotkey::Rewind),
+            ("f12", Hotkey::Screenshot),
+            ("f10", Hotkey::TextScreenshot),
+            ("f5", Hotkey::SaveState),
+            ("f9", Hotkey::LoadState),
+            ("f11", Hotkey::Fullscreen),
+            ("f1", Hotkey::Keypad)
+        ];
+        for (key, hotkey) in defaults.iter() {
+            map.bind(KeyCombo::init(key, false, false, false), *hotkey);
+        }
+        map
+    }
+}
+
+impl HotkeyMap {
+    /// Initialize a table with the default bindings
+    pub fn init() -> Self {
+        HotkeyMap::default()
+    }
+
+    /// Default location of the hotkey config, `hotkeys` in the
+    /// configuration directory, None if it can not be found
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("hotkeys"))
+    }
+
+    /// Load the default bindings changed by a config file, a missing
+    /// file gives the default bindings
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - config file to load
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut map = HotkeyMap::default();
+        match fs::read_to_string(path) {
+            Ok(text) => map.apply(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e)
+        }
+        Ok(map)
+    }
+
+    /// Apply the bindings in a config file's text, every action named
+    /// loses its current keys first
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of a config file
+    pub fn apply(&mut self, text: &str) -> Result<(), HotkeyError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| HotkeyError { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, keys) = match line.split_once('=') {
+                Some((name, keys)) => (name.trim(), keys.trim()),
+                None => return Err(error(format!("expected 'action = key', found '{}'", line)))
+            };
+            let hotkey = Hotkey::from_name(name).ok_or_else(|| error(format!("unknown action '{}'", name)))?;
+            self.unbind(hotkey);
+            for key in keys.split(',').map(|key| key.trim()).filter(|key| !key.is_empty()) {
+                let combo = KeyCombo::parse(key).ok_or_else(|| error(format!("invalid key '{}'", key)))?;
+                self.bind(combo, hotkey);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind a key to an action, a key can only trigger one action so
+    /// any other binding of the key is removed
+    ///
+    /// # Arguments
+    ///
+    /// * `combo` - key to bind
+    /// * `hotkey` - action the key triggers
+    pub fn bind(&mut self, combo: KeyCombo, hotkey: Hotkey) {
+        self.bindings.retain(|(bound, _)| *bound != combo);
+        self.bindings.push((combo, hotkey));
+    }
+
+    /// Remove every key bound to an action
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkey` - action to unbind
+    pub fn unbind(&mut self, hotkey: Hotkey) {
+        self.bindings.retain(|(_, bound)| *bound != hotkey);
+    }
+
+    /// Find the action a key triggers
+    ///
+    /// # Arguments
+    ///
+    /// * `combo` - key that was pressed
+    pub fn lookup(&self, combo: &KeyCombo) -> Option<Hotkey> {
+        self.bindings.iter()
+            .find(|(bound, _)| bound == combo)
+            .map(|(_, hotkey)| *hotkey)
+    }
+
+    /// Get the keys bound to an action
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkey` - action to look up
+    pub fn keys_for(&self, hotkey: Hotkey) -> Vec<&KeyCombo> {
+        self.bindings.iter()
+            .filter(|(_, bound)| *bound == hotkey)
+            .map(|(combo, _)| combo)
+            .collect()
+    }
+
+    /// Format the table as a config file that reproduces it
+    pub fn to_config(&self) -> String {
+        let mut out = String::new();
+        for (hotkey, name) in HOTKEYS.iter() {
+            let keys: Vec<String> = self.keys_for(*hotkey).iter().map(|combo| combo.to_string()).collect();
+            out.push_str(&format!("{} = {}\n", name, keys.join(", ")));
+        }
+        out
+    }
+}
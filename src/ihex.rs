@@ -0,0 +1,156 @@
+// Standard Library Modules //
+use std::path::Path;
+
+/// Address ROMs are loaded at
+const ROM_START: u32 = 0x200;
+
+/// Largest ROM that fits in 4 KB of memory
+const MAX_ROM: u32 = 0x1000 - ROM_START;
+
+/// Data bytes written per record
+const RECORD_BYTES: usize = 16;
+
+/// Returns true if a file is named like an Intel HEX file, `.hex`,
+/// `.ihex` or `.ihx`
+///
+/// # Arguments
+///
+/// * `path` - file name
+pub fn is_ihex_path(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ["hex", "ihex", "ihx"].contains(&ext.to_ascii_lowercase().as_str()),
+        None => false
+    }
+}
+
+/// Decode the text of an Intel HEX file, returning the address of the
+/// lowest byte and the bytes from there to the highest one, gaps
+/// between records are filled with zeros
+///
+/// Data, end of file and both extended address record types are
+/// understood, start address records are ignored.
+///
+/// # Arguments
+///
+/// * `text` - contents of the file
+pub fn decode(text: &str) -> Result<(u32, Vec<u8>), String> {
+    let mut bytes: Vec<(u32, u8)> = Vec::new();
+    let mut base: u32 = 0;
+    let mut ended = false;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |message: &str| format!("line {}: {}", index + 1, message);
+        if ended {
+            return Err(error("record after the end of file record"));
+        }
+        let digits = line.strip_prefix(':').ok_or_else(|| error("records start with ':'"))?;
+        if digits.len() % 2 == 1 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(error("expected pairs of hex digits"));
+        }
+        let record: Vec<u8> = (0..digits.len()).step_by(2)
+            .map(|pos| u8::from_str_radix(&digits[pos..pos + 2], 16).unwrap_or(0))
+            .collect();
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(error("record length does not match its byte count"));
+        }
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(error("checksum mismatch"));
+        }
+        let addr = ((record[1] as u32) << 8) | record[2] as u32;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            0x00 => bytes.extend(data.iter().enumerate().map(|(offset, byte)| (base + addr + offset as u32, *byte))),
+            0x01 => ended = true,
+            0x02 if data.len() == 2 => base = (((data[0] as u32) << 8) | data[1] as u32) << 4,
+            0x04 if data.len() == 2 => base = (((data[0] as u32) << 8) | data[1] as u32) << 16,
+            0x03 | 0x05 => {},
+            kind => return Err(error(&format!("unsupported record type {:02x}", kind)))
+        }
+    }
+    let start = match bytes.iter().map(|(addr, _)| *addr).min() {
+        Some(start) => start,
+        None => return Ok((0, Vec::new()))
+    };
+    let end = bytes.iter().map(|(addr, _)| *addr).max().unwrap_or(start);
+    if end - start >= 0x10000 {
+        return Err(String::from("data spans more than 64 KB"));
+    }
+    let mut data = vec![0; (end - start) as usize + 1];
+    for (addr, byte) in bytes {
+        data[(addr - start) as usize] = byte;
+    }
+    Ok((start, data))
+}
+
+/// Decode an Intel HEX file into a ROM. Files whose data starts at
+/// 0x200 or above are taken to use chip8 addresses, with the ROM
+/// starting at 0x200. Files starting lower are taken to address the
+/// ROM from 0, the way EEPROM images usually are, unless that would
+/// not fit in memory, then they are a dump of the whole memory and
+/// the ROM is what follows 0x200.
+///
+/// # Arguments
+///
+/// * `text` - contents of the file
+pub fn decode_rom(text: &str) -> Result<Vec<u8>, String> {
+    let (start, data) = decode(text)?;
+    let memory_image = start >= ROM_START || start + data.len() as u32 > MAX_ROM;
+    match (memory_image, start >= ROM_START) {
+        (true, true) => {
+            let mut rom = vec![0; (start - ROM_START) as usize];
+            rom.extend(data);
+            Ok(rom)
+        },
+        (true, false) => Ok(data[(ROM_START - start) as usize..].to_vec()),
+        (false, _) => {
+            let mut rom = vec![0; start as usize];
+            rom.extend(data);
+            Ok(rom)
+        }
+    }
+}
+
+/// Write one record with its checksum
+fn record(out: &mut String, kind: u8, addr: u16, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+    bytes.extend_from_slice(data);
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+    out.push(':');
+    for byte in bytes.iter().chain(std::iter::once(&checksum)) {
+        out.push_str(&format!("{:02X}", byte));
+    }
+    out.push('\n');
+}
+
+/// Encode bytes as an Intel HEX file, 16 bytes per record, ending with
+/// an end of file record
+///
+/// # Arguments
+///
+/// * `data` - bytes to write
+/// * `start` - address of the first byte
+pub fn encode(data: &[u8], start: u32) -> String {
+    let mut out = String::new();
+    let mut base = 0;
+    for (index, chunk) in data.chunks(RECORD_BYTES).enumerate() {
+        let addr = start + (index * RECORD_BYTES) as u32;
+        // A record can not cross a 64 KB boundary, split it there
+        let split = ((0x10000 - (addr & 0xFFFF)) as usize).min(chunk.len());
+        for (offset, part) in [(0, &chunk[..split]), (split, &chunk[split..])] {
+            if part.is_empty() {
+                continue;
+            }
+            let addr = addr + offset as u32;
+            if addr >> 16 != base {
+                base = addr >> 16;
+                record(&mut out, 0x04, 0, &[(base >> 8) as u8, base as u8]);
+            }
+            record(&mut out, 0x00, addr as u16, part);
+        }
+    }
+    record(&mut out, 0x01, 0, &[]);
+    out
+}
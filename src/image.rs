@@ -0,0 +1,183 @@
+// Standard Library Modules //
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter};
+
+/// A simple 8 bit RGB image used for exporting pictures of the system
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbImage {
+    /// Width in pixels
+    width: usize,
+    /// Height in pixels
+    height: usize,
+    /// Pixel data, 3 bytes per pixel row by row
+    pixels: Vec<u8>
+}
+
+impl RgbImage {
+    /// Create a black image
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - width in pixels
+    /// * `height` - height in pixels
+    pub fn init(width: usize, height: usize) -> Self {
+        RgbImage {
+            width,
+            height,
+            pixels: vec![0; width * height * 3]
+        }
+    }
+
+    /// Get the width of the image
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height of the image
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the raw RGB bytes of the image
+    pub fn get_pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Set a pixel, pixels outside the image are ignored
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - x position
+    /// * `y` - y position
+    /// * `color` - RGB color
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let pos = (y * self.width + x) * 3;
+            self.pixels[pos..pos + 3].copy_from_slice(&color);
+        }
+    }
+
+    /// Get a pixel, black if outside the image
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - x position
+    /// * `y` - y position
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        if x < self.width && y < self.height {
+            let pos = (y * self.width + x) * 3;
+            [self.pixels[pos], self.pixels[pos + 1], self.pixels[pos + 2]]
+        } else {
+            [0, 0, 0]
+        }
+    }
+
+    /// Fill a rectangle with a color
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - left edge
+    /// * `y` - top edge
+    /// * `w` - width of the rectangle
+    /// * `h` - height of the rectangle
+    /// * `color` - RGB color
+    pub fn fill_rect(&mut self, x: usize, y: usize, w: usize, h: usize, color: [u8; 3]) {
+        for py in y..y + h {
+            for px in x..x + w {
+                self.set_pixel(px, py, color);
+            }
+        }
+    }
+
+    /// Scale the image up by an integer factor
+    ///
+    /// # Arguments
+    ///
+    /// * `scale` - how many output pixels each pixel becomes, per axis
+    pub fn scaled(&self, scale: usize) -> RgbImage {
+        let scale = scale.max(1);
+        let mut out = RgbImage::init(self.width * scale, self.height * scale);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                out.fill_rect(x * scale, y * scale, scale, scale, self.get_pixel(x, y));
+            }
+        }
+        out
+    }
+
+    /// Render the image for a terminal using ANSI true color, each
+    /// line of text holds two rows of pixels using half blocks
+    pub fn render_ansi(&self) -> String {
+        let mut out = String::new();
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let top = self.get_pixel(x, y);
+                let bottom = self.get_pixel(x, y + 1);
+                // Upper half block, foreground is the top pixel and
+                // background the bottom one
+                out.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                ));
+            }
+            out.push_str("\x1b[0m\n");
+        }
+        out
+    }
+
+    /// Read a PNG file, transparent pixels are drawn over black
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - file to read
+    pub fn load_png(path: &str) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(io::Error::other)?;
+        let channels = info.color_type.samples();
+        let mut image = RgbImage::init(info.width as usize, info.height as usize);
+        for (pos, pixel) in buffer[..info.buffer_size()].chunks(channels).enumerate() {
+            let (color, alpha) = match *pixel {
+                [gray] => ([gray; 3], 255),
+                [gray, alpha] => ([gray; 3], alpha),
+                [r, g, b] => ([r, g, b], 255),
+                [r, g, b, alpha] => ([r, g, b], alpha),
+                _ => return Err(io::Error::other("unsupported PNG color type"))
+            };
+            let color = color.map(|c| (c as u16 * alpha as u16 / 255) as u8);
+            image.set_pixel(pos % image.width, pos / image.width, color);
+        }
+        Ok(image)
+    }
+
+    /// Write the image to a PNG file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - file to write
+    pub fn save_png(&self, path: &str) -> io::Result<()> {
+        let file = File::create(path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.pixels).map_err(io::Error::other)?;
+        Ok(())
+    }
+
+    /// Encode the image as a baseline JPEG
+    ///
+    /// # Arguments
+    ///
+    /// * `quality` - JPEG quality from 1 to 100
+    pub fn encode_jpeg(&self, quality: u8) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let encoder = jpeg_encoder::Encoder::new(&mut out, quality.clamp(1, 100));
+        encoder.encode(&self.pixels, self.width as u16, self.height as u16, jpeg_encoder::ColorType::Rgb)
+            .map_err(io::Error::other)?;
+        Ok(out)
+    }
+}
@@ -0,0 +1,385 @@
+// Standard Library Modules //
+use std::fmt;
+
+// Local Modules Use //
+use super::variant::Variant;
+
+/// A decoded opcode, shared by the interpreter, the disassembler, the
+/// explanations and everything else reading programs
+///
+/// Registers are numbered 0-F, `Vx` first. Addresses are 12 bits and
+/// bytes are the low byte of the opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0nnn - Call a machine code routine, ignored
+    Sys(u16),
+    /// 00E0 - Clear the display
+    Clear,
+    /// 00EE - Return from a subroutine
+    Return,
+    /// 00FD - Stop the interpreter
+    Exit,
+    /// 00Cn - Scroll the display down n rows (SCHIP)
+    ScrollDown(u8),
+    /// 00Dn - Scroll the display up n rows (XO-CHIP)
+    ScrollUp(u8),
+    /// 00FB - Scroll the display right 4 columns (SCHIP)
+    ScrollRight,
+    /// 00FC - Scroll the display left 4 columns (SCHIP)
+    ScrollLeft,
+    /// 00FE - Switch to the 64x32 display (SCHIP)
+    LowRes,
+    /// 00FF - Switch to the 128x64 display (SCHIP)
+    HighRes,
+    /// 1nnn - Jump to nnn
+    Jump(u16),
+    /// 2nnn - Call the subroutine at nnn
+    Call(u16),
+    /// 3xkk - Skip the next instruction if `Vx == kk`
+    SkipEqByte(u8, u8),
+    /// 4xkk - Skip the next instruction if `Vx != kk`
+    SkipNeByte(u8, u8),
+    /// 5xy0 - Skip the next instruction if `Vx == Vy`
+    SkipEqReg(u8, u8),
+    /// 5xy2 - Store `Vx` to `Vy` at I (XO-CHIP)
+    StoreRange(u8, u8),
+    /// 5xy3 - Load `Vx` to `Vy` from I (XO-CHIP)
+    LoadRange(u8, u8),
+    /// 6xkk - `Vx = kk`
+    LoadByte(u8, u8),
+    /// 7xkk - `Vx = Vx + kk`, no carry flag
+    AddByte(u8, u8),
+    /// 8xy0 - `Vx = Vy`
+    LoadReg(u8, u8),
+    /// 8xy1 - `Vx = Vx | Vy`
+    Or(u8, u8),
+    /// 8xy2 - `Vx = Vx & Vy`
+    And(u8, u8),
+    /// 8xy3 - `Vx = Vx ^ Vy`
+    Xor(u8, u8),
+    /// 8xy4 - `Vx = Vx + Vy`, VF is the carry
+    AddReg(u8, u8),
+    /// 8xy5 - `Vx = Vx - Vy`, VF is 1 without a borrow
+    Sub(u8, u8),
+    /// 8xy6 - `Vx = Vx >> 1`, VF is the bit shifted out
+    ShiftRight(u8, u8),
+    /// 8xy7 - `Vx = Vy - Vx`, VF is 1 without a borrow
+    SubN(u8, u8),
+    /// 8xyE - `Vx = Vx << 1`, VF is the bit shifted out
+    ShiftLeft(u8, u8),
+    /// 9xy0 - Skip the next instruction if `Vx != Vy`
+    SkipNeReg(u8, u8),
+    /// Annn - `I = nnn`
+    LoadI(u16),
+    /// Bnnn - Jump to `nnn + V0`
+    JumpOffset(u16),
+    /// Cxkk - `Vx = random byte & kk`
+    Random(u8, u8),
+    /// Dxyn - Draw the n byte sprite at I at `Vx, Vy`
+    Draw(u8, u8, u8),
+    /// Ex9E - Skip the next instruction if the key in `Vx` is pressed
+    SkipKey(u8),
+    /// ExA1 - Skip the next instruction if the key in `Vx` is not pressed
+    SkipNotKey(u8),
+    /// F000 nnnn - `I = nnnn`, the address in the next two bytes (XO-CHIP)
+    LoadILong,
+    /// Fn01 - Draw on the planes in the bitmask n (XO-CHIP)
+    Plane(u8),
+    /// F002 - Load the 16 byte audio pattern from I (XO-CHIP)
+    Audio,
+    /// Fx07 - `Vx = delay timer`
+    ReadDelay(u8),
+    /// Fx0A - Wait for a key to be pressed and released, store it in `Vx`
+    WaitKey(u8),
+    /// Fx15 - `delay timer = Vx`
+    SetDelay(u8),
+    /// Fx18 - `sound timer = Vx`
+    SetSound(u8),
+    /// Fx1E - `I = I + Vx`
+    AddI(u8),
+    /// Fx29 - Point I at the font sprite for the digit in `Vx`
+    Font(u8),
+    /// Fx30 - Point I at the large font sprite for the digit in `Vx` (SCHIP)
+    BigFont(u8),
+    /// Fx33 - Store the decimal digits of `Vx` at I, I+1 and I+2
+    Bcd(u8),
+    /// Fx3A - Set the audio pitch to `Vx` (XO-CHIP)
+    Pitch(u8),
+    /// Fx55 - Store `V0` to `Vx` at I
+    StoreRegs(u8),
+    /// Fx65 - Load `V0` to `Vx` from I
+    LoadRegs(u8),
+    /// Fx75 - Save `V0` to `Vx` in the RPL user flags (SCHIP)
+    SaveFlags(u8),
+    /// Fx85 - Load `V0` to `Vx` from the RPL user flags (SCHIP)
+    LoadFlags(u8),
+    /// Not an instruction of any variant
+    Invalid(u16)
+}
+
+impl Instruction {
+    /// Decode an opcode into the instruction it is in the largest
+    /// instruction set, XO-CHIP
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - two byte opcode to decode
+    pub fn decode(opcode: u16) -> Self {
+        let x = ((opcode >> 8) & 0xf) as u8;
+        let y = ((opcode >> 4) & 0xf) as u8;
+        let n = (opcode & 0xf) as u8;
+        let kk = (opcode & 0xff) as u8;
+        let nnn = opcode & 0xfff;
+
+        match opcode >> 12 {
+            0x0 => match opcode {
+                0x00E0 => Instruction::Clear,
+                0x00EE => Instruction::Return,
+                0x00FD => Instruction::Exit,
+                0x00FB => Instruction::ScrollRight,
+                0x00FC => Instruction::ScrollLeft,
+                0x00FE => Instruction::LowRes,
+                0x00FF => Instruction::HighRes,
+                0x00C0..=0x00CF => Instruction::ScrollDown(n),
+                0x00D0..=0x00DF => Instruction::ScrollUp(n),
+                _ => Instruction::Sys(nnn)
+            },
+            0x1 => Instruction::Jump(nnn),
+            0x2 => Instruction::Call(nnn),
+            0x3 => Instruction::SkipEqByte(x, kk),
+            0x4 => Instruction::SkipNeByte(x, kk),
+            0x5 => match n {
+                0x0 => Instruction::SkipEqReg(x, y),
+                0x2 => Instruction::StoreRange(x, y),
+                0x3 => Instruction::LoadRange(x, y),
+                _ => Instruction::Invalid(opcode)
+            },
+            0x6 => Instruction::LoadByte(x, kk),
+            0x7 => Instruction::AddByte(x, kk),
+            0x8 => match n {
+                0x0 => Instruction::LoadReg(x, y),
+                0x1 => Instruction::Or(x, y),
+                0x2 => Instruction::And(x, y),
+                0x3 => Instruction::Xor(x, y),
+                0x4 => Instruction::AddReg(x, y),
+                0x5 => Instruction::Sub(x, y),
+                0x6 => Instruction::ShiftRight(x, y),
+                0x7 => Instruction::SubN(x, y),
+                0xE => Instruction::ShiftLeft(x, y),
+                _ => Instruction::Invalid(opcode)
+            },
+            0x9 if n == 0 => Instruction::SkipNeReg(x, y),
+            0xA => Instruction::LoadI(nnn),
+            0xB => Instruction::JumpOffset(nnn),
+            0xC => Instruction::Random(x, kk),
+            0xD => Instruction::Draw(x, y, n),
+            0xE => match kk {
+                0x9E => Instruction::SkipKey(x),
+                0xA1 => Instruction::SkipNotKey(x),
+                _ => Instruction::Invalid(opcode)
+            },
+            0xF => match kk {
+                0x00 if x == 0 => Instruction::LoadILong,
+                0x01 => Instruction::Plane(x),
+                0x02 if x == 0 => Instruction::Audio,
+                0x07 => Instruction::ReadDelay(x),
+                0x0A => Instruction::WaitKey(x),
+                0x15 => Instruction::SetDelay(x),
+                0x18 => Instruction::SetSound(x),
+                0x1E => Instruction::AddI(x),
+                0x29 => Instruction::Font(x),
+                0x30 => Instruction::BigFont(x),
+                0x33 => Instruction::Bcd(x),
+                0x3A => Instruction::Pitch(x),
+                0x55 => Instruction::StoreRegs(x),
+                0x65 => Instruction::LoadRegs(x),
+                0x75 => Instruction::SaveFlags(x),
+                0x85 => Instruction::LoadFlags(x),
+                _ => Instruction::Invalid(opcode)
+            },
+            _ => Instruction::Invalid(opcode)
+        }
+    }
+
+    /// Decode an opcode into the instruction a variant runs it as
+    ///
+    /// An instruction the variant lacks runs as the CHIP-8 opcode with
+    /// its encoding: the display opcodes are machine code calls,
+    /// `5xy2`/`5xy3` compare `Vx` and `Vy`, and the rest are invalid.
+    /// `F000` runs only with extended memory, which the interpreter
+    /// checks.
+    /// Like the COSMAC VIP the low nibble of other `5xyn` and `9xyn`
+    /// opcodes is ignored, though they do not disassemble.
+    ///
+    /// # Arguments
+    ///
+    /// * `opcode` - two byte opcode to decode
+    /// * `variant` - instruction set being run
+    pub fn decode_for(opcode: u16, variant: Variant) -> Self {
+        let instruction = Instruction::decode(opcode);
+        let supported = match instruction {
+            Instruction::ScrollDown(_) | Instruction::ScrollRight | Instruction::ScrollLeft
+            | Instruction::LowRes | Instruction::HighRes | Instruction::Exit | Instruction::BigFont(_)
+            | Instruction::SaveFlags(_) | Instruction::LoadFlags(_) => variant.has_schip(),
+            Instruction::ScrollUp(_) | Instruction::StoreRange(_, _) | Instruction::LoadRange(_, _)
+            | Instruction::Plane(_) | Instruction::Audio | Instruction::Pitch(_) => variant.has_xochip(),
+            _ => true
+        };
+        match (supported, instruction) {
+            (_, Instruction::Invalid(_)) => match opcode >> 12 {
+                0x5 => Instruction::SkipEqReg((opcode >> 8) as u8 & 0xf, (opcode >> 4) as u8 & 0xf),
+                0x9 => Instruction::SkipNeReg((opcode >> 8) as u8 & 0xf, (opcode >> 4) as u8 & 0xf),
+                _ => instruction
+            },
+            (true, _) => instruction,
+            (false, Instruction::StoreRange(x, y)) | (false, Instruction::LoadRange(x, y)) => Instruction::SkipEqReg(x, y),
+            (false, _) if opcode >> 12 == 0x0 => Instruction::Sys(opcode & 0xfff),
+            (false, _) => Instruction::Invalid(opcode)
+        }
+    }
+
+    /// Get the instruction set that added the instruction, Chip8 for
+    /// the original ones and for invalid opcodes
+    pub fn get_variant(&self) -> Variant {
+        match self {
+            Instruction::ScrollDown(_) | Instruction::ScrollRight | Instruction::ScrollLeft
+            | Instruction::LowRes | Instruction::HighRes | Instruction::Exit | Instruction::BigFont(_)
+            | Instruction::SaveFlags(_) | Instruction::LoadFlags(_) => Variant::Schip,
+            Instruction::ScrollUp(_) | Instruction::StoreRange(_, _) | Instruction::LoadRange(_, _)
+            | Instruction::LoadILong | Instruction::Plane(_) | Instruction::Audio
+            | Instruction::Pitch(_) => Variant::XoChip,
+            _ => Variant::Chip8
+        }
+    }
+}
+
+/// Written with the mnemonics of Cowgod's Chip-8 technical reference,
+/// e.g. `LD V3, 0x05` or `DRW V0, V1, 5`, and invalid opcodes as
+/// `DW 0xNNNN`
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Sys(nnn) => write!(f, "SYS 0x{:03X}", nnn),
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::ScrollDown(n) => write!(f, "SCD {}", n),
+            Instruction::ScrollUp(n) => write!(f, "SCU {}", n),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::Jump(nnn) => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipEqByte(x, kk) => write!(f, "SE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipNeByte(x, kk) => write!(f, "SNE V{:X}, 0x{:02X}", x, kk),
+            Instruction::SkipEqReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::StoreRange(x, y) => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange(x, y) => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LoadByte(x, kk) => write!(f, "LD V{:X}, 0x{:02X}", x, kk),
+            Instruction::AddByte(x, kk) => write!(f, "ADD V{:X}, 0x{:02X}", x, kk),
+            Instruction::LoadReg(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNeReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadI(nnn) => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpOffset(nnn) => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Random(x, kk) => write!(f, "RND V{:X}, 0x{:02X}", x, kk),
+            Instruction::Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKey(x) => write!(f, "SKP V{:X}", x),
+            Instruction::SkipNotKey(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadILong => write!(f, "LD I, long"),
+            Instruction::Plane(n) => write!(f, "PLANE {}", n),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::ReadDelay(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::Font(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::BigFont(x) => write!(f, "LD HF, V{:X}", x),
+            Instruction::Bcd(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Pitch(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::StoreRegs(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::SaveFlags(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags(x) => write!(f, "LD V{:X}, R", x),
+            Instruction::Invalid(opcode) => write!(f, "DW 0x{:04X}", opcode)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_VARIANTS: [Variant; 3] = [Variant::Chip8, Variant::Schip, Variant::XoChip];
+
+    /// True if a variant runs instructions added by another
+    fn includes(variant: Variant, added_by: Variant) -> bool {
+        match added_by {
+            Variant::Chip8 => true,
+            Variant::Schip => variant.has_schip(),
+            Variant::XoChip => variant.has_xochip()
+        }
+    }
+
+    #[test]
+    fn extension_opcodes_follow_the_variant() {
+        let cases = [
+            (0x00FD, Instruction::Sys(0x0FD), Instruction::Exit, Instruction::Exit),
+            (0x00FF, Instruction::Sys(0x0FF), Instruction::HighRes, Instruction::HighRes),
+            (0x00C4, Instruction::Sys(0x0C4), Instruction::ScrollDown(4), Instruction::ScrollDown(4)),
+            (0x00D4, Instruction::Sys(0x0D4), Instruction::Sys(0x0D4), Instruction::ScrollUp(4)),
+            (0xF330, Instruction::Invalid(0xF330), Instruction::BigFont(3), Instruction::BigFont(3)),
+            (0xF375, Instruction::Invalid(0xF375), Instruction::SaveFlags(3), Instruction::SaveFlags(3)),
+            (0xF002, Instruction::Invalid(0xF002), Instruction::Invalid(0xF002), Instruction::Audio),
+            (0xF33A, Instruction::Invalid(0xF33A), Instruction::Invalid(0xF33A), Instruction::Pitch(3)),
+            (0xF201, Instruction::Invalid(0xF201), Instruction::Invalid(0xF201), Instruction::Plane(2)),
+            (0x5122, Instruction::SkipEqReg(1, 2), Instruction::SkipEqReg(1, 2), Instruction::StoreRange(1, 2)),
+            (0x5123, Instruction::SkipEqReg(1, 2), Instruction::SkipEqReg(1, 2), Instruction::LoadRange(1, 2))
+        ];
+        for (opcode, chip8, schip, xochip) in cases.iter() {
+            assert_eq!(Instruction::decode_for(*opcode, Variant::Chip8), *chip8, "{:04X} on chip8", opcode);
+            assert_eq!(Instruction::decode_for(*opcode, Variant::Schip), *schip, "{:04X} on schip", opcode);
+            assert_eq!(Instruction::decode_for(*opcode, Variant::XoChip), *xochip, "{:04X} on xochip", opcode);
+        }
+    }
+
+    #[test]
+    fn vip_ignores_the_low_nibble_of_register_skips() {
+        assert_eq!(Instruction::decode_for(0x5127, Variant::Chip8), Instruction::SkipEqReg(1, 2));
+        assert_eq!(Instruction::decode_for(0x912F, Variant::XoChip), Instruction::SkipNeReg(1, 2));
+        assert_eq!(Instruction::decode(0x5127), Instruction::Invalid(0x5127));
+    }
+
+    #[test]
+    fn no_variant_runs_instructions_it_lacks() {
+        for variant in ALL_VARIANTS.iter() {
+            for opcode in 0..=0xFFFF {
+                let instruction = Instruction::decode_for(opcode, *variant);
+                // Long addressing is checked by the interpreter against
+                // the memory size instead
+                if instruction != Instruction::LoadILong {
+                    assert!(includes(*variant, instruction.get_variant()), "{:04X} on {:?} is {:?}", opcode, variant, instruction);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn xochip_decodes_like_decode() {
+        for opcode in 0..=0xFFFF {
+            match Instruction::decode(opcode) {
+                Instruction::Invalid(_) => {},
+                instruction => assert_eq!(Instruction::decode_for(opcode, Variant::XoChip), instruction, "{:04X}", opcode)
+            }
+        }
+    }
+}
@@ -1,64 +1,121 @@
 
-// Standard Library Modules //
-use std::time::Duration;
-use std::thread;
-
 /// Struct representing a keyboard that uses hex values (0-9, A-F)
-/// this is represented by a boolean vector, true for pressed
+/// this is represented by a boolean array, true for pressed
+#[derive(Debug, Clone)]
 pub struct ChipKeyboard {
-    keys: Vec<bool>
+    keys: [bool; 16],
+    /// Register a FX0A opcode is waiting to store a key in
+    waiting: Option<u8>,
+    /// Key pressed during a FX0A wait, stored once it is released
+    wait_press: Option<u8>
 }
 
 impl ChipKeyboard {
     /// Initialize the Chip8 keyboard
     pub fn init() -> Self {
         // create the vector of keys
-        let keys = vec![false; 15];
+        let keys = [false; 16];
         ChipKeyboard {
-            keys
+            keys,
+            waiting: None,
+            wait_press: None
         }
     }
 
     /// Returns true if any key is currently pressed (true)
     fn any_pressed(&self) -> bool {
-        for i in 0..15 {
-            if self.keys[i] == true {
+        for i in 0..16 {
+            if self.keys[i] {
                 return true;
             }
         }
-        return false;
+        false
     }
 
     /// Checks which key is pressed, returning its index
     fn which_pressed(&self) -> u8 {
-        for i in 0..15 {
-            if self.keys[i] == true {
+        for i in 0..16 {
+            if self.keys[i] {
                 return i as u8;
             }
         }
-        return 16;
+        16
     }
 
-    /// Set a key to pressed (true) or not pressed (false)
+    /// Set a key to pressed (true) or not pressed (false), indexes
+    /// past 0xF are not keys and are ignored
     /// 
     /// # Arguments
     /// 
     /// * `index` - index of the key (0-15)
     /// * `value` - true or false
     pub fn set_key(&mut self, index: u8, value: bool) {
-        self.keys[index as usize] = value;
+        if let Some(key) = self.keys.get_mut(index as usize) {
+            *key = value;
+        }
     }
 
-    /// Get the state of a certain key (0-15)
+    /// Get the state of a certain key (0-15), false past 0xF
     pub fn get_key(&self, index: u8) -> bool{
-        self.keys[index as usize]
+        self.keys.get(index as usize).copied().unwrap_or(false)
+    }
+
+    /// Return the pressed key with the lowest index, if any
+    pub fn poll_key(&self) -> Option<u8> {
+        match self.any_pressed() {
+            true => Some(self.which_pressed()),
+            false => None
+        }
+    }
+
+    /// Set which register a FX0A opcode is waiting on, None once
+    /// it has received a key
+    /// 
+    /// # Arguments
+    /// 
+    /// * `reg` - index of the register waiting for a key
+    pub fn set_waiting(&mut self, reg: Option<u8>) {
+        self.waiting = reg;
+        if reg.is_none() {
+            self.wait_press = None;
+        }
     }
 
-    /// Wait for a keypress to happen and return which key
-    pub fn wait_key(&self) -> u8 {
-        while !self.any_pressed() {
-            thread::sleep(Duration::from_millis(100));
+    /// Advance a FX0A wait, returning the key once it has been pressed
+    /// and released again like the COSMAC VIP, so a held key is not
+    /// read by several waits in a row
+    pub fn wait_release(&mut self) -> Option<u8> {
+        match self.wait_press {
+            Some(key) if !self.get_key(key) => {
+                self.wait_press = None;
+                Some(key)
+            },
+            Some(_) => None,
+            None => {
+                self.wait_press = self.poll_key();
+                None
+            }
         }
-        return self.which_pressed();
     }
-}
\ No newline at end of file
+
+    /// Get the register a FX0A opcode is waiting to store a key in,
+    /// None if the program is not waiting for a key
+    pub fn get_waiting(&self) -> Option<u8> {
+        self.waiting
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_past_f_are_ignored() {
+        let mut keyboard = ChipKeyboard::init();
+        keyboard.set_key(0x10, true);
+        keyboard.set_key(0xFF, true);
+        assert!(!keyboard.get_key(0x10));
+        assert_eq!(keyboard.poll_key(), None);
+        keyboard.set_key(0xF, true);
+        assert_eq!(keyboard.poll_key(), Some(0xF));
+    }
+}
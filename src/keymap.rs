@@ -0,0 +1,184 @@
+// Standard Library Modules //
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::config::config_dir;
+
+/// Physical keys of the classic layout for keypad keys 0-F, the left
+/// side of a QWERTY keyboard standing in for the COSMAC VIP keypad
+///
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  ->  q w e r
+/// 7 8 9 E      a s d f
+/// A 0 B F      z x c v
+/// ```
+pub const DEFAULT_KEYS: [&str; 16] = [
+    "x", "1", "2", "3", "q", "w", "e", "a", "s", "d", "z", "c", "4", "r", "f", "v"
+];
+
+/// A problem in a keypad config file
+#[derive(Debug, Clone)]
+pub struct KeyMapError {
+    /// Line number starting at 1
+    line: usize,
+    /// What is wrong with the line
+    message: String
+}
+
+impl fmt::Display for KeyMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for KeyMapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Table of the physical keys pressing each chip8 keypad key, shared
+/// by every frontend
+///
+/// Keys are named like `hotkeys::KeyCombo` names them, lowercase
+/// letters and digits, `space`, `enter` and so on, plus `up`, `down`,
+/// `left` and `right` for the arrow keys. The table starts with the
+/// classic layout, `DEFAULT_KEYS`, and can be changed from a config
+/// file with one `keypad key = key, key` line per keypad key, for
+/// example `5 = w, up`. Keypad keys not named keep their default keys
+/// and `#` starts a comment.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    /// Bound physical keys and the keypad key they press
+    bindings: Vec<(String, u8)>
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut map = KeyMap { bindings: Vec::new() };
+        for (chip_key, key) in DEFAULT_KEYS.iter().enumerate() {
+            map.bind(key, chip_key as u8);
+        }
+        map
+    }
+}
+
+impl KeyMap {
+    /// Initialize a table with the classic layout
+    pub fn init() -> Self {
+        KeyMap::default()
+    }
+
+    /// Default location of the keypad config, `keypad` in the
+    /// configuration directory, None if it can not be found
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("keypad"))
+    }
+
+    /// Load the classic layout changed by a config file, a missing file
+    /// gives the classic layout
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - config file to load
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut map = KeyMap::default();
+        match fs::read_to_string(path) {
+            Ok(text) => map.apply(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e)
+        }
+        Ok(map)
+    }
+
+    /// Apply the bindings in a config file's text, every keypad key
+    /// named loses its current keys first
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of a config file
+    pub fn apply(&mut self, text: &str) -> Result<(), KeyMapError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| KeyMapError { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, keys) = match line.split_once('=') {
+                Some((name, keys)) => (name.trim(), keys.trim()),
+                None => return Err(error(format!("expected 'keypad key = key', found '{}'", line)))
+            };
+            let chip_key = match u8::from_str_radix(name, 16) {
+                Ok(chip_key) if name.len() == 1 => chip_key,
+                _ => return Err(error(format!("unknown keypad key '{}', expected 0-F", name)))
+            };
+            self.unbind(chip_key);
+            for key in keys.split(',').map(|key| key.trim()).filter(|key| !key.is_empty()) {
+                if key.contains(char::is_whitespace) {
+                    return Err(error(format!("invalid key '{}'", key)));
+                }
+                self.bind(key, chip_key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind a physical key to a keypad key, a physical key can only
+    /// press one keypad key so any other binding of it is removed
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the physical key, case is ignored
+    /// * `chip_key` - keypad key it presses, 0-F
+    pub fn bind(&mut self, key: &str, chip_key: u8) {
+        let key = key.to_ascii_lowercase();
+        self.bindings.retain(|(bound, _)| *bound != key);
+        self.bindings.push((key, chip_key & 0xf));
+    }
+
+    /// Remove every physical key bound to a keypad key
+    ///
+    /// # Arguments
+    ///
+    /// * `chip_key` - keypad key to unbind
+    pub fn unbind(&mut self, chip_key: u8) {
+        self.bindings.retain(|(_, bound)| *bound != chip_key);
+    }
+
+    /// Find the keypad key a physical key presses
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - name of the physical key, lowercase
+    pub fn lookup(&self, key: &str) -> Option<u8> {
+        self.bindings.iter()
+            .find(|(bound, _)| bound == key)
+            .map(|(_, chip_key)| *chip_key)
+    }
+
+    /// Get the physical keys bound to a keypad key
+    ///
+    /// # Arguments
+    ///
+    /// * `chip_key` - keypad key to look up
+    pub fn keys_for(&self, chip_key: u8) -> Vec<&str> {
+        self.bindings.iter()
+            .filter(|(_, bound)| *bound == chip_key)
+            .map(|(key, _)| key.as_str())
+            .collect()
+    }
+
+    /// Format the table as a config file that reproduces it
+    pub fn to_config(&self) -> String {
+        let mut out = String::new();
+        for chip_key in 0..16 {
+            out.push_str(&format!("{:X} = {}\n", chip_key, self.keys_for(chip_key).join(", ")));
+        }
+        out
+    }
+}
@@ -0,0 +1,103 @@
+// Local Modules Use //
+use super::hotkeys::{Hotkey, HotkeyMap, KeyCombo};
+
+/// What a frontend should do with a key press in kiosk mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KioskKey {
+    /// The admin combination was pressed, leave kiosk mode and quit
+    Exit,
+    /// A hotkey that is allowed in kiosk mode
+    Hotkey(Hotkey),
+    /// Not a hotkey, pass it on to the keypad mapping
+    Gameplay,
+    /// A hotkey that kiosk mode does not allow, ignore the press
+    Blocked
+}
+
+/// Locked down mode for arcade cabinets and exhibitions
+///
+/// Frontends pass every key press through `filter`, `TerminalKeys`
+/// does so after `set_kiosk`. Only keypad keys reach the game, every
+/// hotkey that could quit, open the menu or touch files is dropped,
+/// and a frontend with a window refuses to close it. The admin
+/// combination is the only way out.
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    /// Key combination that exits kiosk mode
+    admin: KeyCombo,
+    /// Hotkeys still allowed
+    allowed: Vec<Hotkey>
+}
+
+impl Default for Kiosk {
+    /// Kiosk mode exited with ctrl+shift+alt+q, awkward enough that it
+    /// will not be hit by accident on a cabinet's control panel
+    fn default() -> Self {
+        Kiosk::init(KeyCombo::init("q", true, true, true))
+    }
+}
+
+impl Kiosk {
+    /// Kiosk mode for the terminal, exited with ctrl+alt+q since a
+    /// terminal does not report shift held with ctrl
+    pub fn terminal() -> Self {
+        Kiosk::init(KeyCombo::init("q", true, false, true))
+    }
+
+    /// Initialize kiosk mode with an admin combination and no hotkeys
+    /// allowed
+    ///
+    /// # Arguments
+    ///
+    /// * `admin` - key combination that exits kiosk mode
+    pub fn init(admin: KeyCombo) -> Self {
+        Kiosk {
+            admin,
+            allowed: Vec::new()
+        }
+    }
+
+    /// Get the key combination that exits kiosk mode
+    pub fn get_admin(&self) -> &KeyCombo {
+        &self.admin
+    }
+
+    /// Allow a hotkey in kiosk mode, such as reset on a cabinet with a
+    /// dedicated reset button. The menu, screenshot and state hotkeys
+    /// can not be allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkey` - hotkey to allow
+    pub fn allow(&mut self, hotkey: Hotkey) {
+        let locked = matches!(
+            hotkey,
+            Hotkey::Menu | Hotkey::Screenshot | Hotkey::TextScreenshot | Hotkey::SaveState | Hotkey::LoadState | Hotkey::Fullscreen
+        );
+        if !locked && !self.allowed.contains(&hotkey) {
+            self.allowed.push(hotkey);
+        }
+    }
+
+    /// Decide what a key press does in kiosk mode
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkeys` - bindings the frontend uses outside kiosk mode
+    /// * `combo` - key that was pressed
+    pub fn filter(&self, hotkeys: &HotkeyMap, combo: &KeyCombo) -> KioskKey {
+        if *combo == self.admin {
+            return KioskKey::Exit;
+        }
+        match hotkeys.lookup(combo) {
+            Some(hotkey) if self.allowed.contains(&hotkey) => KioskKey::Hotkey(hotkey),
+            Some(_) => KioskKey::Blocked,
+            None => KioskKey::Gameplay
+        }
+    }
+
+    /// Returns false, a window close request is ignored in kiosk mode
+    pub fn allow_close(&self) -> bool {
+        false
+    }
+}
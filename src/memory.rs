@@ -1,9 +1,124 @@
 
 // Standard Library Modules //
+use std::error;
+use std::fmt;
 use std::io;
 use std::fs::File;
 use std::io::prelude::*;
 
+// Local Modules Use //
+use super::fonts::Fontset;
+use super::heatmap::MemoryHeat;
+use super::watchpoint::{WatchTarget, Watchpoints};
+
+/// Size of the classic chip8 address space
+pub const RAM_SIZE: usize = 0x1000;
+
+/// Size of the XO-CHIP address space, reached with 16 bit `I` values
+pub const EXTENDED_RAM_SIZE: usize = 0x10000;
+
+/// Address the built in font is loaded at
+pub const FONT_START: usize = 0x050;
+
+/// Address the SUPER-CHIP large font is loaded at, right after the
+/// small font
+pub const BIG_FONT_START: usize = FONT_START + 80;
+
+/// Built in font, 5 bytes per hex digit 0-F, each byte is a 4 pixel
+/// wide row stored in the upper nibble. This is the SUPER-CHIP font,
+/// other styles are in `fonts`.
+pub const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80  // F
+];
+
+/// How writes below the program start are handled, real interpreters
+/// lived in that area so a program writing there is usually corrupting
+/// the font or a bug
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteGuard {
+    /// Allow the write without recording it
+    Off,
+    /// Allow the write and record a violation
+    Warn,
+    /// Drop the write and record a violation
+    Block
+}
+
+/// A write into the protected area below the program start
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteViolation {
+    /// Address written to
+    pub addr: u16,
+    /// Value the program tried to write
+    pub value: u8,
+    /// True if the write was dropped
+    pub blocked: bool
+}
+
+/// Reasons a ROM can not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    /// The ROM does not fit between the load address and the end of
+    /// memory
+    RomTooLarge {
+        /// Size of the ROM in bytes
+        size: usize,
+        /// Largest ROM that fits in bytes
+        max: usize
+    }
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::RomTooLarge { size, max } => write!(
+                f, "ROM is {} bytes but only {} bytes fit in memory ({} bytes too large)",
+                size, max, size - max
+            )
+        }
+    }
+}
+
+impl error::Error for RomError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Signs a ROM may be broken that do not stop it from loading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomWarning {
+    /// The ROM has no bytes
+    Empty,
+    /// The ROM has an odd number of bytes, opcodes are two bytes long
+    /// so the file may be truncated
+    OddSize(usize)
+}
+
+impl fmt::Display for RomWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomWarning::Empty => write!(f, "ROM is empty"),
+            RomWarning::OddSize(size) => write!(f, "ROM has an odd size ({} bytes), it may be truncated", size)
+        }
+    }
+}
+
 /// A representation of chip8 ram
 pub struct ChipMemory {
     /// a vector representing the ram
@@ -11,87 +126,224 @@ pub struct ChipMemory {
     /// true if a rom has been loaded
     loaded: bool, 
     /// program start location
-    start: usize
+    start: usize,
+    /// size of the last rom loaded, None before one is loaded
+    rom_size: Option<usize>,
+    /// access counters, only kept when enabled
+    heat: Option<MemoryHeat>,
+    /// handling of writes below the program start
+    guard: WriteGuard,
+    /// writes caught by the guard and not yet taken
+    violations: Vec<WriteViolation>,
+    /// watchpoints on bytes of memory
+    watchpoints: Watchpoints
 }
 
 impl ChipMemory {
     /// Init a chip8 memory structure 
     pub fn init() -> Self {
         // let ram: Vec<u8> = vec![0; 4096];
-        let ram = ChipMemory::load_symbols(vec![0; 4096]);
+        let ram = ChipMemory::load_symbols(vec![0; RAM_SIZE]);
         ChipMemory {
             ram, // Size of chip8 ram
             loaded: false,
-            start: 512
+            start: 512,
+            rom_size: None,
+            heat: None,
+            guard: WriteGuard::Off,
+            violations: Vec::new(),
+            watchpoints: Watchpoints::init()
         }
     }
 
     fn load_symbols(mut ram: Vec<u8>) -> Vec<u8> {
-        let hex_chars = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80  // F
-        ];
-        for i in 0..hex_chars.len() {
-            ram[i + 0x050] = hex_chars[i];
-        }
+        let big = Fontset::default().big();
+        ram[FONT_START..FONT_START + FONT.len()].copy_from_slice(&FONT);
+        ram[BIG_FONT_START..BIG_FONT_START + big.len()].copy_from_slice(big);
         ram
     }
 
+    /// Get the size of the address space in bytes
+    pub fn get_size(&self) -> usize {
+        self.ram.len()
+    }
+
+    /// Returns true if the full 64 KB XO-CHIP address space is enabled
+    pub fn is_extended(&self) -> bool {
+        self.ram.len() == EXTENDED_RAM_SIZE
+    }
+
+    /// Switch between the 4 KB chip8 address space and the 64 KB
+    /// XO-CHIP one, memory above 4 KB is lost when switching back
+    ///
+    /// # Arguments
+    ///
+    /// * `extended` - true for 64 KB of memory
+    pub fn set_extended(&mut self, extended: bool) {
+        let size = match extended {
+            true => EXTENDED_RAM_SIZE,
+            false => RAM_SIZE
+        };
+        self.ram.resize(size, 0);
+        if self.heat.is_some() {
+            self.enable_heat();
+        }
+    }
+
+    /// Index into ram for an address, addresses wrap around the end of
+    /// the address space
+    fn index(&self, loc: u16) -> usize {
+        loc as usize % self.ram.len()
+    }
+
+    /// Replace the font in memory with another style, call this before
+    /// a program runs since the font area is ordinary memory
+    /// 
+    /// # Arguments
+    /// 
+    /// * `fontset` - font style to load
+    pub fn set_fontset(&mut self, fontset: Fontset) {
+        let (small, big) = (fontset.small(), fontset.big());
+        self.ram[FONT_START..FONT_START + small.len()].copy_from_slice(small);
+        self.ram[BIG_FONT_START..BIG_FONT_START + big.len()].copy_from_slice(big);
+    }
+
     /// Returns true if a ROM has been loaded, false otherwise
     pub fn has_loaded(&self) -> bool {
         self.loaded
     }
 
+    /// Get the addresses the last ROM was loaded to, start included and
+    /// end excluded, None before a ROM is loaded
+    pub fn get_rom_range(&self) -> Option<(usize, usize)> {
+        self.rom_size.map(|size| (self.start, self.start + size))
+    }
+
     /// Return a two byte opcode
     /// 
     /// # Arguments
     /// 
     /// * `index` - index where opcode starts
     pub fn get_opcode(&self, index: u16) -> u16 {
-        ((self.get_byte(index) as u16) << 8) | self.get_byte(index + 1) as u16
+        ((self.get_byte(index) as u16) << 8) | self.get_byte(index.wrapping_add(1)) as u16
     }
 
-    /// Load a binary into 
+    /// Fetch a two byte opcode for execution, this is the same as
+    /// `get_opcode` but counts as an execute instead of a read
     /// 
     /// # Arguments
     /// 
-    /// * `rom` - a Vec<u8> holding rom contents
-    pub fn load_bytes(&mut self, rom: Vec<u8>) {
-        let len = rom.len();
-        for i in 0..len {
-            self.ram[i + self.start] = rom[i]; 
-            
-            // { // Flip bit order
-            //     let mut switched: u8 = 0;
-            //     let mut mask: u8 = 0x80;
-            //     let mut mask_inv: u8 = 0x01;
-            //     for _ in 0..8 {
-            //         switched += {
-            //             match mask & rom[i] == mask {
-            //                 true => mask_inv,
-            //                 false => 0
-            //             }
-            //         };
-            //         mask >>= 1;
-            //         mask_inv <<= 1;
-            //     }
-            //     switched
-            // }
+    /// * `index` - index where opcode starts
+    pub fn fetch_opcode(&self, index: u16) -> u16 {
+        let (high, low) = (self.index(index), self.index(index.wrapping_add(1)));
+        if let Some(heat) = &self.heat {
+            heat.record_execute(high);
+            heat.record_execute(low);
+        }
+        ((self.ram[high] as u16) << 8) | self.ram[low] as u16
+    }
+
+    /// Start counting reads, writes and executes of each address,
+    /// clearing any previous counts
+    pub fn enable_heat(&mut self) {
+        self.heat = Some(MemoryHeat::init(self.ram.len()));
+    }
+
+    /// Stop counting memory accesses
+    pub fn disable_heat(&mut self) {
+        self.heat = None;
+    }
+
+    /// Get the access counters if they are enabled
+    pub fn get_heat(&self) -> Option<&MemoryHeat> {
+        self.heat.as_ref()
+    }
+
+    /// Get how writes below the program start are handled
+    pub fn get_guard(&self) -> WriteGuard {
+        self.guard
+    }
+
+    /// Change how writes below the program start are handled
+    ///
+    /// # Arguments
+    ///
+    /// * `guard` - new handling of protected writes
+    pub fn set_guard(&mut self, guard: WriteGuard) {
+        self.guard = guard;
+    }
+
+    /// Take the writes caught by the guard since the last call, oldest
+    /// first, so a debugger or frontend can report them
+    pub fn take_violations(&mut self) -> Vec<WriteViolation> {
+        std::mem::take(&mut self.violations)
+    }
+
+    /// Get the watchpoints on memory
+    pub fn get_watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    /// Get the watchpoints on memory to change them, every write made
+    /// with `set_byte` is checked against them
+    pub fn get_watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.watchpoints
+    }
+
+    /// Check that a ROM fits in memory, returning any warnings about
+    /// it without loading it
+    /// 
+    /// # Arguments
+    /// 
+    /// * `rom` - rom contents
+    pub fn check_rom(&self, rom: &[u8]) -> Result<Vec<RomWarning>, RomError> {
+        let max = self.ram.len() - self.start;
+        if rom.len() > max {
+            return Err(RomError::RomTooLarge { size: rom.len(), max });
+        }
+        let mut warnings = Vec::new();
+        if rom.is_empty() {
+            warnings.push(RomWarning::Empty);
+        } else if rom.len() % 2 == 1 {
+            warnings.push(RomWarning::OddSize(rom.len()));
         }
+        Ok(warnings)
+    }
+
+    /// Load a binary into ram at the program start, returning any
+    /// warnings about it
+    /// 
+    /// # Arguments
+    /// 
+    /// * `rom` - a Vec<u8> holding rom contents
+    pub fn load_bytes(&mut self, rom: Vec<u8>) -> Result<Vec<RomWarning>, RomError> {
+        let warnings = self.check_rom(&rom)?;
+        self.ram[self.start..self.start + rom.len()].copy_from_slice(&rom);
+        self.rom_size = Some(rom.len());
+        self.loaded = true;
+        Ok(warnings)
+    }
+
+    /// Get the whole address space without counting it as reads, for
+    /// save states
+    pub fn get_ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Replace the whole address space, bypassing the write guard, for
+    /// restoring save states. The size picks between the chip8 and the
+    /// XO-CHIP address space and must be one of the two.
+    ///
+    /// # Arguments
+    ///
+    /// * `ram` - contents of the address space
+    /// * `rom_size` - size of the ROM it holds, None if none was loaded
+    pub fn restore_ram(&mut self, ram: Vec<u8>, rom_size: Option<usize>) {
+        self.set_extended(ram.len() == EXTENDED_RAM_SIZE);
+        let len = self.ram.len().min(ram.len());
+        self.ram[..len].copy_from_slice(&ram[..len]);
+        self.rom_size = rom_size;
+        self.loaded = rom_size.is_some();
     }
 
     /// Set a byte in ram to a passed value
@@ -101,7 +353,21 @@ impl ChipMemory {
     /// * `loc` - location to set
     /// * `val` - value to set with
     pub fn set_byte(&mut self, loc: u16, val: u8) {
-        self.ram[loc as usize] = val;
+        let index = self.index(loc);
+        if let Some(heat) = &self.heat {
+            heat.record_write(index);
+        }
+        if index < self.start && self.guard != WriteGuard::Off {
+            let blocked = self.guard == WriteGuard::Block;
+            self.violations.push(WriteViolation { addr: index as u16, value: val, blocked });
+            if blocked {
+                return;
+            }
+        }
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(WatchTarget::Memory(index as u16), self.ram[index] as u16, val as u16);
+        }
+        self.ram[index] = val;
     }
 
     /// Get a byte at `loc`
@@ -110,7 +376,22 @@ impl ChipMemory {
     /// 
     /// * `loc` - location of byte
     pub fn get_byte(&self, loc: u16) -> u8 {
-        self.ram[loc as usize]
+        let index = self.index(loc);
+        if let Some(heat) = &self.heat {
+            heat.record_read(index);
+        }
+        self.ram[index]
+    }
+
+    /// Get a range of bytes without counting them as reads, for
+    /// debuggers and monitors that should not disturb the heatmap
+    /// 
+    /// # Arguments
+    /// 
+    /// * `loc` - start location of bytes
+    /// * `nbytes` - how many bytes
+    pub fn peek_bytes(&self, loc: u16, nbytes: usize) -> Vec<u8> {
+        (0..nbytes).map(|i| self.ram[self.index(loc.wrapping_add(i as u16))]).collect()
     }
 
     /// Get a range of bytes
@@ -121,8 +402,8 @@ impl ChipMemory {
     /// * `nbytes` - how many bytes
     pub fn get_nbytes(&self, loc: u16, nbytes: u16) -> Vec<u8> {
         let mut out_bytes: Vec<u8> = vec![0; nbytes as usize];
-        for i in 0..nbytes as usize {
-            out_bytes[i] = self.get_byte(loc + i as u16);
+        for (i, byte) in out_bytes.iter_mut().enumerate() {
+            *byte = self.get_byte(loc.wrapping_add(i as u16));
         }
         out_bytes
     }
@@ -136,11 +417,11 @@ impl ChipMemory {
                 print!(" ");
             }
             if i % 32 == 0 {
-                println!("");
+                println!();
             }
             print!("{:02x}", self.ram[i]);
         }
-        println!("");
+        println!();
     }
 
     /// Load a file from disk and write its bytes into 
@@ -149,7 +430,7 @@ impl ChipMemory {
     /// # Arguments
     /// 
     /// * `rom_file` - the filename to open and read from
-    pub fn load_rom_file(&mut self, rom_file: &str) -> io::Result<()> {
+    pub fn load_rom_file(&mut self, rom_file: &str) -> io::Result<Vec<RomWarning>> {
         // Load bytes from file
         let mut file = File::open(rom_file)?;
 
@@ -161,8 +442,8 @@ impl ChipMemory {
         file.read_to_end(&mut rom)?;
 
         // Load bytes into chip8 ram
-        self.load_bytes(rom);
-        self.loaded = true;
-        Ok(())
+        let warnings = self.load_bytes(rom)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(warnings)
     }
 }
\ No newline at end of file
@@ -0,0 +1,202 @@
+// Standard Library Modules //
+use std::fmt;
+
+// Local Modules Use //
+use super::ChipSystem;
+
+/// Range of addresses to watch, `start` to `end` inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchRange {
+    /// First address
+    pub start: u16,
+    /// Last address
+    pub end: u16
+}
+
+impl WatchRange {
+    /// Parse a range written `0x300..0x310` (end excluded), `0x300..=0x30f`
+    /// (end included) or a single address `0x300`
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - range to parse
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let address = |text: &str| -> Result<u32, String> {
+            let text = text.trim();
+            let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                Some(hex) => u32::from_str_radix(hex, 16),
+                None => text.parse::<u32>()
+            };
+            match parsed {
+                Ok(addr) if addr <= 0xFFFF => Ok(addr),
+                _ => Err(format!("invalid address '{}'", text))
+            }
+        };
+        let (start, end) = match (text.split_once("..="), text.split_once("..")) {
+            (Some((start, end)), _) => (address(start)?, address(end)?),
+            (None, Some((start, end))) => match address(end)?.checked_sub(1) {
+                Some(end) => (address(start)?, end),
+                None => return Err(format!("range '{}' is empty", text))
+            },
+            (None, None) => (address(text)?, address(text)?)
+        };
+        match start <= end {
+            true => Ok(WatchRange { start: start as u16, end: end as u16 }),
+            false => Err(format!("range '{}' is empty", text))
+        }
+    }
+
+    /// Get the number of addresses in the range
+    pub fn get_size(&self) -> usize {
+        (self.end - self.start) as usize + 1
+    }
+}
+
+impl fmt::Display for WatchRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:03x}..=0x{:03x}", self.start, self.end)
+    }
+}
+
+/// A watched byte that changed during a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryChange {
+    /// Frame the change happened in
+    pub frame: u64,
+    /// Address that changed
+    pub addr: u16,
+    /// Value at the start of the frame
+    pub old: u8,
+    /// Value at the end of the frame
+    pub new: u8,
+    /// Address of the last opcode that wrote it, None when it was
+    /// changed from outside the program such as by the monitor
+    pub pc: Option<u16>
+}
+
+impl fmt::Display for MemoryChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "frame {}: 0x{:03x} {:02x} -> {:02x}", self.frame, self.addr, self.old, self.new)?;
+        match self.pc {
+            Some(pc) => write!(f, " (written at 0x{:03x})", pc),
+            None => write!(f, " (changed outside the program)")
+        }
+    }
+}
+
+/// Logs changes to ranges of memory once per frame, a lighter weight
+/// alternative to watchpoints for long sessions
+///
+/// Call `observe` before every opcode, from `run_frame_traced`, and
+/// `end_frame` after the frame. Only the watched bytes are compared, so
+/// small ranges cost little. A byte written several times in a frame
+/// is logged once with the value it had before the frame, the value it
+/// has after and the last opcode that wrote it.
+pub struct MemoryWatch {
+    /// Ranges watched
+    ranges: Vec<WatchRange>,
+    /// Watched bytes at the start of the frame, range after range
+    frame_start: Vec<u8>,
+    /// Watched bytes when last compared
+    current: Vec<u8>,
+    /// Last opcode that wrote each watched byte this frame
+    writers: Vec<Option<u16>>,
+    /// Address of the opcode run since the last comparison
+    last_pc: Option<u16>
+}
+
+impl MemoryWatch {
+    /// Start watching ranges of a system's memory
+    ///
+    /// # Arguments
+    ///
+    /// * `ranges` - ranges to watch
+    /// * `sys` - system to watch
+    pub fn init(ranges: Vec<WatchRange>, sys: &ChipSystem) -> Self {
+        let mut watch = MemoryWatch {
+            ranges,
+            frame_start: Vec::new(),
+            current: Vec::new(),
+            writers: Vec::new(),
+            last_pc: None
+        };
+        watch.resync(sys);
+        watch
+    }
+
+    /// Get the watched ranges
+    pub fn get_ranges(&self) -> &[WatchRange] {
+        &self.ranges
+    }
+
+    /// Take the current memory as the starting point without logging
+    /// anything, for after the system is reset
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system being watched
+    pub fn resync(&mut self, sys: &ChipSystem) {
+        self.current = self.read(sys);
+        self.frame_start = self.current.clone();
+        self.writers = vec![None; self.current.len()];
+        self.last_pc = None;
+    }
+
+    /// Compare the watched bytes before an opcode runs, changes since
+    /// the last call were made by the opcode before it
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system being watched
+    /// * `_opcode` - opcode about to run
+    pub fn observe(&mut self, sys: &ChipSystem, _opcode: u16) {
+        self.compare(sys);
+        self.last_pc = Some(sys.registers.get_pc());
+    }
+
+    /// Finish a frame, returning the watched bytes that differ from the
+    /// start of the frame
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system being watched
+    pub fn end_frame(&mut self, sys: &ChipSystem) -> Vec<MemoryChange> {
+        self.compare(sys);
+        self.last_pc = None;
+        let frame = sys.get_frame();
+        let mut changes = Vec::new();
+        let addrs = self.ranges.iter().flat_map(|range| range.start..=range.end);
+        for (index, addr) in addrs.enumerate() {
+            if self.current[index] != self.frame_start[index] {
+                changes.push(MemoryChange {
+                    frame,
+                    addr,
+                    old: self.frame_start[index],
+                    new: self.current[index],
+                    pc: self.writers[index]
+                });
+            }
+        }
+        self.frame_start = self.current.clone();
+        self.writers.iter_mut().for_each(|writer| *writer = None);
+        changes
+    }
+
+    /// Record which watched bytes changed since the last comparison
+    fn compare(&mut self, sys: &ChipSystem) {
+        let now = self.read(sys);
+        for (index, value) in now.iter().enumerate() {
+            if *value != self.current[index] {
+                self.writers[index] = self.last_pc;
+            }
+        }
+        self.current = now;
+    }
+
+    /// Read the watched bytes
+    fn read(&self, sys: &ChipSystem) -> Vec<u8> {
+        self.ranges.iter()
+            .flat_map(|range| sys.ram.peek_bytes(range.start, range.get_size()))
+            .collect()
+    }
+}
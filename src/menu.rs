@@ -0,0 +1,456 @@
+// Local Modules Use //
+use super::image::RgbImage;
+use super::palette::PRESETS;
+use super::quirks::{Quirks, QUIRK_NAMES};
+use super::recent::RecentRoms;
+
+const PANEL: [u8; 3] = [24, 24, 32];
+const PANEL_BORDER: [u8; 3] = [90, 90, 110];
+const TITLE: [u8; 3] = [230, 200, 40];
+const ITEM: [u8; 3] = [200, 200, 200];
+const ITEM_SELECTED: [u8; 3] = [255, 255, 255];
+const SELECTION: [u8; 3] = [60, 60, 90];
+
+/// Input used to drive the menu, frontends map their keys and buttons
+/// onto these (Escape should map to `Back`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back
+}
+
+/// Something the frontend has to do after a menu input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// Close the menu and continue the game
+    Resume,
+    /// Restart the loaded ROM
+    Reset,
+    /// Ask the player for a ROM to load
+    LoadRom,
+    /// Load the recent ROM at this position of the recent list
+    LoadRecent(usize),
+    /// Save the machine state
+    SaveState,
+    /// Restore the saved machine state
+    LoadState,
+    /// One of the settings was changed, re-read `MenuSettings`
+    SettingsChanged,
+    /// Exit the emulator
+    Quit
+}
+
+/// Settings that can be changed from the menu, owned by the frontend
+/// and read back after a `MenuAction::SettingsChanged`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuSettings {
+    /// Opcodes executed per frame
+    pub cycles: u32,
+    /// Index into `palette::PRESETS`
+    pub palette: usize,
+    /// Interpreter quirks
+    pub quirks: Quirks,
+    /// Pause and mute while the window is in the background, see
+    /// `focus::FocusPause`
+    pub pause_unfocused: bool
+}
+
+/// Page of the menu being shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Page {
+    Main,
+    Recent,
+    Settings
+}
+
+/// Position of the recent roms entry on the main page
+const RECENT_ITEM: usize = 3;
+
+/// Position of the settings entry on the main page
+const SETTINGS_ITEM: usize = 6;
+
+/// Entries of the main page, in order
+const MAIN_ITEMS: [&str; 8] = [
+    "resume",
+    "reset",
+    "load rom",
+    "recent roms",
+    "save state",
+    "load state",
+    "settings",
+    "quit"
+];
+
+/// Pause menu drawn over the game by graphical frontends
+///
+/// The menu only keeps track of what is selected, frontends feed it
+/// `MenuInput`s while it is open and act on the `MenuAction`s it
+/// returns. Settings are changed in place on a `MenuSettings` owned by
+/// the frontend, left and right change the selected setting.
+#[derive(Debug, Clone)]
+pub struct PauseMenu {
+    /// True while the menu is shown and the game is paused
+    open: bool,
+    /// Page being shown
+    page: Page,
+    /// Selected entry on the page
+    selected: usize,
+    /// Names of the recent ROMs, most recent first
+    recent: Vec<String>
+}
+
+impl PauseMenu {
+    /// Initialize a closed menu
+    pub fn init() -> Self {
+        PauseMenu {
+            open: false,
+            page: Page::Main,
+            selected: 0,
+            recent: Vec::new()
+        }
+    }
+
+    /// Returns true while the menu is shown, the game should not run
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Show the menu on its main page with Resume selected
+    pub fn open(&mut self) {
+        self.open = true;
+        self.page = Page::Main;
+        self.selected = 0;
+    }
+
+    /// Hide the menu
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Update the ROMs listed on the recent page
+    ///
+    /// # Arguments
+    ///
+    /// * `recent` - recently opened ROMs
+    pub fn set_recent(&mut self, recent: &RecentRoms) {
+        self.recent = recent.get_entries().iter().map(|entry| entry.name().to_string()).collect();
+    }
+
+    /// Go back to the main page with the entry that opened the current
+    /// page selected
+    fn back_to_main(&mut self) {
+        self.selected = match self.page {
+            Page::Recent => RECENT_ITEM,
+            _ => SETTINGS_ITEM
+        };
+        self.page = Page::Main;
+    }
+
+    /// Number of entries on the current page
+    fn len(&self) -> usize {
+        match self.page {
+            Page::Main => MAIN_ITEMS.len(),
+            // every recent ROM and back
+            Page::Recent => self.recent.len() + 1,
+            // speed, palette, every quirk, pause in background and back
+            Page::Settings => 2 + QUIRK_NAMES.len() + 2
+        }
+    }
+
+    /// Handle an input while the menu is open, returning what the
+    /// frontend should do. Resume and the state actions close the menu.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - input to handle
+    /// * `settings` - settings changed by the settings page
+    pub fn handle(&mut self, input: MenuInput, settings: &mut MenuSettings) -> Option<MenuAction> {
+        if !self.open {
+            return None;
+        }
+        let len = self.len();
+        match input {
+            MenuInput::Up => {
+                self.selected = (self.selected + len - 1) % len;
+                None
+            },
+            MenuInput::Down => {
+                self.selected = (self.selected + 1) % len;
+                None
+            },
+            MenuInput::Back => match self.page {
+                Page::Main => {
+                    self.close();
+                    Some(MenuAction::Resume)
+                },
+                Page::Recent | Page::Settings => {
+                    self.back_to_main();
+                    None
+                }
+            },
+            MenuInput::Left | MenuInput::Right | MenuInput::Select => match self.page {
+                Page::Main if input == MenuInput::Select => self.select_main(),
+                Page::Main => None,
+                Page::Recent if input == MenuInput::Select => self.select_recent(),
+                Page::Recent => None,
+                Page::Settings => self.change_setting(input, settings)
+            }
+        }
+    }
+
+    /// Activate the selected entry of the main page
+    fn select_main(&mut self) -> Option<MenuAction> {
+        let action = match self.selected {
+            0 => MenuAction::Resume,
+            1 => MenuAction::Reset,
+            2 => MenuAction::LoadRom,
+            RECENT_ITEM => {
+                self.page = Page::Recent;
+                self.selected = 0;
+                return None;
+            },
+            4 => MenuAction::SaveState,
+            5 => MenuAction::LoadState,
+            SETTINGS_ITEM => {
+                self.page = Page::Settings;
+                self.selected = 0;
+                return None;
+            },
+            _ => MenuAction::Quit
+        };
+        self.close();
+        Some(action)
+    }
+
+    /// Activate the selected entry of the recent page
+    fn select_recent(&mut self) -> Option<MenuAction> {
+        if self.selected >= self.recent.len() {
+            self.back_to_main();
+            return None;
+        }
+        self.close();
+        Some(MenuAction::LoadRecent(self.selected))
+    }
+
+    /// Change the selected entry of the settings page
+    fn change_setting(&mut self, input: MenuInput, settings: &mut MenuSettings) -> Option<MenuAction> {
+        let step: i64 = match input {
+            MenuInput::Left => -1,
+            _ => 1
+        };
+        match self.selected {
+            0 => {
+                settings.cycles = (settings.cycles as i64 + step).clamp(1, 1000) as u32;
+            },
+            1 => {
+                let count = PRESETS.len() as i64;
+                settings.palette = (settings.palette as i64 + step).rem_euclid(count) as usize;
+            },
+            index if index < 2 + QUIRK_NAMES.len() => {
+                let bits = settings.quirks.to_bits() ^ (1 << (index - 2));
+                settings.quirks = Quirks::from_bits(bits);
+            },
+            index if index == 2 + QUIRK_NAMES.len() => {
+                settings.pause_unfocused = !settings.pause_unfocused;
+            },
+            _ => {
+                if input == MenuInput::Select {
+                    self.back_to_main();
+                }
+                return None;
+            }
+        }
+        Some(MenuAction::SettingsChanged)
+    }
+
+    /// Labels of the entries on the current page
+    fn labels(&self, settings: &MenuSettings) -> Vec<String> {
+        match self.page {
+            Page::Main => MAIN_ITEMS.iter().map(|item| item.to_string()).collect(),
+            Page::Recent => {
+                let mut labels: Vec<String> = self.recent.iter()
+                    .enumerate()
+                    .map(|(index, name)| format!("{}. {}", index + 1, name))
+                    .collect();
+                labels.push(String::from("back"));
+                labels
+            },
+            Page::Settings => {
+                let mut labels = vec![
+                    format!("speed: < {} >", settings.cycles),
+                    format!("palette: < {} >", PRESETS[settings.palette % PRESETS.len()].0)
+                ];
+                let bits = settings.quirks.to_bits();
+                for (index, name) in QUIRK_NAMES.iter().enumerate() {
+                    let state = if bits & (1 << index) != 0 { "on" } else { "off" };
+                    labels.push(format!("{}: {}", name, state));
+                }
+                let state = if settings.pause_unfocused { "on" } else { "off" };
+                labels.push(format!("pause in background: {}", state));
+                labels.push(String::from("back"));
+                labels
+            }
+        }
+    }
+
+    /// Title of the current page
+    fn title(&self) -> &'static str {
+        match self.page {
+            Page::Main => "paused",
+            Page::Recent => "recent roms",
+            Page::Settings => "settings"
+        }
+    }
+
+    /// Render the menu as a box of text for terminal frontends if it is
+    /// open, empty otherwise. The selected entry is shown in reverse.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - settings shown on the settings page
+    /// * `col` - terminal column of the top left corner, from 1
+    /// * `row` - terminal row of the top left corner, from 1
+    pub fn render_ansi(&self, settings: &MenuSettings, col: u16, row: u16) -> String {
+        if !self.open {
+            return String::new();
+        }
+        let title = self.title();
+        let labels = self.labels(settings);
+        let width = labels.iter().map(|label| label.chars().count()).max().unwrap_or(0).max(title.len()) + 2;
+        let border = format!("+{}+", "-".repeat(width + 2));
+        let mut lines = vec![border.clone(), format!("| {:<width$} |", title.to_uppercase(), width = width)];
+        for (index, label) in labels.iter().enumerate() {
+            lines.push(match index == self.selected {
+                true => format!("| \x1b[7m> {:<width$}\x1b[0m |", label, width = width - 2),
+                false => format!("|   {:<width$} |", label, width = width - 2)
+            });
+        }
+        lines.push(border);
+        lines.iter()
+            .enumerate()
+            .map(|(line, text)| format!("\x1b[{};{}H{}", row as usize + line, col, text))
+            .collect()
+    }
+
+    /// Draw the menu centered on an image if it is open, text is scaled
+    /// with the height of the image
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - rendered display to draw on
+    /// * `settings` - settings shown on the settings page
+    pub fn draw(&self, image: &mut RgbImage, settings: &MenuSettings) {
+        if !self.open {
+            return;
+        }
+        let title = self.title();
+        let labels = self.labels(settings);
+        let longest = labels.iter().map(|label| label.len()).max().unwrap_or(0).max(title.len());
+        let line_count = labels.len() + 2;
+        // Largest font scale that fits every line on the image
+        let scale = (image.get_height() / (line_count * 7 + 4)).max(1);
+        let line = 7 * scale;
+        let width = (longest + 4) * 4 * scale;
+        let height = (line_count + 1) * line;
+        let x = image.get_width().saturating_sub(width) / 2;
+        let y = image.get_height().saturating_sub(height) / 2;
+
+        image.fill_rect(x, y, width, height, PANEL_BORDER);
+        image.fill_rect(x + scale, y + scale, width.saturating_sub(2 * scale), height.saturating_sub(2 * scale), PANEL);
+        draw_text(image, title, x + 2 * 4 * scale, y + line / 2 + scale, scale, TITLE);
+        for (index, label) in labels.iter().enumerate() {
+            let ly = y + (index + 2) * line;
+            let color = match index == self.selected {
+                true => {
+                    image.fill_rect(x + 2 * scale, ly - scale, width.saturating_sub(4 * scale), line - scale, SELECTION);
+                    draw_text(image, ">", x + 4 * scale, ly + scale, scale, ITEM_SELECTED);
+                    ITEM_SELECTED
+                },
+                false => ITEM
+            };
+            draw_text(image, label, x + 2 * 4 * scale, ly + scale, scale, color);
+        }
+    }
+}
+
+/// Rows of a 3x5 glyph, bit 2 is the leftmost pixel. Characters
+/// without a glyph are drawn as blanks.
+pub(crate) fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [6, 1, 2, 4, 7],
+        '3' => [6, 1, 2, 1, 6],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 6, 1, 6],
+        '6' => [3, 4, 7, 5, 7],
+        '7' => [7, 1, 2, 2, 2],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 6],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        '.' => [0, 0, 0, 0, 2],
+        '-' => [0, 0, 7, 0, 0],
+        '_' => [0, 0, 0, 0, 7],
+        '/' => [1, 1, 2, 4, 4],
+        '#' => [5, 7, 5, 7, 5],
+        _ => [0; 5]
+    }
+}
+
+/// Draw a line of text with a small 3x5 font, each character takes
+/// 4x5 pixels before scaling. Returns the width drawn in pixels.
+///
+/// # Arguments
+///
+/// * `image` - image to draw on
+/// * `text` - text to draw, letters are drawn uppercase
+/// * `x` - left edge of the text
+/// * `y` - top edge of the text
+/// * `scale` - output pixels per font pixel
+/// * `color` - color of the text
+pub fn draw_text(image: &mut RgbImage, text: &str, x: usize, y: usize, scale: usize, color: [u8; 3]) -> usize {
+    let scale = scale.max(1);
+    for (index, c) in text.chars().enumerate() {
+        let cx = x + index * 4 * scale;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0x4 >> col) != 0 {
+                    image.fill_rect(cx + col * scale, y + row * scale, scale, scale, color);
+                }
+            }
+        }
+    }
+    text.chars().count() * 4 * scale
+}
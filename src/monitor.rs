@@ -0,0 +1,374 @@
+// Standard Library Modules //
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::assembler::SourceMap;
+use super::callstack;
+use super::disasm::mnemonic;
+use super::fonts::{self, GlyphSize};
+use super::hexview::HexView;
+use super::savestate;
+use super::watchpoint::Watchpoint;
+
+/// Commands understood by the monitor
+pub const MONITOR_HELP: &str = "\
+regs                 show the registers, timers and stack depth
+where                show the instruction at PC and its source line
+stack                show the subroutine calls PC is nested in
+peek ADDR [LEN]      show LEN bytes of memory (default 16)
+poke ADDR VALUE...   write bytes to memory from ADDR on
+                     with a source map ADDR can be FILE:LINE
+hex [ADDR]           show a page of memory in hex, PC and I marked
+hex next|prev        scroll the hex view a page
+hex pc|i             show the page PC or I points into
+find BYTES           find hex bytes such as a2 0a or a20a in memory
+                     from the hex view on, again for the next match
+key KEY on|off       press or release a keypad key
+watch [WATCH]        pause when a register or byte is written, V3, I
+                     or 0x300 on any change and V3=5 once it becomes 5,
+                     without WATCH list the watchpoints
+unwatch WATCH|all    remove a watchpoint or all of them
+pause                pause the game
+continue             run on after a pause or a watchpoint
+text X Y HEX [big]   draw hex digits on the display with the font,
+                     drawing them again erases them
+save FILE            write a save state of the machine to FILE
+load FILE            restore the save state in FILE
+speed IPS            run IPS instructions per second
+reset                restart the ROM
+quit                 stop the emulator
+help                 show this list
+";
+
+/// Requests the monitor can not carry out on the system by itself,
+/// they are returned to the run loop that owns the session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorRequest {
+    /// Run this many instructions per second
+    Speed(u32),
+    /// Restart the ROM
+    Reset,
+    /// Stop running frames until asked to continue
+    Pause,
+    /// Run frames again after a pause or a watchpoint hit
+    Continue,
+    /// Stop the emulator
+    Quit
+}
+
+/// Console for inspecting a running game without pausing it
+///
+/// Commands are read on their own thread, from stdin or any other
+/// reader such as a second terminal, and handed to the run loop which
+/// calls `poll` once per frame. Inspection commands are answered right
+/// away so the game keeps running between frames.
+pub struct Monitor {
+    /// Lines read by the input thread
+    lines: Receiver<String>,
+    /// Where answers are written
+    output: Box<dyn Write + Send>,
+    /// True once the input has been closed
+    closed: bool,
+    /// Map from addresses to the source lines they were assembled from
+    source_map: Option<SourceMap>,
+    /// Lines of the source files read so far
+    sources: HashMap<PathBuf, Vec<String>>,
+    /// Page of memory shown by hex
+    hex: HexView
+}
+
+impl Monitor {
+    /// Start a monitor reading commands from a reader on a new thread
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - where commands are read from
+    /// * `output` - where answers are written
+    pub fn init<R, W>(input: R, output: W) -> Self
+    where R: BufRead + Send + 'static, W: Write + Send + 'static {
+        let (sender, lines) = mpsc::channel();
+        // Platforms without threads, such as WASI, get a monitor that
+        // is closed from the start instead of a panic
+        let reader = thread::Builder::new().name(String::from("monitor input")).spawn(move || {
+            for line in input.lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() {
+                        break;
+                    },
+                    Err(_) => break
+                }
+            }
+        });
+        Monitor {
+            lines,
+            output: Box::new(output),
+            closed: reader.is_err(),
+            source_map: None,
+            sources: HashMap::new(),
+            hex: HexView::init()
+        }
+    }
+
+    /// Start a monitor on stdin, answering on stderr so answers do not
+    /// mix with a display drawn on stdout
+    pub fn stdin() -> Self {
+        Monitor::init(io::BufReader::new(io::stdin()), io::stderr())
+    }
+
+    /// Set the source map of the running ROM, so the monitor shows
+    /// source lines and takes addresses as FILE:LINE
+    ///
+    /// # Arguments
+    ///
+    /// * `map` - source map written by the assembler
+    pub fn set_source_map(&mut self, map: SourceMap) {
+        self.source_map = Some(map);
+    }
+
+    /// Returns true once the input has been closed, no more commands
+    /// will arrive
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Write a line of news to the monitor, such as a watchpoint hit,
+    /// without waiting for a command
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - line to write
+    pub fn report(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", text)?;
+        self.output.flush()
+    }
+
+    /// Run every command received since the last call, returning the
+    /// requests the run loop has to carry out
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system being run
+    pub fn poll(&mut self, sys: &mut ChipSystem) -> io::Result<Vec<MonitorRequest>> {
+        let mut requests = Vec::new();
+        loop {
+            let line = match self.lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            };
+            match self.execute(&line, sys) {
+                Ok(Some(request)) => requests.push(request),
+                Ok(None) => {},
+                Err(message) => writeln!(self.output, "error: {}", message)?
+            }
+        }
+        self.output.flush()?;
+        Ok(requests)
+    }
+
+    /// Run one command line
+    fn execute(&mut self, line: &str, sys: &mut ChipSystem) -> Result<Option<MonitorRequest>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let write_err = |e: io::Error| e.to_string();
+        match words.as_slice() {
+            [] => {},
+            ["regs"] => {
+                let reg = &sys.registers;
+                writeln!(
+                    self.output, "PC {:03x}  I {:03x}  DT {:02x}  ST {:02x}  SP {}",
+                    reg.get_pc(), reg.get_i(), reg.get_d(), reg.get_s(), reg.get_sp()
+                ).map_err(write_err)?;
+                let gp: Vec<String> = (0..16).map(|i| format!("V{:X} {:02x}", i, reg.get_gp(i))).collect();
+                writeln!(self.output, "{}", gp[..8].join("  ")).map_err(write_err)?;
+                writeln!(self.output, "{}", gp[8..].join("  ")).map_err(write_err)?;
+            },
+            ["where"] => {
+                let text = self.where_text(sys);
+                writeln!(self.output, "{}", text).map_err(write_err)?;
+            },
+            ["stack"] => {
+                let reg = &sys.registers;
+                writeln!(
+                    self.output, "Depth {} of 16, deepest {}",
+                    reg.get_sp(), reg.get_max_depth()
+                ).map_err(write_err)?;
+                for (addr, line) in callstack::backtrace(sys) {
+                    match self.source_map.as_ref().and_then(|map| map.lookup(addr)) {
+                        Some(location) => writeln!(self.output, "{}  at {}", line, location),
+                        None => writeln!(self.output, "{}", line)
+                    }.map_err(write_err)?;
+                }
+            },
+            ["peek", addr] => self.peek(sys, self.address(addr)?, 16)?,
+            ["peek", addr, len] => self.peek(sys, self.address(addr)?, parse_value(len)? as usize)?,
+            ["poke", addr, values @ ..] if !values.is_empty() => {
+                let addr = self.address(addr)?;
+                let mut bytes = Vec::new();
+                for value in values {
+                    match parse_value(value)? {
+                        value if value > 0xff => return Err(format!("{} does not fit in a byte", value)),
+                        value => bytes.push(value as u8)
+                    }
+                }
+                for (offset, byte) in bytes.into_iter().enumerate() {
+                    sys.ram.set_byte((addr as u16).wrapping_add(offset as u16), byte);
+                }
+            },
+            ["hex", rest @ ..] if rest.len() <= 1 => {
+                match rest.first().copied() {
+                    None => {},
+                    Some("next") => self.hex.next_page(&sys.ram),
+                    Some("prev") => self.hex.prev_page(&sys.ram),
+                    Some("pc") => self.hex.set_addr(&sys.ram, sys.registers.get_pc() as usize),
+                    Some("i") => self.hex.set_addr(&sys.ram, sys.registers.get_i() as usize),
+                    Some(addr) => self.hex.set_addr(&sys.ram, self.address(addr)? as usize)
+                }
+                let page = self.hex.render(&sys.ram, sys.registers.get_pc(), sys.registers.get_i());
+                write!(self.output, "{}", page).map_err(write_err)?;
+            },
+            ["find", bytes @ ..] if !bytes.is_empty() => {
+                let pattern = parse_bytes(&bytes.concat())?;
+                // Searching again finds the next match
+                let addr = self.hex.find_next(&sys.ram, &pattern)
+                    .ok_or_else(|| format!("{} not found", bytes.join(" ")))?;
+                writeln!(self.output, "Found at {:03x}", addr).map_err(write_err)?;
+                let page = self.hex.render(&sys.ram, sys.registers.get_pc(), sys.registers.get_i());
+                write!(self.output, "{}", page).map_err(write_err)?;
+            },
+            ["key", key, state] => {
+                let key = parse_value(key)?;
+                if key > 0xf {
+                    return Err(format!("no key {:x}, keys are 0-f", key));
+                }
+                let down = match *state {
+                    "on" | "down" => true,
+                    "off" | "up" => false,
+                    _ => return Err(format!("expected on or off, found {}", state))
+                };
+                sys.keyboard.set_key(key as u8, down);
+            },
+            ["text", x, y, digits] | ["text", x, y, digits, "big"] => {
+                let size = match words.len() {
+                    5 => GlyphSize::Big,
+                    _ => GlyphSize::Small
+                };
+                fonts::draw_text(sys, digits, parse_value(x)? as u16, parse_value(y)? as u16, size)?;
+            },
+            ["save", path] => {
+                savestate::save_state_file(sys, Path::new(path)).map_err(|e| format!("could not write {}: {}", path, e))?;
+                writeln!(self.output, "Saved the state to {}", path).map_err(write_err)?;
+            },
+            ["load", path] => {
+                savestate::load_state_file(sys, Path::new(path)).map_err(|e| format!("could not load {}: {}", path, e))?;
+                writeln!(self.output, "Loaded the state from {}", path).map_err(write_err)?;
+            },
+            ["watch"] => {
+                let points = sys.get_watchpoints();
+                if points.is_empty() {
+                    writeln!(self.output, "No watchpoints").map_err(write_err)?;
+                }
+                for point in points {
+                    writeln!(self.output, "{}", point).map_err(write_err)?;
+                }
+            },
+            ["watch", point] => sys.add_watchpoint(Watchpoint::parse(point)?),
+            ["unwatch", "all"] => sys.clear_watchpoints(),
+            ["unwatch", point] => {
+                let point = Watchpoint::parse(point)?;
+                if !sys.remove_watchpoint(&point) {
+                    return Err(format!("no watchpoint {}", point));
+                }
+            },
+            ["pause"] => return Ok(Some(MonitorRequest::Pause)),
+            ["continue"] | ["c"] => return Ok(Some(MonitorRequest::Continue)),
+            ["speed", ips] => return Ok(Some(MonitorRequest::Speed(parse_value(ips)?))),
+            ["reset"] => return Ok(Some(MonitorRequest::Reset)),
+            ["quit"] | ["exit"] => return Ok(Some(MonitorRequest::Quit)),
+            ["help"] => write!(self.output, "{}", MONITOR_HELP).map_err(write_err)?,
+            _ => return Err(format!("unknown command '{}', try help", line.trim()))
+        }
+        Ok(None)
+    }
+
+    /// Describe the instruction at PC, with its source line when there
+    /// is a source map
+    fn where_text(&mut self, sys: &ChipSystem) -> String {
+        let pc = sys.registers.get_pc();
+        let opcode = sys.ram.get_opcode(pc);
+        let mut text = format!("PC {:03x}  {:04x}  {}", pc, opcode, mnemonic(opcode));
+        let location = match self.source_map.as_ref() {
+            Some(map) => map.lookup(pc).cloned(),
+            None => return text
+        };
+        let location = match location {
+            Some(location) => location,
+            None => return text + "  (no source line)"
+        };
+        text.push_str(&format!("  at {}", location));
+        if let Some(file) = location.file {
+            let lines = self.sources.entry(file.clone()).or_insert_with(|| {
+                fs::read_to_string(&file)
+                    .map(|source| source.lines().map(String::from).collect())
+                    .unwrap_or_default()
+            });
+            if let Some(line) = location.line.checked_sub(1).and_then(|index| lines.get(index)) {
+                text.push_str(&format!("\n  {}", line.trim()));
+            }
+        }
+        text
+    }
+
+    /// Parse an address, a number or with a source map FILE:LINE
+    fn address(&self, text: &str) -> Result<u32, String> {
+        match (text.rsplit_once(':'), self.source_map.as_ref()) {
+            (Some((file, line)), Some(map)) => {
+                let line = parse_value(line)? as usize;
+                map.address_of(file, line)
+                    .map(|addr| addr as u32)
+                    .ok_or_else(|| format!("no code or data at {}:{}", file, line))
+            },
+            (Some(_), None) => Err(format!("{} needs a source map", text)),
+            (None, _) => parse_value(text)
+        }
+    }
+
+    /// Write a hex dump of memory, 16 bytes per line
+    fn peek(&mut self, sys: &ChipSystem, addr: u32, len: usize) -> Result<(), String> {
+        let bytes = sys.ram.peek_bytes(addr as u16, len);
+        for (row, chunk) in bytes.chunks(16).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            writeln!(self.output, "{:03x}: {}", addr as usize + row * 16, hex.join(" ")).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse hex bytes written together, such as a20a
+fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected hex bytes, found {}", text));
+    }
+    (0..digits.len()).step_by(2)
+        .map(|start| u8::from_str_radix(&digits[start..start + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// Parse a decimal or 0x prefixed hex value
+fn parse_value(text: &str) -> Result<u32, String> {
+    let parsed = match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => text.parse::<u32>()
+    };
+    parsed.map_err(|_| format!("invalid number: {}", text))
+}
@@ -0,0 +1,363 @@
+// Standard Library Modules //
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+// Local Modules Use //
+use super::menu::glyph;
+
+/// Most games a menu can list, one per 6 pixel row of text
+pub const MAX_GAMES: usize = 5;
+
+/// Longest game name shown, longer names are cut
+pub const MAX_NAME: usize = 14;
+
+/// Address programs are loaded at
+const START: u16 = 0x200;
+
+/// Largest combined ROM, everything from the program start to the end
+/// of the 4 KB address space
+const MAX_SIZE: usize = 0x1000 - START as usize;
+
+/// Key that moves the cursor up, W in Octo's keyboard layout
+const KEY_UP: u16 = 0x5;
+
+/// Key that moves the cursor down, S in Octo's keyboard layout
+const KEY_DOWN: u16 = 0x8;
+
+/// Key that starts the selected game, E in Octo's keyboard layout
+const KEY_SELECT: u16 = 0x6;
+
+/// A reason a menu ROM could not be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MulticartError {
+    /// No games were given
+    NoGames,
+    /// More games than `MAX_GAMES` were given
+    TooManyGames(usize),
+    /// A game can not be moved to a new address
+    NotRelocatable {
+        /// Name of the game
+        name: String,
+        /// Why it can not be moved
+        reason: String
+    },
+    /// The games do not fit in memory together
+    TooLarge {
+        /// Size of the combined ROM
+        size: usize,
+        /// Largest size that fits
+        max: usize
+    }
+}
+
+impl fmt::Display for MulticartError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MulticartError::NoGames => write!(f, "no games given"),
+            MulticartError::TooManyGames(count) => write!(f, "{} games given, a menu holds at most {}", count, MAX_GAMES),
+            MulticartError::NotRelocatable { name, reason } => write!(f, "{} can not be relocated: {}", name, reason),
+            MulticartError::TooLarge { size, max } => write!(f, "combined ROM is {} bytes, at most {} fit", size, max)
+        }
+    }
+}
+
+impl error::Error for MulticartError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Where a game was put in a menu ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedGame {
+    /// Name shown in the menu
+    pub name: String,
+    /// Address the game starts at
+    pub addr: u16,
+    /// Size of the game in bytes
+    pub size: usize,
+    /// Number of addresses rewritten to move the game
+    pub relocations: usize
+}
+
+/// A ROM holding several games behind a menu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Multicart {
+    /// Contents of the combined ROM
+    pub rom: Vec<u8>,
+    /// Games in menu order
+    pub games: Vec<PlacedGame>
+}
+
+/// Build one ROM that shows a menu of games and runs the one picked
+///
+/// The menu lists the games by name, 5 (up) and 8 (down) move the
+/// cursor and 6 starts the selected game, the W, S and E keys in
+/// Octo's keyboard layout. Each game is stored after the menu and has
+/// its addresses rewritten for its new location, see `relocate` for
+/// which games can be moved.
+///
+/// # Arguments
+///
+/// * `games` - name and contents of each game, in menu order
+pub fn build(games: &[(String, Vec<u8>)]) -> Result<Multicart, MulticartError> {
+    if games.is_empty() {
+        return Err(MulticartError::NoGames);
+    }
+    if games.len() > MAX_GAMES {
+        return Err(MulticartError::TooManyGames(games.len()));
+    }
+    let names: Vec<String> = games.iter()
+        .map(|(name, _)| name.to_ascii_uppercase().chars().take(MAX_NAME).collect())
+        .collect();
+    let mut asm = Assembler::init();
+
+    // Draw the names and the cursor, V0 is the selected game and V1
+    // the row of the cursor
+    asm.op(0x00E0);
+    for (index, name) in names.iter().enumerate() {
+        asm.op(0x6D00 | (1 + 6 * index as u16));
+        for (col, c) in name.chars().enumerate() {
+            if glyph(c) == [0; 5] {
+                continue;
+            }
+            asm.op_to(0xA000, &glyph_label(c));
+            asm.op(0x6E00 | (6 + 4 * col as u16));
+            asm.op(0xDED5);
+        }
+    }
+    asm.op(0x6000);
+    asm.op(0x6101);
+    asm.op(0x6E01);
+    asm.op_to(0xA000, &glyph_label('>'));
+    asm.op(0xDE15);
+
+    // Wait for a key and act on it
+    asm.label("loop");
+    asm.op(0xF20A);
+    for (key, target) in [(KEY_UP, "up"), (KEY_DOWN, "down"), (KEY_SELECT, "select")].iter() {
+        asm.op(0x4200 | key);
+        asm.op_to(0x1000, target);
+    }
+    asm.op_to(0x1000, "loop");
+
+    asm.label("up");
+    asm.op(0x4000);
+    asm.op_to(0x1000, "release");
+    asm.op_to(0xA000, &glyph_label('>'));
+    asm.op(0xDE15);
+    asm.op(0x70FF);
+    asm.op(0x71FA);
+    asm.op(0xDE15);
+    asm.op_to(0x1000, "release");
+
+    asm.label("down");
+    asm.op(0x4000 | (games.len() as u16 - 1));
+    asm.op_to(0x1000, "release");
+    asm.op_to(0xA000, &glyph_label('>'));
+    asm.op(0xDE15);
+    asm.op(0x7001);
+    asm.op(0x7106);
+    asm.op(0xDE15);
+
+    // Wait for the key to be let go so holding it moves only one row
+    asm.label("release");
+    asm.op(0xE29E);
+    asm.op_to(0x1000, "loop");
+    asm.op_to(0x1000, "release");
+
+    asm.label("select");
+    for index in 0..games.len() {
+        asm.op(0x4000 | index as u16);
+        asm.op_to(0x1000, &format!("start{}", index));
+    }
+    asm.op_to(0x1000, "loop");
+
+    // Clear the screen and every register the menu used before
+    // starting a game, games may expect a clean machine
+    for index in 0..games.len() {
+        asm.label(&format!("start{}", index));
+        for op in [0x00E0, 0x6000, 0x6100, 0x6200, 0x6D00, 0x6E00].iter() {
+            asm.op(*op);
+        }
+        asm.op_to(0x1000, &format!("game{}", index));
+    }
+
+    let mut chars: Vec<char> = names.iter().flat_map(|name| name.chars()).collect();
+    chars.push('>');
+    chars.sort_unstable();
+    chars.dedup();
+    for c in chars.into_iter().filter(|c| glyph(*c) != [0; 5]) {
+        asm.label(&glyph_label(c));
+        for row in glyph(c).iter() {
+            asm.byte(row << 5);
+        }
+    }
+
+    let mut placed = Vec::new();
+    for (index, ((name, rom), shown)) in games.iter().zip(names).enumerate() {
+        if asm.here() % 2 == 1 {
+            asm.byte(0);
+        }
+        let addr = asm.here();
+        let (moved, relocations) = relocate(rom, addr)
+            .map_err(|reason| MulticartError::NotRelocatable { name: name.clone(), reason })?;
+        asm.label(&format!("game{}", index));
+        for byte in moved {
+            asm.byte(byte);
+        }
+        placed.push(PlacedGame { name: shown, addr, size: rom.len(), relocations });
+    }
+
+    let rom = asm.finish();
+    if rom.len() > MAX_SIZE {
+        return Err(MulticartError::TooLarge { size: rom.len(), max: MAX_SIZE });
+    }
+    Ok(Multicart { rom, games: placed })
+}
+
+/// Label of the sprite for a character
+fn glyph_label(c: char) -> String {
+    format!("glyph_{}", c)
+}
+
+/// Move a game written to run at 0x200 so it runs at `addr`, returning
+/// the moved game and the number of addresses rewritten
+///
+/// Code is found by following every path from the first opcode, so
+/// sprites and other data mixed in with the code are left alone. The
+/// targets of jumps (1NNN), calls (2NNN) and `I` loads (ANNN) into the
+/// game are moved along with it. Games that can not be followed this
+/// way are refused: computed jumps (BNNN), machine code calls (0NNN),
+/// jumps out of the game, `I` pointing past the end of the game where
+/// other games now live, and XO-CHIP long addressing. Code that writes
+/// over itself is not detected and will not survive the move.
+///
+/// # Arguments
+///
+/// * `rom` - game contents
+/// * `addr` - address the game will start at
+pub fn relocate(rom: &[u8], addr: u16) -> Result<(Vec<u8>, usize), String> {
+    if addr < START {
+        return Err(format!("0x{:03X} is below the program start", addr));
+    }
+    let end = START as usize + rom.len();
+    let shift = (addr - START) as usize;
+    if end + shift > 0x1000 {
+        return Err(String::from("it does not fit at its new address"));
+    }
+    let mut out = rom.to_vec();
+    let mut relocations = 0;
+    let mut seen = vec![false; rom.len()];
+    let mut pending = vec![0usize];
+    while let Some(offset) = pending.pop() {
+        if offset >= rom.len() || seen[offset] {
+            continue;
+        }
+        if offset + 1 >= rom.len() {
+            return Err(format!("code at 0x{:03X} runs past the end of the ROM", offset + START as usize));
+        }
+        seen[offset] = true;
+        let here = offset + START as usize;
+        let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+        let nnn = (opcode & 0x0FFF) as usize;
+        let inside = nnn >= START as usize && nnn < end;
+        match opcode >> 12 {
+            0x0 => match opcode {
+                0x00EE | 0x00FD => {},
+                0x00E0 | 0x00FB | 0x00FC | 0x00FE | 0x00FF => pending.push(offset + 2),
+                _ if opcode & 0xFFF0 == 0x00C0 => pending.push(offset + 2),
+                _ => return Err(format!("machine code call {:04X} at 0x{:03X}", opcode, here))
+            },
+            0x1 | 0x2 => {
+                if !inside {
+                    return Err(format!("{:04X} at 0x{:03X} jumps outside the game", opcode, here));
+                }
+                pending.push(nnn - START as usize);
+                if opcode >> 12 == 0x2 {
+                    pending.push(offset + 2);
+                }
+            },
+            0x3 | 0x4 | 0x5 | 0x9 | 0xE => {
+                pending.push(offset + 2);
+                pending.push(offset + 4);
+            },
+            0xA => {
+                if nnn >= end {
+                    return Err(format!("{:04X} at 0x{:03X} points past the end of the game", opcode, here));
+                }
+                pending.push(offset + 2);
+            },
+            0xB => return Err(format!("computed jump {:04X} at 0x{:03X}", opcode, here)),
+            0xF if opcode == 0xF000 => return Err(format!("long address load at 0x{:03X}", here)),
+            _ => pending.push(offset + 2)
+        }
+        if matches!(opcode >> 12, 0x1 | 0x2 | 0xA) && inside {
+            let moved = (opcode & 0xF000) | (nnn + shift) as u16;
+            out[offset] = (moved >> 8) as u8;
+            out[offset + 1] = moved as u8;
+            relocations += 1;
+        }
+    }
+    Ok((out, relocations))
+}
+
+/// Writes opcodes and data from 0x200 up, filling in label addresses
+/// once everything has been written
+struct Assembler {
+    /// Bytes written so far
+    out: Vec<u8>,
+    /// Address of each label
+    labels: HashMap<String, u16>,
+    /// Opcodes waiting for a label address, by position in `out`
+    fixups: Vec<(usize, u16, String)>
+}
+
+impl Assembler {
+    fn init() -> Self {
+        Assembler {
+            out: Vec::new(),
+            labels: HashMap::new(),
+            fixups: Vec::new()
+        }
+    }
+
+    /// Address of the next byte
+    fn here(&self) -> u16 {
+        START + self.out.len() as u16
+    }
+
+    fn label(&mut self, name: &str) {
+        let here = self.here();
+        self.labels.insert(name.to_string(), here);
+    }
+
+    fn byte(&mut self, value: u8) {
+        self.out.push(value);
+    }
+
+    fn op(&mut self, opcode: u16) {
+        self.out.push((opcode >> 8) as u8);
+        self.out.push(opcode as u8);
+    }
+
+    /// Write an opcode whose address is a label, `opcode` holds the top
+    /// nibble such as 0x1000 for a jump
+    fn op_to(&mut self, opcode: u16, label: &str) {
+        self.fixups.push((self.out.len(), opcode, label.to_string()));
+        self.op(opcode);
+    }
+
+    /// Fill in every label address and return the bytes
+    fn finish(mut self) -> Vec<u8> {
+        for (pos, opcode, label) in self.fixups.iter() {
+            // Every label used is defined by `build`
+            let addr = self.labels.get(label).copied().unwrap_or(0);
+            let full = opcode | (addr & 0x0FFF);
+            self.out[*pos] = (full >> 8) as u8;
+            self.out[*pos + 1] = full as u8;
+        }
+        self.out
+    }
+}
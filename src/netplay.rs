@@ -0,0 +1,329 @@
+// Standard Library Modules //
+use std::collections::{BTreeMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+// Local Modules Use //
+use super::ChipSystem;
+
+/// Protocol version, both peers have to speak the same one
+pub const NETPLAY_VERSION: u32 = 1;
+
+/// Frames between pressing a key and the key reaching the game, giving
+/// it time to cross the network so a frame rarely waits on the peer
+pub const DEFAULT_INPUT_DELAY: u64 = 3;
+
+/// Most frames of input delay a session can have, three seconds. The
+/// delay comes from the host and sizes the input queue, so anything
+/// above it is refused instead of trusted.
+pub const MAX_INPUT_DELAY: u64 = 180;
+
+/// How long to wait for the peer before giving up
+pub const PEER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Keys held by the left player of the common two player layout, the
+/// 1 and 4 keys Pong uses for the left paddle
+pub const LEFT_PLAYER_KEYS: u16 = 1 << 0x1 | 1 << 0x4;
+
+/// Settings both peers have to agree on for their machines to stay the
+/// same, chosen by the host and checked by the peer that joins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetplaySettings {
+    /// Hash of the ROM, see `rom_hash`
+    pub rom_hash: u64,
+    /// Opcodes run per frame
+    pub cycles: u32,
+    /// Seed of the random opcode
+    pub seed: u64,
+    /// Frames of input delay
+    pub delay: u64,
+    /// Keys owned by the host, one bit per key, the peer owns the rest
+    pub host_keys: u16
+}
+
+/// Reasons a netplay session fails
+#[derive(Debug)]
+pub enum NetplayError {
+    /// The connection failed or the peer went away
+    Io(io::Error),
+    /// The peer sent something that is not part of the protocol
+    Protocol(String),
+    /// The peers were not set up the same, such as different ROMs
+    Mismatch(String),
+    /// The machines stopped matching, reported with the first frame
+    /// found to differ
+    Desync(u64)
+}
+
+impl fmt::Display for NetplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetplayError::Io(e) => write!(f, "connection failed: {}", e),
+            NetplayError::Protocol(message) => write!(f, "unexpected message from peer: {}", message),
+            NetplayError::Mismatch(message) => write!(f, "peer is set up differently: {}", message),
+            NetplayError::Desync(frame) => write!(f, "machines stopped matching after frame {}", frame)
+        }
+    }
+}
+
+impl error::Error for NetplayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            NetplayError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for NetplayError {
+    fn from(e: io::Error) -> Self {
+        NetplayError::Io(e)
+    }
+}
+
+/// Hash of a ROM used to check both peers loaded the same one, 64 bit
+/// FNV-1a
+///
+/// # Arguments
+///
+/// * `rom` - ROM bytes
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Hash of the parts of a system that show when two machines differ,
+/// the display, registers and timers
+fn state_hash(sys: &ChipSystem) -> u64 {
+    let reg = &sys.registers;
+    let mut bytes = Vec::with_capacity(32);
+    bytes.extend_from_slice(&sys.display.hash().to_le_bytes());
+    bytes.extend_from_slice(&reg.get_pc().to_le_bytes());
+    bytes.extend_from_slice(&reg.get_i().to_le_bytes());
+    bytes.extend((0..16).map(|index| reg.get_gp(index)));
+    bytes.push(reg.get_d());
+    bytes.push(reg.get_s());
+    bytes.push(reg.get_sp() as u8);
+    rom_hash(&bytes)
+}
+
+/// Two machines running one ROM in lockstep over TCP, each player
+/// owning some of the 16 keys
+///
+/// Call `sync` before every frame with the keys the local player is
+/// holding. Each frame's input is sent to the peer and used `delay`
+/// frames later by both machines, so they see the same keys on the
+/// same frame and with a seeded random opcode stay identical. A hash
+/// of each machine is sent along with the input to catch them drifting
+/// apart.
+pub struct Netplay {
+    /// Connection to the peer
+    reader: BufReader<TcpStream>,
+    /// Connection to the peer
+    writer: TcpStream,
+    /// Settings both peers agreed on
+    settings: NetplaySettings,
+    /// Keys owned by this side
+    local_keys: u16,
+    /// Local input waiting to be used, for the frames after the one
+    /// being synced
+    local: VecDeque<u16>,
+    /// Input received from the peer by frame
+    remote: BTreeMap<u64, u16>,
+    /// State hashes of this machine by frame
+    hashes: BTreeMap<u64, u64>,
+    /// State hashes received from the peer by frame
+    remote_hashes: BTreeMap<u64, u64>
+}
+
+impl Netplay {
+    /// Wait for a peer to join, then send it the settings
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - address to listen on, such as 0.0.0.0:7777
+    /// * `settings` - settings for both machines
+    pub fn host<A: ToSocketAddrs>(addr: A, settings: NetplaySettings) -> Result<Self, NetplayError> {
+        if settings.delay > MAX_INPUT_DELAY {
+            return Err(NetplayError::Protocol(format!("input delay {} is above the limit of {} frames", settings.delay, MAX_INPUT_DELAY)));
+        }
+        let listener = TcpListener::bind(addr)?;
+        let (stream, _) = listener.accept()?;
+        let mut session = Netplay::init(stream, settings, settings.host_keys)?;
+        let hello = format!(
+            "CLIP8 NETPLAY {} {:016x} {} {:016x} {} {:04x}",
+            NETPLAY_VERSION, settings.rom_hash, settings.cycles, settings.seed, settings.delay, settings.host_keys
+        );
+        session.send(&hello)?;
+        let reply = session.receive()?;
+        match reply.split_once(' ') {
+            _ if reply == "OK" => Ok(session),
+            Some(("ERR", reason)) => Err(NetplayError::Mismatch(reason.to_string())),
+            _ => Err(NetplayError::Protocol(reply))
+        }
+    }
+
+    /// Join a host, taking the settings it sends
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - address of the host
+    /// * `rom_hash` - hash of the ROM loaded here, see `rom_hash`
+    pub fn join<A: ToSocketAddrs>(addr: A, rom_hash: u64) -> Result<Self, NetplayError> {
+        let stream = TcpStream::connect(addr)?;
+        let mut session = Netplay::init(stream, NetplaySettings { rom_hash, cycles: 0, seed: 0, delay: 0, host_keys: 0 }, 0)?;
+        let hello = session.receive()?;
+        let words: Vec<&str> = hello.split_whitespace().collect();
+        let settings = match words.as_slice() {
+            ["CLIP8", "NETPLAY", version, hash, cycles, seed, delay, keys] => {
+                if version.parse::<u32>().ok() != Some(NETPLAY_VERSION) {
+                    session.send(&format!("ERR protocol version {} is not {}", version, NETPLAY_VERSION))?;
+                    return Err(NetplayError::Mismatch(format!("host speaks protocol version {}", version)));
+                }
+                let invalid = || NetplayError::Protocol(hello.clone());
+                NetplaySettings {
+                    rom_hash: u64::from_str_radix(hash, 16).map_err(|_| invalid())?,
+                    cycles: cycles.parse().map_err(|_| invalid())?,
+                    seed: u64::from_str_radix(seed, 16).map_err(|_| invalid())?,
+                    delay: delay.parse().ok().filter(|delay| *delay <= MAX_INPUT_DELAY).ok_or_else(invalid)?,
+                    host_keys: u16::from_str_radix(keys, 16).map_err(|_| invalid())?
+                }
+            },
+            _ => return Err(NetplayError::Protocol(hello))
+        };
+        if settings.rom_hash != rom_hash {
+            session.send("ERR the ROMs are different")?;
+            return Err(NetplayError::Mismatch(String::from("the host is running a different ROM")));
+        }
+        session.send("OK")?;
+        session.settings = settings;
+        session.local_keys = !settings.host_keys;
+        session.local = VecDeque::from(vec![0; settings.delay as usize]);
+        Ok(session)
+    }
+
+    /// Set up a session on a connected stream
+    fn init(stream: TcpStream, settings: NetplaySettings, local_keys: u16) -> Result<Self, NetplayError> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(PEER_TIMEOUT))?;
+        Ok(Netplay {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            settings,
+            local_keys,
+            local: VecDeque::from(vec![0; settings.delay as usize]),
+            remote: BTreeMap::new(),
+            hashes: BTreeMap::new(),
+            remote_hashes: BTreeMap::new()
+        })
+    }
+
+    /// Get the settings both machines run with
+    pub fn get_settings(&self) -> NetplaySettings {
+        self.settings
+    }
+
+    /// Get the keys owned by this side, one bit per key
+    pub fn get_local_keys(&self) -> u16 {
+        self.local_keys
+    }
+
+    /// Set up a system the way the peer's is, call after loading the ROM
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to set up
+    pub fn prepare(&self, sys: &mut ChipSystem) {
+        sys.seed_random(self.settings.seed);
+    }
+
+    /// Exchange input for the next frame and set the keyboard to the
+    /// keys both players pressed `delay` frames ago, waiting for the
+    /// peer if its input has not arrived
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system about to run a frame
+    /// * `held` - keys the local player is holding, one bit per key,
+    ///   keys owned by the peer are ignored
+    pub fn sync(&mut self, sys: &mut ChipSystem, held: u16) -> Result<(), NetplayError> {
+        let frame = sys.get_frame();
+        let hash = state_hash(sys);
+        self.hashes.insert(frame, hash);
+        let held = held & self.local_keys;
+        self.local.push_back(held);
+        self.send(&format!("F {} {:04x} {:016x}", frame + self.settings.delay, held, hash))?;
+
+        let remote = match frame < self.settings.delay {
+            true => 0,
+            false => loop {
+                if let Some(keys) = self.remote.remove(&frame) {
+                    break keys;
+                }
+                let message = self.receive()?;
+                self.handle(&message)?;
+            }
+        };
+        let local = self.local.pop_front().unwrap_or(0);
+        let keys = local | (remote & !self.local_keys);
+        for key in 0..16u8 {
+            sys.keyboard.set_key(key, keys & (1 << key) != 0);
+        }
+        self.check()
+    }
+
+    /// Store a frame message from the peer
+    fn handle(&mut self, message: &str) -> Result<(), NetplayError> {
+        let words: Vec<&str> = message.split_whitespace().collect();
+        let invalid = || NetplayError::Protocol(message.to_string());
+        match words.as_slice() {
+            ["F", frame, keys, hash] => {
+                let frame: u64 = frame.parse().map_err(|_| invalid())?;
+                let keys = u16::from_str_radix(keys, 16).map_err(|_| invalid())?;
+                let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid())?;
+                self.remote.insert(frame, keys);
+                self.remote_hashes.insert(frame - self.settings.delay.min(frame), hash);
+                Ok(())
+            },
+            ["BYE"] => Err(NetplayError::Io(io::Error::new(io::ErrorKind::ConnectionAborted, "the peer left"))),
+            _ => Err(invalid())
+        }
+    }
+
+    /// Compare the hashes both machines have reported for the same
+    /// frames, forgetting the ones compared
+    fn check(&mut self) -> Result<(), NetplayError> {
+        let frames: Vec<u64> = self.remote_hashes.keys().copied().filter(|frame| self.hashes.contains_key(frame)).collect();
+        for frame in frames {
+            if self.remote_hashes.remove(&frame) != self.hashes.remove(&frame) {
+                return Err(NetplayError::Desync(frame));
+            }
+        }
+        Ok(())
+    }
+
+    /// Tell the peer this side is leaving
+    pub fn close(mut self) -> Result<(), NetplayError> {
+        self.send("BYE")
+    }
+
+    /// Send a line to the peer
+    fn send(&mut self, line: &str) -> Result<(), NetplayError> {
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Receive a line from the peer
+    fn receive(&mut self) -> Result<String, NetplayError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line)? {
+            0 => Err(NetplayError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "the peer closed the connection"))),
+            _ => Ok(line.trim_end().to_string())
+        }
+    }
+}
@@ -0,0 +1,425 @@
+// Standard Library Modules //
+use std::collections::VecDeque;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::attract::DemoInput;
+use super::display::ChipDisplay;
+use super::image::RgbImage;
+use super::keyboard::ChipKeyboard;
+use super::memory::{RomError, FONT};
+
+/// Corner of the screen an overlay is drawn in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight
+}
+
+/// Layout of the COSMAC VIP hex keypad, row by row
+pub const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF]
+];
+
+const KEY_UP: [u8; 3] = [40, 40, 40];
+const KEY_DOWN: [u8; 3] = [230, 200, 40];
+const GLYPH_UP: [u8; 3] = [200, 200, 200];
+const GLYPH_DOWN: [u8; 3] = [20, 20, 20];
+const BORDER: [u8; 3] = [90, 90, 90];
+const BORDER_WAITING: [u8; 3] = [255, 80, 80];
+
+/// A 4x4 keypad widget showing which keys are held
+///
+/// Frontends draw it on top of the rendered display, it highlights
+/// held keys and turns its border red while a FX0A opcode is waiting
+/// for a key. Visibility is meant to be toggled by a hotkey.
+///
+/// The keypad can also be played with a mouse or touch screen by
+/// passing pointer events to `pointer`, holding a key down presses it
+/// on the keyboard until the pointer is released or leaves the key.
+#[derive(Debug, Clone)]
+pub struct KeypadOverlay {
+    /// True if the overlay should be drawn
+    pub visible: bool,
+    /// Corner the keypad is drawn in
+    pub corner: Corner,
+    /// Size of each key in output pixels
+    cell: usize,
+    /// Key currently held down by the pointer
+    pointer_key: Option<u8>
+}
+
+impl KeypadOverlay {
+    /// Initialize a hidden keypad overlay in the bottom right corner
+    ///
+    /// # Arguments
+    ///
+    /// * `cell` - size of each key in output pixels, glyphs need at
+    ///   least 6 pixels to be readable
+    pub fn init(cell: usize) -> Self {
+        KeypadOverlay {
+            visible: false,
+            corner: Corner::BottomRight,
+            cell: cell.max(6),
+            pointer_key: None
+        }
+    }
+
+    /// Show the overlay if hidden and hide it if shown
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Get the size of each key in output pixels
+    pub fn get_cell(&self) -> usize {
+        self.cell
+    }
+
+    /// Area the overlay covers on an image as (x, y, width, height)
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - width of the image drawn on
+    /// * `height` - height of the image drawn on
+    pub fn bounds(&self, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        let size = self.cell * 4 + 2;
+        let margin = self.cell / 2;
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => margin,
+            Corner::TopRight | Corner::BottomRight => width.saturating_sub(size + margin)
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => margin,
+            Corner::BottomLeft | Corner::BottomRight => height.saturating_sub(size + margin)
+        };
+        (x, y, size, size)
+    }
+
+    /// Find the key under a point on the image, None if the point is
+    /// not on a key or the overlay is hidden
+    ///
+    /// # Arguments
+    ///
+    /// * `px` - x position of the point
+    /// * `py` - y position of the point
+    /// * `width` - width of the image the overlay is drawn on
+    /// * `height` - height of the image the overlay is drawn on
+    pub fn key_at(&self, px: usize, py: usize, width: usize, height: usize) -> Option<u8> {
+        if !self.visible {
+            return None;
+        }
+        let (x, y, size, _) = self.bounds(width, height);
+        if px <= x || py <= y || px >= x + size - 1 || py >= y + size - 1 {
+            return None;
+        }
+        let col = (px - x - 1) / self.cell;
+        let row = (py - y - 1) / self.cell;
+        Some(KEYPAD_LAYOUT[row.min(3)][col.min(3)])
+    }
+
+    /// Handle a pointer event, pressing and releasing keys on the
+    /// keyboard. Returns true if the event was on the keypad and should
+    /// not be handled by anything else.
+    ///
+    /// # Arguments
+    ///
+    /// * `px` - x position of the pointer on the image
+    /// * `py` - y position of the pointer on the image
+    /// * `down` - true while the button is held or the screen touched
+    /// * `width` - width of the image the overlay is drawn on
+    /// * `height` - height of the image the overlay is drawn on
+    /// * `keyboard` - keyboard to press keys on
+    pub fn pointer(&mut self, px: usize, py: usize, down: bool, width: usize, height: usize,
+                   keyboard: &mut ChipKeyboard) -> bool {
+        let key = self.key_at(px, py, width, height);
+        let target = if down { key } else { None };
+        if target != self.pointer_key {
+            if let Some(old) = self.pointer_key {
+                keyboard.set_key(old, false);
+            }
+            if let Some(new) = target {
+                keyboard.set_key(new, true);
+            }
+            self.pointer_key = target;
+        }
+        key.is_some()
+    }
+
+    /// Release the key held by the pointer, for when the pointer leaves
+    /// the window or the overlay is hidden
+    ///
+    /// # Arguments
+    ///
+    /// * `keyboard` - keyboard the key was pressed on
+    pub fn release_pointer(&mut self, keyboard: &mut ChipKeyboard) {
+        if let Some(key) = self.pointer_key.take() {
+            keyboard.set_key(key, false);
+        }
+    }
+
+    /// Draw the keypad onto an image if it is visible
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - rendered display to draw on
+    /// * `keyboard` - keyboard whose state is shown
+    pub fn draw(&self, image: &mut RgbImage, keyboard: &ChipKeyboard) {
+        if !self.visible {
+            return;
+        }
+        let (x, y, size, _) = self.bounds(image.get_width(), image.get_height());
+        let border = match keyboard.get_waiting() {
+            Some(_) => BORDER_WAITING,
+            None => BORDER
+        };
+        image.fill_rect(x, y, size, size, border);
+
+        for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                let held = keyboard.get_key(*key);
+                let cx = x + 1 + col * self.cell;
+                let cy = y + 1 + row * self.cell;
+                let (fill, glyph) = match held {
+                    true => (KEY_DOWN, GLYPH_DOWN),
+                    false => (KEY_UP, GLYPH_UP)
+                };
+                image.fill_rect(cx + 1, cy + 1, self.cell - 2, self.cell - 2, fill);
+                draw_glyph(image, *key, cx, cy, self.cell, glyph);
+            }
+        }
+    }
+}
+
+/// Draw a font digit centered in a square cell, scaled to fit
+///
+/// # Arguments
+///
+/// * `image` - image to draw on
+/// * `digit` - hex digit 0-F
+/// * `x` - left edge of the cell
+/// * `y` - top edge of the cell
+/// * `cell` - size of the cell
+/// * `color` - color of the glyph
+pub fn draw_glyph(image: &mut RgbImage, digit: u8, x: usize, y: usize, cell: usize, color: [u8; 3]) {
+    // Glyphs are 4x5, leave at least one pixel of padding
+    let scale = ((cell - 2) / 5).max(1);
+    let gx = x + (cell - 4 * scale) / 2;
+    let gy = y + (cell - 5 * scale) / 2;
+    let start = (digit as usize & 0xf) * 5;
+    for (row, bits) in FONT[start..start + 5].iter().enumerate() {
+        for col in 0..4 {
+            if bits & (0x80 >> col) != 0 {
+                image.fill_rect(gx + col * scale, gy + row * scale, scale, scale, color);
+            }
+        }
+    }
+}
+
+/// Onion skin of previous frames for frame by frame (TAS) work
+///
+/// Keeps the last few displays and draws them faded behind the current
+/// one, older frames are fainter, so movement between frames is easy to
+/// judge. Frames are pushed with `record` once per frame, or loaded
+/// from a list of saved frames with `load`.
+#[derive(Debug, Clone)]
+pub struct OnionSkin {
+    /// True if previous frames should be drawn
+    pub enabled: bool,
+    /// Number of previous frames drawn
+    depth: usize,
+    /// Previous frames, most recent first
+    history: VecDeque<Vec<bool>>
+}
+
+impl OnionSkin {
+    /// Initialize a disabled onion skin
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - number of previous frames to draw
+    pub fn init(depth: usize) -> Self {
+        OnionSkin {
+            enabled: false,
+            depth,
+            history: VecDeque::with_capacity(depth + 1)
+        }
+    }
+
+    /// Get the number of previous frames drawn
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Change the number of previous frames drawn
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - number of previous frames to draw
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        self.history.truncate(depth);
+    }
+
+    /// Remember the current display as the most recent previous frame,
+    /// call this at the end of every frame before the next one runs
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - display to remember
+    pub fn record(&mut self, display: &ChipDisplay) {
+        self.history.push_front(display.get_display());
+        self.history.truncate(self.depth);
+    }
+
+    /// Replace the remembered frames, for example with frames taken from
+    /// saved states when stepping backwards
+    ///
+    /// # Arguments
+    ///
+    /// * `frames` - previous frames, most recent first
+    pub fn load(&mut self, frames: Vec<Vec<bool>>) {
+        self.history = frames.into_iter().take(self.depth).collect();
+    }
+
+    /// Forget all remembered frames
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Render the display with the previous frames faded behind it
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - current display
+    /// * `on` - color of lit pixels
+    /// * `off` - color of unlit pixels
+    pub fn render(&self, display: &ChipDisplay, on: [u8; 3], off: [u8; 3]) -> RgbImage {
+        let mut image = display.to_image(on, off);
+        if !self.enabled {
+            return image;
+        }
+        let current = display.get_display();
+        let width = image.get_width();
+        for (pos, lit) in current.iter().enumerate() {
+            if *lit {
+                continue;
+            }
+            // Most recent previous frame the pixel was lit in
+            let age = self.history.iter().position(|frame| frame.get(pos) == Some(&true));
+            if let Some(age) = age {
+                let alpha = 0.5 * (self.depth - age) as f32 / self.depth as f32;
+                image.set_pixel(pos % width, pos / width, blend(off, on, alpha));
+            }
+        }
+        image
+    }
+}
+
+/// Color ghost pixels are blended towards by default, a pale blue that
+/// stands apart from the usual palettes
+pub const GHOST_COLOR: [u8; 3] = [120, 170, 255];
+
+/// A recorded run of a ROM played back faintly behind live play, so a
+/// speedrunner can race their best attempt
+///
+/// The ghost is a second system running the same ROM with the keys of
+/// a demo file, kept one frame at a time in step with the live game by
+/// calling `advance` once per live frame. When the recording ends or
+/// its program exits the ghost stays on its last frame.
+pub struct GhostReplay {
+    /// True if the ghost should be drawn
+    pub enabled: bool,
+    /// Color ghost pixels are blended towards
+    pub color: [u8; 3],
+    /// Strength of the ghost from 0 (invisible) to 1 (solid)
+    pub alpha: f32,
+    /// System replaying the recording
+    sys: ChipSystem,
+    /// Keys recorded in the earlier run
+    inputs: DemoInput,
+    /// Opcodes run per frame, the same as the live game
+    cycles: u32,
+    /// True once the ghost's program exited or failed
+    finished: bool
+}
+
+impl GhostReplay {
+    /// Initialize an enabled ghost at the start of a recording, warnings
+    /// about the ROM are left to the live game which loads the same one
+    ///
+    /// # Arguments
+    ///
+    /// * `rom` - ROM the recording was made with
+    /// * `inputs` - keys pressed during the recording
+    /// * `cycles` - opcodes run per frame
+    pub fn init(rom: Vec<u8>, inputs: DemoInput, cycles: u32) -> Result<Self, RomError> {
+        let mut sys = ChipSystem::init();
+        sys.load_rom(rom)?;
+        Ok(GhostReplay {
+            enabled: true,
+            color: GHOST_COLOR,
+            alpha: 0.35,
+            sys,
+            inputs,
+            cycles,
+            finished: false
+        })
+    }
+
+    /// Get the system replaying the recording, for setting quirks or a
+    /// random seed to match the recorded run
+    pub fn system(&mut self) -> &mut ChipSystem {
+        &mut self.sys
+    }
+
+    /// Returns true once the ghost stopped on its last frame
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Run the ghost for one frame, call once per live frame
+    pub fn advance(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.inputs.apply(self.sys.get_frame(), &mut self.sys.keyboard);
+        if self.sys.run_frame(self.cycles).is_err() || self.sys.get_exit().is_some() {
+            self.finished = true;
+        }
+    }
+
+    /// Render the live display with the ghost composited behind it,
+    /// pixels lit only by the ghost are drawn in a faint ghost color
+    ///
+    /// # Arguments
+    ///
+    /// * `display` - live display
+    /// * `on` - color of lit pixels
+    /// * `off` - color of unlit pixels
+    pub fn render(&self, display: &ChipDisplay, on: [u8; 3], off: [u8; 3]) -> RgbImage {
+        let mut image = display.to_image(on, off);
+        if !self.enabled {
+            return image;
+        }
+        let width = image.get_width();
+        let live = display.get_display();
+        let ghost = self.sys.display.get_display();
+        for (pos, lit) in ghost.iter().enumerate() {
+            if *lit && !live[pos] {
+                image.set_pixel(pos % width, pos / width, blend(off, self.color, self.alpha));
+            }
+        }
+        image
+    }
+}
+
+/// Blend two colors, `alpha` of 0 gives `from` and 1 gives `to`
+fn blend(from: [u8; 3], to: [u8; 3], alpha: f32) -> [u8; 3] {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * alpha) as u8;
+    [mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2])]
+}
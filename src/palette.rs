@@ -0,0 +1,67 @@
+/// Named palettes offered by menus, the first is the default
+pub const PRESETS: [(&str, Palette); 6] = [
+    ("white", Palette { on: [255, 255, 255], off: [0, 0, 0] }),
+    ("green", Palette { on: [51, 255, 102], off: [8, 24, 12] }),
+    ("amber", Palette { on: [255, 176, 0], off: [26, 16, 0] }),
+    ("lcd", Palette { on: [15, 56, 15], off: [155, 188, 15] }),
+    ("paper", Palette { on: [0, 0, 0], off: [240, 240, 230] }),
+    // Yellow on black, the highest contrast pairing for low vision
+    ("contrast", Palette { on: [255, 255, 0], off: [0, 0, 0] })
+];
+
+/// Colors used to draw the chip8 display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Color of lit pixels
+    pub on: [u8; 3],
+    /// Color of unlit pixels
+    pub off: [u8; 3]
+}
+
+impl Default for Palette {
+    /// White pixels on a black background
+    fn default() -> Self {
+        Palette {
+            on: [255, 255, 255],
+            off: [0, 0, 0]
+        }
+    }
+}
+
+impl Palette {
+    /// Create a palette from two colors
+    ///
+    /// # Arguments
+    ///
+    /// * `on` - color of lit pixels
+    /// * `off` - color of unlit pixels
+    pub fn init(on: [u8; 3], off: [u8; 3]) -> Self {
+        Palette { on, off }
+    }
+
+    /// Find a named preset
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the preset, see `PRESETS`
+    pub fn preset(name: &str) -> Option<Palette> {
+        PRESETS.iter()
+            .find(|(preset, _)| *preset == name)
+            .map(|(_, palette)| *palette)
+    }
+
+    /// Format a color as a `#rrggbb` hex string
+    pub fn hex(color: [u8; 3]) -> String {
+        format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+    }
+
+    /// Parse a `#rrggbb` or `rrggbb` hex color
+    pub fn parse_hex(value: &str) -> Option<[u8; 3]> {
+        let hex = value.trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some([channel(0)?, channel(2)?, channel(4)?])
+    }
+}
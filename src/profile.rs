@@ -0,0 +1,120 @@
+// Local Modules Use //
+use super::ChipSystem;
+use super::fonts::Fontset;
+use super::quirks::Quirks;
+use super::variant::Variant;
+
+/// Everything a historical platform did differently, so a program can
+/// be run the way it was written without knowing the single quirks
+///
+/// `apply` sets up a system, the speed is left to the frontend since it
+/// runs the frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    /// Name as given to `--profile`
+    pub name: &'static str,
+    /// Platform the profile stands in for
+    pub description: &'static str,
+    /// Instruction set decoded
+    pub variant: Variant,
+    /// Interpreter quirks
+    pub quirks: Quirks,
+    /// Opcodes run per frame
+    pub cycles: u32,
+    /// True for the 64 KB XO-CHIP address space
+    pub extended_memory: bool,
+    /// Font loaded into memory
+    pub fontset: Fontset
+}
+
+/// Every profile, oldest platform first
+pub const PROFILES: [Profile; 4] = [
+    Profile {
+        name: "vip",
+        description: "COSMAC VIP, the original 1977 interpreter",
+        variant: Variant::Chip8,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            load_store_incr_i: true,
+            jump_uses_vx: false,
+            vf_reset: true,
+            clip_sprites: true,
+            add_i_sets_vf: false
+        },
+        cycles: 15,
+        extended_memory: false,
+        fontset: Fontset::Vip
+    },
+    Profile {
+        name: "chip48",
+        description: "CHIP-48 on the HP 48 calculators",
+        variant: Variant::Chip8,
+        quirks: Quirks {
+            shift_uses_vy: false,
+            load_store_incr_i: false,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            add_i_sets_vf: false
+        },
+        cycles: 30,
+        extended_memory: false,
+        fontset: Fontset::Schip
+    },
+    Profile {
+        name: "schip",
+        description: "SUPER-CHIP 1.1 on the HP 48 calculators",
+        variant: Variant::Schip,
+        quirks: Quirks {
+            shift_uses_vy: false,
+            load_store_incr_i: false,
+            jump_uses_vx: true,
+            vf_reset: false,
+            clip_sprites: true,
+            add_i_sets_vf: false
+        },
+        cycles: 30,
+        extended_memory: false,
+        fontset: Fontset::Schip
+    },
+    Profile {
+        name: "xochip",
+        description: "XO-CHIP as run by Octo",
+        variant: Variant::XoChip,
+        quirks: Quirks {
+            shift_uses_vy: true,
+            load_store_incr_i: true,
+            jump_uses_vx: false,
+            vf_reset: false,
+            clip_sprites: false,
+            add_i_sets_vf: false
+        },
+        cycles: 1000,
+        extended_memory: true,
+        fontset: Fontset::Octo
+    }
+];
+
+impl Profile {
+    /// Find a profile by its name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the profile, see `PROFILES`
+    pub fn from_name(name: &str) -> Option<&'static Profile> {
+        PROFILES.iter().find(|profile| profile.name == name)
+    }
+
+    /// Set up a system the way the platform ran, call before the ROM is
+    /// loaded since the font and the memory size change
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to set up
+    pub fn apply(&self, sys: &mut ChipSystem) {
+        sys.variant = self.variant;
+        sys.quirks = self.quirks;
+        sys.ram.set_extended(self.extended_memory);
+        sys.ram.set_fontset(self.fontset);
+    }
+}
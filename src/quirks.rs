@@ -0,0 +1,198 @@
+// Standard Library Modules //
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::config::config_dir;
+
+/// Behaviour differences between chip8 interpreters
+///
+/// Programs were written against different interpreters which did not
+/// agree on how a few opcodes behave, each flag picks one behaviour.
+/// With every flag off the system behaves like CHIP-48/SCHIP.
+///
+/// Changed from a config file with one `name = on|off` line per flag,
+/// for example `vf_reset = on`. Flags not named keep their value and
+/// `#` starts a comment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    /// 8XY6/8XYE shift `Vy` and store the result in `Vx`
+    /// instead of shifting `Vx` in place
+    pub shift_uses_vy: bool,
+    /// FX55/FX65 leave `I` incremented by `x + 1`
+    pub load_store_incr_i: bool,
+    /// BNNN jumps to `NNN + Vx` (BXNN) instead of `NNN + V0`
+    pub jump_uses_vx: bool,
+    /// 8XY1/8XY2/8XY3 reset `Vf` to 0
+    pub vf_reset: bool,
+    /// Sprites are clipped at the screen edges instead of wrapping
+    pub clip_sprites: bool,
+    /// FX1E sets `Vf` to 1 when `I` goes past 0xFFF and to 0 otherwise,
+    /// as the Amiga interpreter did (needed by Spacefight 2091!)
+    pub add_i_sets_vf: bool
+}
+
+/// Names of every quirk flag, in the order used by `Quirks::from_bits`
+pub const QUIRK_NAMES: [&str; 6] = [
+    "shift_uses_vy",
+    "load_store_incr_i",
+    "jump_uses_vx",
+    "vf_reset",
+    "clip_sprites",
+    "add_i_sets_vf"
+];
+
+/// A problem in a quirks config file
+#[derive(Debug, Clone)]
+pub struct QuirkError {
+    /// Line number starting at 1
+    line: usize,
+    /// What is wrong with the line
+    message: String
+}
+
+impl fmt::Display for QuirkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for QuirkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+impl Quirks {
+    /// Build a set of quirks from a bit mask, bit N enables the
+    /// flag named by `QUIRK_NAMES[N]`
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - bit mask of enabled flags
+    pub fn from_bits(bits: u8) -> Self {
+        Quirks {
+            shift_uses_vy: bits & 0x01 != 0,
+            load_store_incr_i: bits & 0x02 != 0,
+            jump_uses_vx: bits & 0x04 != 0,
+            vf_reset: bits & 0x08 != 0,
+            clip_sprites: bits & 0x10 != 0,
+            add_i_sets_vf: bits & 0x20 != 0
+        }
+    }
+
+    /// Convert the set of quirks into a bit mask, see `from_bits`
+    pub fn to_bits(&self) -> u8 {
+        (self.shift_uses_vy as u8)
+            | (self.load_store_incr_i as u8) << 1
+            | (self.jump_uses_vx as u8) << 2
+            | (self.vf_reset as u8) << 3
+            | (self.clip_sprites as u8) << 4
+            | (self.add_i_sets_vf as u8) << 5
+    }
+
+    /// Names of the enabled flags
+    pub fn enabled(&self) -> Vec<&'static str> {
+        let bits = self.to_bits();
+        QUIRK_NAMES.iter()
+            .enumerate()
+            .filter(|(index, _)| bits & (1 << index) != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// Check if a flag is enabled, None for an unknown name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the flag, see `QUIRK_NAMES`
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let index = QUIRK_NAMES.iter().position(|quirk| *quirk == name)?;
+        Some(self.to_bits() & (1 << index) != 0)
+    }
+
+    /// Enable or disable a flag, returning false for an unknown name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the flag, see `QUIRK_NAMES`
+    /// * `enabled` - true to enable the flag
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let index = match QUIRK_NAMES.iter().position(|quirk| *quirk == name) {
+            Some(index) => index,
+            None => return false
+        };
+        let bits = match enabled {
+            true => self.to_bits() | 1 << index,
+            false => self.to_bits() & !(1 << index)
+        };
+        *self = Quirks::from_bits(bits);
+        true
+    }
+
+    /// Default location of the quirks config, `quirks` in the
+    /// configuration directory, None if it can not be found
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("quirks"))
+    }
+
+    /// Load the default flags changed by a config file, a missing file
+    /// gives the defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - config file to load
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut quirks = Quirks::default();
+        match fs::read_to_string(path) {
+            Ok(text) => quirks.apply(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e)
+        }
+        Ok(quirks)
+    }
+
+    /// Apply the flags in a config file's text
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of a config file
+    pub fn apply(&mut self, text: &str) -> Result<(), QuirkError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| QuirkError { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(error(format!("expected 'name = on|off', found '{}'", line)))
+            };
+            let enabled = match value {
+                "on" | "true" => true,
+                "off" | "false" => false,
+                _ => return Err(error(format!("expected on or off for {}, found '{}'", name, value)))
+            };
+            if !self.set(name, enabled) {
+                return Err(error(format!("unknown quirk '{}', expected one of {}", name, QUIRK_NAMES.join(", "))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Format the flags as a config file that reproduces them
+    pub fn to_config(&self) -> String {
+        let mut out = String::new();
+        for name in QUIRK_NAMES.iter() {
+            let value = match self.get(name) {
+                Some(true) => "on",
+                _ => "off"
+            };
+            out.push_str(&format!("{} = {}\n", name, value));
+        }
+        out
+    }
+}
@@ -0,0 +1,47 @@
+// Modules From Crates.io //
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+/// Gives the random bytes of the CXKK opcode, for embedders that want
+/// their own generator, see `ChipSystem::set_random_source`
+pub trait RandomSource {
+    /// Get the next random byte
+    fn random_byte(&mut self) -> u8;
+}
+
+/// A generator started from a known seed, the same seed always gives
+/// the same bytes
+pub struct SeededRandom {
+    /// Seed the generator started from
+    seed: u64,
+    /// The generator itself
+    rng: StdRng
+}
+
+impl SeededRandom {
+    /// Initialize a generator from a seed
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed of the generator
+    pub fn init(seed: u64) -> Self {
+        SeededRandom { seed, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    /// Initialize a generator from a seed picked at random, which can
+    /// be read back to repeat a run
+    pub fn from_entropy() -> Self {
+        SeededRandom::init(rand::random())
+    }
+
+    /// Get the seed the generator started from
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RandomSource for SeededRandom {
+    fn random_byte(&mut self) -> u8 {
+        self.rng.gen()
+    }
+}
@@ -0,0 +1,176 @@
+// Standard Library Modules //
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Local Modules Use //
+use super::config::config_dir;
+use super::palette::Palette;
+use super::quirks::Quirks;
+
+/// Number of ROMs remembered by default
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// A ROM that was opened recently and the settings it was run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentRom {
+    /// Path the ROM was opened from
+    pub path: String,
+    /// Hash of the ROM contents, see `rom_hash`
+    pub hash: u64,
+    /// Opcodes executed per frame
+    pub cycles: u32,
+    /// Colors the display was drawn with
+    pub palette: Palette,
+    /// Interpreter quirks
+    pub quirks: Quirks
+}
+
+impl RecentRom {
+    /// File name of the ROM without its directory, for menus
+    pub fn name(&self) -> &str {
+        Path::new(&self.path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.path)
+    }
+
+    /// Format the entry as a tab separated line
+    fn to_line(&self) -> String {
+        format!(
+            "{:016x}\t{}\t{:02x}\t{}\t{}\t{}",
+            self.hash, self.cycles, self.quirks.to_bits(),
+            Palette::hex(self.palette.on), Palette::hex(self.palette.off), self.path
+        )
+    }
+
+    /// Parse a line written by `to_line`, None if it is malformed
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(6, '\t');
+        let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let cycles = fields.next()?.parse().ok()?;
+        let quirks = Quirks::from_bits(u8::from_str_radix(fields.next()?, 16).ok()?);
+        let on = Palette::parse_hex(fields.next()?)?;
+        let off = Palette::parse_hex(fields.next()?)?;
+        let path = fields.next()?.to_string();
+        Some(RecentRom {
+            path,
+            hash,
+            cycles,
+            palette: Palette::init(on, off),
+            quirks
+        })
+    }
+}
+
+/// List of recently opened ROMs, most recent first
+///
+/// The list is stored as one tab separated line per ROM so it can be
+/// shared by the command line tool, launchers and the pause menu.
+#[derive(Debug, Clone)]
+pub struct RecentRoms {
+    /// Remembered ROMs, most recent first
+    entries: Vec<RecentRom>,
+    /// Most ROMs remembered
+    limit: usize
+}
+
+impl RecentRoms {
+    /// Initialize an empty list
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - most ROMs to remember
+    pub fn init(limit: usize) -> Self {
+        RecentRoms {
+            entries: Vec::new(),
+            limit
+        }
+    }
+
+    /// Default location of the list, `recent` in the configuration
+    /// directory, None if it can not be found
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("recent"))
+    }
+
+    /// Load a list from disk, a missing file gives an empty list and
+    /// malformed lines are skipped
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - file to load
+    /// * `limit` - most ROMs to remember
+    pub fn load(path: &Path, limit: usize) -> io::Result<Self> {
+        let mut recent = RecentRoms::init(limit);
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(recent),
+            Err(e) => return Err(e)
+        };
+        recent.entries = contents.lines()
+            .filter_map(RecentRom::from_line)
+            .take(limit)
+            .collect();
+        Ok(recent)
+    }
+
+    /// Save the list to disk, creating its directory if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - file to write
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut contents = String::new();
+        for entry in &self.entries {
+            contents.push_str(&entry.to_line());
+            contents.push('\n');
+        }
+        fs::write(path, contents)
+    }
+
+    /// Get the remembered ROMs, most recent first
+    pub fn get_entries(&self) -> &[RecentRom] {
+        &self.entries
+    }
+
+    /// Get a remembered ROM by its position in the list
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - position, 0 is the most recent
+    pub fn get(&self, index: usize) -> Option<&RecentRom> {
+        self.entries.get(index)
+    }
+
+    /// Remember a ROM as the most recent one, replacing an older entry
+    /// for the same path and dropping the oldest entry if the list is
+    /// full
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - ROM that was opened
+    pub fn push(&mut self, entry: RecentRom) {
+        self.entries.retain(|old| old.path != entry.path);
+        self.entries.insert(0, entry);
+        self.entries.truncate(self.limit);
+    }
+}
+
+/// FNV-1a hash of ROM contents, used to notice when a remembered path
+/// now holds a different ROM
+///
+/// # Arguments
+///
+/// * `rom` - rom contents
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in rom {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -1,10 +1,35 @@
 
+// Standard Library Modules //
+use std::time::Instant;
+
+// Local Modules Use //
+use super::watchpoint::{WatchTarget, Watchpoints};
+
+/// How the delay and sound timers count down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerMode {
+    /// One tick each time `decr_d`/`decr_s` are called, once per frame
+    /// by `ChipSystem::run_frame`. Runs are deterministic but a timer
+    /// read mid frame only changes at frame boundaries.
+    Frame,
+    /// Ticks at 60 Hz of host time, a timer read at any moment gives
+    /// the exact value for the time elapsed since it was set no matter
+    /// how many instructions run per frame. The frame ticks are ignored.
+    RealTime
+}
+
+/// Number of 60 Hz timer ticks since an instant, capped at 255
+fn ticks_since(start: Instant) -> u8 {
+    (start.elapsed().as_nanos() * 60 / 1_000_000_000).min(255) as u8
+}
+
 /// A struct representing the chip8 registers
+#[derive(Debug, Clone)]
 pub struct ChipRegisters {
     /// General purpose registers
-    gp_reg: Vec<u8>, 
+    gp_reg: [u8; 16],
     /// Address call stack
-    stack: Vec<u16>, 
+    stack: [u16; 16],
     /// Register I, address storage
     i_reg: u16,      
     /// Delay timer register
@@ -15,13 +40,25 @@ pub struct ChipRegisters {
     pc_reg: u16,     
     /// Stack pointer
     sp_reg: usize,      
+    /// Deepest the stack has been, see `get_max_depth`
+    max_sp: usize,
+    /// Set when the sound timer goes from 0 to non-zero
+    sound_started: bool,
+    /// How the timers count down
+    timer_mode: TimerMode,
+    /// When the delay timer was last set, used in real time mode
+    d_set: Instant,
+    /// When the sound timer was last set, used in real time mode
+    s_set: Instant,
+    /// Watchpoints on V0-VF and I
+    watchpoints: Watchpoints,
 }
 
 impl ChipRegisters {
     /// Init a Chip8 register struct
     pub fn init() -> Self {
-        let gp_reg = vec![0; 16];
-        let stack = vec![0; 16];
+        let gp_reg = [0; 16];
+        let stack = [0; 16];
         ChipRegisters {
             gp_reg,
             stack,
@@ -30,6 +67,12 @@ impl ChipRegisters {
             s_reg: 0,
             pc_reg: 512,
             sp_reg: 0,
+            max_sp: 0,
+            sound_started: false,
+            timer_mode: TimerMode::Frame,
+            d_set: Instant::now(),
+            s_set: Instant::now(),
+            watchpoints: Watchpoints::init(),
         }
     }
 
@@ -40,6 +83,9 @@ impl ChipRegisters {
     /// * `index` - which general purpose register
     /// * `value` - value to fill register with
     pub fn set_gp(&mut self, index: usize, value: u8) {
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(WatchTarget::Register(index as u8), self.gp_reg[index] as u16, value as u16);
+        }
         self.gp_reg[index] = value;
     }
 
@@ -59,14 +105,8 @@ impl ChipRegisters {
     /// * `index` - which general purpose register
     /// * `value` - u8 value to add to register
     pub fn add_gp(&mut self, index: usize, value: u8) {
-        // self.gp_reg[index] += value;
-        self.gp_reg[index] = {
-            let val = value as u16 + self.gp_reg[index] as u16;
-            match val > 255 {
-                true => (val & 0x00ff) as u8,
-                false => val as u8
-            }
-        };
+        // Through set_gp so watchpoints see the write
+        self.set_gp(index, self.gp_reg[index].wrapping_add(value));
     }
 
     /// Set the value of the I register
@@ -75,9 +115,23 @@ impl ChipRegisters {
     /// 
     /// * `value` - what to put in I register
     pub fn set_i(&mut self, value: u16) {
+        if !self.watchpoints.is_empty() {
+            self.watchpoints.check(WatchTarget::I, self.i_reg, value);
+        }
         self.i_reg = value;
     }
 
+    /// Get the watchpoints on V0-VF and I
+    pub fn get_watchpoints(&self) -> &Watchpoints {
+        &self.watchpoints
+    }
+
+    /// Get the watchpoints on V0-VF and I to change them, every write
+    /// made with `set_gp`, `add_gp` and `set_i` is checked against them
+    pub fn get_watchpoints_mut(&mut self) -> &mut Watchpoints {
+        &mut self.watchpoints
+    }
+
     /// Get the value of the I register
     pub fn get_i(&self) -> u16 {
         self.i_reg
@@ -94,7 +148,7 @@ impl ChipRegisters {
 
     /// Increment the value of the pc register by 2
     pub fn incr_pc(&mut self) {
-        self.pc_reg += 2;
+        self.pc_reg = self.pc_reg.wrapping_add(2);
     }
 
     /// Get the value of the pc register
@@ -102,9 +156,32 @@ impl ChipRegisters {
         self.pc_reg
     }
 
+    /// Get how the timers count down
+    pub fn get_timer_mode(&self) -> TimerMode {
+        self.timer_mode
+    }
+
+    /// Change how the timers count down, the timers keep their current
+    /// values
+    /// 
+    /// # Arguments
+    /// 
+    /// * `mode` - new timer mode
+    pub fn set_timer_mode(&mut self, mode: TimerMode) {
+        let (delay, sound) = (self.get_d(), self.get_s());
+        self.timer_mode = mode;
+        self.d_reg = delay;
+        self.s_reg = sound;
+        self.d_set = Instant::now();
+        self.s_set = self.d_set;
+    }
+
     /// Get the value of the delay register
     pub fn get_d(&self) -> u8 {
-        self.d_reg
+        match self.timer_mode {
+            TimerMode::Frame => self.d_reg,
+            TimerMode::RealTime => self.d_reg.saturating_sub(ticks_since(self.d_set))
+        }
     }
 
     /// Set the value of the delay register
@@ -114,11 +191,15 @@ impl ChipRegisters {
     /// * `value` - what value to put in the pc register
     pub fn set_d(&mut self, value: u8) {
         self.d_reg = value;
+        self.d_set = Instant::now();
     }
 
     /// Get the value of the sound delay register
     pub fn get_s(&self) -> u8 {
-        self.s_reg
+        match self.timer_mode {
+            TimerMode::Frame => self.s_reg,
+            TimerMode::RealTime => self.s_reg.saturating_sub(ticks_since(self.s_set))
+        }
     }
 
     /// Set the value of the sound delay register
@@ -127,38 +208,111 @@ impl ChipRegisters {
     /// 
     /// * `value` - what value to put in the sound register
     pub fn set_s(&mut self, value: u8) {
+        if self.get_s() == 0 && value > 0 {
+            self.sound_started = true;
+        }
         self.s_reg = value;
+        self.s_set = Instant::now();
+    }
+
+    /// Returns true if the sound timer went from 0 to non-zero since
+    /// the last call, so short sounds that start and end between two
+    /// checks of the timer are not missed
+    pub fn take_sound_started(&mut self) -> bool {
+        let started = self.sound_started;
+        self.sound_started = false;
+        started
     }
 
-    /// Decrement the delay register if value is not 0
+    /// Decrement the delay register if value is not 0, does nothing
+    /// in real time mode
     pub fn decr_d(&mut self) {
-        if self.d_reg > 0 {
+        if self.timer_mode == TimerMode::Frame && self.d_reg > 0 {
             self.d_reg -= 1;
         }
     }
 
-    /// Decrement the sound register if value is not 0
+    /// Decrement the sound register if value is not 0, does nothing
+    /// in real time mode
     pub fn decr_s(&mut self) {
-        if self.s_reg > 0 {
+        if self.timer_mode == TimerMode::Frame && self.s_reg > 0 {
             self.s_reg -= 1;
         }
     }
 
-    /// Push a address onto the stack, increment stack pointer
+    /// Replace every register and the stack, for restoring save states.
+    /// Watchpoints are not checked and a non-zero sound timer does not
+    /// count as the sound starting, nothing was executed to change them.
+    ///
+    /// # Arguments
+    ///
+    /// * `gp` - values of V0-VF
+    /// * `i` - value of the I register
+    /// * `pc` - value of the program counter
+    /// * `delay` - value of the delay timer
+    /// * `sound` - value of the sound timer
+    /// * `stack` - addresses on the stack, the oldest call first, at
+    ///   most 16
+    pub fn restore(&mut self, gp: [u8; 16], i: u16, pc: u16, delay: u8, sound: u8, stack: &[u16]) {
+        self.gp_reg = gp;
+        self.i_reg = i;
+        self.pc_reg = pc;
+        self.d_reg = delay;
+        self.s_reg = sound;
+        self.d_set = Instant::now();
+        self.s_set = Instant::now();
+        self.sound_started = false;
+        self.sp_reg = stack.len().min(self.stack.len());
+        self.stack[..self.sp_reg].copy_from_slice(&stack[..self.sp_reg]);
+        self.max_sp = self.max_sp.max(self.sp_reg);
+    }
+
+    /// Push a address onto the stack, increment stack pointer.
+    /// Returns false and leaves the stack alone if it is full.
     /// 
     /// # Arguments
     /// 
     /// * `addr` - address to push to the stack
-    pub fn push_stack(&mut self, addr: u16) {
-        self.stack[self.sp_reg as usize] = addr;
+    pub fn push_stack(&mut self, addr: u16) -> bool {
+        if self.sp_reg >= self.stack.len() {
+            return false;
+        }
+        self.stack[self.sp_reg] = addr;
         self.sp_reg += 1;
+        self.max_sp = self.max_sp.max(self.sp_reg);
+        true
+    }
+
+    /// Pop an address from the stack, decrementing sp, None if the
+    /// stack is empty
+    pub fn pop_stack(&mut self) -> Option<u16> {
+        self.sp_reg = self.sp_reg.checked_sub(1)?;
+        Some(self.stack[self.sp_reg])
+    }
+
+    /// Get the address on top of the stack without popping it,
+    /// None if the stack is empty
+    pub fn peek_stack(&self) -> Option<u16> {
+        match self.sp_reg {
+            0 => None,
+            sp => Some(self.stack[sp - 1])
+        }
+    }
+
+    /// Get the addresses on the stack, the oldest call first
+    pub fn get_stack(&self) -> &[u16] {
+        &self.stack[..self.sp_reg.min(self.stack.len())]
+    }
+
+    /// Get the value of the stack pointer
+    pub fn get_sp(&self) -> usize {
+        self.sp_reg
     }
 
-    /// Pop an address from the stack, decrementing sp
-    pub fn pop_stack(&mut self) -> u16 {
-        self.sp_reg -= 1;
-        let addr = self.stack[self.sp_reg];
-        return addr;
+    /// Get the most calls that have been nested at once, a program
+    /// getting close to 16 is about to overflow the stack
+    pub fn get_max_depth(&self) -> usize {
+        self.max_sp
     }
 
     /// print information on all registers
@@ -166,8 +320,8 @@ impl ChipRegisters {
         println!("========BEGIN CHIP8 REGISTERS========");
         println!("General: {:?}", self.gp_reg);
         println!("      I: {:#?}", self.i_reg);
-        println!("  Delay: {:#?}", self.d_reg);
-        println!("  Sound: {:#?}", self.s_reg);
+        println!("  Delay: {:#?}", self.get_d());
+        println!("  Sound: {:#?}", self.get_s());
         println!("PCountr: {:#?}", self.pc_reg);
         println!("SPointr: {:#?}", self.sp_reg);
         println!("  Stack: {:?}", self.stack);
@@ -0,0 +1,132 @@
+// Standard Library Modules //
+use std::fmt::Write;
+
+// Local Modules Use //
+use super::attract::{DemoEvent, DemoInput};
+use super::keyboard::ChipKeyboard;
+
+/// A recorded session: the seed of the random opcode, the speed and
+/// every keypad press and release with the frame it happened before
+///
+/// Replay files are demo files with two settings in front,
+///
+/// ```text
+/// seed 1234
+/// cycles 10
+/// 120 5 down
+/// 131 5 up
+/// ```
+///
+/// Running the same ROM seeded the same, at the same speed, with the
+/// keys pressed on the same frames draws the same frames, so a replay
+/// plays a session back exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    /// Seed of the random opcode
+    pub seed: u64,
+    /// Opcodes run per frame
+    pub cycles: u32,
+    /// Keypad events in the order they happened
+    pub events: Vec<DemoEvent>
+}
+
+impl Replay {
+    /// Parse the text of a replay file
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of the replay file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut seed = None;
+        let mut cycles = None;
+        let mut events = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || format!("line {}: expected 'seed N', 'cycles N' or 'frame key down|up', found '{}'", index + 1, line);
+            match line.split_whitespace().collect::<Vec<&str>>().as_slice() {
+                ["seed", value] => seed = Some(value.parse::<u64>().map_err(|_| error())?),
+                ["cycles", value] => cycles = Some(value.parse::<u32>().ok().filter(|cycles| *cycles > 0).ok_or_else(error)?),
+                [_, _, _] => events.extend(DemoInput::parse(line).map_err(|_| error())?.get_events()),
+                _ => return Err(error())
+            }
+        }
+        match (seed, cycles) {
+            (Some(seed), Some(cycles)) => Ok(Replay { seed, cycles, events }),
+            _ => Err(String::from("a replay needs 'seed N' and 'cycles N' lines"))
+        }
+    }
+
+    /// Get the keypad events as demo input, to press the keys while the
+    /// replay runs
+    pub fn input(&self) -> DemoInput {
+        DemoInput::from_events(self.events.clone())
+    }
+
+    /// Format the replay as the text of a replay file
+    pub fn to_text(&self) -> String {
+        let mut out = format!("seed {}\ncycles {}\n", self.seed, self.cycles);
+        for event in self.events.iter() {
+            // Writing to a String can not fail
+            let _ = writeln!(out, "{} {:X} {}", event.frame, event.key, if event.down { "down" } else { "up" });
+        }
+        out
+    }
+}
+
+/// Records the keypad as a session runs, noting each key that changed
+/// since the frame before
+#[derive(Debug, Clone)]
+pub struct InputRecorder {
+    /// Keys down when last looked at
+    keys: [bool; 16],
+    /// Events recorded so far
+    events: Vec<DemoEvent>
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        InputRecorder::init()
+    }
+}
+
+impl InputRecorder {
+    /// Initialize a recorder with every key up
+    pub fn init() -> Self {
+        InputRecorder { keys: [false; 16], events: Vec::new() }
+    }
+
+    /// Look at the keypad before a frame runs, recording the keys
+    /// pressed or released since the last look
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - number of frames run so far
+    /// * `keyboard` - keypad the frame will see
+    pub fn record(&mut self, frame: u64, keyboard: &ChipKeyboard) {
+        for key in 0..16u8 {
+            let down = keyboard.get_key(key);
+            if down != self.keys[key as usize] {
+                self.keys[key as usize] = down;
+                self.events.push(DemoEvent { frame, key, down });
+            }
+        }
+    }
+
+    /// Get the events recorded so far
+    pub fn get_events(&self) -> &[DemoEvent] {
+        &self.events
+    }
+
+    /// Finish the recording
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed the random opcode was given
+    /// * `cycles` - opcodes run per frame
+    pub fn finish(self, seed: u64, cycles: u32) -> Replay {
+        Replay { seed, cycles, events: self.events }
+    }
+}
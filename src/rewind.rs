@@ -0,0 +1,107 @@
+// Standard Library Modules //
+use std::collections::VecDeque;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::timing::TIMER_HZ;
+
+/// Frames between the snapshots kept by `RewindBuffer::init`
+pub const DEFAULT_INTERVAL: u64 = 4;
+
+/// The last few seconds of play, as save states taken every few frames
+/// in a ring buffer, so a player can step back in time
+///
+/// Call `record` after every frame and `rewind` for each step back,
+/// such as while the rewind hotkey is held. Once full the oldest
+/// snapshot is dropped for each new one.
+pub struct RewindBuffer {
+    /// Snapshots from `ChipSystem::save_state`, the newest last
+    states: VecDeque<Vec<u8>>,
+    /// Most snapshots kept
+    capacity: usize,
+    /// Frames between snapshots
+    interval: u64,
+    /// Frames recorded since the last snapshot
+    since: u64,
+    /// True while the newest snapshot is of the frame just recorded
+    fresh: bool
+}
+
+impl RewindBuffer {
+    /// Initialize a buffer holding a number of seconds of play, a
+    /// snapshot every `DEFAULT_INTERVAL` frames
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - seconds of play to keep
+    pub fn init(seconds: u64) -> Self {
+        RewindBuffer::init_interval(seconds, DEFAULT_INTERVAL)
+    }
+
+    /// Initialize a buffer taking snapshots at another interval
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - seconds of play to keep
+    /// * `interval` - frames between snapshots, at least 1
+    pub fn init_interval(seconds: u64, interval: u64) -> Self {
+        let interval = interval.max(1);
+        let capacity = (seconds * TIMER_HZ as u64).div_ceil(interval).max(1) as usize;
+        RewindBuffer { states: VecDeque::with_capacity(capacity), capacity, interval, since: 0, fresh: false }
+    }
+
+    /// Get the number of snapshots held
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Returns true if there is nothing to rewind to
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Count a frame, taking a snapshot if one is due
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system that just ran a frame
+    pub fn record(&mut self, sys: &ChipSystem) {
+        self.since += 1;
+        self.fresh = self.since >= self.interval;
+        if !self.fresh {
+            return;
+        }
+        self.since = 0;
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(sys.save_state());
+    }
+
+    /// Step back to the newest snapshot and drop it, returning false
+    /// if there was none left
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to restore into
+    pub fn rewind(&mut self, sys: &mut ChipSystem) -> bool {
+        self.since = 0;
+        // Going back to the frame on screen would not move
+        if self.fresh {
+            self.states.pop_back();
+            self.fresh = false;
+        }
+        match self.states.pop_back() {
+            // Snapshots come from save_state so they always load
+            Some(state) => sys.load_state(&state).is_ok(),
+            None => false
+        }
+    }
+
+    /// Drop every snapshot, such as when the ROM is restarted
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.since = 0;
+        self.fresh = false;
+    }
+}
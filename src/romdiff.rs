@@ -0,0 +1,95 @@
+// Local Modules Use //
+use super::disasm::mnemonic;
+
+/// Bytes of unchanged context disassembled around each change
+const CONTEXT: usize = 4;
+
+/// Changes closer together than this are reported as one range
+const MERGE_GAP: usize = 4;
+
+/// Address ROMs are loaded at
+const START: usize = 0x200;
+
+/// A run of bytes that differ between two ROMs, as offsets into them.
+/// Bytes past the end of the shorter ROM count as different.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRange {
+    /// First differing byte
+    pub start: usize,
+    /// One past the last differing byte
+    pub end: usize
+}
+
+/// Find the byte ranges that differ between two ROMs, comparing the
+/// bytes at the same address. Nearby changes are merged into one range.
+///
+/// # Arguments
+///
+/// * `a` - first ROM
+/// * `b` - second ROM
+pub fn diff_ranges(a: &[u8], b: &[u8]) -> Vec<DiffRange> {
+    let len = a.len().max(b.len());
+    let mut ranges: Vec<DiffRange> = Vec::new();
+    for offset in 0..len {
+        if a.get(offset) == b.get(offset) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if offset - last.end < MERGE_GAP => last.end = offset + 1,
+            _ => ranges.push(DiffRange { start: offset, end: offset + 1 })
+        }
+    }
+    ranges
+}
+
+/// Render the differences between two ROMs, each range of changed
+/// bytes is shown with the instructions around it disassembled side by
+/// side and changed lines marked with `*`
+///
+/// # Arguments
+///
+/// * `a` - first ROM
+/// * `b` - second ROM
+pub fn render_diff(a: &[u8], b: &[u8]) -> String {
+    let ranges = diff_ranges(a, b);
+    let mut out = String::new();
+    if ranges.is_empty() {
+        out.push_str("ROMs are identical\n");
+        return out;
+    }
+    let changed: usize = ranges.iter().map(|range| range.end - range.start).sum();
+    out.push_str(&format!(
+        "{} changed bytes in {} {} ({} bytes vs {} bytes)\n",
+        changed, ranges.len(), if ranges.len() == 1 { "range" } else { "ranges" }, a.len(), b.len()
+    ));
+    let side = |rom: &[u8], offset: usize| -> String {
+        match (rom.get(offset), rom.get(offset + 1)) {
+            (Some(high), Some(low)) => {
+                let opcode = (*high as u16) << 8 | *low as u16;
+                format!("{:04X}  {:<16}", opcode, mnemonic(opcode))
+            },
+            (Some(high), None) => format!("{:02X}    {:<16}", high, "DB"),
+            _ => format!("{:<22}", "-")
+        }
+    };
+    for range in ranges {
+        out.push_str(&format!(
+            "\n@@ 0x{:03X}-0x{:03X} @@\n",
+            range.start + START, range.end + START - 1
+        ));
+        // Opcodes are disassembled on even addresses, like the
+        // interpreter fetches them
+        let first = range.start.saturating_sub(CONTEXT) & !1;
+        let last = (range.end + CONTEXT).min(a.len().max(b.len()));
+        for offset in (first..last).step_by(2) {
+            let differs = a.get(offset) != b.get(offset) || a.get(offset + 1) != b.get(offset + 1);
+            let line = format!(
+                "{} 0x{:03X}  {} | {}",
+                if differs { "*" } else { " " }, offset + START, side(a, offset), side(b, offset)
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -0,0 +1,94 @@
+// Standard Library Modules //
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Number of program counters kept for runaway reports
+pub const PC_HISTORY_LEN: usize = 16;
+
+/// Why execution was judged to have escaped the program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunawayReason {
+    /// The program counter left the addresses the ROM was loaded to,
+    /// start included and end excluded
+    OutsideRom(usize, usize),
+    /// The opcode fetched was 0000, memory nothing was written to
+    ZeroOpcode
+}
+
+impl fmt::Display for RunawayReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunawayReason::OutsideRom(start, end) => write!(
+                f, "outside the ROM loaded at 0x{:03x}..0x{:03x}", start, end
+            ),
+            RunawayReason::ZeroOpcode => write!(f, "on an uninitialized 0000 word")
+        }
+    }
+}
+
+/// Execution that escaped the program, with the program counters that
+/// led there so the jump or return that went wrong can be found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Runaway {
+    /// Program counter that escaped
+    pub pc: u16,
+    /// Why it counts as escaped
+    pub reason: RunawayReason,
+    /// Program counters of the opcodes run before it, oldest first
+    pub history: Vec<u16>
+}
+
+impl fmt::Display for Runaway {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "execution escaped program at 0x{:03x}, {}", self.pc, self.reason)?;
+        if !self.history.is_empty() {
+            let pcs: Vec<String> = self.history.iter().map(|pc| format!("0x{:03x}", pc)).collect();
+            write!(f, "; recent PCs {}", pcs.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The last few instructions executed with their program counters,
+/// oldest first
+#[derive(Debug, Clone, Default)]
+pub struct PcHistory {
+    /// Program counters and opcodes, at most `PC_HISTORY_LEN`
+    pcs: VecDeque<(u16, u16)>
+}
+
+impl PcHistory {
+    /// Initialize an empty history
+    pub fn init() -> Self {
+        PcHistory { pcs: VecDeque::with_capacity(PC_HISTORY_LEN) }
+    }
+
+    /// Record an instruction, dropping the oldest once full
+    ///
+    /// # Arguments
+    ///
+    /// * `pc` - program counter of the opcode about to run
+    /// * `opcode` - opcode about to run
+    pub fn record(&mut self, pc: u16, opcode: u16) {
+        if self.pcs.len() == PC_HISTORY_LEN {
+            self.pcs.pop_front();
+        }
+        self.pcs.push_back((pc, opcode));
+    }
+
+    /// Get the recorded program counters, oldest first
+    pub fn get_pcs(&self) -> Vec<u16> {
+        self.pcs.iter().map(|(pc, _)| *pc).collect()
+    }
+
+    /// Get the recorded program counters with the opcodes run there,
+    /// oldest first
+    pub fn get_instructions(&self) -> Vec<(u16, u16)> {
+        self.pcs.iter().copied().collect()
+    }
+
+    /// Forget every recorded program counter
+    pub fn clear(&mut self) {
+        self.pcs.clear();
+    }
+}
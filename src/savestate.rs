@@ -0,0 +1,369 @@
+// Standard Library Modules //
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::display::PLANES;
+use super::memory::{EXTENDED_RAM_SIZE, RAM_SIZE};
+use super::quirks::Quirks;
+use super::sound::AudioPattern;
+use super::timing::TIMING_MODES;
+use super::variant::VARIANTS;
+
+/// First bytes of every save state file
+pub const STATE_MAGIC: [u8; 4] = *b"C8ST";
+
+/// Version of the save state layout written by `save_state`, states of
+/// other versions are refused rather than misread
+pub const STATE_VERSION: u16 = 2;
+
+/// Reasons a save state can not be restored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with `STATE_MAGIC`
+    NotAState,
+    /// The state was written by another version of the layout
+    Version(u16),
+    /// The data ends before the state does
+    Truncated,
+    /// A value in the state is out of range
+    Invalid(&'static str)
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateError::NotAState => write!(f, "not a clip8 save state"),
+            StateError::Version(version) => write!(f, "save state version {} is not supported, expected {}", version, STATE_VERSION),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::Invalid(what) => write!(f, "save state has an invalid {}", what)
+        }
+    }
+}
+
+impl error::Error for StateError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// Take a snapshot of everything a program can observe: registers,
+/// stack, timers and their progress to the next tick, the timing mode
+/// and the machine cycles left in the frame, memory, both
+/// display planes, the key wait of FX0A, the RPL flags, the audio
+/// pattern, the variant and quirks and the frame count
+///
+/// The layout is a versioned little endian binary format starting
+/// with `STATE_MAGIC` and `STATE_VERSION`. Keys held, frame callbacks,
+/// statistics and a seeded random generator are not part of it.
+///
+/// # Arguments
+///
+/// * `sys` - system to snapshot
+pub fn save_state(sys: &ChipSystem) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&STATE_MAGIC);
+    out.extend_from_slice(&STATE_VERSION.to_le_bytes());
+
+    let variant = VARIANTS.iter().position(|(variant, _)| *variant == sys.variant).unwrap_or(0);
+    out.push(variant as u8);
+    out.push(sys.quirks.to_bits());
+    out.extend_from_slice(&sys.frames.to_le_bytes());
+
+    let reg = &sys.registers;
+    out.extend((0..16).map(|index| reg.get_gp(index)));
+    out.extend_from_slice(&reg.get_i().to_le_bytes());
+    out.extend_from_slice(&reg.get_pc().to_le_bytes());
+    out.push(reg.get_d());
+    out.push(reg.get_s());
+    let stack = reg.get_stack();
+    out.push(stack.len() as u8);
+    for addr in stack {
+        out.extend_from_slice(&addr.to_le_bytes());
+    }
+    out.extend_from_slice(&sys.timer_clock.get_ips().to_le_bytes());
+    out.extend_from_slice(&sys.timer_clock.get_phase().to_le_bytes());
+    let timing = TIMING_MODES.iter().position(|(timing, _)| *timing == sys.timing).unwrap_or(0);
+    out.push(timing as u8);
+    out.extend_from_slice(&sys.vip_cycles_left.to_le_bytes());
+    out.extend_from_slice(&sys.rpl);
+    out.push(sys.keyboard.get_waiting().unwrap_or(0xff));
+
+    out.push(sys.audio.is_custom() as u8);
+    out.extend_from_slice(&sys.audio.get_pattern());
+    out.push(sys.audio.get_pitch());
+
+    let ram = sys.ram.get_ram();
+    out.extend_from_slice(&(ram.len() as u32).to_le_bytes());
+    let rom_size = sys.ram.get_rom_range().map_or(u32::MAX, |(start, end)| (end - start) as u32);
+    out.extend_from_slice(&rom_size.to_le_bytes());
+    out.extend_from_slice(ram);
+
+    let display = &sys.display;
+    out.extend_from_slice(&(display.get_width() as u16).to_le_bytes());
+    out.extend_from_slice(&(display.get_height() as u16).to_le_bytes());
+    out.push(display.get_planes());
+    for plane in 0..PLANES {
+        // Eight pixels to a byte, the first in the top bit
+        for pixels in display.get_plane(plane).chunks(8) {
+            out.push(pixels.iter().enumerate().fold(0, |byte, (bit, lit)| byte | (*lit as u8) << (7 - bit)));
+        }
+    }
+    out
+}
+
+/// Reads the fields of a save state in order
+struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize
+}
+
+impl<'a> StateReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], StateError> {
+        let end = self.pos.checked_add(count).filter(|end| *end <= self.data.len()).ok_or(StateError::Truncated)?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, StateError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn i64(&mut self) -> Result<i64, StateError> {
+        Ok(self.u64()? as i64)
+    }
+}
+
+/// Restore a snapshot taken by `save_state`. The whole state is checked
+/// before anything is changed, so a bad state leaves the system as it
+/// was. The program keeps running from where the snapshot was taken.
+///
+/// # Arguments
+///
+/// * `sys` - system to restore into
+/// * `data` - snapshot from `save_state`
+pub fn load_state(sys: &mut ChipSystem, data: &[u8]) -> Result<(), StateError> {
+    let mut reader = StateReader { data, pos: 0 };
+    if reader.bytes(4).map_err(|_| StateError::NotAState)? != STATE_MAGIC {
+        return Err(StateError::NotAState);
+    }
+    let version = reader.u16()?;
+    if version != STATE_VERSION {
+        return Err(StateError::Version(version));
+    }
+
+    let (variant, _) = *VARIANTS.get(reader.u8()? as usize).ok_or(StateError::Invalid("variant"))?;
+    let quirks = Quirks::from_bits(reader.u8()?);
+    let frames = reader.u64()?;
+
+    let mut gp = [0; 16];
+    gp.copy_from_slice(reader.bytes(16)?);
+    let i = reader.u16()?;
+    let pc = reader.u16()?;
+    let (delay, sound) = (reader.u8()?, reader.u8()?);
+    let depth = reader.u8()? as usize;
+    if depth > 16 {
+        return Err(StateError::Invalid("stack depth"));
+    }
+    let mut stack = Vec::with_capacity(depth);
+    for _ in 0..depth {
+        stack.push(reader.u16()?);
+    }
+    let ips = reader.u32()?;
+    let phase = reader.u32()?;
+    let (timing, _) = *TIMING_MODES.get(reader.u8()? as usize).ok_or(StateError::Invalid("timing mode"))?;
+    let vip_cycles_left = reader.i64()?;
+    let mut rpl = [0; 16];
+    rpl.copy_from_slice(reader.bytes(16)?);
+    let waiting = match reader.u8()? {
+        0xff => None,
+        reg if reg < 16 => Some(reg),
+        _ => return Err(StateError::Invalid("key wait register"))
+    };
+
+    let custom_audio = reader.u8()? != 0;
+    let mut pattern = [0; 16];
+    pattern.copy_from_slice(reader.bytes(16)?);
+    let pitch = reader.u8()?;
+
+    let ram_size = reader.u32()? as usize;
+    if ram_size != RAM_SIZE && ram_size != EXTENDED_RAM_SIZE {
+        return Err(StateError::Invalid("memory size"));
+    }
+    let rom_size = match reader.u32()? {
+        u32::MAX => None,
+        size => Some(size as usize)
+    };
+    let ram = reader.bytes(ram_size)?.to_vec();
+
+    let (width, height) = (reader.u16()? as usize, reader.u16()? as usize);
+    if width == 0 || height == 0 {
+        return Err(StateError::Invalid("display size"));
+    }
+    let selected = reader.u8()?;
+    let mut planes = Vec::with_capacity(PLANES);
+    for _ in 0..PLANES {
+        let packed = reader.bytes((width * height).div_ceil(8))?;
+        planes.push((0..width * height).map(|pos| packed[pos / 8] & (0x80 >> (pos % 8)) != 0).collect::<Vec<bool>>());
+    }
+    if reader.pos != data.len() {
+        return Err(StateError::Invalid("length"));
+    }
+
+    // Everything checked, restore it
+    sys.variant = variant;
+    sys.quirks = quirks;
+    sys.frames = frames;
+    sys.exit = None;
+    sys.registers.restore(gp, i, pc, delay, sound, &stack);
+    sys.timer_clock.set_ips(ips);
+    sys.timer_clock.set_phase(phase);
+    sys.timing = timing;
+    sys.vip_cycles_left = vip_cycles_left;
+    sys.rpl = rpl;
+    sys.keyboard.set_waiting(waiting);
+    sys.audio = AudioPattern::init();
+    if custom_audio {
+        sys.audio.set_pattern(pattern);
+        sys.audio.set_pitch(pitch);
+    }
+    sys.ram.restore_ram(ram, rom_size);
+    sys.display.set_size(width, height);
+    sys.display.select_planes(selected);
+    for (plane, pixels) in planes.into_iter().enumerate() {
+        sys.display.set_plane(plane, pixels);
+    }
+    Ok(())
+}
+
+/// Write a snapshot of a system to a file, see `save_state`
+///
+/// # Arguments
+///
+/// * `sys` - system to snapshot
+/// * `path` - file to write
+pub fn save_state_file(sys: &ChipSystem, path: &Path) -> io::Result<()> {
+    fs::write(path, save_state(sys))
+}
+
+/// Restore a snapshot from a file written by `save_state_file`
+///
+/// # Arguments
+///
+/// * `sys` - system to restore into
+/// * `path` - file to read
+pub fn load_state_file(sys: &mut ChipSystem, path: &Path) -> io::Result<()> {
+    let data = fs::read(path)?;
+    load_state(sys, &data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watchpoint::Watchpoint;
+
+    /// A system part way through a program, with a sprite drawn, the
+    /// sound timer running and a call on the stack
+    fn running_system() -> ChipSystem {
+        let rom = vec![
+            0x60, 0x05,                     // 200: LD V0, 5
+            0x61, 0x0A,                     // 202: LD V1, 10
+            0xA2, 0x10,                     // 204: LD I, 0x210
+            0xD0, 0x15,                     // 206: DRW V0, V1, 5
+            0xF0, 0x18,                     // 208: LD ST, V0
+            0x22, 0x0E,                     // 20a: CALL 0x20E
+            0x12, 0x0C,                     // 20c: JP 0x20C
+            0x12, 0x0E,                     // 20e: JP 0x20E
+            0xF0, 0x90, 0x90, 0x90, 0xF0    // 210: sprite
+        ];
+        let mut sys = ChipSystem::init();
+        sys.load_rom(rom).unwrap();
+        sys.run_frame(10).unwrap();
+        sys
+    }
+
+    #[test]
+    fn state_round_trips() {
+        let sys = running_system();
+        let state = save_state(&sys);
+        let mut restored = ChipSystem::init();
+        assert_eq!(load_state(&mut restored, &state), Ok(()));
+        assert_eq!(save_state(&restored), state);
+        assert_eq!(restored.registers.get_pc(), 0x20E);
+        assert_eq!(restored.registers.get_stack(), sys.registers.get_stack());
+        assert_eq!(restored.registers.get_sp(), 1);
+        assert_eq!(restored.registers.get_s(), sys.registers.get_s());
+        assert_eq!(restored.display.hash(), sys.display.hash());
+        assert_eq!(restored.get_frame(), 1);
+    }
+
+    #[test]
+    fn restoring_fires_no_watchpoints_or_sound() {
+        let state = save_state(&running_system());
+        let mut restored = ChipSystem::init();
+        restored.add_watchpoint(Watchpoint::parse("v0").unwrap());
+        restored.add_watchpoint(Watchpoint::parse("i").unwrap());
+        load_state(&mut restored, &state).unwrap();
+        assert!(restored.registers.get_s() > 0);
+        assert!(restored.take_watch_hits().is_empty());
+        assert!(!restored.registers.take_sound_started());
+    }
+
+    #[test]
+    fn truncated_states_are_refused_untouched() {
+        let state = save_state(&running_system());
+        for len in 0..state.len() {
+            let mut sys = ChipSystem::init();
+            let expected = match len {
+                0..=3 => StateError::NotAState,
+                _ => StateError::Truncated
+            };
+            assert_eq!(load_state(&mut sys, &state[..len]), Err(expected), "{} bytes", len);
+            assert_eq!(sys.registers.get_pc(), 0x200, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let mut state = save_state(&running_system());
+        state[4..6].copy_from_slice(&(STATE_VERSION + 1).to_le_bytes());
+        assert_eq!(load_state(&mut ChipSystem::init(), &state), Err(StateError::Version(STATE_VERSION + 1)));
+    }
+
+    #[test]
+    fn bad_lengths_and_values_are_refused() {
+        let state = save_state(&running_system());
+        let mut longer = state.clone();
+        longer.push(0);
+        assert_eq!(load_state(&mut ChipSystem::init(), &longer), Err(StateError::Invalid("length")));
+
+        let mut bad_variant = state.clone();
+        bad_variant[6] = 0xFF;
+        assert_eq!(load_state(&mut ChipSystem::init(), &bad_variant), Err(StateError::Invalid("variant")));
+
+        let mut not_state = state;
+        not_state[..4].copy_from_slice(b"PNG\0");
+        assert_eq!(load_state(&mut ChipSystem::init(), &not_state), Err(StateError::NotAState));
+    }
+}
@@ -0,0 +1,451 @@
+// Standard Library Modules //
+use std::error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Instant;
+
+// Local Modules Use //
+use super::config::config_dir;
+use super::registers::ChipRegisters;
+
+/// Told when the sound timer starts and stops, for embedders with their
+/// own audio output, see `ChipSystem::set_audio_sink`
+pub trait AudioSink {
+    /// The sound timer was set above zero
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - XO-CHIP pattern and pitch to play, check
+    ///   `AudioPattern::is_custom` to play a plain tone otherwise
+    fn start(&mut self, audio: &AudioPattern);
+
+    /// The sound timer reached zero
+    fn stop(&mut self);
+}
+
+/// Terminal bell used as a sound fallback
+///
+/// When there is no audio device (pure terminal mode, ssh sessions)
+/// the terminal can still beep: this writes the BEL character each
+/// time the sound timer starts, so games still give audible feedback.
+pub struct TerminalBell {
+    /// True if the bell should ring
+    pub enabled: bool
+}
+
+impl TerminalBell {
+    /// Initialize the terminal bell
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true if the bell should ring
+    pub fn init(enabled: bool) -> Self {
+        TerminalBell { enabled }
+    }
+
+    /// Ring the bell if the sound timer started since the last update,
+    /// call this once per frame. Returns true if the bell rang.
+    ///
+    /// # Arguments
+    ///
+    /// * `registers` - registers holding the sound timer
+    pub fn update(&mut self, registers: &mut ChipRegisters) -> io::Result<bool> {
+        let started = registers.take_sound_started();
+        if !(self.enabled && started) {
+            return Ok(false);
+        }
+        let mut out = io::stdout();
+        out.write_all(b"\x07")?;
+        out.flush()?;
+        Ok(true)
+    }
+}
+
+/// Playback rate of the audio pattern at the default pitch of 64
+pub const BASE_PLAYBACK_RATE: f64 = 4000.0;
+
+/// XO-CHIP audio: a 128 bit pattern played one bit per sample while
+/// the sound timer is running
+///
+/// F002 loads the pattern from memory and FX3A sets the pitch, which
+/// changes how fast the pattern is played back. `render` resamples the
+/// pattern to the output device's sample rate so audio backends only
+/// need to copy samples.
+#[derive(Debug, Clone)]
+pub struct AudioPattern {
+    /// Pattern bits, most significant bit of the first byte first
+    pattern: [u8; 16],
+    /// Pitch register, 64 plays at 4000 bits per second
+    pitch: u8,
+    /// Position in the pattern in bits
+    phase: f64,
+    /// True once the program loaded a pattern or set the pitch
+    custom: bool
+}
+
+impl Default for AudioPattern {
+    /// A 500 Hz square wave until a program loads its own pattern
+    fn default() -> Self {
+        AudioPattern {
+            pattern: [0xF0; 16],
+            pitch: 64,
+            phase: 0.0,
+            custom: false
+        }
+    }
+}
+
+impl AudioPattern {
+    /// Initialize the default pattern and pitch
+    pub fn init() -> Self {
+        AudioPattern::default()
+    }
+
+    /// Get the pattern bits
+    pub fn get_pattern(&self) -> [u8; 16] {
+        self.pattern
+    }
+
+    /// Replace the pattern bits
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - 16 bytes of pattern, played first byte first
+    pub fn set_pattern(&mut self, pattern: [u8; 16]) {
+        self.pattern = pattern;
+        self.custom = true;
+    }
+
+    /// Get the pitch register
+    pub fn get_pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Set the pitch register
+    ///
+    /// # Arguments
+    ///
+    /// * `pitch` - new pitch, 64 is the default rate
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+        self.custom = true;
+    }
+
+    /// Returns true once the program loaded its own pattern (F002) or
+    /// set the pitch (FX3A), plain CHIP-8 programs only ever beep
+    pub fn is_custom(&self) -> bool {
+        self.custom
+    }
+
+    /// Pattern bits played per second, `4000 * 2 ^ ((pitch - 64) / 48)`
+    pub fn playback_rate(&self) -> f64 {
+        BASE_PLAYBACK_RATE * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    /// Fill a buffer of samples between -1 and 1, silence when the
+    /// sound timer is not running
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - buffer to fill
+    /// * `sample_rate` - samples per second of the output device
+    /// * `playing` - true while the sound timer is above zero
+    pub fn render(&mut self, out: &mut [f32], sample_rate: u32, playing: bool) {
+        if !playing {
+            out.iter_mut().for_each(|sample| *sample = 0.0);
+            return;
+        }
+        let step = self.playback_rate() / sample_rate.max(1) as f64;
+        for sample in out.iter_mut() {
+            let bit = self.phase as usize % 128;
+            let set = self.pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            *sample = if set { 1.0 } else { -1.0 };
+            self.phase = (self.phase + step) % 128.0;
+        }
+    }
+}
+
+/// Samples per second sent to the audio player
+pub const PLAYER_SAMPLE_RATE: u32 = 44100;
+
+/// Shape of the beep played for programs without their own pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    /// Buzzy, the sound of most CHIP-8 interpreters
+    Square,
+    /// Pure and soft
+    Sine,
+    /// Between the two
+    Triangle
+}
+
+/// Every waveform with its config name
+pub const WAVEFORMS: [(Waveform, &str); 3] = [
+    (Waveform::Square, "square"),
+    (Waveform::Sine, "sine"),
+    (Waveform::Triangle, "triangle")
+];
+
+impl Waveform {
+    /// Name of the waveform used in config files
+    pub fn name(&self) -> &'static str {
+        WAVEFORMS.iter()
+            .find(|(waveform, _)| waveform == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Find a waveform by its config name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - config name of the waveform
+    pub fn from_name(name: &str) -> Option<Waveform> {
+        WAVEFORMS.iter()
+            .find(|(_, waveform_name)| *waveform_name == name)
+            .map(|(waveform, _)| *waveform)
+    }
+
+    /// Sample of the wave between -1 and 1
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - position in the cycle, from 0 up to 1
+    fn sample(&self, phase: f64) -> f64 {
+        match self {
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Sine => (phase * 2.0 * PI).sin(),
+            Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0
+        }
+    }
+}
+
+/// A problem in a sound config file
+#[derive(Debug, Clone)]
+pub struct ToneError {
+    /// Line number starting at 1
+    line: usize,
+    /// What is wrong with the line
+    message: String
+}
+
+impl fmt::Display for ToneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl error::Error for ToneError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        None
+    }
+}
+
+/// How `PlayerAudio` beeps: a wave of some shape, pitch and loudness
+/// for plain CHIP-8 programs, XO-CHIP patterns are played as they are
+/// at the volume
+///
+/// Changed from a config file with `name = value` lines, for example
+/// `waveform = sine`, `frequency = 440` and `volume = 0.5`. Settings
+/// not named keep their defaults and `#` starts a comment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneSettings {
+    /// Shape of the wave
+    pub waveform: Waveform,
+    /// Pitch in Hz
+    pub frequency: f64,
+    /// Loudness from 0 (silent) to 1
+    pub volume: f64
+}
+
+impl Default for ToneSettings {
+    /// A quiet 500 Hz square wave, the same tone as the default
+    /// XO-CHIP pattern
+    fn default() -> Self {
+        ToneSettings {
+            waveform: Waveform::Square,
+            frequency: 500.0,
+            volume: 0.25
+        }
+    }
+}
+
+impl ToneSettings {
+    /// Default location of the sound config, `sound` in the
+    /// configuration directory, None if it can not be found
+    pub fn default_path() -> Option<PathBuf> {
+        Some(config_dir()?.join("sound"))
+    }
+
+    /// Load the default settings changed by a config file, a missing
+    /// file gives the defaults
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - config file to load
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut settings = ToneSettings::default();
+        match fs::read_to_string(path) {
+            Ok(text) => settings.apply(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e)
+        }
+        Ok(settings)
+    }
+
+    /// Apply the settings in a config file's text
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of a config file
+    pub fn apply(&mut self, text: &str) -> Result<(), ToneError> {
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ToneError { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => return Err(error(format!("expected 'name = value', found '{}'", line)))
+            };
+            let number = || value.parse::<f64>().ok().filter(|number| number.is_finite());
+            match name {
+                "waveform" => self.waveform = Waveform::from_name(value).ok_or_else(|| {
+                    let names: Vec<&str> = WAVEFORMS.iter().map(|(_, name)| *name).collect();
+                    error(format!("unknown waveform '{}', expected one of {}", value, names.join(", ")))
+                })?,
+                "frequency" => self.frequency = number().filter(|hz| (20.0..=20000.0).contains(hz))
+                    .ok_or_else(|| error(format!("frequency '{}' is not between 20 and 20000 Hz", value)))?,
+                "volume" => self.volume = number().filter(|volume| (0.0..=1.0).contains(volume))
+                    .ok_or_else(|| error(format!("volume '{}' is not between 0 and 1", value)))?,
+                _ => return Err(error(format!("unknown setting '{}', expected waveform, frequency or volume", name)))
+            }
+        }
+        Ok(())
+    }
+
+    /// Format the settings as a config file that reproduces them
+    pub fn to_config(&self) -> String {
+        format!("waveform = {}\nfrequency = {}\nvolume = {}\n", self.waveform.name(), self.frequency, self.volume)
+    }
+}
+
+/// Most audio written at once, a tenth of a second, so a stall of the
+/// emulator is not made up for with a burst of old sound
+const MAX_WRITE: u64 = PLAYER_SAMPLE_RATE as u64 / 10;
+
+/// Audio players tried in order, each reading raw signed 16 bit little
+/// endian mono samples at `PLAYER_SAMPLE_RATE` from stdin
+const PLAYERS: [(&str, &[&str]); 3] = [
+    ("aplay", &["-q", "-t", "raw", "-f", "S16_LE", "-c", "1", "-r", "44100"]),
+    ("paplay", &["--raw", "--format=s16le", "--channels=1", "--rate=44100"]),
+    ("play", &["-q", "-t", "raw", "-e", "signed", "-b", "16", "-c", "1", "-r", "44100", "-"])
+];
+
+/// Beeps while the sound timer runs by piping samples to an audio
+/// player, `aplay` (ALSA), `paplay` (PulseAudio and PipeWire) or SoX's
+/// `play`, whichever is installed first
+///
+/// Plain CHIP-8 programs get the tone of the `ToneSettings` given, an
+/// XO-CHIP program that loads its own pattern hears it played back
+/// from the system's `AudioPattern`. `update` writes as much
+/// sound as real time has passed since the last call, so the player
+/// neither runs dry nor falls behind when frames are not exactly 60 Hz.
+pub struct PlayerAudio {
+    /// The player process
+    player: Child,
+    /// Its stdin, taking samples
+    input: ChildStdin,
+    /// Name of the player
+    name: &'static str,
+    /// When the first samples were written
+    start: Option<Instant>,
+    /// Samples written so far
+    written: u64,
+    /// How plain programs beep
+    tone: ToneSettings,
+    /// Position in the tone's cycle, from 0 up to 1
+    tone_phase: f64
+}
+
+impl PlayerAudio {
+    /// Start the first audio player found
+    ///
+    /// # Arguments
+    ///
+    /// * `tone` - how plain programs beep
+    pub fn start(tone: ToneSettings) -> io::Result<Self> {
+        for (name, args) in PLAYERS.iter() {
+            let spawned = Command::new(name)
+                .args(args.iter())
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            let mut player = match spawned {
+                Ok(player) => player,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e)
+            };
+            let input = match player.stdin.take() {
+                Some(input) => input,
+                None => continue
+            };
+            return Ok(PlayerAudio { player, input, name, start: None, written: 0, tone, tone_phase: 0.0 });
+        }
+        let names: Vec<&str> = PLAYERS.iter().map(|(name, _)| *name).collect();
+        Err(io::Error::new(io::ErrorKind::NotFound, format!("no audio player found, tried {}", names.join(", "))))
+    }
+
+    /// Get the name of the player in use
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Write the sound since the last call, call this once per frame
+    ///
+    /// # Arguments
+    ///
+    /// * `audio` - pattern to play
+    /// * `playing` - true while the sound timer is above zero
+    pub fn update(&mut self, audio: &mut AudioPattern, playing: bool) -> io::Result<()> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let due = (start.elapsed().as_secs_f64() * PLAYER_SAMPLE_RATE as f64) as u64;
+        let count = due.saturating_sub(self.written).min(MAX_WRITE) as usize;
+        // Time lost to a stall is skipped rather than played late
+        self.written = self.written.max(due.saturating_sub(MAX_WRITE)) + count as u64;
+        if count == 0 {
+            return Ok(());
+        }
+        let mut samples = vec![0.0; count];
+        match (playing, audio.is_custom()) {
+            (true, false) => {
+                let step = self.tone.frequency / PLAYER_SAMPLE_RATE as f64;
+                for sample in samples.iter_mut() {
+                    *sample = self.tone.waveform.sample(self.tone_phase) as f32;
+                    self.tone_phase = (self.tone_phase + step) % 1.0;
+                }
+            },
+            _ => audio.render(&mut samples, PLAYER_SAMPLE_RATE, playing)
+        }
+        let volume = self.tone.volume as f32;
+        let bytes: Vec<u8> = samples.iter()
+            .flat_map(|sample| ((sample * volume * i16::MAX as f32) as i16).to_le_bytes())
+            .collect();
+        self.input.write_all(&bytes)
+    }
+}
+
+impl Drop for PlayerAudio {
+    fn drop(&mut self) {
+        // The player may already be gone, nothing to do about it then
+        let _ = self.player.kill();
+        let _ = self.player.wait();
+    }
+}
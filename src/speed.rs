@@ -0,0 +1,125 @@
+// Standard Library Modules //
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Real time a frame takes at normal speed, one 60hz tick
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Percent of normal speed slow motion runs at unless told otherwise
+pub const DEFAULT_SLOW_PERCENT: u32 = 10;
+
+/// How fast emulated time passes against real time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Speed {
+    /// One frame every 60hz tick
+    #[default]
+    Normal,
+    /// Frames run back to back as fast as the host allows
+    Turbo,
+    /// Frames stretched out to run at a percent of normal speed
+    Slow(u32)
+}
+
+/// Paces emulated frames against real time for the turbo and slow
+/// motion hotkeys
+///
+/// A frame always runs the same opcodes and counts the timers down
+/// once whatever the speed, so speed changes are invisible to the
+/// program and to recordings. Only the real time between frames
+/// changes, and with turbo the display is drawn at most once a tick
+/// however many frames run in it.
+#[derive(Debug, Clone)]
+pub struct SpeedControl {
+    /// Current speed
+    speed: Speed,
+    /// Percent of normal speed slow motion runs at
+    slow_percent: u32,
+    /// When the display was last drawn
+    last_present: Option<Instant>
+}
+
+impl Default for SpeedControl {
+    fn default() -> Self {
+        SpeedControl::init(DEFAULT_SLOW_PERCENT)
+    }
+}
+
+impl SpeedControl {
+    /// Initialize at normal speed
+    ///
+    /// # Arguments
+    ///
+    /// * `slow_percent` - percent of normal speed for slow motion,
+    ///   kept between 1 and 100
+    pub fn init(slow_percent: u32) -> Self {
+        SpeedControl { speed: Speed::Normal, slow_percent: slow_percent.clamp(1, 100), last_present: None }
+    }
+
+    /// Get the current speed
+    pub fn get_speed(&self) -> Speed {
+        self.speed
+    }
+
+    /// Set the current speed
+    ///
+    /// # Arguments
+    ///
+    /// * `speed` - speed to run at from the next frame
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+    }
+
+    /// Switch between turbo and normal speed, returning the new speed
+    pub fn toggle_turbo(&mut self) -> Speed {
+        self.speed = match self.speed {
+            Speed::Turbo => Speed::Normal,
+            _ => Speed::Turbo
+        };
+        self.speed
+    }
+
+    /// Switch between slow motion and normal speed, returning the new
+    /// speed
+    pub fn toggle_slow(&mut self) -> Speed {
+        self.speed = match self.speed {
+            Speed::Slow(_) => Speed::Normal,
+            _ => Speed::Slow(self.slow_percent)
+        };
+        self.speed
+    }
+
+    /// Get the real time a frame should take, None when frames are not
+    /// throttled
+    pub fn frame_time(&self) -> Option<Duration> {
+        match self.speed {
+            Speed::Normal => Some(FRAME_TIME),
+            Speed::Turbo => None,
+            Speed::Slow(percent) => Some(FRAME_TIME * 100 / percent.max(1))
+        }
+    }
+
+    /// Returns true if the frame that just ran should be drawn, which
+    /// is every frame unless turbo runs several in one tick
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - time the frame finished
+    pub fn should_present(&mut self, now: Instant) -> bool {
+        let due = self.speed != Speed::Turbo
+            || self.last_present.is_none_or(|last| now.duration_since(last) >= FRAME_TIME);
+        if due {
+            self.last_present = Some(now);
+        }
+        due
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Speed::Normal => write!(f, "normal"),
+            Speed::Turbo => write!(f, "turbo"),
+            Speed::Slow(percent) => write!(f, "slow motion ({}%)", percent)
+        }
+    }
+}
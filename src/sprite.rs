@@ -0,0 +1,254 @@
+// Standard Library Modules //
+use std::fmt;
+
+// Local Modules Use //
+use super::image::RgbImage;
+
+/// Output formats of `SpriteSheet`, by name
+pub const SPRITE_FORMATS: [&str; 3] = ["hex", "asm", "octo"];
+
+/// How an image is turned into sprites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteOptions {
+    /// Sprite width, 8 or 16 for SCHIP 16x16 sprites
+    pub width: usize,
+    /// Rows per sprite, at most 15 for 8 wide sprites and always 16
+    /// for 16 wide ones. None uses the image height up to the maximum.
+    pub height: Option<usize>,
+    /// Brightness from 0 to 255 at or above which a pixel is lit, None
+    /// uses the middle of the darkest and brightest pixel so colored
+    /// images work without tuning
+    pub threshold: Option<u8>,
+    /// Dither with Floyd-Steinberg error diffusion instead of a hard
+    /// threshold, for photos and gradients
+    pub dither: bool,
+    /// Light dark pixels instead of bright ones
+    pub invert: bool
+}
+
+impl Default for SpriteOptions {
+    fn default() -> Self {
+        SpriteOptions {
+            width: 8,
+            height: None,
+            threshold: None,
+            dither: false,
+            invert: false
+        }
+    }
+}
+
+/// One sprite cut from an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+    /// Column of the sprite in the image, counted in sprites
+    pub col: usize,
+    /// Row of the sprite in the image, counted in sprites
+    pub row: usize,
+    /// Sprite bytes, 1 per row or 2 per row for 16 wide sprites
+    pub bytes: Vec<u8>
+}
+
+/// Sprites cut from an image, left to right then top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteSheet {
+    /// Sprite width, 8 or 16
+    pub width: usize,
+    /// Rows per sprite
+    pub height: usize,
+    /// Sprites in order
+    pub sprites: Vec<Sprite>
+}
+
+/// Reasons an image can not be turned into sprites
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpriteError {
+    /// Sprites are 8 or 16 pixels wide
+    InvalidWidth(usize),
+    /// Too many or no rows for the sprite width
+    InvalidHeight(usize),
+    /// The image has no pixels
+    EmptyImage
+}
+
+impl fmt::Display for SpriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpriteError::InvalidWidth(width) => write!(f, "sprites are 8 or 16 pixels wide, not {}", width),
+            SpriteError::InvalidHeight(height) => write!(f, "8 wide sprites have 1 to 15 rows and 16 wide sprites 16, not {}", height),
+            SpriteError::EmptyImage => write!(f, "the image is empty")
+        }
+    }
+}
+
+/// Turn an image into sprites, an image larger than one sprite is cut
+/// into several, padding the right and bottom edges with unlit pixels
+///
+/// # Arguments
+///
+/// * `image` - image to convert
+/// * `options` - sprite size and how pixels are lit
+pub fn image_to_sprites(image: &RgbImage, options: &SpriteOptions) -> Result<SpriteSheet, SpriteError> {
+    let (image_width, image_height) = (image.get_width(), image.get_height());
+    if image_width == 0 || image_height == 0 {
+        return Err(SpriteError::EmptyImage);
+    }
+    let height = match (options.width, options.height) {
+        (8, None) => image_height.min(15),
+        (8, Some(height)) if (1..=15).contains(&height) => height,
+        (16, None) | (16, Some(16)) => 16,
+        (8, Some(height)) | (16, Some(height)) => return Err(SpriteError::InvalidHeight(height)),
+        (width, _) => return Err(SpriteError::InvalidWidth(width))
+    };
+    let lit = pixels(image, options);
+    let cols = image_width.div_ceil(options.width);
+    let rows = image_height.div_ceil(height);
+    let mut sprites = Vec::new();
+    for row in 0..rows {
+        for col in 0..cols {
+            let mut bytes = Vec::new();
+            for y in row * height..(row + 1) * height {
+                let mut bits: u16 = 0;
+                for x in col * options.width..(col + 1) * options.width {
+                    bits <<= 1;
+                    if x < image_width && y < image_height && lit[y * image_width + x] {
+                        bits |= 1;
+                    }
+                }
+                match options.width {
+                    16 => bytes.extend_from_slice(&bits.to_be_bytes()),
+                    _ => bytes.push(bits as u8)
+                }
+            }
+            sprites.push(Sprite { col, row, bytes });
+        }
+    }
+    Ok(SpriteSheet { width: options.width, height, sprites })
+}
+
+/// Decide which pixels of an image are lit, row by row
+fn pixels(image: &RgbImage, options: &SpriteOptions) -> Vec<bool> {
+    let (width, height) = (image.get_width(), image.get_height());
+    let mut levels: Vec<i32> = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let [r, g, b] = image.get_pixel(x, y);
+            let luma = (299 * r as i32 + 587 * g as i32 + 114 * b as i32) / 1000;
+            levels.push(if options.invert { 255 - luma } else { luma });
+        }
+    }
+    let threshold = match options.threshold {
+        Some(threshold) => threshold as i32,
+        None => {
+            let darkest = levels.iter().copied().min().unwrap_or(0);
+            let brightest = levels.iter().copied().max().unwrap_or(255);
+            match darkest == brightest {
+                true => 128,
+                false => (darkest + brightest + 1) / 2
+            }
+        }
+    };
+    if !options.dither {
+        return levels.iter().map(|level| *level >= threshold).collect();
+    }
+    let mut lit = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let pos = y * width + x;
+            lit[pos] = levels[pos] >= threshold;
+            let error = levels[pos] - if lit[pos] { 255 } else { 0 };
+            let mut spread = |dx: isize, dy: usize, weight: i32| {
+                let nx = x as isize + dx;
+                if nx >= 0 && (nx as usize) < width && y + dy < height {
+                    levels[(y + dy) * width + nx as usize] += error * weight / 16;
+                }
+            };
+            spread(1, 0, 7);
+            spread(-1, 1, 3);
+            spread(0, 1, 5);
+            spread(1, 1, 1);
+        }
+    }
+    lit
+}
+
+impl SpriteSheet {
+    /// Label of a sprite, the name alone for a single sprite otherwise
+    /// followed by its column and row
+    fn label(&self, name: &str, sprite: &Sprite) -> String {
+        match self.sprites.len() {
+            1 => name.to_string(),
+            _ => format!("{}_{}_{}", name, sprite.col, sprite.row)
+        }
+    }
+
+    /// Bytes of each sprite row as binary literals
+    fn rows(&self, sprite: &Sprite) -> Vec<String> {
+        sprite.bytes.chunks(self.width / 8)
+            .map(|row| row.iter().map(|byte| format!("0b{:08b}", byte)).collect::<Vec<_>>().join(", "))
+            .collect()
+    }
+
+    /// Write every sprite as a line of hex bytes
+    pub fn to_hex(&self) -> String {
+        let mut out = String::new();
+        for sprite in self.sprites.iter() {
+            let hex: Vec<String> = sprite.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+            out.push_str(&hex.join(" "));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Write every sprite as a label and DB lines for `clip8 asm`, one
+    /// line per sprite row
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - label of the sprite, or prefix of the labels when
+    ///   there are several
+    pub fn to_asm(&self, name: &str) -> String {
+        let mut out = String::new();
+        for sprite in self.sprites.iter() {
+            out.push_str(&format!("{}:  ; {}x{}\n", self.label(name, sprite), self.width, self.height));
+            for row in self.rows(sprite) {
+                out.push_str(&format!("    DB {}\n", row));
+            }
+        }
+        out
+    }
+
+    /// Write every sprite in Octo syntax, a `: label` followed by the
+    /// bytes one sprite row per line
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - label of the sprite, or prefix of the labels when
+    ///   there are several
+    pub fn to_octo(&self, name: &str) -> String {
+        let mut out = String::new();
+        for sprite in self.sprites.iter() {
+            out.push_str(&format!(": {}\n", self.label(name, sprite)));
+            for row in self.rows(sprite) {
+                out.push_str(&format!("  {}\n", row.replace(", ", " ")));
+            }
+        }
+        out
+    }
+
+    /// Write the sprites in a format from `SPRITE_FORMATS`, None for an
+    /// unknown format
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - format name
+    /// * `name` - label for the formats that use one
+    pub fn format(&self, format: &str, name: &str) -> Option<String> {
+        match format {
+            "hex" => Some(self.to_hex()),
+            "asm" => Some(self.to_asm(name)),
+            "octo" => Some(self.to_octo(name)),
+            _ => None
+        }
+    }
+}
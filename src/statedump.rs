@@ -0,0 +1,103 @@
+// Standard Library Modules //
+use std::io;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::{callstack, disasm};
+use super::export::{display_to_text, TextStyle};
+
+/// Instructions disassembled before and after the program counter
+const DUMP_CONTEXT: u16 = 8;
+
+/// Describe the whole machine as text: registers, timers, the stack,
+/// the code around the program counter, the recent program counters
+/// and the display. Meant for finding out what a hung instance is
+/// doing, see `DumpSignal`.
+///
+/// # Arguments
+///
+/// * `sys` - system to describe
+pub fn state_dump(sys: &ChipSystem) -> String {
+    let reg = &sys.registers;
+    let pc = reg.get_pc();
+    let mut out = format!("==== clip8 state dump, frame {} ====\n", sys.get_frame());
+    out.push_str(&format!(
+        "PC {:03x}  I {:03x}  DT {:02x}  ST {:02x}  SP {}\n",
+        pc, reg.get_i(), reg.get_d(), reg.get_s(), reg.get_sp()
+    ));
+    let gp: Vec<String> = (0..16).map(|i| format!("V{:X} {:02x}", i, reg.get_gp(i))).collect();
+    out.push_str(&format!("{}\n{}\n", gp[..8].join("  "), gp[8..].join("  ")));
+    if let Some(reason) = sys.get_exit() {
+        out.push_str(&format!("Exited: {:?}\n", reason));
+    }
+
+    out.push_str("\nStack:\n");
+    for (_, line) in callstack::backtrace(sys) {
+        out.push_str(&format!("  {}\n", line));
+    }
+
+    out.push_str("\nCode:\n");
+    for line in disasm::listing_around(&sys.ram, pc, DUMP_CONTEXT) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+
+    let history = sys.get_pc_history();
+    if !history.is_empty() {
+        let pcs: Vec<String> = history.iter().map(|pc| format!("{:03x}", pc)).collect();
+        out.push_str(&format!("\nRecent PCs: {}\n", pcs.join(" ")));
+    }
+
+    out.push_str("\nDisplay:\n");
+    out.push_str(&display_to_text(&sys.display, &TextStyle::default()));
+    out
+}
+
+/// Set by the signal handler, taken by `DumpSignal::take_request`
+#[cfg(unix)]
+static DUMP_REQUESTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_dump_signal(_signal: libc::c_int) {
+    DUMP_REQUESTED.store(true, std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Requests for a state dump sent as SIGUSR1, `kill -USR1 <pid>`
+///
+/// The handler only records the request, the emulator loop checks it
+/// with `take_request` between frames and writes the dump itself, so
+/// emulation carries on afterwards. Only available on Unix.
+pub struct DumpSignal {
+    _private: ()
+}
+
+impl DumpSignal {
+    /// Start listening for SIGUSR1, replacing its default action of
+    /// ending the process
+    #[cfg(unix)]
+    pub fn install() -> io::Result<Self> {
+        let handler = on_dump_signal as extern "C" fn(libc::c_int);
+        // The handler only stores to an atomic, which is safe to do
+        // from a signal handler
+        let previous = unsafe { libc::signal(libc::SIGUSR1, handler as libc::sighandler_t) };
+        match previous == libc::SIG_ERR {
+            true => Err(io::Error::last_os_error()),
+            false => Ok(DumpSignal { _private: () })
+        }
+    }
+
+    /// Start listening for SIGUSR1, which this platform does not have
+    #[cfg(not(unix))]
+    pub fn install() -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Other, "state dumps on a signal need a Unix system"))
+    }
+
+    /// Returns true once for each time the signal arrived since the
+    /// last call, signals sent close together may count once
+    pub fn take_request(&self) -> bool {
+        #[cfg(unix)]
+        return DUMP_REQUESTED.swap(false, std::sync::atomic::Ordering::SeqCst);
+        #[cfg(not(unix))]
+        false
+    }
+}
@@ -0,0 +1,299 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+use std::ops::AddAssign;
+use std::time::Duration;
+
+// Local Modules Use //
+use super::registers::ChipRegisters;
+
+/// Counters for the work done during one frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Opcodes executed
+    pub instructions: u64,
+    /// DXYN opcodes executed
+    pub draws: u64,
+    /// Sprite rows drawn by all DXYN opcodes
+    pub sprite_rows: u64,
+    /// DXYN opcodes that set VF because a pixel was erased
+    pub collisions: u64,
+    /// Pixels that changed state, from drawing or clearing
+    pub pixels_toggled: u64,
+    /// 00E0 opcodes executed
+    pub clears: u64
+}
+
+impl FrameStats {
+    /// One line summary suitable for a heads up display
+    pub fn hud(&self) -> String {
+        format!(
+            "ops {:>4} drw {:>3} rows {:>4} hit {:>3} px {:>5} cls {:>2}",
+            self.instructions, self.draws, self.sprite_rows,
+            self.collisions, self.pixels_toggled, self.clears
+        )
+    }
+}
+
+impl AddAssign for FrameStats {
+    fn add_assign(&mut self, other: FrameStats) {
+        self.instructions += other.instructions;
+        self.draws += other.draws;
+        self.sprite_rows += other.sprite_rows;
+        self.collisions += other.collisions;
+        self.pixels_toggled += other.pixels_toggled;
+        self.clears += other.clears;
+    }
+}
+
+/// Statistics kept by the system while it runs
+///
+/// Counters accumulate into the current frame, when a frame ends they
+/// are moved to `last` and added to the totals.
+#[derive(Debug, Clone, Default)]
+pub struct ChipStats {
+    /// Frame currently being run
+    current: FrameStats,
+    /// Most recently finished frame
+    last: FrameStats,
+    /// Sum of every finished frame
+    total: FrameStats,
+    /// Number of finished frames
+    frames: u64
+}
+
+impl ChipStats {
+    /// Initialize empty statistics
+    pub fn init() -> Self {
+        ChipStats::default()
+    }
+
+    /// Count an executed opcode
+    pub fn record_instruction(&mut self) {
+        self.current.instructions += 1;
+    }
+
+    /// Count a sprite draw
+    ///
+    /// # Arguments
+    ///
+    /// * `rows` - height of the sprite
+    /// * `collision` - true if a pixel was erased
+    /// * `toggled` - pixels that changed state
+    pub fn record_draw(&mut self, rows: u64, collision: bool, toggled: u64) {
+        self.current.draws += 1;
+        self.current.sprite_rows += rows;
+        self.current.collisions += collision as u64;
+        self.current.pixels_toggled += toggled;
+    }
+
+    /// Count a display clear
+    ///
+    /// # Arguments
+    ///
+    /// * `toggled` - pixels that were turned off
+    pub fn record_clear(&mut self, toggled: u64) {
+        self.current.clears += 1;
+        self.current.pixels_toggled += toggled;
+    }
+
+    /// Finish the current frame and start a new one
+    pub fn end_frame(&mut self) {
+        self.last = self.current;
+        self.total += self.current;
+        self.current = FrameStats::default();
+        self.frames += 1;
+    }
+
+    /// Get the counters of the frame being run
+    pub fn get_current(&self) -> FrameStats {
+        self.current
+    }
+
+    /// Get the counters of the last finished frame
+    pub fn get_last(&self) -> FrameStats {
+        self.last
+    }
+
+    /// Get the counters summed over every finished frame
+    pub fn get_total(&self) -> FrameStats {
+        self.total
+    }
+
+    /// Get the number of finished frames
+    pub fn get_frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Reset all statistics
+    pub fn reset(&mut self) {
+        *self = ChipStats::default();
+    }
+}
+
+/// Per frame telemetry written as CSV
+///
+/// Each row holds the counters of one finished frame along with how
+/// long the host took to run it and the timer values at its end, so a
+/// run can be charted in a spreadsheet to look at performance and game
+/// pacing.
+pub struct CsvTelemetry<W: Write> {
+    /// Destination of the rows
+    out: W,
+    /// Number of rows written
+    rows: u64
+}
+
+impl<W: Write> CsvTelemetry<W> {
+    /// Header row naming every column
+    pub const HEADER: &'static str = "frame,duration_us,instructions,draws,sprite_rows,collisions,pixels_toggled,clears,delay_timer,sound_timer";
+
+    /// Start a telemetry log, writing the header row
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - destination of the rows, usually a buffered file
+    pub fn init(mut out: W) -> io::Result<Self> {
+        writeln!(out, "{}", Self::HEADER)?;
+        Ok(CsvTelemetry { out, rows: 0 })
+    }
+
+    /// Get the number of rows written, not counting the header
+    pub fn get_rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Write a row for the frame that just finished
+    ///
+    /// # Arguments
+    ///
+    /// * `stats` - statistics after the frame ended
+    /// * `registers` - registers holding the timers
+    /// * `duration` - host time spent running the frame
+    pub fn record(&mut self, stats: &ChipStats, registers: &ChipRegisters, duration: Duration) -> io::Result<()> {
+        let last = stats.get_last();
+        writeln!(
+            self.out, "{},{},{},{},{},{},{},{},{},{}",
+            stats.get_frames(), duration.as_micros(), last.instructions, last.draws,
+            last.sprite_rows, last.collisions, last.pixels_toggled, last.clears,
+            registers.get_d(), registers.get_s()
+        )?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Flush buffered rows to the destination
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Host frame durations collected over a session, for measuring how
+/// evenly frames are paced
+#[derive(Debug, Clone, Default)]
+pub struct FrameTiming {
+    /// Duration of every recorded frame in microseconds
+    samples: Vec<u32>
+}
+
+impl FrameTiming {
+    /// Initialize with no frames recorded
+    pub fn init() -> Self {
+        FrameTiming::default()
+    }
+
+    /// Record how long a frame took
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - host time between the start of this frame and
+    ///   the start of the next
+    pub fn record(&mut self, duration: Duration) {
+        self.samples.push(duration.as_micros().min(u32::MAX as u128) as u32);
+    }
+
+    /// Get the number of frames recorded
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Returns true if no frames were recorded
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Shortest frame
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.samples.iter().copied().min().unwrap_or(0) as u64)
+    }
+
+    /// Longest frame
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.samples.iter().copied().max().unwrap_or(0) as u64)
+    }
+
+    /// Mean frame duration
+    pub fn avg(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::from_micros(0);
+        }
+        let sum: u64 = self.samples.iter().map(|sample| *sample as u64).sum();
+        Duration::from_micros(sum / self.samples.len() as u64)
+    }
+
+    /// Duration that `percent` percent of frames were at or under
+    ///
+    /// # Arguments
+    ///
+    /// * `percent` - percentile between 0 and 100
+    pub fn percentile(&self, percent: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::from_micros(0);
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Duration::from_micros(sorted[rank.clamp(1, sorted.len()) - 1] as u64)
+    }
+
+    /// Count frames into 1 ms wide buckets between the shortest and
+    /// longest frame, buckets are widened to keep at most `max_buckets`.
+    /// Returns (bucket start in ms, bucket width in ms, frames).
+    ///
+    /// # Arguments
+    ///
+    /// * `max_buckets` - most buckets to return
+    pub fn histogram(&self, max_buckets: usize) -> Vec<(u32, u32, usize)> {
+        if self.samples.is_empty() {
+            return Vec::new();
+        }
+        let low = self.min().as_millis() as u32;
+        let high = self.max().as_millis() as u32;
+        let span = high - low + 1;
+        let width = span.div_ceil(max_buckets.max(1) as u32);
+        let mut buckets: Vec<(u32, u32, usize)> = (0..span.div_ceil(width))
+            .map(|index| (low + index * width, width, 0))
+            .collect();
+        for sample in &self.samples {
+            let index = ((sample / 1000 - low) / width) as usize;
+            buckets[index].2 += 1;
+        }
+        buckets
+    }
+
+    /// Summary with min/avg/p99/max and a text histogram
+    pub fn report(&self) -> String {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let mut out = format!(
+            "Frame timing over {} frames\n  min {:.2} ms  avg {:.2} ms  p99 {:.2} ms  max {:.2} ms\n",
+            self.len(), ms(self.min()), ms(self.avg()), ms(self.percentile(99.0)), ms(self.max())
+        );
+        let buckets = self.histogram(16);
+        let most = buckets.iter().map(|(_, _, count)| *count).max().unwrap_or(0).max(1);
+        for (start, width, count) in buckets {
+            let bar = "#".repeat((count * 40).div_ceil(most));
+            out.push_str(&format!("  {:>4}-{:<4} ms {:>6} {}\n", start, start + width, count, bar));
+        }
+        out
+    }
+}
@@ -0,0 +1,145 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Local Modules Use //
+use super::image::RgbImage;
+
+/// Boundary between the JPEG parts of the stream
+const BOUNDARY: &str = "clip8frame";
+
+/// JPEG quality of streamed frames, chip8 frames are two flat colors so
+/// a high quality costs little and avoids smearing around pixels
+const QUALITY: u8 = 90;
+
+/// How long a slow viewer can hold up a frame before it is dropped
+const WRITE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Page served at `/`, the stream scaled to the window
+const INDEX_PAGE: &str = "<!DOCTYPE html>
+<html><head><title>clip8</title>
+<style>body{margin:0;background:#000}img{width:100vw;height:100vh;object-fit:contain;image-rendering:pixelated}</style>
+</head><body><img src=\"/stream\"></body></html>
+";
+
+/// Serves the display as an MJPEG stream over HTTP
+///
+/// `/stream` is a `multipart/x-mixed-replace` stream of JPEG frames
+/// that browsers show as a moving image, and that OBS can use as a
+/// browser or media source. `/` serves a page showing the stream
+/// fullscreen. Connections are accepted on a background thread, frames
+/// are sent to every viewer by `send`.
+pub struct MjpegServer {
+    /// Address the server is listening on
+    addr: SocketAddr,
+    /// Viewers of `/stream`
+    clients: Arc<Mutex<Vec<TcpStream>>>
+}
+
+impl MjpegServer {
+    /// Start listening for viewers
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - address to listen on, such as `127.0.0.1:8080`
+    pub fn init(addr: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        thread::Builder::new().name(String::from("mjpeg accept")).spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A viewer that sends a bad request is simply dropped
+                if let Ok(Some(stream)) = answer(stream) {
+                    if let Ok(mut clients) = accepted.lock() {
+                        clients.push(stream);
+                    }
+                }
+            }
+        })?;
+        Ok(MjpegServer { addr, clients })
+    }
+
+    /// Get the address the server is listening on
+    pub fn get_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Get the number of viewers connected to the stream
+    pub fn get_clients(&self) -> usize {
+        self.clients.lock().map(|clients| clients.len()).unwrap_or(0)
+    }
+
+    /// Send a frame to every viewer, viewers that have disconnected or
+    /// can not keep up are dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - frame to send
+    pub fn send(&mut self, image: &RgbImage) -> io::Result<()> {
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(_) => return Ok(())
+        };
+        if clients.is_empty() {
+            return Ok(());
+        }
+        let jpeg = image.encode_jpeg(QUALITY)?;
+        let header = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY, jpeg.len()
+        );
+        clients.retain_mut(|client| {
+            client.write_all(header.as_bytes())
+                .and_then(|_| client.write_all(&jpeg))
+                .and_then(|_| client.write_all(b"\r\n"))
+                .is_ok()
+        });
+        Ok(())
+    }
+}
+
+/// Read a request and answer it, returning the connection if it asked
+/// for the stream
+fn answer(mut stream: TcpStream) -> io::Result<Option<TcpStream>> {
+    stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0; 512];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buf)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    match path {
+        "/stream" => {
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary={}\r\n\
+                 Cache-Control: no-cache\r\nConnection: close\r\n\r\n",
+                BOUNDARY
+            )?;
+            Ok(Some(stream))
+        },
+        "/" => {
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                INDEX_PAGE.len(), INDEX_PAGE
+            )?;
+            Ok(None)
+        },
+        _ => {
+            write!(stream, "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")?;
+            Ok(None)
+        }
+    }
+}
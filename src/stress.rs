@@ -0,0 +1,194 @@
+// Modules From Crates.io //
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+/// Address ROMs are loaded at
+const ROM_START: u16 = 0x200;
+/// Largest ROM that fits between 0x200 and 0xFFF
+const ROM_MAX: usize = 3584;
+
+/// Kinds of adversarial code blocks the generator can emit
+#[derive(Debug, Clone, Copy)]
+enum Block {
+    /// Chain of nested subroutine calls up to the full stack depth
+    CallChain,
+    /// Sprites drawn at and beyond the edges of the screen
+    BoundaryDraw,
+    /// Memory opcodes with I close to the end of memory
+    HighI,
+    /// Register arithmetic with random operands and flag registers
+    Arithmetic,
+    /// Conditional skips over a harmless load
+    Skip,
+    /// Delay and sound timer reads and writes
+    Timers,
+    /// Computed jump that lands on the next instruction
+    ComputedJump
+}
+
+const BLOCKS: [Block; 7] = [
+    Block::CallChain,
+    Block::BoundaryDraw,
+    Block::HighI,
+    Block::Arithmetic,
+    Block::Skip,
+    Block::Timers,
+    Block::ComputedJump
+];
+
+/// Settings for generating a stress ROM
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    /// Seed for the random generator, the same seed gives the same ROM
+    pub seed: u64,
+    /// How many blocks to generate (limited by the ROM size)
+    pub blocks: usize,
+    /// Deepest call chain to generate, 16 fills the stack exactly
+    pub max_depth: usize
+}
+
+impl StressConfig {
+    /// Create a config with default settings for a seed
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - seed for the random generator
+    pub fn init(seed: u64) -> Self {
+        StressConfig {
+            seed,
+            blocks: 64,
+            max_depth: 16
+        }
+    }
+}
+
+/// Generate a syntactically valid but adversarial chip8 ROM
+///
+/// The ROM is made of randomly chosen blocks that push the interpreter
+/// to its edge cases: call chains filling the stack, sprites drawn at
+/// the screen boundaries, I pointing at the last bytes of memory and
+/// arithmetic on VF. It ends with a jump to itself so a run settles.
+/// FX0A is never emitted since nothing will press a key.
+///
+/// # Arguments
+///
+/// * `config` - generator settings
+pub fn generate(config: &StressConfig) -> Vec<u8> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut rom = RomBuilder { bytes: Vec::with_capacity(ROM_MAX) };
+
+    for _ in 0..config.blocks {
+        let block = BLOCKS[rng.gen_range(0, BLOCKS.len())];
+        let mut candidate = RomBuilder { bytes: rom.bytes.clone() };
+        candidate.emit_block(block, &mut rng, config);
+        // Leave room for the final loop
+        if candidate.bytes.len() + 2 > ROM_MAX {
+            break;
+        }
+        rom = candidate;
+    }
+
+    let end = rom.addr();
+    rom.op(0x1000 | end);
+    rom.bytes
+}
+
+/// Helper to append opcodes while tracking addresses
+struct RomBuilder {
+    bytes: Vec<u8>
+}
+
+impl RomBuilder {
+    /// Address the next opcode will be placed at
+    fn addr(&self) -> u16 {
+        ROM_START + self.bytes.len() as u16
+    }
+
+    /// Append an opcode
+    fn op(&mut self, opcode: u16) {
+        self.bytes.push((opcode >> 8) as u8);
+        self.bytes.push((opcode & 0xff) as u8);
+    }
+
+    /// Append a block of the given kind
+    fn emit_block(&mut self, block: Block, rng: &mut StdRng, config: &StressConfig) {
+        match block {
+            Block::CallChain => {
+                // JP over the subroutines, each calls the next and the
+                // last just returns, then CALL into the first one
+                let depth = rng.gen_range(1, config.max_depth.max(1) + 1) as u16;
+                let first = self.addr() + 2;
+                let skip = first + (depth - 1) * 4 + 4;
+                self.op(0x1000 | skip);
+                for level in 0..depth - 1 {
+                    self.op(0x2000 | (first + (level + 1) * 4));
+                    self.op(0x00EE);
+                }
+                self.op(0x6000 | ((rng.gen_range(0, 15) as u16) << 8) | depth);
+                self.op(0x00EE);
+                self.op(0x2000 | first);
+            },
+            Block::BoundaryDraw => {
+                let edges_x = [0u8, 56, 60, 63, 64, 127, 255];
+                let edges_y = [0u8, 27, 30, 31, 32, 63, 255];
+                let x = edges_x[rng.gen_range(0, edges_x.len())];
+                let y = edges_y[rng.gen_range(0, edges_y.len())];
+                self.op(0x6000 | x as u16);
+                self.op(0x6100 | y as u16);
+                // Either a font sprite or random bytes from the ROM itself
+                match rng.gen::<bool>() {
+                    true => self.op(0xA050 + rng.gen_range(0, 16) * 5),
+                    false => self.op(0xA000 | (ROM_START + rng.gen_range(0, 0x100))),
+                }
+                self.op(0xD010 | rng.gen_range(1, 16));
+            },
+            Block::HighI => {
+                let x: u16 = rng.gen_range(0, 16);
+                self.op(0xA000 | (0xFFF - rng.gen_range(0, 4)));
+                match rng.gen_range(0, 5) {
+                    0 => self.op(0xF033 | (x << 8)),
+                    1 => self.op(0xF055 | (x << 8)),
+                    2 => self.op(0xF065 | (x << 8)),
+                    3 => self.op(0xF01E | (x << 8)),
+                    _ => self.op(0xD000 | (x << 8) | rng.gen_range(1, 16)),
+                }
+            },
+            Block::Arithmetic => {
+                let ops = [0x0u16, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE];
+                for _ in 0..rng.gen_range(1, 6) {
+                    let x: u16 = rng.gen_range(0, 16);
+                    let y: u16 = rng.gen_range(0, 16);
+                    match rng.gen_range(0, 3) {
+                        0 => self.op(0x6000 | (x << 8) | rng.gen::<u8>() as u16),
+                        1 => self.op(0x7000 | (x << 8) | rng.gen::<u8>() as u16),
+                        _ => self.op(0x8000 | (x << 8) | (y << 4) | ops[rng.gen_range(0, ops.len())]),
+                    }
+                }
+            },
+            Block::Skip => {
+                let x: u16 = rng.gen_range(0, 16);
+                let y: u16 = rng.gen_range(0, 16);
+                match rng.gen_range(0, 4) {
+                    0 => self.op(0x3000 | (x << 8) | rng.gen::<u8>() as u16),
+                    1 => self.op(0x4000 | (x << 8) | rng.gen::<u8>() as u16),
+                    2 => self.op(0x5000 | (x << 8) | (y << 4)),
+                    _ => self.op(0x9000 | (x << 8) | (y << 4)),
+                }
+                self.op(0x6000 | (x << 8) | rng.gen::<u8>() as u16);
+            },
+            Block::Timers => {
+                let x: u16 = rng.gen_range(0, 16);
+                match rng.gen_range(0, 3) {
+                    0 => self.op(0xF015 | (x << 8)),
+                    1 => self.op(0xF018 | (x << 8)),
+                    _ => self.op(0xF007 | (x << 8)),
+                }
+            },
+            Block::ComputedJump => {
+                self.op(0x6000);
+                let next = self.addr() + 2;
+                self.op(0xB000 | next);
+            }
+        }
+    }
+}
@@ -3,56 +3,234 @@
 use std::error;
 use std::fmt;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 // Local Modules Use //
-use memory::ChipMemory;
+use memory::{ChipMemory, RomError, RomWarning};
 use registers::ChipRegisters;
 use display::ChipDisplay;
 use keyboard::ChipKeyboard;
-
-// Modules From Crates.io //
-use rand::Rng;
+use quirks::Quirks;
+use stats::ChipStats;
+use sound::{AudioPattern, AudioSink};
+use runaway::{PcHistory, Runaway, RunawayReason};
+use timing::{TimerClock, TimingMode};
+use variant::Variant;
+use savestate::StateError;
+use random::{RandomSource, SeededRandom};
+use watchpoint::{WatchHit, WatchTarget, Watchpoint};
+use instruction::Instruction;
 
 // Local Modules //
 pub mod memory;
 pub mod registers;
 pub mod display;
 pub mod keyboard;
+pub mod quirks;
+pub mod stats;
+pub mod compare;
+pub mod explain;
+pub mod teach;
+pub mod stress;
+pub mod differential;
+pub mod image;
+pub mod heatmap;
+pub mod overlay;
+pub mod palette;
+pub mod export;
+pub mod sound;
+pub mod menu;
+pub mod recent;
+pub mod config;
+pub mod hotkeys;
+pub mod monitor;
+pub mod fonts;
+pub mod tickrate;
+pub mod attract;
+pub mod kiosk;
+pub mod focus;
+pub mod stream;
+pub mod termpad;
+pub mod trace;
+pub mod accessibility;
+pub mod trim;
+pub mod multicart;
+pub mod disasm;
+pub mod assembler;
+pub mod romdiff;
+pub mod flow;
+pub mod callgraph;
+pub mod frames;
+pub mod sprite;
+pub mod memwatch;
+pub mod netplay;
+pub mod tutorial;
+pub mod corrupt;
+pub mod testscript;
+pub mod json;
+pub mod archive;
+pub mod runaway;
+pub mod statedump;
+pub mod crashreport;
+pub mod ihex;
+pub mod timing;
+pub mod frontend;
+pub mod keymap;
+pub mod termkeys;
+pub mod variant;
+pub mod profile;
+pub mod savestate;
+pub mod rewind;
+pub mod replay;
+pub mod random;
+pub mod hexview;
+pub mod watchpoint;
+pub mod callstack;
+pub mod instruction;
+pub mod speed;
+
 
+/// Instructions per second run by `ChipSystem::run`, about the speed of
+/// the original COSMAC VIP interpreter
+pub const DEFAULT_IPS: u32 = 600;
 
 // Define a opcode execution error type //
-pub type ExResult<T> = std::result::Result<T, ExError>;
+pub type ExResult<T> = std::result::Result<T, EmulatorError>;
 
-#[derive(Debug, Clone)]
-pub struct ExError {
-    opcode: u16
+/// Why an opcode could not be executed
+///
+/// The system is left as it was before the opcode, with PC still
+/// pointing at it, so a caller can stop and report the error or skip
+/// the opcode with `registers.incr_pc()` and carry on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmulatorError {
+    /// The opcode is not part of any supported instruction set
+    InvalidOpcode {
+        /// Address of the opcode
+        pc: u16,
+        /// Opcode that could not be executed
+        opcode: u16
+    },
+    /// A CALL with all 16 stack entries in use
+    StackOverflow {
+        /// Address of the CALL
+        pc: u16,
+        /// The CALL opcode
+        opcode: u16
+    },
+    /// A RET with no call to return from
+    StackUnderflow {
+        /// Address of the RET
+        pc: u16,
+        /// The RET opcode
+        opcode: u16
+    },
+    /// The opcode was refused because execution escaped the program,
+    /// see `ChipSystem::set_runaway_check`
+    Runaway {
+        /// Opcode that was about to run
+        opcode: u16,
+        /// Where and how execution escaped
+        runaway: Runaway
+    }
 }
 
-impl fmt::Display for ExError {
+impl EmulatorError {
+    /// Get the address of the opcode that could not be executed
+    pub fn get_pc(&self) -> u16 {
+        match self {
+            EmulatorError::InvalidOpcode { pc, .. }
+            | EmulatorError::StackOverflow { pc, .. }
+            | EmulatorError::StackUnderflow { pc, .. } => *pc,
+            EmulatorError::Runaway { runaway, .. } => runaway.pc
+        }
+    }
+
+    /// Get the opcode that could not be executed
+    pub fn get_opcode(&self) -> u16 {
+        match self {
+            EmulatorError::InvalidOpcode { opcode, .. }
+            | EmulatorError::StackOverflow { opcode, .. }
+            | EmulatorError::StackUnderflow { opcode, .. }
+            | EmulatorError::Runaway { opcode, .. } => *opcode
+        }
+    }
+
+    /// Get where execution escaped the program, None for the other
+    /// errors
+    pub fn get_runaway(&self) -> Option<&Runaway> {
+        match self {
+            EmulatorError::Runaway { runaway, .. } => Some(runaway),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for EmulatorError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "invalid opcode previded for execution: {:04x}", self.opcode)
+        match self {
+            EmulatorError::InvalidOpcode { pc, opcode } => write!(
+                f, "invalid opcode {:04x} at 0x{:03x}", opcode, pc
+            ),
+            EmulatorError::StackOverflow { pc, opcode } => write!(
+                f, "stack overflow, {:04x} at 0x{:03x} called with all 16 stack entries in use", opcode, pc
+            ),
+            EmulatorError::StackUnderflow { pc, opcode } => write!(
+                f, "stack underflow, {:04x} at 0x{:03x} returned with nothing to return to", opcode, pc
+            ),
+            EmulatorError::Runaway { runaway, .. } => write!(f, "{}", runaway)
+        }
     }
 }
 
-impl error::Error for ExError {
+impl error::Error for EmulatorError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         // Generic error, underlying cause isn't tracked.
         None
     }
 }
 
+/// Why a program stopped running on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The SCHIP exit opcode 00FD was executed
+    ExitOpcode,
+    /// A jump to its own address, the usual way programs end since
+    /// nothing can break out of that loop, only reported once
+    /// `ChipSystem::set_exit_on_jump_self` turns it on
+    JumpToSelf
+}
+
+/// Handle to a frame callback, used to cancel it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+/// Function run by the system at a frame boundary
+pub type FrameFn = Box<dyn FnMut(&mut ChipSystem)>;
+
+/// A frame callback waiting to run
+struct FrameCallback {
+    /// Handle given to whoever registered it
+    id: CallbackId,
+    /// Frame number the callback runs at the end of
+    due: u64,
+    /// Frames between runs for repeating callbacks
+    every: Option<u64>,
+    /// Function to run
+    func: FrameFn
+}
+
 /// Representation of a 2 byte chip8 opcode
-struct Opcode {
-    h1: u16,
-    v1: u16, 
-    v2: u16, 
-    v3: u16
+pub(crate) struct Opcode {
+    pub(crate) h1: u16,
+    pub(crate) v1: u16, 
+    pub(crate) v2: u16, 
+    pub(crate) v3: u16
 }
 
 impl Opcode {
     /// create a new opcode struct from a 2 byte opcode
-    fn new(opcode: u16) -> Self {
+    pub(crate) fn new(opcode: u16) -> Self {
         let h1 = (opcode >> 12) & 0xf;
         let v1 = (opcode >> 8) & 0xf;
         let v2 = (opcode >> 4) & 0xf;
@@ -61,6 +239,16 @@ impl Opcode {
     }
 }
 
+/// Registers from `Vx` to `Vy` for the XO-CHIP 5XY2/5XY3 opcodes,
+/// counting down when x > y
+fn register_range(x: u8, y: u8) -> Box<dyn Iterator<Item = usize>> {
+    let (x, y) = (x as usize, y as usize);
+    match x <= y {
+        true => Box::new(x..=y),
+        false => Box::new((y..=x).rev())
+    }
+}
+
 
 /// A representation of the Chip8 Architecture
 pub struct ChipSystem {
@@ -72,6 +260,48 @@ pub struct ChipSystem {
     pub ram: ChipMemory,
     /// Keyboard and related functions
     pub keyboard: ChipKeyboard,
+    /// Interpreter behaviour differences to emulate
+    pub quirks: Quirks,
+    /// Instruction set extensions decoded
+    pub variant: Variant,
+    /// Per frame statistics
+    pub stats: ChipStats,
+    /// XO-CHIP audio pattern and pitch
+    pub audio: AudioPattern,
+    /// Set once the program has exited
+    exit: Option<ExitReason>,
+    /// Number of frames run by `run_frame`
+    frames: u64,
+    /// Callbacks waiting for a frame boundary
+    callbacks: Vec<FrameCallback>,
+    /// Id given to the next registered callback
+    next_callback: u64,
+    /// Callback being run and whether it cancelled itself
+    running_callback: Option<(CallbackId, bool)>,
+    /// Generator for the random opcode
+    rng: Box<dyn RandomSource>,
+    /// Seed the generator started from, None for a source given by
+    /// `set_random_source`
+    seed: Option<u64>,
+    /// Program counters of the last opcodes executed
+    pc_history: PcHistory,
+    /// Refuse to run opcodes once execution escapes the program
+    runaway_check: bool,
+    /// Treat a jump to its own address as the end of the program
+    exit_on_jump_self: bool,
+    /// Timer ticks for opcodes run one at a time outside of frames
+    timer_clock: TimerClock,
+    /// How long instructions take to run
+    timing: TimingMode,
+    /// Machine cycles left in the frame with VIP timing, below zero
+    /// when the last instruction ran past the end of the frame
+    vip_cycles_left: i64,
+    /// Told when the sound timer starts and stops
+    audio_sink: Option<Box<dyn AudioSink>>,
+    /// True while the audio sink has been told the sound is playing
+    sounding: bool,
+    /// SCHIP RPL user flags, saved and loaded by FX75/FX85
+    rpl: [u8; 16],
 }
 
 impl ChipSystem {
@@ -81,19 +311,134 @@ impl ChipSystem {
         let disp = ChipDisplay::init();
         let reg = ChipRegisters::init();
         let key = ChipKeyboard::init();
+        let rng = SeededRandom::from_entropy();
+        let seed = rng.get_seed();
         ChipSystem {
             registers: reg,
             display: disp,
-            ram: ram,
-            keyboard: key
+            ram,
+            keyboard: key,
+            quirks: Quirks::default(),
+            variant: Variant::default(),
+            stats: ChipStats::init(),
+            audio: AudioPattern::init(),
+            exit: None,
+            frames: 0,
+            callbacks: Vec::new(),
+            next_callback: 0,
+            running_callback: None,
+            rng: Box::new(rng),
+            seed: Some(seed),
+            pc_history: PcHistory::init(),
+            runaway_check: false,
+            exit_on_jump_self: false,
+            timer_clock: TimerClock::init(DEFAULT_IPS),
+            timing: TimingMode::default(),
+            vip_cycles_left: timing::vip_frame_budget(),
+            audio_sink: None,
+            sounding: false,
+            rpl: [0; 16]
         }
     }
 
+    /// Turn detection of execution escaping the program on or off, it
+    /// is off by default. Once a ROM is loaded, running an opcode outside
+    /// of it or a 0000 word is an error reporting the recent program
+    /// counters. Programs that run code they write past their own end
+    /// need it left off.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true to detect runaway execution
+    pub fn set_runaway_check(&mut self, enabled: bool) {
+        self.runaway_check = enabled;
+    }
+
+    /// Returns true if runaway execution is detected
+    pub fn get_runaway_check(&self) -> bool {
+        self.runaway_check
+    }
+
+    /// Turn ending the program on a jump to its own address on or off,
+    /// it is off by default so only 00FD exits. Programs that idle in a
+    /// final `JMP $` loop keep their last frame on screen while it is
+    /// off, scripted runs turn it on to stop there.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true to exit on a jump to itself
+    pub fn set_exit_on_jump_self(&mut self, enabled: bool) {
+        self.exit_on_jump_self = enabled;
+    }
+
+    /// Returns true if a jump to its own address ends the program
+    pub fn get_exit_on_jump_self(&self) -> bool {
+        self.exit_on_jump_self
+    }
+
+    /// Get the program counters of the last opcodes executed, oldest
+    /// first
+    pub fn get_pc_history(&self) -> Vec<u16> {
+        self.pc_history.get_pcs()
+    }
+
+    /// Get the last opcodes executed with their program counters,
+    /// oldest first
+    pub fn get_instruction_history(&self) -> Vec<(u16, u16)> {
+        self.pc_history.get_instructions()
+    }
+
+    /// Check the opcode about to run is part of the program and record
+    /// its address in the history
+    fn check_runaway(&mut self, opcode: u16) -> ExResult<()> {
+        let pc = self.registers.get_pc();
+        if let (true, Some((start, end))) = (self.runaway_check, self.ram.get_rom_range()) {
+            let reason = match (pc as usize) < start || pc as usize >= end {
+                true => Some(RunawayReason::OutsideRom(start, end)),
+                false if opcode == 0x0000 => Some(RunawayReason::ZeroOpcode),
+                false => None
+            };
+            if let Some(reason) = reason {
+                let runaway = Runaway { pc, reason, history: self.pc_history.get_pcs() };
+                return Err(EmulatorError::Runaway { opcode, runaway });
+            }
+        }
+        self.pc_history.record(pc, opcode);
+        Ok(())
+    }
+
+    /// Seed the random numbers of the CXKK opcode, two systems seeded
+    /// the same and given the same input run identically. Unless seeded
+    /// a system starts from a seed picked at random, see `get_seed`
+    /// 
+    /// # Arguments
+    /// 
+    /// * `seed` - seed of the generator
+    pub fn seed_random(&mut self, seed: u64) {
+        self.rng = Box::new(SeededRandom::init(seed));
+        self.seed = Some(seed);
+    }
+
+    /// Get the seed of the random numbers, to run the same again with
+    /// `seed_random`. None once `set_random_source` gave another source
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Give the system its own source of random numbers for the CXKK
+    /// opcode, such as a fixed sequence for tests
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - generator of the random bytes
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.rng = source;
+        self.seed = None;
+    }
+
     /// Return a random u8
-    fn random_byte() -> u8 {
-        let mut rng = rand::thread_rng();
-        let rand_byte: u8 = rng.gen();
-        return rand_byte;
+    fn random_byte(&mut self) -> u8 {
+        self.rng.random_byte()
     }
 
     /// Execute a Chip8 Opcode
@@ -132,371 +477,935 @@ impl ChipSystem {
     /// 24. 0xEx9E - Skip next instruction if key with value `Vx` is pressed
     /// 25. 0xExA1 - Skip next instruction if key with value `Vx` is not pressed
     /// 26. 0xFx07 - Set the value in `Vx` to the delay timer
-    /// 27. 0xFx0A - Wait for keypress, store value in `Vx`
+    /// 27. 0xFx0A - Wait for a key to be pressed and released, store it in `Vx`
     /// 28. 0xFx15 - Set the delay timer to value in `Vx`
     /// 29. 0xFx18 - Set the sound timer to value in `Vx`
     /// 30. 0xFx1E - Set value of `I` to `I = I + Vx`
-    /// 31. 0xFx29 - Set I to location of sprite location in `Vx`
+    /// 31. 0xFx29 - Set I to the font sprite for the digit in `Vx`
     /// 32. 0xFx33 - Store BCD of `Vx` in `I, I+1, I+2`
     /// 33. 0xFx55 - Store `V0 -> Vx` at I
     /// 34. 0xFx65 - Retrieve `V0 -> Vx` from I
-    ///  
+    /// 35. 0xFx30 - Set I to the large font sprite for `Vx` (SCHIP)
+    /// 36. 0xF000 nnnn - Set `I` to the 16 bit address `nnnn` (XO-CHIP,
+    ///     only with extended memory)
+    /// 37. 0xF002 - Load the 16 byte audio pattern from I (XO-CHIP)
+    /// 38. 0xFx3A - Set the audio pitch to `Vx` (XO-CHIP)
+    /// 39. 0xFn01 - Draw on the planes in the bitmask `n` (XO-CHIP)
+    /// 40. 0x5xy2 - Store `Vx -> Vy` at I (XO-CHIP)
+    /// 41. 0x5xy3 - Retrieve `Vx -> Vy` from I (XO-CHIP)
+    /// 42. 0x00Dn - Scroll the display up `n` rows (XO-CHIP)
+    ///
+    /// Each opcode run is logged through the `log` crate at trace level
+    /// with its address, mnemonic and the registers it changed, see
+    /// `trace::TraceLogger`.
     pub fn ex_opcode(&mut self, opcode: u16) -> ExResult<()> {
-        let comps = Opcode::new(opcode);
-        let mut update_pc = true;
-        match comps.h1 {
-            0x0 => {
-                match comps.v3 {
-                    // CLS - Clear Display
-                    0 => self.display.clear_display(),
-                    // RET - Return from subroutine
-                    14 => {
-                        let pc: u16 = self.registers.pop_stack();
-                        self.registers.set_pc(pc);
-                        // update_pc = false;
-                    },
-                    // Skip Opcode
-                    _ => {},
-                }
+        // Taking the registers costs on every opcode, only do it when
+        // someone is listening
+        if !log::log_enabled!(log::Level::Trace) {
+            return self.execute_opcode(opcode);
+        }
+        let before = trace::RegisterSnapshot::take(self);
+        let result = self.execute_opcode(opcode);
+        match &result {
+            Ok(()) => log::trace!("{}", trace::log_line(&before, self, opcode)),
+            Err(e) => log::trace!("{:03x}: {:04x}  {}  failed: {}", before.pc, opcode, disasm::mnemonic(opcode), e)
+        }
+        result
+    }
+
+    /// Execute an opcode, see `ex_opcode`
+    fn execute_opcode(&mut self, opcode: u16) -> ExResult<()> {
+        self.check_runaway(opcode)?;
+        let instruction = Instruction::decode_for(opcode, self.variant);
+        self.stats.record_instruction();
+        if self.timing == TimingMode::Vip {
+            self.vip_cycles_left -= timing::vip_cycles(&instruction) as i64;
+            // The VIP draws during the display interrupt, so nothing
+            // else runs until the next frame
+            if let Instruction::Draw(_, _, _) = instruction {
+                self.vip_cycles_left = self.vip_cycles_left.min(0);
+            }
+        }
+        if self.execute(instruction, opcode)? {
+            // Increment program counter after opcode execution
+            self.registers.incr_pc()
+        }
+        Ok(())
+    }
+
+    /// Execute a decoded instruction, returning false if it set the
+    /// program counter itself
+    ///
+    /// # Arguments
+    ///
+    /// * `instruction` - instruction to execute
+    /// * `opcode` - opcode it was decoded from, for errors
+    fn execute(&mut self, instruction: Instruction, opcode: u16) -> ExResult<bool> {
+        let invalid = EmulatorError::InvalidOpcode { pc: self.registers.get_pc(), opcode };
+        match instruction {
+            Instruction::Clear => {
+                let toggles = self.display.get_toggle_count();
+                self.display.clear_display();
+                self.stats.record_clear(self.display.get_toggle_count() - toggles);
             },
-            // JP - Jumps to address without modifying stack
-            0x1 => {
-                let pc: u16 = (comps.v1 << 8) + (comps.v2 << 4) + comps.v3;
+            Instruction::Return => {
+                let pc = self.registers.pop_stack().ok_or(EmulatorError::StackUnderflow {
+                    pc: self.registers.get_pc(),
+                    opcode
+                })?;
+                // Back at the CALL, which the program counter steps over
                 self.registers.set_pc(pc);
-                update_pc = false;
             },
-            // CALL - Jump to address with push to stack
-            0x2 => {
-                let new_pc: u16 = (comps.v1 << 8) + (comps.v2 << 4) + comps.v3;
+            Instruction::Exit => {
+                self.exit = Some(ExitReason::ExitOpcode);
+                return Ok(false);
+            },
+            Instruction::ScrollDown(n) => self.display.scroll(0, n as isize),
+            Instruction::ScrollUp(n) => self.display.scroll(0, -(n as isize)),
+            Instruction::ScrollRight => self.display.scroll(4, 0),
+            Instruction::ScrollLeft => self.display.scroll(-4, 0),
+            Instruction::LowRes => self.display.set_size(display::DISPLAY_WIDTH, display::DISPLAY_HEIGHT),
+            Instruction::HighRes => self.display.set_size(display::DISPLAY_WIDTH * 2, display::DISPLAY_HEIGHT * 2),
+            // Machine code routines can not be run, they are ignored
+            Instruction::Sys(_) => {},
+            Instruction::Jump(addr) => {
+                if self.exit_on_jump_self && addr == self.registers.get_pc() {
+                    self.exit = Some(ExitReason::JumpToSelf);
+                }
+                self.registers.set_pc(addr);
+                return Ok(false);
+            },
+            Instruction::Call(addr) => {
                 let cur_pc = self.registers.get_pc();
-                self.registers.push_stack(cur_pc);
-                self.registers.set_pc(new_pc);
-                update_pc = false;
-            },
-            // SE Vx, Byte - Skip instruction if Vx == Byte
-            0x3 => {
-                let comp_val: u8 = ((comps.v2 as u8) << 4) + comps.v3 as u8;
-                let reg_val: u8 = self.registers.get_gp(comps.v1 as usize);
-                if comp_val == reg_val {
-                    self.registers.incr_pc();
+                if !self.registers.push_stack(cur_pc) {
+                    return Err(EmulatorError::StackOverflow { pc: cur_pc, opcode });
                 }
+                self.registers.set_pc(addr);
+                return Ok(false);
             },
-            // SNE Vx, Byte - Skip instruction if Vx != Byte
-            0x4 => {
-                let comp_val: u8 = ((comps.v2 as u8) << 4) + comps.v3 as u8;
-                let reg_val: u8 = self.registers.get_gp(comps.v1 as usize);
-                if comp_val != reg_val {
-                    self.registers.incr_pc();
+            Instruction::SkipEqByte(x, kk) => {
+                if self.gp(x) == kk {
+                    self.skip_next();
                 }
             },
-            // SE Vx, Vy - Skip instruction if Vx == Vy
-            0x5 => {
-                let reg_x_val: u8 = self.registers.get_gp(comps.v1 as usize);
-                let reg_y_val: u8 = self.registers.get_gp(comps.v2 as usize);
-                if reg_x_val == reg_y_val {
-                    self.registers.incr_pc();
+            Instruction::SkipNeByte(x, kk) => {
+                if self.gp(x) != kk {
+                    self.skip_next();
                 }
             },
-            // LD Vx, Byte - Load byte value into Vx (Vx = Byte)
-            0x6 => {
-                let value: u8 = ((comps.v2 as u8) << 4) + comps.v3 as u8;
-                self.registers.set_gp(comps.v1 as usize, value);
-            },
-            // ADD Vx, Byte - Add byte value to Vx (Vx += Byte) no carry flag
-            0x7 => {
-                let value: u16 = ((comps.v2) << 4) + comps.v3;
-                self.registers.add_gp(comps.v1 as usize, (value & 0xff) as u8);
-            },
-            0x8 => {
-                match comps.v3 {
-                    // LD Vx, Vy - Store value of Vy in Vx (Vx = Vy)
-                    0x0 => {
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        self.registers.set_gp(comps.v1 as usize, reg_y_val);
-                    },
-                    // OR Vx, Vy - Bitwise OR on Vx, Vy store in Vx (Vx = Vx | Vy)
-                    0x1 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        let value = reg_x_val | reg_y_val;
-                        self.registers.set_gp(comps.v1 as usize, value);
-                    },
-                    // AND Vx, Vy - Bitwise AND on Vx, Vy store in Vx (Vx = Vx & Vy)
-                    0x2 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        let value = reg_x_val & reg_y_val;
-                        self.registers.set_gp(comps.v1 as usize, value);
-                    },
-                    // XOR Vx, Vy - Bitwise XOR on Vx, Vy store in Vx (Vx = Vx ^ Vy)
-                    0x3 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        let value = reg_x_val ^ reg_y_val;
-                        self.registers.set_gp(comps.v1 as usize, value);
-                    },
-                    // ADD Vx, Vy - Add Vx, Vy if > 255 set Vf to 1 (Vx = Vx + Vy)
-                    0x4 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        let holder: u16 = reg_x_val as u16 + reg_y_val as u16;
-                        match holder > 255 {
-                            true => self.registers.set_gp(15, 1),
-                            false => self.registers.set_gp(15, 0)
-                        }
-                        self.registers.set_gp(comps.v1 as usize, (holder & 0xff) as u8);
-                    },
-                    // SUB Vx, Vy - Subtract Vx, Vy if Vx < Vy set Vf to 0 (Vx = Vx - Vy)
-                    0x5 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        match reg_x_val < reg_y_val {
-                            true => self.registers.set_gp(15, 0),
-                            false => self.registers.set_gp(15, 1)
-                        }
-                        let holder = reg_x_val - reg_y_val;
-                        self.registers.set_gp(comps.v1 as usize, holder);
-                    },
-                    // SHR Vx, _ - Shift Vx right by 1, set Vf to LSB (Vx = Vx >> 1)
-                    0x6 => {
-                        let mut reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        self.registers.set_gp(15, reg_x_val & 0x01);
-                        reg_x_val = reg_x_val >> 1; 
-                        self.registers.set_gp(comps.v1 as usize, reg_x_val);
-                    },
-                    // SUBN Vx, Vy - Subtract Vy, Vx if Vy < Vx set Vf to 0 (Vx = Vy - Vx)
-                    0x7 => {
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                        match reg_y_val < reg_x_val {
-                            true => self.registers.set_gp(15, 0),
-                            false => self.registers.set_gp(15, 1)
-                        }
-                        let holder = reg_y_val - reg_x_val;
-                        self.registers.set_gp(comps.v1 as usize, holder);
-                    },
-                    // SHL Vx, _ - Shift Vx left by 1, set Vf to MSB (Vx = Vx << 1)
-                    0xE => {
-                        let mut reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        self.registers.set_gp(15, reg_x_val & 0x80);
-                        reg_x_val = reg_x_val << 1;
-                        self.registers.set_gp(comps.v1 as usize, reg_x_val);
-                    },
-                    _ => return Err(ExError {opcode})
+            Instruction::SkipEqReg(x, y) => {
+                if self.gp(x) == self.gp(y) {
+                    self.skip_next();
                 }
             },
-            // SNE Vx, Vy - Skip next instruction if Vx != Vy
-            0x9 => {
-                let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                let reg_y_val = self.registers.get_gp(comps.v2 as usize);
-                if reg_x_val != reg_y_val {
-                    self.registers.incr_pc();
+            // Stored in reverse order when x > y, I is unchanged
+            Instruction::StoreRange(x, y) => {
+                let i_val = self.registers.get_i();
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    let value = self.registers.get_gp(reg);
+                    self.ram.set_byte(i_val.wrapping_add(offset as u16), value);
                 }
             },
-            // LD I, Addr (12bit) - Register I is set to the address
-            0xA => {
-                let value = (comps.v1 << 8) + (comps.v2 << 4) + comps.v3;
-                self.registers.set_i(value);
+            // Loaded in reverse order when x > y, I is unchanged
+            Instruction::LoadRange(x, y) => {
+                let i_val = self.registers.get_i();
+                for (offset, reg) in register_range(x, y).enumerate() {
+                    let value = self.ram.get_byte(i_val.wrapping_add(offset as u16));
+                    self.registers.set_gp(reg, value);
+                }
             },
-            // JP V0, Addr (12bit) - Jump to the location Addr + V0
-            0xB => {
-                let reg_v0_val = self.registers.get_gp(0);
-                let address = (comps.v1 << 8) + (comps.v2 << 4) + comps.v3;
-                self.registers.set_pc(address + reg_v0_val as u16);
-                update_pc = false;
-            },
-            // RND Vx, Byte - Set Vx to Byte & Random byte
-            0xC => {
-                let byte_val: u8 = ((comps.v2 as u8) << 4) + comps.v3 as u8;
-                let value = byte_val & ChipSystem::random_byte(); 
-                self.registers.set_gp(comps.v1 as usize, value);
-            },
-            // DRW Vx, Vy, N - Draw a sprite coord (Vx, Vy) with height N
-            0xD => {
-                let x_loc = self.registers.get_gp(comps.v1 as usize) as u16;
-                let y_loc = self.registers.get_gp(comps.v2 as usize) as u16;
-                // let y_loc = comps.v2;
-                let nbytes = comps.v3;
-                let sprite_mem_loc = self.registers.get_i();
-                let sprite_bytes = self.ram.get_nbytes(sprite_mem_loc, nbytes);
-                let overlap = self.display.draw_sprite(x_loc, y_loc, sprite_bytes);
-                match overlap {
-                    true => self.registers.set_gp(15, 1),
-                    false => self.registers.set_gp(15, 0),
+            Instruction::LoadByte(x, kk) => self.registers.set_gp(x as usize, kk),
+            Instruction::AddByte(x, kk) => self.registers.add_gp(x as usize, kk),
+            Instruction::LoadReg(x, y) => self.registers.set_gp(x as usize, self.gp(y)),
+            Instruction::Or(x, y) => self.logic(x, self.gp(x) | self.gp(y)),
+            Instruction::And(x, y) => self.logic(x, self.gp(x) & self.gp(y)),
+            Instruction::Xor(x, y) => self.logic(x, self.gp(x) ^ self.gp(y)),
+            Instruction::AddReg(x, y) => {
+                let holder = self.gp(x) as u16 + self.gp(y) as u16;
+                self.registers.set_gp(x as usize, (holder & 0xff) as u8);
+                self.registers.set_gp(15, (holder > 255) as u8);
+            },
+            Instruction::Sub(x, y) => {
+                let (reg_x_val, reg_y_val) = (self.gp(x), self.gp(y));
+                self.registers.set_gp(x as usize, reg_x_val.wrapping_sub(reg_y_val));
+                self.registers.set_gp(15, (reg_x_val >= reg_y_val) as u8);
+            },
+            Instruction::ShiftRight(x, y) => {
+                let reg_x_val = self.shift_source(x, y);
+                self.registers.set_gp(x as usize, reg_x_val >> 1);
+                self.registers.set_gp(15, reg_x_val & 0x01);
+            },
+            Instruction::SubN(x, y) => {
+                let (reg_x_val, reg_y_val) = (self.gp(x), self.gp(y));
+                self.registers.set_gp(x as usize, reg_y_val.wrapping_sub(reg_x_val));
+                self.registers.set_gp(15, (reg_y_val >= reg_x_val) as u8);
+            },
+            Instruction::ShiftLeft(x, y) => {
+                let reg_x_val = self.shift_source(x, y);
+                self.registers.set_gp(x as usize, reg_x_val << 1);
+                self.registers.set_gp(15, reg_x_val >> 7);
+            },
+            Instruction::SkipNeReg(x, y) => {
+                if self.gp(x) != self.gp(y) {
+                    self.skip_next();
                 }
             },
-            0xE => {
-                match (comps.v2 << 4) + comps.v3 {
-                    // SKP Vx - Skip next instruction if key (0-15) is pressed
-                    0x9E => {
-                        let index = comps.v1 as u8;
-                        let key_val = self.keyboard.get_key(index);
-                        if key_val {
-                            self.registers.incr_pc();
-                        }
+            Instruction::LoadI(addr) => self.registers.set_i(addr),
+            // Addr + Vx with the jump_uses_vx quirk, x being the top
+            // nibble of the address
+            Instruction::JumpOffset(addr) => {
+                let offset = match self.quirks.jump_uses_vx {
+                    true => self.gp((addr >> 8) as u8),
+                    false => self.gp(0)
+                };
+                self.registers.set_pc(addr + offset as u16);
+                return Ok(false);
+            },
+            Instruction::Random(x, kk) => {
+                let value = kk & self.random_byte();
+                self.registers.set_gp(x as usize, value);
+            },
+            Instruction::Draw(x, y, n) => {
+                let x_loc = self.gp(x) as u16;
+                let y_loc = self.gp(y) as u16;
+                let sprite_mem_loc = self.registers.get_i();
+                let clip = self.quirks.clip_sprites;
+                let toggles = self.display.get_toggle_count();
+                // XO-CHIP keeps the sprite for each selected plane one
+                // after the other
+                let planes = self.display.get_planes().count_ones() as u16;
+                // DRW Vx, Vy, 0 - Draw a 16x16 sprite (SCHIP)
+                let (nbytes, overlap) = match n == 0 && self.variant.has_schip() {
+                    true => {
+                        let sprite_bytes = self.ram.get_nbytes(sprite_mem_loc, 32 * planes);
+                        (32 * planes, self.display.draw_sprite_16(x_loc, y_loc, sprite_bytes, clip))
                     },
-                    // SKNP Vx - Skip next instruction if key (0-15) is not pressed
-                    0xA1 => {
-                        let index = comps.v1 as u8;
-                        let key_val = self.keyboard.get_key(index);
-                        if !key_val {
-                            self.registers.incr_pc();
-                        }
+                    false => {
+                        let sprite_bytes = self.ram.get_nbytes(sprite_mem_loc, n as u16 * planes);
+                        (n as u16 * planes, self.display.draw_sprite(x_loc, y_loc, sprite_bytes, clip))
                     }
-                    _ => return Err(ExError {opcode})
+                };
+                let toggled = self.display.get_toggle_count() - toggles;
+                self.stats.record_draw(nbytes as u64, overlap, toggled);
+                self.registers.set_gp(15, overlap as u8);
+            },
+            Instruction::SkipKey(x) => {
+                if self.keyboard.get_key(self.gp(x) & 0xf) {
+                    self.skip_next();
                 }
             },
-            0xF => {
-                match (comps.v2 << 4) + comps.v3 {
-                    // LD Vx, DT - Set Vx to the value of the delay timer
-                    0x07 => {
-                        let delay_val = self.registers.get_d();
-                        self.registers.set_gp(comps.v1 as usize, delay_val);
-                    },
-                    // LD Vx, K - Wait for keypress (halt), put key value in Vx
-                    0x0A => {
-                        let key = self.keyboard.wait_key();
-                        let index = comps.v1 as usize;
-                        self.registers.set_gp(index, key); 
-                    },
-                    // LD DT, Vx - Set the delay timer to the value in Vx
-                    0x15 => {
-                        let delay_val = comps.v1 as u8;
-                        self.registers.set_d(delay_val);
-                    },
-                    // LD ST, Vx - Set the sound timer to the value in Vx
-                    0x18 => {
-                        let delay_val = comps.v1 as u8;
-                        self.registers.set_s(delay_val);
-                    },
-                    // ADD I, Vx - Set register I to I + Vx
-                    0x1E => {
-                        let i_val = self.registers.get_i();
-                        let reg_x_val = self.registers.get_gp(comps.v1 as usize);
-                        let value = i_val + reg_x_val as u16;
-                        self.registers.set_i(value);
-                    },
-                    // LD F, Vx - Set I to the location of sprite (I = Vx * 5)
-                    0x29 => {
-                        let reg_x_val = comps.v1;
-                        let new_i_val = reg_x_val * 5;
-                        self.registers.set_i(new_i_val);
-                    },
-                    // LD B, Vx - Place the BCD of Vx in I (Hundreds), I+1 (Tens), I+2 (Ones)
-                    0x33 => {
-                        let reg_val = self.registers.get_gp(comps.v1 as usize);
-                        let i_val = self.registers.get_i();
-                        let ones = reg_val % 10;
-                        let tens = (reg_val / 10) % 10;
-                        let huns = (reg_val / 100) % 10;
-                        self.ram.set_byte(i_val, huns);
-                        self.ram.set_byte(i_val + 1, tens);
-                        self.ram.set_byte(i_val + 2, ones);
-                    },
-                    // LD I, Vx - Stores V0 to Vx in memory starting at address I, then (I = I + x + 1)
-                    0x55 => {
-                        let i_val = self.registers.get_i();
-                        let x_range = comps.v1;
-                        let mut cur_reg: u8;
-                        for loc in 0..x_range {
-                            cur_reg = self.registers.get_gp(loc as usize);
-                            self.ram.set_byte(i_val + loc, cur_reg);
-                        }
-                        let new_i = i_val + x_range + 1;
-                        self.registers.set_i(new_i);
-                    },
-                    // LD Vx, I - Fills V0 to Vx with values from memory starting at address then (I = I + x + 1)
-                    0x65 => {
-                        let i_val = self.registers.get_i();
-                        let x_range = comps.v1;
-                        let mut cur_reg: u8;
-                        for loc in 0..x_range {
-                            cur_reg = self.ram.get_byte(i_val + loc);
-                            self.registers.set_gp(loc as usize, cur_reg);
-                        }
+            Instruction::SkipNotKey(x) => {
+                if !self.keyboard.get_key(self.gp(x) & 0xf) {
+                    self.skip_next();
+                }
+            },
+            Instruction::LoadILong if self.ram.is_extended() => {
+                let pc = self.registers.get_pc();
+                let address = self.ram.get_opcode(pc.wrapping_add(2));
+                self.registers.set_i(address);
+                self.registers.incr_pc();
+            },
+            Instruction::LoadILong => return Err(invalid),
+            Instruction::Plane(n) => self.display.select_planes(n),
+            Instruction::Audio => {
+                let bytes = self.ram.get_nbytes(self.registers.get_i(), 16);
+                let mut pattern = [0; 16];
+                pattern.copy_from_slice(&bytes);
+                self.audio.set_pattern(pattern);
+            },
+            Instruction::Pitch(x) => self.audio.set_pitch(self.gp(x)),
+            Instruction::ReadDelay(x) => self.registers.set_gp(x as usize, self.registers.get_d()),
+            // The PC is not advanced until a key is pressed and
+            // released so the opcode runs again on the next cycle
+            Instruction::WaitKey(x) => {
+                match self.keyboard.wait_release() {
+                    Some(key) => {
+                        self.registers.set_gp(x as usize, key);
+                        self.keyboard.set_waiting(None);
                     },
-                    _ => return Err(ExError {opcode})
+                    None => {
+                        self.keyboard.set_waiting(Some(x));
+                        return Ok(false);
+                    }
                 }
-            }
-            _ => return Err(ExError {opcode})
+            },
+            Instruction::SetDelay(x) => self.registers.set_d(self.gp(x)),
+            Instruction::SetSound(x) => self.registers.set_s(self.gp(x)),
+            // VF = 1 past 0xFFF with the add_i_sets_vf quirk
+            Instruction::AddI(x) => {
+                let value = self.registers.get_i().wrapping_add(self.gp(x) as u16);
+                self.registers.set_i(value);
+                if self.quirks.add_i_sets_vf {
+                    self.registers.set_gp(15, (value > 0xFFF) as u8);
+                }
+            },
+            Instruction::Font(x) => {
+                let digit = self.gp(x) & 0xf;
+                self.registers.set_i(memory::FONT_START as u16 + digit as u16 * 5);
+            },
+            Instruction::BigFont(x) => {
+                let digit = self.gp(x) & 0xf;
+                self.registers.set_i(memory::BIG_FONT_START as u16 + digit as u16 * 10);
+            },
+            // Hundreds at I, tens at I+1 and ones at I+2
+            Instruction::Bcd(x) => {
+                let reg_val = self.gp(x);
+                let i_val = self.registers.get_i();
+                self.ram.set_byte(i_val, (reg_val / 100) % 10);
+                self.ram.set_byte(i_val.wrapping_add(1), (reg_val / 10) % 10);
+                self.ram.set_byte(i_val.wrapping_add(2), reg_val % 10);
+            },
+            // I = I + x + 1 afterwards with the load_store_incr_i quirk
+            Instruction::StoreRegs(x) => {
+                let i_val = self.registers.get_i();
+                for loc in 0..=x as u16 {
+                    let cur_reg = self.registers.get_gp(loc as usize);
+                    self.ram.set_byte(i_val.wrapping_add(loc), cur_reg);
+                }
+                if self.quirks.load_store_incr_i {
+                    self.registers.set_i(i_val.wrapping_add(x as u16 + 1));
+                }
+            },
+            Instruction::LoadRegs(x) => {
+                let i_val = self.registers.get_i();
+                for loc in 0..=x as u16 {
+                    let cur_reg = self.ram.get_byte(i_val.wrapping_add(loc));
+                    self.registers.set_gp(loc as usize, cur_reg);
+                }
+                if self.quirks.load_store_incr_i {
+                    self.registers.set_i(i_val.wrapping_add(x as u16 + 1));
+                }
+            },
+            Instruction::SaveFlags(x) => {
+                for reg in 0..=x as usize {
+                    self.rpl[reg] = self.registers.get_gp(reg);
+                }
+            },
+            Instruction::LoadFlags(x) => {
+                for reg in 0..=x as usize {
+                    self.registers.set_gp(reg, self.rpl[reg]);
+                }
+            },
+            Instruction::Invalid(_) => return Err(invalid)
         }
-        // Increment program counter after opcode execution
-        if update_pc {
-            self.registers.incr_pc()
+        Ok(true)
+    }
+
+    /// Get the value of a general purpose register
+    fn gp(&self, reg: u8) -> u8 {
+        self.registers.get_gp(reg as usize)
+    }
+
+    /// Store the result of 8XY1/8XY2/8XY3 in `Vx`, clearing VF after
+    /// it with the vf_reset quirk
+    fn logic(&mut self, x: u8, value: u8) {
+        self.registers.set_gp(x as usize, value);
+        if self.quirks.vf_reset {
+            self.registers.set_gp(15, 0);
+        }
+    }
+
+    /// Skip the next instruction, with extended memory the four byte
+    /// F000 nnnn instruction is skipped as a whole
+    fn skip_next(&mut self) {
+        let next = self.registers.get_pc().wrapping_add(2);
+        if self.ram.is_extended() && self.ram.get_opcode(next) == 0xF000 {
+            self.registers.incr_pc();
+        }
+        self.registers.incr_pc();
+    }
+
+    /// Value shifted by 8XY6/8XYE, `Vy` with the shift_uses_vy quirk
+    /// and `Vx` otherwise
+    fn shift_source(&self, x: u8, y: u8) -> u8 {
+        match self.quirks.shift_uses_vy {
+            true => self.gp(y),
+            false => self.gp(x)
         }
-        return Ok(());
     }
 
     fn get_next_opcode(&self) -> u16 {
         let mut index = self.registers.get_pc();
-        if index % 2 != 0 {
+        if !index.is_multiple_of(2) {
             index -= 1;
-            println!("Program Counter is not even: {}", index);
+            log::warn!("Program Counter is not even: {}", index);
             // panic!("Program Counter register invalid")
         }
-        self.ram.get_opcode(index)
+        self.ram.fetch_opcode(index)
     }
 
-    /// Run the chip8 emulator in an infinite loop
+    /// Run the chip8 emulator until the program exits or fails, at
+    /// `DEFAULT_IPS` instructions per second
     pub fn run(&mut self) {
+        self.run_at(DEFAULT_IPS)
+    }
+
+    /// Run the chip8 emulator until the program exits or fails, drawing
+    /// the display in the terminal when it changes
+    ///
+    /// Each 60hz frame fetches and executes about `ips / 60` opcodes
+    /// then counts the timers down once, and frames are paced to real
+    /// time.
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - instructions executed per second
+    pub fn run_at(&mut self, ips: u32) {
+        if !self.ram.has_loaded() {
+            println!("No ROM has been loaded.");
+            return 
+        }
+
+        let frame_time = Duration::from_secs(1) / timing::TIMER_HZ;
+        self.set_ips(ips);
+        let mut deadline = Instant::now();
+        loop {
+            // Frames alternate lengths when the rate is not a multiple
+            // of 60, so each second still runs `ips` opcodes
+            let cycles = self.timer_clock.until_tick();
+            self.timer_clock.advance(cycles);
+            if let Err(e) = self.run_frame(cycles) {
+                println!("Execution halted; error occured");
+                println!("Error: {}", e);
+                break;
+            }
+            if self.display.mod_check() {
+                self.display.draw_display();
+            }
+            if self.exit.is_some() {
+                break;
+            }
+            // Sleep to the next frame boundary, a slow frame does not
+            // make the following ones run fast to catch up
+            deadline += frame_time;
+            let now = Instant::now();
+            match deadline > now {
+                true => thread::sleep(deadline - now),
+                false => deadline = now
+            }
+        }
+        println!("Program Stopped");
+    }
+
+    /// Run the chip8 emulator printing a plain english explanation
+    /// of each opcode before it is executed, meant to be run at slow
+    /// speeds to follow along with what the program is doing
+    /// 
+    /// # Arguments
+    /// 
+    /// * `delay` - milliseconds to wait between opcodes
+    pub fn run_explain(&mut self, delay: u64) {
         if !self.ram.has_loaded() {
             println!("No ROM has been loaded.");
             return 
         }
 
         let mut opcode: u16;
-        let mut res: ExResult<()>;
         loop {
-            // Get current opcode and execute
             opcode = self.get_next_opcode();
-            res = self.ex_opcode(opcode);
-            match res {
-                Ok(_) => self.registers.incr_pc(),
-                Err(e) => {
-                    println!("Execution halted; error occured");
-                    println!("Error: {:#?}", e);
-                    break;
-                }
+            println!("{:03x}: {:04x}  {}", self.registers.get_pc(), opcode, explain::explain_opcode(opcode, self));
+            if let Err(e) = self.ex_opcode(opcode) {
+                println!("Execution halted; error occured");
+                println!("Error: {}", e);
+                break;
             }
+            if self.exit.is_some() {
+                break;
+            }
+            self.tick_timers(1);
+            thread::sleep(Duration::from_millis(delay))
+        }
+        println!("Program Stopped");
+    }
+
+    /// Run a single 60hz frame, executing `cycles` opcodes then
+    /// decrementing the delay and sound timers once
+    /// 
+    /// # Arguments
+    /// 
+    /// * `cycles` - how many opcodes to execute this frame
+    pub fn run_frame(&mut self, cycles: u32) -> ExResult<()> {
+        self.run_frame_traced(cycles, |_, _| {})
+    }
+
+    /// Iterate over the frames of the program, each step runs one 60hz
+    /// frame like `run_frame` and yields the display at its end, so
+    /// `for frame in sys.frames(10).take(600)` feeds ten seconds of
+    /// output to image processing code
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - how many opcodes to execute each frame
+    pub fn frames(&mut self, cycles: u32) -> frames::Frames<'_> {
+        frames::Frames::init(self, cycles)
+    }
+
+    /// Run a single 60hz frame like `run_frame`, calling a function
+    /// with the system and opcode before each opcode is executed
+    /// 
+    /// # Arguments
+    /// 
+    /// * `cycles` - how many opcodes to execute this frame
+    /// * `trace` - function called before each opcode, see `trace`
+    pub fn run_frame_traced<F>(&mut self, cycles: u32, mut trace: F) -> ExResult<()>
+    where F: FnMut(&ChipSystem, u16) {
+        self.run_opcodes(cycles, &mut trace)?;
+        self.end_frame();
+        Ok(())
+    }
+
+    /// Run a single 60hz frame like `run_frame_traced`, with the
+    /// opcodes split into the slices of a budget and a function called
+    /// before each slice to read input, so a key pressed part way
+    /// through a frame is seen before the frame ends
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - opcodes run this frame and how often input is read
+    /// * `poll` - function called before each slice, given the system
+    ///   and the number of the slice starting at 0
+    /// * `trace` - function called before each opcode, see `trace`
+    pub fn run_frame_polled<P, F>(&mut self, budget: &frames::FrameBudget, mut poll: P, mut trace: F) -> ExResult<()>
+    where P: FnMut(&mut ChipSystem, usize), F: FnMut(&ChipSystem, u16) {
+        for (slice, cycles) in budget.get_slices().into_iter().enumerate() {
+            poll(self, slice);
+            self.run_opcodes(cycles, &mut trace)?;
+        }
+        self.end_frame();
+        Ok(())
+    }
+
+    /// Execute opcodes, stopping early once the program exits or a
+    /// watchpoint is hit. With VIP timing opcodes run until the frame's
+    /// machine cycles are used up instead of `cycles` of them.
+    fn run_opcodes<F>(&mut self, cycles: u32, trace: &mut F) -> ExResult<()>
+    where F: FnMut(&ChipSystem, u16) {
+        let mut opcode: u16;
+        let mut executed = 0;
+        loop {
+            let more = match self.timing {
+                TimingMode::Instructions => executed < cycles,
+                TimingMode::Vip => self.vip_cycles_left > 0
+            };
+            if !more || self.exit.is_some() || self.has_watch_hits() {
+                break;
+            }
+            executed += 1;
+            opcode = self.get_next_opcode();
+            trace(self, opcode);
+            self.ex_opcode(opcode)?;
+        }
+        Ok(())
+    }
+
+    /// Set a watchpoint, stopping frames early once a write hits it.
+    /// Writes are checked in `ChipRegisters::set_gp`, `set_i` and
+    /// `ChipMemory::set_byte`, so every opcode and the monitor are seen.
+    /// The frame ends after the opcode that hit it, see `take_watch_hits`.
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - watchpoint to set
+    pub fn add_watchpoint(&mut self, point: Watchpoint) {
+        match point.target {
+            WatchTarget::Memory(_) => self.ram.get_watchpoints_mut().add(point),
+            _ => self.registers.get_watchpoints_mut().add(point)
+        }
+    }
+
+    /// Remove a watchpoint, returning false if it was not set
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - watchpoint to remove
+    pub fn remove_watchpoint(&mut self, point: &Watchpoint) -> bool {
+        match point.target {
+            WatchTarget::Memory(_) => self.ram.get_watchpoints_mut().remove(point),
+            _ => self.registers.get_watchpoints_mut().remove(point)
+        }
+    }
+
+    /// Remove every watchpoint
+    pub fn clear_watchpoints(&mut self) {
+        self.registers.get_watchpoints_mut().clear();
+        self.ram.get_watchpoints_mut().clear();
+    }
+
+    /// Get the watchpoints set, registers first
+    pub fn get_watchpoints(&self) -> Vec<Watchpoint> {
+        let registers = self.registers.get_watchpoints().get_points().iter();
+        registers.chain(self.ram.get_watchpoints().get_points()).copied().collect()
+    }
+
+    /// Returns true if a watchpoint was hit and the hits not yet taken,
+    /// frames stop before the next opcode until they are
+    pub fn has_watch_hits(&self) -> bool {
+        self.registers.get_watchpoints().has_hits() || self.ram.get_watchpoints().has_hits()
+    }
+
+    /// Take the watchpoint hits since the last call, letting frames run
+    /// on again
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        let mut hits = self.registers.get_watchpoints_mut().take_hits();
+        hits.extend(self.ram.get_watchpoints_mut().take_hits());
+        hits
+    }
+
+    /// Get the instructions per second that opcodes run one at a time
+    /// by `step` and `run_explain` are taken to execute at, the timers
+    /// tick once every `ips / 60` of them
+    pub fn get_ips(&self) -> u32 {
+        self.timer_clock.get_ips()
+    }
+
+    /// Set the instructions per second that opcodes run one at a time
+    /// are taken to execute at, `DEFAULT_IPS` by default. `run_at` sets
+    /// it to the rate it runs at.
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - instructions executed per second
+    pub fn set_ips(&mut self, ips: u32) {
+        self.timer_clock.set_ips(ips);
+    }
+
+    /// Get how long instructions take to run
+    pub fn get_timing(&self) -> TimingMode {
+        self.timing
+    }
+
+    /// Set how long instructions take to run, `TimingMode::Instructions`
+    /// by default. With `TimingMode::Vip` the `cycles` given to
+    /// `run_frame` are ignored and each frame runs as many instructions
+    /// as a COSMAC VIP would have.
+    ///
+    /// # Arguments
+    ///
+    /// * `timing` - timing mode to use from the next frame
+    pub fn set_timing(&mut self, timing: TimingMode) {
+        self.timing = timing;
+        self.vip_cycles_left = timing::vip_frame_budget();
+    }
+
+    /// Count down the timers for each 60hz tick that fell due while
+    /// opcodes ran outside of a frame
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - opcodes executed
+    fn tick_timers(&mut self, instructions: u32) {
+        for _ in 0..self.timer_clock.advance(instructions) {
             self.registers.decr_d();
             self.registers.decr_s();
-            self.registers.dump_registers();
-            thread::sleep(Duration::from_millis(16))
         }
-        println!("Program Stopped");
+        self.update_audio_sink();
+    }
+
+    /// Give the system a sink to tell when the sound timer starts and
+    /// stops, checked at the end of every frame or step
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - receiver of the sound events, None to remove it
+    pub fn set_audio_sink(&mut self, sink: Option<Box<dyn AudioSink>>) {
+        if let (Some(old), true) = (self.audio_sink.as_mut(), self.sounding) {
+            old.stop();
+        }
+        self.audio_sink = sink;
+        self.sounding = false;
+    }
+
+    /// Tell the audio sink if the sound timer started or stopped
+    fn update_audio_sink(&mut self) {
+        let sounding = self.registers.get_s() > 0;
+        if sounding == self.sounding {
+            return;
+        }
+        self.sounding = sounding;
+        if let Some(sink) = self.audio_sink.as_mut() {
+            match sounding {
+                true => sink.start(&self.audio),
+                false => sink.stop()
+            }
+        }
+    }
+
+    /// Count down the timers and finish the frame's statistics and
+    /// callbacks
+    fn end_frame(&mut self) {
+        // Cycles a frame stopped early did not use are not kept, an
+        // instruction that ran past the end is paid for by the next
+        self.vip_cycles_left = self.vip_cycles_left.min(0) + timing::vip_frame_budget();
+        self.registers.decr_d();
+        self.registers.decr_s();
+        self.update_audio_sink();
+        self.stats.end_frame();
+        self.frames += 1;
+        self.run_callbacks();
+    }
+
+    /// Get the number of frames run by `run_frame`
+    pub fn get_frame(&self) -> u64 {
+        self.frames
+    }
+
+    /// Run a function once at the end of the frame `frames` frames from
+    /// now, 1 is the end of the next frame. Callbacks due on the same
+    /// frame run in the order they were registered, so scripted runs
+    /// are deterministic.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `frames` - frames to wait
+    /// * `func` - function to run, given the system
+    pub fn after_frames<F>(&mut self, frames: u64, func: F) -> CallbackId
+    where F: FnMut(&mut ChipSystem) + 'static {
+        self.add_callback(frames, None, Box::new(func))
+    }
+
+    /// Run a function at the end of every `frames`th frame from now
+    /// until it is cancelled
+    /// 
+    /// # Arguments
+    /// 
+    /// * `frames` - frames between runs, at least 1
+    /// * `func` - function to run, given the system
+    pub fn every_frames<F>(&mut self, frames: u64, func: F) -> CallbackId
+    where F: FnMut(&mut ChipSystem) + 'static {
+        let frames = frames.max(1);
+        self.add_callback(frames, Some(frames), Box::new(func))
+    }
+
+    /// Cancel a callback, returns false if it already ran or was
+    /// cancelled
+    /// 
+    /// # Arguments
+    /// 
+    /// * `id` - handle returned when the callback was registered
+    pub fn cancel_callback(&mut self, id: CallbackId) -> bool {
+        if let Some((running, cancelled)) = self.running_callback.as_mut() {
+            if *running == id && !*cancelled {
+                *cancelled = true;
+                return true;
+            }
+        }
+        let count = self.callbacks.len();
+        self.callbacks.retain(|callback| callback.id != id);
+        self.callbacks.len() != count
+    }
+
+    fn add_callback(&mut self, frames: u64, every: Option<u64>, func: FrameFn) -> CallbackId {
+        let id = CallbackId(self.next_callback);
+        self.next_callback += 1;
+        self.callbacks.push(FrameCallback {
+            id,
+            due: self.frames + frames.max(1),
+            every,
+            func
+        });
+        id
+    }
+
+    /// Run the callbacks due this frame, callbacks may register or
+    /// cancel other callbacks while they run
+    fn run_callbacks(&mut self) {
+        if !self.callbacks.iter().any(|callback| callback.due <= self.frames) {
+            return;
+        }
+        let (mut due, waiting): (Vec<FrameCallback>, Vec<FrameCallback>) = std::mem::take(&mut self.callbacks)
+            .into_iter()
+            .partition(|callback| callback.due <= self.frames);
+        self.callbacks = waiting;
+        due.sort_by_key(|callback| (callback.due, callback.id.0));
+        for mut callback in due {
+            self.running_callback = Some((callback.id, false));
+            (callback.func)(self);
+            let cancelled = self.running_callback.take().is_some_and(|(_, cancelled)| cancelled);
+            if cancelled {
+                continue;
+            }
+            if let Some(every) = callback.every {
+                callback.due = self.frames + every;
+                self.callbacks.push(callback);
+            }
+        }
+        self.callbacks.sort_by_key(|callback| callback.id.0);
+    }
+
+    /// Get the SCHIP RPL user flags written by FX75, the HP48 kept
+    /// them between programs so games store high scores in them
+    pub fn get_rpl_flags(&self) -> [u8; 16] {
+        self.rpl
+    }
+
+    /// Set the RPL user flags read by FX85, such as ones saved by an
+    /// earlier session
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - values of the 16 flags
+    pub fn set_rpl_flags(&mut self, flags: [u8; 16]) {
+        self.rpl = flags;
+    }
+
+    /// Take a snapshot of the machine, see `savestate::save_state`
+    pub fn save_state(&self) -> Vec<u8> {
+        savestate::save_state(self)
+    }
+
+    /// Restore a snapshot taken by `save_state`, a bad snapshot leaves
+    /// the machine as it was
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - snapshot to restore
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        savestate::load_state(self, data)
+    }
+
+    /// Get why the program exited, None while it is still running.
+    /// Once set `run_frame` stops executing opcodes.
+    pub fn get_exit(&self) -> Option<ExitReason> {
+        self.exit
     }
 
     /// Run an emulaton step, this executes a single opcode
-    /// from the chip8 memory system, pointed to by the PC reg
+    /// from the chip8 memory system, pointed to by the PC reg.
+    /// The timers tick once every `get_ips() / 60` steps.
     /// 
-    /// Returns a representation of the screen if it has been modified
-    pub fn step(&mut self, display_opcode:  bool) -> (u16, Option<Vec<bool>>) {
+    /// Returns the opcode with a representation of the screen if it has
+    /// been modified, or why the opcode could not be executed
+    pub fn step(&mut self, display_opcode:  bool) -> ExResult<(u16, Option<Vec<bool>>)> {
         let opcode = self.get_next_opcode();
         if display_opcode {
             println!("Opcode: {:04x}", opcode);
         }
-        let res: ExResult<()> = self.ex_opcode(opcode);
-        match res {
-            Ok(_) => {},
-            Err(e) => {
-                println!("Execution halted; error occured");
-                println!("Error: {:#?}", e);
-            }
-        }
-        self.registers.decr_d();
-        self.registers.decr_s();
+        self.ex_opcode(opcode)?;
+        self.tick_timers(1);
         match self.display.mod_check() {
-            true => return (opcode, Some(self.display.get_display())),
-            false => return (opcode, None)
+            true => Ok((opcode, Some(self.display.get_display()))),
+            false => Ok((opcode, None))
         }
     }
 
-    /// Load a ROM into the chip8 memory
+    /// Load a ROM into the chip8 memory, returning any warnings about
+    /// it or an error if it does not fit
     /// 
     /// # Arguments
     /// 
     /// * `rom` - a u8 vector representing the rom
-    pub fn load_rom(&mut self, rom: Vec<u8>) {
-        self.ram.load_bytes(rom);
+    pub fn load_rom(&mut self, rom: Vec<u8>) -> Result<Vec<RomWarning>, RomError> {
+        self.pc_history.clear();
+        self.timer_clock.reset();
+        self.ram.load_bytes(rom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run opcodes one after another, each expected to succeed
+    fn run_opcodes(sys: &mut ChipSystem, opcodes: &[u16]) {
+        for opcode in opcodes {
+            sys.ex_opcode(*opcode).unwrap();
+        }
     }
-}
\ No newline at end of file
+
+    /// A system running a variant
+    fn system_for(variant: Variant) -> ChipSystem {
+        let mut sys = ChipSystem::init();
+        sys.variant = variant;
+        sys
+    }
+
+    #[test]
+    fn arithmetic_sets_vf_after_vx() {
+        // (setup, opcode, Vx, VF) with V1 = 0xFF and V2 = 0x02 unless
+        // changed by the setup
+        let cases: [(&[u16], u16, u8, u8); 8] = [
+            (&[], 0x8124, 0x01, 1),
+            (&[0x6101], 0x8124, 0x03, 0),
+            (&[], 0x8125, 0xFD, 1),
+            (&[0x6101], 0x8125, 0xFF, 0),
+            (&[0x6202], 0x8225, 0x00, 1),
+            (&[], 0x8127, 0x03, 0),
+            (&[0x6101], 0x8127, 0x01, 1),
+            (&[0x6202], 0x8227, 0x00, 1)
+        ];
+        for (setup, opcode, vx, vf) in cases.iter() {
+            let mut sys = ChipSystem::init();
+            run_opcodes(&mut sys, &[0x61FF, 0x6202]);
+            run_opcodes(&mut sys, setup);
+            sys.ex_opcode(*opcode).unwrap();
+            let x = ((opcode >> 8) & 0xF) as usize;
+            assert_eq!(sys.registers.get_gp(x), *vx, "{:04X} result", opcode);
+            assert_eq!(sys.registers.get_gp(15), *vf, "{:04X} flag", opcode);
+        }
+    }
+
+    #[test]
+    fn flag_wins_when_vf_is_the_target() {
+        let cases = [(0x8F24, 0xFF, 0x01, 1), (0x8F25, 0x05, 0x07, 0), (0x8F27, 0x05, 0x07, 1)];
+        for (opcode, vf, v2, flag) in cases.iter() {
+            let mut sys = ChipSystem::init();
+            run_opcodes(&mut sys, &[0x6F00 | *vf, 0x6200 | *v2]);
+            sys.ex_opcode(*opcode).unwrap();
+            assert_eq!(sys.registers.get_gp(15), *flag, "{:04X}", opcode);
+        }
+    }
+
+    #[test]
+    fn schip_opcodes_need_the_variant() {
+        let mut chip8 = system_for(Variant::Chip8);
+        run_opcodes(&mut chip8, &[0x00FF, 0x00FD]);
+        assert_eq!(chip8.display.get_width(), 64);
+        assert_eq!(chip8.get_exit(), None);
+        assert_eq!(chip8.registers.get_pc(), 0x204);
+        assert!(chip8.ex_opcode(0xF130).is_err());
+        assert!(chip8.ex_opcode(0xF175).is_err());
+
+        let mut schip = system_for(Variant::Schip);
+        schip.ex_opcode(0x00FF).unwrap();
+        assert_eq!((schip.display.get_width(), schip.display.get_height()), (128, 64));
+        schip.ex_opcode(0x00FE).unwrap();
+        assert_eq!((schip.display.get_width(), schip.display.get_height()), (64, 32));
+        schip.ex_opcode(0x00FD).unwrap();
+        assert_eq!(schip.get_exit(), Some(ExitReason::ExitOpcode));
+    }
+
+    #[test]
+    fn schip_big_font_and_flags() {
+        let mut sys = system_for(Variant::Schip);
+        run_opcodes(&mut sys, &[0x6107, 0xF130]);
+        assert_eq!(sys.registers.get_i(), memory::BIG_FONT_START as u16 + 70);
+        run_opcodes(&mut sys, &[0x6011, 0x6122, 0xF175, 0x6000, 0x6100, 0xF085]);
+        assert_eq!((sys.registers.get_gp(0), sys.registers.get_gp(1)), (0x11, 0x00));
+        assert_eq!(&sys.get_rpl_flags()[..2], &[0x11, 0x22]);
+    }
+
+    #[test]
+    fn wait_key_stores_the_key_once_released() {
+        let mut sys = ChipSystem::init();
+        sys.ex_opcode(0xF30A).unwrap();
+        assert_eq!((sys.registers.get_pc(), sys.keyboard.get_waiting()), (0x200, Some(3)));
+        sys.keyboard.set_key(5, true);
+        sys.ex_opcode(0xF30A).unwrap();
+        sys.ex_opcode(0xF30A).unwrap();
+        assert_eq!(sys.registers.get_pc(), 0x200, "a held key is not read yet");
+        sys.keyboard.set_key(5, false);
+        sys.ex_opcode(0xF30A).unwrap();
+        assert_eq!(sys.registers.get_gp(3), 5);
+        assert_eq!((sys.registers.get_pc(), sys.keyboard.get_waiting()), (0x202, None));
+    }
+
+    #[test]
+    fn jump_to_self_exits_only_when_asked() {
+        let mut sys = ChipSystem::init();
+        sys.ex_opcode(0x1200).unwrap();
+        assert_eq!(sys.get_exit(), None);
+        sys.set_exit_on_jump_self(true);
+        sys.ex_opcode(0x1200).unwrap();
+        assert_eq!(sys.get_exit(), Some(ExitReason::JumpToSelf));
+    }
+}
@@ -0,0 +1,200 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+
+// Local Modules Use //
+use super::{ChipSystem, ExResult, Opcode};
+use super::explain::explain_opcode;
+
+/// Copy of the register values at one point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterSnapshot {
+    /// General purpose registers V0-VF
+    pub gp: [u8; 16],
+    /// Register I
+    pub i: u16,
+    /// Delay timer
+    pub d: u8,
+    /// Sound timer
+    pub s: u8,
+    /// Program counter
+    pub pc: u16,
+    /// Stack pointer
+    pub sp: usize
+}
+
+impl RegisterSnapshot {
+    /// Take a snapshot of the registers of a system
+    pub fn take(sys: &ChipSystem) -> Self {
+        let reg = &sys.registers;
+        let mut gp = [0; 16];
+        for (index, value) in gp.iter_mut().enumerate() {
+            *value = reg.get_gp(index);
+        }
+        RegisterSnapshot {
+            gp,
+            i: reg.get_i(),
+            d: reg.get_d(),
+            s: reg.get_s(),
+            pc: reg.get_pc(),
+            sp: reg.get_sp()
+        }
+    }
+}
+
+/// Everything that happened during one fetch/decode/execute cycle
+pub struct CycleView {
+    /// Opcode that was fetched
+    pub opcode: u16,
+    /// Registers before execution
+    pub before: RegisterSnapshot,
+    /// Registers after execution
+    pub after: RegisterSnapshot,
+    /// Memory writes as (address, old value, new value)
+    pub mem_changes: Vec<(u16, u8, u8)>,
+    /// True if the display was drawn to or cleared
+    pub display_changed: bool
+}
+
+impl CycleView {
+    /// Run a single cycle on the system and record what it changed
+    pub fn run(sys: &mut ChipSystem) -> ExResult<Self> {
+        let before = RegisterSnapshot::take(sys);
+        let opcode = sys.get_next_opcode();
+        let ram_before = sys.ram.get_nbytes(0, 4096);
+        let display_before = sys.display.get_display();
+
+        sys.ex_opcode(opcode)?;
+
+        let ram_after = sys.ram.get_nbytes(0, 4096);
+        let mem_changes = ram_before.iter()
+            .zip(ram_after.iter())
+            .enumerate()
+            .filter(|(_, (old, new))| old != new)
+            .map(|(addr, (old, new))| (addr as u16, *old, *new))
+            .collect();
+        Ok(CycleView {
+            opcode,
+            before,
+            after: RegisterSnapshot::take(sys),
+            mem_changes,
+            display_changed: display_before != sys.display.get_display()
+        })
+    }
+}
+
+/// Render the fetch stage: the memory row holding PC with the two
+/// fetched bytes highlighted in brackets
+pub fn render_fetch(sys: &ChipSystem) -> String {
+    let pc = sys.registers.get_pc();
+    let row_start = pc & !0xf;
+    let mut out = format!("FETCH   PC = 0x{:03x}\n  {:03x}:", pc, row_start);
+    for addr in row_start..row_start + 16 {
+        if addr >= 4096 {
+            break;
+        }
+        let byte = sys.ram.get_byte(addr);
+        if addr == pc {
+            out.push_str(&format!(" [{:02x}", byte));
+        } else if addr == pc + 1 {
+            out.push_str(&format!(" {:02x}]", byte));
+        } else {
+            out.push_str(&format!(" {:02x}", byte));
+        }
+    }
+    out.push_str(&format!(
+        "\n  opcode = (0x{:02x} << 8) | 0x{:02x} = 0x{:04x}\n",
+        sys.ram.get_byte(pc), sys.ram.get_byte(pc + 1), sys.ram.get_opcode(pc)
+    ));
+    out
+}
+
+/// Render the decode stage: the nibbles of the opcode as split up by
+/// the `Opcode` struct, plus the combined byte and address fields
+///
+/// # Arguments
+///
+/// * `opcode` - opcode being decoded
+/// * `sys` - system the opcode will run on, used for the explanation
+pub fn render_decode(opcode: u16, sys: &ChipSystem) -> String {
+    let comps = Opcode::new(opcode);
+    format!(
+        "DECODE  0x{:04x}\n  h1 = 0x{:X}  v1 (x) = 0x{:X}  v2 (y) = 0x{:X}  v3 (n) = 0x{:X}\n  kk = 0x{:02x}  nnn = 0x{:03x}\n  {}\n",
+        opcode, comps.h1, comps.v1, comps.v2, comps.v3,
+        opcode & 0xff, opcode & 0xfff,
+        explain_opcode(opcode, sys)
+    )
+}
+
+/// Render the execute stage: every register, memory location and the
+/// display that the cycle changed
+pub fn render_execute(view: &CycleView) -> String {
+    let mut out = String::from("EXECUTE\n");
+    let (b, a) = (&view.before, &view.after);
+    for index in 0..16 {
+        if b.gp[index] != a.gp[index] {
+            out.push_str(&format!("  V{:X}: 0x{:02x} -> 0x{:02x}\n", index, b.gp[index], a.gp[index]));
+        }
+    }
+    if b.i != a.i {
+        out.push_str(&format!("  I:  0x{:03x} -> 0x{:03x}\n", b.i, a.i));
+    }
+    if b.d != a.d {
+        out.push_str(&format!("  DT: 0x{:02x} -> 0x{:02x}\n", b.d, a.d));
+    }
+    if b.s != a.s {
+        out.push_str(&format!("  ST: 0x{:02x} -> 0x{:02x}\n", b.s, a.s));
+    }
+    if b.sp != a.sp {
+        out.push_str(&format!("  SP: {} -> {}\n", b.sp, a.sp));
+    }
+    out.push_str(&format!("  PC: 0x{:03x} -> 0x{:03x}\n", b.pc, a.pc));
+    for (addr, old, new) in view.mem_changes.iter() {
+        out.push_str(&format!("  RAM[0x{:03x}]: 0x{:02x} -> 0x{:02x}\n", addr, old, new));
+    }
+    if view.display_changed {
+        out.push_str("  display updated\n");
+    }
+    out
+}
+
+/// Run the emulator one cycle at a time, showing the fetch, decode and
+/// execute stages in turn and waiting for enter between each of them.
+/// Entering `q` quits.
+pub fn run_teach(sys: &mut ChipSystem) {
+    let stdin = io::stdin();
+    let mut input = String::new();
+    let mut wait = || -> bool {
+        input.clear();
+        print!("-- enter to continue, q to quit -- ");
+        let _ = io::stdout().flush();
+        match stdin.lock().read_line(&mut input) {
+            Ok(0) | Err(_) => false,
+            Ok(_) => input.trim() != "q"
+        }
+    };
+
+    loop {
+        print!("{}", render_fetch(sys));
+        if !wait() {
+            break;
+        }
+        let opcode = sys.get_next_opcode();
+        print!("{}", render_decode(opcode, sys));
+        if !wait() {
+            break;
+        }
+        match CycleView::run(sys) {
+            Ok(view) => print!("{}", render_execute(&view)),
+            Err(e) => {
+                println!("Execution halted; error occured");
+                println!("Error: {:#?}", e);
+                break;
+            }
+        }
+        if !wait() {
+            break;
+        }
+    }
+    println!("Program Stopped");
+}
@@ -0,0 +1,462 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Local Modules Use //
+use super::focus::FocusPause;
+use super::hotkeys::{Hotkey, HotkeyMap, KeyCombo};
+use super::keyboard::ChipKeyboard;
+use super::keymap::KeyMap;
+use super::kiosk::{Kiosk, KioskKey};
+use super::termpad::{self, MouseEvent, DISABLE_MOUSE, ENABLE_MOUSE};
+
+/// How long a keypad key stays down after its key arrives. Terminals
+/// only report presses, so a key counts as held while presses keep
+/// arriving from the key repeat.
+pub const HOLD_TIME: Duration = Duration::from_millis(150);
+
+/// Turns on focus reporting, the terminal then sends `\x1b[I` when it
+/// comes to the front and `\x1b[O` when it goes to the background
+pub const ENABLE_FOCUS: &str = "\x1b[?1004h";
+
+/// Turns focus reporting back off, write it before exiting
+pub const DISABLE_FOCUS: &str = "\x1b[?1004l";
+
+/// Read a focus report, returning true if the terminal came to the
+/// front and the number of bytes it took, None for anything else
+///
+/// # Arguments
+///
+/// * `input` - bytes read from the terminal
+pub fn parse_focus(input: &[u8]) -> Option<(bool, usize)> {
+    match input {
+        [0x1b, b'[', b'I', ..] => Some((true, 3)),
+        [0x1b, b'[', b'O', ..] => Some((false, 3)),
+        _ => None
+    }
+}
+
+/// Name a key read from the terminal the way `KeyMap` names keys,
+/// returning the name and the number of bytes it took. Escape
+/// sequences other than the arrow and function keys are consumed with
+/// no name.
+///
+/// # Arguments
+///
+/// * `input` - bytes read from the terminal, not empty
+pub fn parse_key(input: &[u8]) -> (Option<String>, usize) {
+    let name = |name: &str| Some(String::from(name));
+    match input {
+        [0x1b, b'[' | b'O', rest @ ..] if !rest.is_empty() => {
+            // Parameters then a final byte in 0x40-0x7e
+            match rest.iter().position(|b| (0x40..=0x7e).contains(b)) {
+                Some(end) => {
+                    let key = match &rest[..=end] {
+                        b"A" => name("up"),
+                        b"B" => name("down"),
+                        b"C" => name("right"),
+                        b"D" => name("left"),
+                        b"P" | b"11~" => name("f1"),
+                        b"Q" | b"12~" => name("f2"),
+                        b"R" | b"13~" => name("f3"),
+                        b"S" | b"14~" => name("f4"),
+                        b"15~" => name("f5"),
+                        b"17~" => name("f6"),
+                        b"18~" => name("f7"),
+                        b"19~" => name("f8"),
+                        b"20~" => name("f9"),
+                        b"21~" => name("f10"),
+                        b"23~" => name("f11"),
+                        b"24~" => name("f12"),
+                        _ => None
+                    };
+                    (key, end + 3)
+                },
+                None => (None, input.len())
+            }
+        },
+        [0x1b, ..] => (name("escape"), 1),
+        [b' ', ..] => (name("space"), 1),
+        [b'\r' | b'\n', ..] => (name("enter"), 1),
+        [b'\t', ..] => (name("tab"), 1),
+        [0x7f | 0x08, ..] => (name("backspace"), 1),
+        [byte, ..] if byte.is_ascii_graphic() => (Some((byte.to_ascii_lowercase() as char).to_string()), 1),
+        [_, ..] => (None, 1),
+        [] => (None, 0)
+    }
+}
+
+/// Read a key with the modifiers the terminal can report, returning it
+/// and the number of bytes it took. Control bytes are read as ctrl
+/// with a letter and an escape in front of a key as alt with the key,
+/// everything else is named by `parse_key`.
+///
+/// # Arguments
+///
+/// * `input` - bytes read from the terminal, not empty
+pub fn parse_combo(input: &[u8]) -> (Option<KeyCombo>, usize) {
+    match input {
+        // Tab, enter and backspace are control bytes with names of their own
+        [byte @ 0x01..=0x1a, ..] if ![0x08, b'\t', b'\n', b'\r'].contains(byte) => {
+            let letter = ((byte - 1 + b'a') as char).to_string();
+            (Some(KeyCombo::init(&letter, true, false, false)), 1)
+        },
+        [0x1b, rest @ ..] if !rest.is_empty() && ![b'[', b'O', 0x1b].contains(&rest[0]) => {
+            let (combo, used) = parse_combo(rest);
+            (combo.map(|combo| KeyCombo { alt: true, ..combo }), used + 1)
+        },
+        _ => {
+            let (key, used) = parse_key(input);
+            (key.map(|key| KeyCombo::init(&key, false, false, false)), used)
+        }
+    }
+}
+
+/// Terminal settings to restore when the keys are released
+#[cfg(unix)]
+type SavedMode = libc::termios;
+
+#[cfg(not(unix))]
+type SavedMode = ();
+
+/// Keypad input read from the terminal the emulator runs in
+///
+/// The terminal is switched out of line mode so each key arrives as it
+/// is pressed, the keys are looked up in a `KeyMap` and the keypad keys
+/// they press are held for `HOLD_TIME`. Keys not on the keypad can
+/// trigger the actions of a `HotkeyMap`, collected by `take_hotkeys`,
+/// and with `enable_mouse` clicks are collected by `take_mouse`. With
+/// `enable_focus` the terminal going to the background is passed on to
+/// a `FocusPause` by `update_focus`. A menu can take every key for
+/// itself with `set_capture`.
+/// Ctrl+C arrives as a key too and is reported as a request to quit,
+/// unless `set_kiosk` locked the keys down, so the terminal is always
+/// put back by `restore` or when this is dropped. Only available on
+/// Unix.
+pub struct TerminalKeys {
+    /// Bytes read by the input thread
+    bytes: Receiver<Vec<u8>>,
+    /// When each keypad key is released, None while it is up
+    held: [Option<Instant>; 16],
+    /// Terminal settings from before, None once restored
+    saved: Option<SavedMode>,
+    /// Actions bound to keys, None to leave them unbound
+    hotkeys: Option<HotkeyMap>,
+    /// Actions triggered and not yet taken
+    triggered: Vec<Hotkey>,
+    /// True while the terminal reports the mouse
+    mouse_enabled: bool,
+    /// Mouse events not yet taken
+    mouse: Vec<MouseEvent>,
+    /// True while the terminal reports focus changes
+    focus_enabled: bool,
+    /// Focus reported last and not yet passed on, true when focused
+    focused: Option<bool>,
+    /// Kiosk mode deciding what each key may do, None outside it
+    kiosk: Option<Kiosk>,
+    /// Keys read while captured, None while keys go to the keypad
+    captured: Option<Vec<KeyCombo>>
+}
+
+impl TerminalKeys {
+    /// Switch the terminal on stdin to reading single keys and start
+    /// reading them on a new thread
+    #[cfg(unix)]
+    pub fn init() -> io::Result<Self> {
+        // The settings are only read and written through the pointer
+        // given, for stdin which stays open for the whole process
+        let saved = unsafe {
+            let mut mode: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut mode) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let saved = mode;
+            mode.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            // Ctrl+Q and Ctrl+S are keys too instead of flow control
+            mode.c_iflag &= !libc::IXON;
+            mode.c_cc[libc::VMIN] = 1;
+            mode.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &mode) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            saved
+        };
+        let (sender, bytes) = mpsc::channel();
+        let reader = thread::Builder::new().name(String::from("terminal keys")).spawn(move || {
+            let mut stdin = io::stdin();
+            let mut buffer = [0u8; 64];
+            while let Ok(read @ 1..) = stdin.read(&mut buffer) {
+                if sender.send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut keys = TerminalKeys {
+            bytes,
+            held: [None; 16],
+            saved: Some(saved),
+            hotkeys: None,
+            triggered: Vec::new(),
+            mouse_enabled: false,
+            mouse: Vec::new(),
+            focus_enabled: false,
+            focused: None,
+            kiosk: None,
+            captured: None
+        };
+        if let Err(e) = reader {
+            keys.restore()?;
+            return Err(e);
+        }
+        Ok(keys)
+    }
+
+    /// Read keys from the terminal, which this platform can not do
+    #[cfg(not(unix))]
+    pub fn init() -> io::Result<Self> {
+        Err(io::Error::new(io::ErrorKind::Other, "reading keys from the terminal needs a Unix system"))
+    }
+
+    /// Press the keypad keys of every key that arrived since the last
+    /// call and release the ones held long enough, returning true if
+    /// Ctrl+C was pressed
+    ///
+    /// # Arguments
+    ///
+    /// * `keymap` - keys pressing each keypad key
+    /// * `keyboard` - keypad to update
+    pub fn poll(&mut self, keymap: &KeyMap, keyboard: &mut ChipKeyboard) -> bool {
+        let now = Instant::now();
+        let mut quit = false;
+        while let Ok(bytes) = self.bytes.try_recv() {
+            let mut pos = 0;
+            while pos < bytes.len() {
+                if bytes[pos] == 0x03 && self.kiosk.is_none() {
+                    quit = true;
+                }
+                if let Some((focused, used)) = parse_focus(&bytes[pos..]) {
+                    self.focused = Some(focused);
+                    pos += used;
+                    continue;
+                }
+                if let Some((event, used)) = termpad::parse_sgr_mouse(&bytes[pos..]) {
+                    self.mouse.extend(event);
+                    pos += used;
+                    continue;
+                }
+                let (combo, used) = parse_combo(&bytes[pos..]);
+                pos += used.max(1);
+                let combo = match combo {
+                    Some(combo) => combo,
+                    None => continue
+                };
+                if let Some(captured) = self.captured.as_mut() {
+                    captured.push(combo);
+                    continue;
+                }
+                match self.classify(keymap, &combo) {
+                    KioskKey::Exit => quit = true,
+                    KioskKey::Hotkey(hotkey) => self.triggered.push(hotkey),
+                    KioskKey::Gameplay => if let Some(chip_key) = keymap.lookup(&combo.key).filter(|_| !combo.ctrl && !combo.alt) {
+                        keyboard.set_key(chip_key, true);
+                        self.held[chip_key as usize] = Some(now + HOLD_TIME);
+                    },
+                    KioskKey::Blocked => {}
+                }
+            }
+        }
+        for (chip_key, release) in self.held.iter_mut().enumerate() {
+            if release.is_some_and(|release| release <= now) {
+                keyboard.set_key(chip_key as u8, false);
+                *release = None;
+            }
+        }
+        quit
+    }
+
+    /// Decide what a key does, keys on the keypad press their keypad
+    /// key before anything else but the kiosk admin combination
+    fn classify(&self, keymap: &KeyMap, combo: &KeyCombo) -> KioskKey {
+        let plain = !combo.ctrl && !combo.alt;
+        let admin = self.kiosk.as_ref().is_some_and(|kiosk| kiosk.get_admin() == combo);
+        match (self.hotkeys.as_ref(), self.kiosk.as_ref()) {
+            _ if plain && !admin && keymap.lookup(&combo.key).is_some() => KioskKey::Gameplay,
+            (Some(hotkeys), Some(kiosk)) => kiosk.filter(hotkeys, combo),
+            (None, Some(kiosk)) => kiosk.filter(&HotkeyMap::init(), combo),
+            (Some(hotkeys), None) => hotkeys.lookup(combo).map_or(KioskKey::Gameplay, KioskKey::Hotkey),
+            (None, None) => KioskKey::Gameplay
+        }
+    }
+
+    /// Lock the keys down for kiosk mode: only the keypad and the
+    /// hotkeys the kiosk allows work, Ctrl+C no longer quits and the
+    /// admin combination is the only way out
+    ///
+    /// # Arguments
+    ///
+    /// * `kiosk` - kiosk mode to apply, None to unlock the keys
+    pub fn set_kiosk(&mut self, kiosk: Option<Kiosk>) {
+        self.kiosk = kiosk;
+    }
+
+    /// Set the actions keys trigger, keys on the keypad keep pressing
+    /// their keypad key
+    ///
+    /// # Arguments
+    ///
+    /// * `hotkeys` - bindings to use, None to trigger no actions
+    pub fn set_hotkeys(&mut self, hotkeys: Option<HotkeyMap>) {
+        self.hotkeys = hotkeys;
+    }
+
+    /// Send every key to `take_captured` instead of the keypad and the
+    /// hotkeys, for a menu drawn over the game. Ctrl+C still quits.
+    ///
+    /// # Arguments
+    ///
+    /// * `capture` - true to capture keys, false to play with them again
+    pub fn set_capture(&mut self, capture: bool) {
+        self.captured = match capture {
+            true => Some(Vec::new()),
+            false => None
+        };
+    }
+
+    /// Take the keys captured since the last call, in order
+    pub fn take_captured(&mut self) -> Vec<KeyCombo> {
+        self.captured.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Take the actions triggered since the last call, in order
+    pub fn take_hotkeys(&mut self) -> Vec<Hotkey> {
+        std::mem::take(&mut self.triggered)
+    }
+
+    /// Turn on mouse reporting, clicks then arrive with the keys and
+    /// are collected by `take_mouse`. It is turned off by `restore`.
+    pub fn enable_mouse(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(ENABLE_MOUSE.as_bytes())?;
+        stdout.flush()?;
+        self.mouse_enabled = true;
+        Ok(())
+    }
+
+    /// Take the mouse events that arrived since the last call, in order
+    pub fn take_mouse(&mut self) -> Vec<MouseEvent> {
+        std::mem::take(&mut self.mouse)
+    }
+
+    /// Turn on focus reporting, changes are then passed on by
+    /// `update_focus`. It is turned off by `restore`.
+    pub fn enable_focus(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(ENABLE_FOCUS.as_bytes())?;
+        stdout.flush()?;
+        self.focus_enabled = true;
+        Ok(())
+    }
+
+    /// Tell a focus pause whether the terminal went to the background
+    /// or came back since the last call
+    ///
+    /// # Arguments
+    ///
+    /// * `focus` - focus pause to update
+    pub fn update_focus(&mut self, focus: &mut FocusPause) {
+        match self.focused.take() {
+            Some(true) => focus.focus_gained(),
+            Some(false) => focus.focus_lost(),
+            None => {}
+        }
+    }
+
+    /// Put the terminal back in line mode with mouse and focus
+    /// reporting off
+    pub fn restore(&mut self) -> io::Result<()> {
+        if self.focus_enabled {
+            self.focus_enabled = false;
+            let mut stdout = io::stdout();
+            stdout.write_all(DISABLE_FOCUS.as_bytes())?;
+            stdout.flush()?;
+        }
+        if self.mouse_enabled {
+            self.mouse_enabled = false;
+            let mut stdout = io::stdout();
+            stdout.write_all(DISABLE_MOUSE.as_bytes())?;
+            stdout.flush()?;
+        }
+        #[cfg(unix)]
+        if let Some(saved) = self.saved.take() {
+            // Writes back the settings read by init
+            if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &saved) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        #[cfg(not(unix))]
+        self.saved.take();
+        Ok(())
+    }
+}
+
+impl Drop for TerminalKeys {
+    fn drop(&mut self) {
+        // Nothing more can be done about a failure while dropping
+        let _ = self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `parse_key` returns for a named key
+    fn named(name: &str, used: usize) -> (Option<String>, usize) {
+        (Some(String::from(name)), used)
+    }
+
+    #[test]
+    fn plain_keys_are_one_byte() {
+        assert_eq!(parse_key(b"Wq"), named("w", 1));
+        assert_eq!(parse_key(b"5"), named("5", 1));
+        assert_eq!(parse_key(b" "), named("space", 1));
+        assert_eq!(parse_key(b"\r"), named("enter", 1));
+        assert_eq!(parse_key(b"\t"), named("tab", 1));
+        assert_eq!(parse_key(&[0x7f]), named("backspace", 1));
+        assert_eq!(parse_key(&[0x1b]), named("escape", 1));
+        assert_eq!(parse_key(&[0x80]), (None, 1));
+        assert_eq!(parse_key(&[]), (None, 0));
+    }
+
+    #[test]
+    fn escape_sequences_are_consumed_whole() {
+        assert_eq!(parse_key(b"\x1b[Aw"), named("up", 3));
+        assert_eq!(parse_key(b"\x1bOD"), named("left", 3));
+        assert_eq!(parse_key(b"\x1bOP"), named("f1", 3));
+        assert_eq!(parse_key(b"\x1b[15~"), named("f5", 5));
+        assert_eq!(parse_key(b"\x1b[24~x"), named("f12", 5));
+        // Unknown sequences and modified arrows have no name
+        assert_eq!(parse_key(b"\x1b[1;5A"), (None, 6));
+        assert_eq!(parse_key(b"\x1b[99~"), (None, 5));
+        // A sequence cut off by the read is dropped
+        assert_eq!(parse_key(b"\x1b[12"), (None, 4));
+    }
+
+    #[test]
+    fn control_and_alt_combos() {
+        assert_eq!(parse_combo(&[0x13]), (Some(KeyCombo::init("s", true, false, false)), 1));
+        assert_eq!(parse_combo(&[0x03]), (Some(KeyCombo::init("c", true, false, false)), 1));
+        assert_eq!(parse_combo(b"\x1bq"), (Some(KeyCombo::init("q", false, false, true)), 2));
+        assert_eq!(parse_combo(&[0x1b, 0x11]), (Some(KeyCombo::init("q", true, false, true)), 2));
+        // Control bytes with names of their own stay those keys
+        assert_eq!(parse_combo(b"\t"), (Some(KeyCombo::init("tab", false, false, false)), 1));
+        assert_eq!(parse_combo(b"\r"), (Some(KeyCombo::init("enter", false, false, false)), 1));
+        assert_eq!(parse_combo(&[0x08]), (Some(KeyCombo::init("backspace", false, false, false)), 1));
+        // An escape starting a sequence or on its own is not alt
+        assert_eq!(parse_combo(b"\x1b[B"), (Some(KeyCombo::init("down", false, false, false)), 3));
+        assert_eq!(parse_combo(&[0x1b, 0x1b]), (Some(KeyCombo::init("escape", false, false, false)), 1));
+    }
+}
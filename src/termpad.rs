@@ -0,0 +1,182 @@
+// Local Modules Use //
+use super::keyboard::ChipKeyboard;
+use super::overlay::KEYPAD_LAYOUT;
+
+/// Turns on mouse reporting with button motion in SGR format, write it
+/// to the terminal before reading mouse events
+pub const ENABLE_MOUSE: &str = "\x1b[?1000h\x1b[?1002h\x1b[?1006h";
+
+/// Turns mouse reporting back off, write it before exiting
+pub const DISABLE_MOUSE: &str = "\x1b[?1006l\x1b[?1002l\x1b[?1000l";
+
+/// Terminal columns taken by one key, `[ 5 ]`
+const KEY_WIDTH: u16 = 5;
+
+/// Columns from the start of one key to the next
+const KEY_STRIDE: u16 = KEY_WIDTH + 1;
+
+/// A mouse button event reported by the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// Column of the pointer, starting at 0
+    pub col: u16,
+    /// Row of the pointer, starting at 0
+    pub row: u16,
+    /// True while the left button is held, false once released
+    pub down: bool
+}
+
+/// Parse an SGR mouse report like `\x1b[<0;12;5M` from the start of
+/// terminal input, returning the event and the number of bytes it
+/// took. Reports for other buttons and the scroll wheel are consumed
+/// but give None as the event.
+///
+/// # Arguments
+///
+/// * `input` - bytes read from the terminal
+pub fn parse_sgr_mouse(input: &[u8]) -> Option<(Option<MouseEvent>, usize)> {
+    let body = input.strip_prefix(b"\x1b[<")?;
+    let end = body.iter().position(|b| *b == b'M' || *b == b'm')?;
+    let fields: Vec<u16> = std::str::from_utf8(&body[..end]).ok()?
+        .split(';')
+        .map(|field| field.parse().ok())
+        .collect::<Option<Vec<u16>>>()?;
+    if fields.len() != 3 {
+        return None;
+    }
+    let (button, col, row) = (fields[0], fields[1], fields[2]);
+    let used = 3 + end + 1;
+    // Bits 0-1 pick the button and bit 5 marks motion, only the left
+    // button, pressed, dragged or released, is of interest
+    if button & !0x20 != 0 {
+        return Some((None, used));
+    }
+    let event = MouseEvent {
+        col: col.saturating_sub(1),
+        row: row.saturating_sub(1),
+        down: body[end] == b'M'
+    };
+    Some((Some(event), used))
+}
+
+/// A clickable hex keypad drawn with text in a terminal
+///
+/// The keypad is four rows of `[ 1 ] [ 2 ] [ 3 ] [ C ]` in the COSMAC
+/// VIP layout, drawn at a fixed position under or beside the display.
+/// Terminal frontends turn on mouse capture, pass mouse events to
+/// `mouse` and draw the keypad with `render` so held keys show up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalKeypad {
+    /// Column of the top left corner, starting at 0
+    col: u16,
+    /// Row of the top left corner, starting at 0
+    row: u16,
+    /// Key held down by the mouse
+    pointer_key: Option<u8>
+}
+
+impl TerminalKeypad {
+    /// Create a keypad with its top left corner at a terminal cell
+    ///
+    /// # Arguments
+    ///
+    /// * `col` - column of the corner, starting at 0
+    /// * `row` - row of the corner, starting at 0
+    pub fn init(col: u16, row: u16) -> Self {
+        TerminalKeypad {
+            col,
+            row,
+            pointer_key: None
+        }
+    }
+
+    /// Move the keypad, such as below a display that changed size
+    ///
+    /// # Arguments
+    ///
+    /// * `col` - column of the corner, starting at 0
+    /// * `row` - row of the corner, starting at 0
+    pub fn set_position(&mut self, col: u16, row: u16) {
+        self.col = col;
+        self.row = row;
+    }
+
+    /// Size of the keypad in columns and rows
+    pub fn size() -> (u16, u16) {
+        (KEY_STRIDE * 4 - 1, 4)
+    }
+
+    /// Find the key under a terminal cell, None if the cell is outside
+    /// the keypad or in the gap between two keys
+    ///
+    /// # Arguments
+    ///
+    /// * `col` - column of the cell
+    /// * `row` - row of the cell
+    pub fn key_at(&self, col: u16, row: u16) -> Option<u8> {
+        let x = col.checked_sub(self.col)?;
+        let y = row.checked_sub(self.row)? as usize;
+        if x % KEY_STRIDE >= KEY_WIDTH {
+            return None;
+        }
+        KEYPAD_LAYOUT.get(y)?.get((x / KEY_STRIDE) as usize).copied()
+    }
+
+    /// Press or release keys for a mouse event, dragging off a key
+    /// releases it. Returns true if the event was on the keypad.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - mouse event from the terminal
+    /// * `keyboard` - keyboard to press keys on
+    pub fn mouse(&mut self, event: MouseEvent, keyboard: &mut ChipKeyboard) -> bool {
+        let key = self.key_at(event.col, event.row);
+        let target = if event.down { key } else { None };
+        if target != self.pointer_key {
+            if let Some(old) = self.pointer_key {
+                keyboard.set_key(old, false);
+            }
+            if let Some(new) = target {
+                keyboard.set_key(new, true);
+            }
+            self.pointer_key = target;
+        }
+        key.is_some()
+    }
+
+    /// Release the key held by the mouse, for when mouse capture is
+    /// turned off
+    ///
+    /// # Arguments
+    ///
+    /// * `keyboard` - keyboard the key was pressed on
+    pub fn release(&mut self, keyboard: &mut ChipKeyboard) {
+        if let Some(key) = self.pointer_key.take() {
+            keyboard.set_key(key, false);
+        }
+    }
+
+    /// Draw the keypad at its position, held keys are shown in
+    /// reverse video
+    ///
+    /// # Arguments
+    ///
+    /// * `keyboard` - keyboard whose state is shown
+    pub fn render(&self, keyboard: &ChipKeyboard) -> String {
+        let mut out = String::new();
+        for (y, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+            // Cursor positions in escape codes start at 1
+            out.push_str(&format!("\x1b[{};{}H", self.row as usize + y + 1, self.col + 1));
+            for (x, key) in keys.iter().enumerate() {
+                if x > 0 {
+                    out.push(' ');
+                }
+                match keyboard.get_key(*key) {
+                    true => out.push_str(&format!("\x1b[7m[ {:X} ]\x1b[0m", key)),
+                    false => out.push_str(&format!("[ {:X} ]", key))
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,319 @@
+// Standard Library Modules //
+use std::fmt;
+use std::io;
+use std::io::prelude::*;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::corrupt::{self, RunOutcome};
+use super::export::{display_to_text, TextStyle};
+
+/// Opcodes run per frame unless the script sets `cycles`
+pub const DEFAULT_SCRIPT_CYCLES: u32 = 10;
+
+/// Register an assertion can check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    /// General purpose register V0-VF
+    V(usize),
+    I,
+    Pc,
+    Delay,
+    Sound
+}
+
+impl Register {
+    /// Get a register from its name, such as `V3` or `I`
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "I" => Some(Register::I),
+            "PC" => Some(Register::Pc),
+            "DT" => Some(Register::Delay),
+            "ST" => Some(Register::Sound),
+            other => other.strip_prefix('V')
+                .filter(|index| index.len() == 1)
+                .and_then(|index| usize::from_str_radix(index, 16).ok())
+                .map(Register::V)
+        }
+    }
+
+    /// Read the register from a system
+    fn read(&self, sys: &ChipSystem) -> u16 {
+        let reg = &sys.registers;
+        match self {
+            Register::V(index) => reg.get_gp(*index) as u16,
+            Register::I => reg.get_i(),
+            Register::Pc => reg.get_pc(),
+            Register::Delay => reg.get_d() as u16,
+            Register::Sound => reg.get_s() as u16
+        }
+    }
+}
+
+/// One statement of a test script
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    /// Opcodes run per frame from here on
+    Cycles(u32),
+    /// Press (true) or release (false) a key
+    Key(u8, bool),
+    /// Run a number of frames
+    Run(u64),
+    /// Check a pixel is lit (true) or unlit (false)
+    Pixel(usize, usize, bool),
+    /// Check the display hash starts with the given hex digits
+    ScreenHash(String),
+    /// Check a register equals (true) or differs from (false) a value
+    Register(Register, bool, u16),
+    /// Check a byte of memory equals (true) or differs from (false) a
+    /// value
+    Memory(u16, bool, u8),
+    /// Check the program exited (true) or is still running (false)
+    Exited(bool),
+    /// Write the display to the output
+    PrintScreen,
+    /// Write the display hash to the output
+    PrintHash
+}
+
+/// A script failed to parse, with the line it failed on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    /// Line number starting at 1
+    pub line: usize,
+    /// What is wrong
+    pub message: String
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Totals of a finished test script
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestSummary {
+    /// Assertions that held
+    pub passed: usize,
+    /// Assertions that did not hold
+    pub failed: usize,
+    /// Why the script stopped early, such as the program running an
+    /// invalid opcode
+    pub error: Option<String>
+}
+
+impl TestSummary {
+    /// Returns true if every assertion held and the script ran to the end
+    pub fn is_success(&self) -> bool {
+        self.failed == 0 && self.error.is_none()
+    }
+}
+
+/// An end to end acceptance test for a ROM, written as statements that
+/// press keys, run frames and check the result
+///
+/// Statements are separated by new lines or `;` and `#` starts a
+/// comment. A `rom` line names the ROM tested, relative to the script.
+///
+/// ```text
+/// rom pong.ch8
+/// cycles 10
+/// press 1; run 120 frames; release 1
+/// assert pixel 10,4 on
+/// assert V3 == 2
+/// assert mem 0x300 != 0
+/// assert screen-hash 3fa2c1
+/// print screen-hash
+/// ```
+///
+/// Other statements are `assert exited`, `assert running`, `print
+/// screen` and assertions on the `I`, `PC`, `DT` and `ST` registers.
+/// Screen hashes may be shortened to their first few digits.
+#[derive(Debug, Clone, Default)]
+pub struct TestScript {
+    /// ROM named by the script
+    rom: Option<String>,
+    /// Statements with their line numbers and text
+    steps: Vec<(usize, String, Step)>
+}
+
+/// Parse a decimal or 0x prefixed hex number
+fn number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok()
+    }
+}
+
+/// Parse a comparison operator, true for `==` and false for `!=`
+fn equals(text: &str) -> Option<bool> {
+    match text {
+        "==" => Some(true),
+        "!=" => Some(false),
+        _ => None
+    }
+}
+
+impl TestScript {
+    /// Parse the text of a test script
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of the script
+    pub fn parse(text: &str) -> Result<Self, ScriptError> {
+        let mut script = TestScript::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            for statement in line.split(';').map(str::trim).filter(|statement| !statement.is_empty()) {
+                let error = |message: &str| ScriptError { line: index + 1, message: format!("{} in '{}'", message, statement) };
+                let words: Vec<&str> = statement.split_whitespace().collect();
+                let step = match words.as_slice() {
+                    ["rom", path] => {
+                        script.rom = Some(path.to_string());
+                        continue;
+                    },
+                    ["cycles", count] => Step::Cycles(number(count).filter(|count| *count > 0).ok_or_else(|| error("invalid cycle count"))? as u32),
+                    ["press", key] | ["release", key] => {
+                        let key = u8::from_str_radix(key, 16).ok().filter(|key| *key < 16).ok_or_else(|| error("keys are 0-F"))?;
+                        Step::Key(key, words[0] == "press")
+                    },
+                    ["run", count] | ["run", count, "frame"] | ["run", count, "frames"] => {
+                        Step::Run(number(count).ok_or_else(|| error("invalid frame count"))?)
+                    },
+                    ["assert", "pixel", position, state] | ["assert", "pixel", position, "is", state] => {
+                        let (x, y) = position.split_once(',').ok_or_else(|| error("pixels are given as x,y"))?;
+                        let (x, y) = match (number(x), number(y)) {
+                            (Some(x), Some(y)) => (x as usize, y as usize),
+                            _ => return Err(error("invalid pixel position"))
+                        };
+                        match *state {
+                            "on" => Step::Pixel(x, y, true),
+                            "off" => Step::Pixel(x, y, false),
+                            _ => return Err(error("expected on or off"))
+                        }
+                    },
+                    ["assert", "screen-hash", hash] => {
+                        let hash = hash.trim_start_matches("0x").to_ascii_lowercase();
+                        if hash.is_empty() || hash.len() > 16 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                            return Err(error("screen hashes are up to 16 hex digits"));
+                        }
+                        Step::ScreenHash(hash)
+                    },
+                    ["assert", "mem", addr, op, value] => {
+                        let addr = number(addr).filter(|addr| *addr < 0x1000).ok_or_else(|| error("invalid address"))?;
+                        let value = number(value).filter(|value| *value <= 0xFF).ok_or_else(|| error("invalid byte"))?;
+                        Step::Memory(addr as u16, equals(op).ok_or_else(|| error("expected == or !="))?, value as u8)
+                    },
+                    ["assert", "exited"] => Step::Exited(true),
+                    ["assert", "running"] => Step::Exited(false),
+                    ["assert", register, op, value] => {
+                        let register = Register::from_name(register).ok_or_else(|| error("unknown assertion"))?;
+                        let value = number(value).filter(|value| *value <= 0xFFFF).ok_or_else(|| error("invalid value"))?;
+                        Step::Register(register, equals(op).ok_or_else(|| error("expected == or !="))?, value as u16)
+                    },
+                    ["print", "screen"] => Step::PrintScreen,
+                    ["print", "screen-hash"] => Step::PrintHash,
+                    _ => return Err(error("unknown statement"))
+                };
+                script.steps.push((index + 1, statement.to_string(), step));
+            }
+        }
+        Ok(script)
+    }
+
+    /// Get the ROM named by the script's `rom` line, None if it has none
+    pub fn get_rom(&self) -> Option<&str> {
+        self.rom.as_deref()
+    }
+
+    /// Run the script on a system with the ROM loaded, writing a line for
+    /// every assertion and anything printed
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to test
+    /// * `out` - where results are written
+    pub fn run<W: Write>(&self, sys: &mut ChipSystem, mut out: W) -> io::Result<TestSummary> {
+        let mut summary = TestSummary::default();
+        let mut cycles = DEFAULT_SCRIPT_CYCLES;
+        for (line, text, step) in self.steps.iter() {
+            let failure = match step {
+                Step::Cycles(count) => {
+                    cycles = *count;
+                    continue;
+                },
+                Step::Key(key, down) => {
+                    sys.keyboard.set_key(*key, *down);
+                    continue;
+                },
+                Step::Run(frames) => {
+                    for _ in 0..*frames {
+                        match corrupt::run_frame_guarded(sys, cycles) {
+                            None | Some(RunOutcome::Exited(_)) => {},
+                            Some(outcome) => {
+                                let error = format!("line {}: the program {} at frame {}", line, outcome, sys.get_frame());
+                                writeln!(out, "ERROR {}", error)?;
+                                summary.error = Some(error);
+                                return Ok(summary);
+                            }
+                        }
+                        if sys.get_exit().is_some() {
+                            break;
+                        }
+                    }
+                    continue;
+                },
+                Step::PrintScreen => {
+                    write!(out, "{}", display_to_text(&sys.display, &TextStyle::default()))?;
+                    continue;
+                },
+                Step::PrintHash => {
+                    writeln!(out, "     {:4}: screen-hash {:016x}", line, sys.display.hash())?;
+                    continue;
+                },
+                Step::Pixel(x, y, on) => match (*x < sys.display.get_width() && *y < sys.display.get_height(), sys.display.get_pixel(*x, *y) == *on) {
+                    (false, _) => Some(format!("pixel is off the {}x{} display", sys.display.get_width(), sys.display.get_height())),
+                    (true, true) => None,
+                    (true, false) => Some(format!("pixel is {}", if *on { "off" } else { "on" }))
+                },
+                Step::ScreenHash(hash) => {
+                    let actual = format!("{:016x}", sys.display.hash());
+                    match actual.starts_with(hash.as_str()) {
+                        true => None,
+                        false => Some(format!("screen hash is {}", actual))
+                    }
+                },
+                Step::Register(register, equal, value) => {
+                    let actual = register.read(sys);
+                    match (actual == *value) == *equal {
+                        true => None,
+                        false => Some(format!("value is 0x{:02x}", actual))
+                    }
+                },
+                Step::Memory(addr, equal, value) => {
+                    let actual = sys.ram.get_byte(*addr);
+                    match (actual == *value) == *equal {
+                        true => None,
+                        false => Some(format!("value is 0x{:02x}", actual))
+                    }
+                },
+                Step::Exited(exited) => match sys.get_exit().is_some() == *exited {
+                    true => None,
+                    false => Some(String::from(if *exited { "the program is still running" } else { "the program exited" }))
+                }
+            };
+            match failure {
+                None => {
+                    summary.passed += 1;
+                    writeln!(out, "ok   {:4}: {}", line, text)?;
+                },
+                Some(reason) => {
+                    summary.failed += 1;
+                    writeln!(out, "FAIL {:4}: {} ({})", line, text, reason)?;
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
@@ -0,0 +1,134 @@
+// Standard Library Modules //
+use std::panic;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::instruction::Instruction;
+use super::variant::Variant;
+
+/// Cycles per frame used when nothing better can be guessed
+pub const DEFAULT_CYCLES: u32 = 10;
+
+/// Frames run when probing a classic ROM
+const PROBE_FRAMES: u32 = 120;
+
+/// Cycles per frame used when probing, fast enough for the program to
+/// reach its main loop quickly
+const PROBE_CYCLES: u32 = 500;
+
+/// A guessed speed for a ROM and why it was picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickRate {
+    /// Opcodes to execute per frame
+    pub cycles: u32,
+    /// Short explanation of the guess
+    pub reason: String
+}
+
+/// Interpreter family a ROM was written for, from the opcodes it uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Chip8,
+    Schip,
+    XoChip
+}
+
+/// Guess a sensible number of opcodes per frame for a ROM
+///
+/// XO-CHIP and SUPER-CHIP programs are recognised by their opcodes and
+/// get the speeds those interpreters usually ran at. Classic programs
+/// are run briefly: programs that wait on the delay timer pace
+/// themselves, the rest are given roughly a few sprite draws worth of
+/// opcodes per frame so they animate at a playable speed.
+///
+/// # Arguments
+///
+/// * `rom` - rom contents
+pub fn guess(rom: &[u8]) -> TickRate {
+    match family(rom) {
+        Family::XoChip => TickRate {
+            cycles: 1000,
+            reason: String::from("uses XO-CHIP opcodes")
+        },
+        Family::Schip => TickRate {
+            cycles: 30,
+            reason: String::from("uses SUPER-CHIP opcodes")
+        },
+        Family::Chip8 => probe(rom)
+    }
+}
+
+/// Look for opcodes only found in the extended instruction sets
+fn family(rom: &[u8]) -> Family {
+    let mut found = Family::Chip8;
+    for pair in rom.chunks_exact(2) {
+        let opcode = (pair[0] as u16) << 8 | pair[1] as u16;
+        match Instruction::decode(opcode).get_variant() {
+            Variant::XoChip => return Family::XoChip,
+            Variant::Schip => found = Family::Schip,
+            Variant::Chip8 => {}
+        }
+    }
+    found
+}
+
+/// Run a classic ROM for a couple of seconds and pick a speed from how
+/// it uses the delay timer and how often it draws
+fn probe(rom: &[u8]) -> TickRate {
+    let result = panic::catch_unwind(|| {
+        let mut sys = ChipSystem::init();
+        sys.set_exit_on_jump_self(true);
+        if sys.load_rom(rom.to_vec()).is_err() {
+            return None;
+        }
+        let (mut timer_reads, mut draws, mut instructions) = (0u64, 0u64, 0u64);
+        for _ in 0..PROBE_FRAMES {
+            for _ in 0..PROBE_CYCLES {
+                if sys.get_exit().is_some() {
+                    break;
+                }
+                let bytes = sys.ram.peek_bytes(sys.registers.get_pc(), 2);
+                let opcode = (bytes[0] as u16) << 8 | bytes[1] as u16;
+                if sys.ex_opcode(opcode).is_err() {
+                    return Some((timer_reads, draws, instructions));
+                }
+                instructions += 1;
+                match Instruction::decode(opcode) {
+                    Instruction::ReadDelay(_) => timer_reads += 1,
+                    Instruction::Draw(_, _, _) => draws += 1,
+                    _ => {}
+                }
+            }
+            sys.registers.decr_d();
+            sys.registers.decr_s();
+        }
+        Some((timer_reads, draws, instructions))
+    });
+    let (timer_reads, draws, instructions) = match result {
+        Ok(Some(counts)) => counts,
+        _ => return TickRate {
+            cycles: DEFAULT_CYCLES,
+            reason: String::from("could not be probed")
+        }
+    };
+    if timer_reads >= PROBE_FRAMES as u64 {
+        return TickRate {
+            cycles: 20,
+            reason: String::from("paces itself with the delay timer")
+        };
+    }
+    if draws == 0 {
+        return TickRate {
+            cycles: DEFAULT_CYCLES,
+            reason: String::from("did not draw while probed")
+        };
+    }
+    // About four sprite draws per frame, enough to move a couple of
+    // objects (erase and redraw) each frame
+    let per_draw = instructions / draws;
+    let cycles = (per_draw * 4).clamp(7, 30) as u32;
+    TickRate {
+        cycles,
+        reason: format!("runs about {} opcodes per sprite drawn", per_draw)
+    }
+}
@@ -0,0 +1,190 @@
+// Local Modules Use //
+use super::instruction::Instruction;
+
+/// Times per second the delay and sound timers count down
+pub const TIMER_HZ: u32 = 60;
+
+/// Schedules the 60 Hz delay and sound timer ticks against the opcodes
+/// executed, so the timers run at the same emulated speed whatever the
+/// instruction rate and however the opcodes are stepped through
+///
+/// With 600 instructions per second the timers tick every 10 opcodes,
+/// with 700 they tick every 11 or 12 opcodes so 60 ticks still fall in
+/// each 700.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimerClock {
+    /// Instructions executed per emulated second
+    ips: u32,
+    /// Progress to the next tick, in sixtieths of an instruction
+    phase: u32
+}
+
+impl TimerClock {
+    /// Initialize a clock with no progress towards the first tick
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - instructions executed per second, at least 1
+    pub fn init(ips: u32) -> Self {
+        TimerClock { ips: ips.max(1), phase: 0 }
+    }
+
+    /// Get the instructions executed per second
+    pub fn get_ips(&self) -> u32 {
+        self.ips
+    }
+
+    /// Change the instructions executed per second, the progress to the
+    /// next tick is kept as a fraction of the tick
+    ///
+    /// # Arguments
+    ///
+    /// * `ips` - instructions executed per second, at least 1
+    pub fn set_ips(&mut self, ips: u32) {
+        let ips = ips.max(1);
+        self.phase = (self.phase as u64 * ips as u64 / self.ips as u64) as u32;
+        self.ips = ips;
+    }
+
+    /// Count executed instructions, returning how many timer ticks fell
+    /// due while they ran
+    ///
+    /// # Arguments
+    ///
+    /// * `instructions` - opcodes executed since the last call
+    pub fn advance(&mut self, instructions: u32) -> u32 {
+        let total = self.phase as u64 + instructions as u64 * TIMER_HZ as u64;
+        self.phase = (total % self.ips as u64) as u32;
+        (total / self.ips as u64) as u32
+    }
+
+    /// Get the number of instructions to execute before the next timer
+    /// tick falls due, at least 1
+    pub fn until_tick(&self) -> u32 {
+        let left = self.ips - self.phase;
+        left.div_ceil(TIMER_HZ)
+    }
+
+    /// Get the progress towards the next tick, in sixtieths of an
+    /// instruction, for save states
+    pub fn get_phase(&self) -> u32 {
+        self.phase
+    }
+
+    /// Restore the progress towards the next tick saved by `get_phase`
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - progress in sixtieths of an instruction
+    pub fn set_phase(&mut self, phase: u32) {
+        self.phase = phase % self.ips;
+    }
+
+    /// Forget the progress towards the next tick
+    pub fn reset(&mut self) {
+        self.phase = 0;
+    }
+}
+
+/// Machine cycles a COSMAC VIP runs in each 60 Hz frame, its 1.76 MHz
+/// clock divided by the 8 clocks of a machine cycle
+pub const VIP_FRAME_CYCLES: u32 = 3668;
+
+/// Machine cycles of each frame taken by the CDP1861 display, 128
+/// scan lines of 8 bytes fetched by DMA plus the interrupt routine,
+/// leaving the rest of the frame to the interpreter
+pub const VIP_DISPLAY_CYCLES: u32 = 1024 + 46;
+
+/// Machine cycles the VIP interpreter takes to fetch and decode an
+/// instruction before running it
+pub const VIP_FETCH_CYCLES: u32 = 40;
+
+/// Machine cycles of a frame the VIP interpreter runs instructions in
+pub fn vip_frame_budget() -> i64 {
+    (VIP_FRAME_CYCLES - VIP_DISPLAY_CYCLES) as i64
+}
+
+/// How long instructions take to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimingMode {
+    /// A fixed number of instructions each frame, whatever they are,
+    /// given to `ChipSystem::run_frame`
+    #[default]
+    Instructions,
+    /// Each instruction takes the machine cycles it took on the COSMAC
+    /// VIP, see `vip_cycles`, and a frame runs until the cycles left
+    /// by the display are used up. A draw waits for the next display
+    /// interrupt, so it is the last instruction of its frame.
+    Vip
+}
+
+/// Timing modes with their names, as given to `--timing`
+pub const TIMING_MODES: [(TimingMode, &str); 2] = [
+    (TimingMode::Instructions, "instructions"),
+    (TimingMode::Vip, "vip")
+];
+
+impl TimingMode {
+    /// Name of the timing mode as used on the command line
+    pub fn name(&self) -> &'static str {
+        TIMING_MODES.iter()
+            .find(|(mode, _)| mode == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Find a timing mode by name, None if there is no such mode
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the mode, such as `vip`
+    pub fn from_name(name: &str) -> Option<Self> {
+        TIMING_MODES.iter()
+            .find(|(_, mode_name)| mode_name.eq_ignore_ascii_case(name))
+            .map(|(mode, _)| *mode)
+    }
+}
+
+/// Get the machine cycles an instruction takes on the COSMAC VIP,
+/// fetching and decoding included
+///
+/// The counts approximate the routines of the VIP interpreter, and
+/// some depend on the operands: loops in the interpreter run once per
+/// register for `Fx55`/`Fx65` and once per sprite row for `Dxyn`. The
+/// SUPER-CHIP and XO-CHIP instructions the VIP never had are given the
+/// cost of the nearest original one.
+///
+/// # Arguments
+///
+/// * `instruction` - instruction being run
+pub fn vip_cycles(instruction: &Instruction) -> u32 {
+    let execute = match *instruction {
+        Instruction::LoadByte(_, _) => 6,
+        Instruction::AddByte(_, _) | Instruction::ReadDelay(_) | Instruction::SetDelay(_)
+        | Instruction::SetSound(_) | Instruction::WaitKey(_) | Instruction::Pitch(_)
+        | Instruction::Plane(_) | Instruction::Audio => 10,
+        Instruction::SkipEqByte(_, _) | Instruction::SkipNeByte(_, _) | Instruction::LoadI(_) => 12,
+        Instruction::SkipEqReg(_, _) | Instruction::SkipNeReg(_, _)
+        | Instruction::SkipKey(_) | Instruction::SkipNotKey(_) => 16,
+        Instruction::AddI(_) => 19,
+        Instruction::Font(_) | Instruction::BigFont(_) => 20,
+        Instruction::Sys(_) | Instruction::Return | Instruction::Jump(_) | Instruction::Call(_)
+        | Instruction::JumpOffset(_) | Instruction::LoadILong | Instruction::Exit => 23,
+        Instruction::Clear | Instruction::ScrollDown(_) | Instruction::ScrollUp(_)
+        | Instruction::ScrollRight | Instruction::ScrollLeft
+        | Instruction::LowRes | Instruction::HighRes => 24,
+        Instruction::Random(_, _) => 36,
+        Instruction::LoadReg(_, _) | Instruction::Or(_, _) | Instruction::And(_, _)
+        | Instruction::Xor(_, _) | Instruction::AddReg(_, _) | Instruction::Sub(_, _)
+        | Instruction::ShiftRight(_, _) | Instruction::SubN(_, _) | Instruction::ShiftLeft(_, _) => 44,
+        Instruction::Bcd(_) => 204,
+        Instruction::StoreRegs(x) | Instruction::LoadRegs(x)
+        | Instruction::SaveFlags(x) | Instruction::LoadFlags(x) => 14 + 14 * (x as u32 + 1),
+        Instruction::StoreRange(x, y) | Instruction::LoadRange(x, y) => 14 + 14 * (x.abs_diff(y) as u32 + 1),
+        // A 16x16 SCHIP sprite is 16 rows of two bytes
+        Instruction::Draw(_, _, 0) => 26 + 68 * 32,
+        Instruction::Draw(_, _, n) => 26 + 68 * n as u32,
+        Instruction::Invalid(_) => 0
+    };
+    VIP_FETCH_CYCLES + execute
+}
@@ -0,0 +1,211 @@
+// Standard Library Modules //
+use std::io;
+use std::io::prelude::*;
+use std::sync::Mutex;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::disasm;
+
+// Modules From Crates.io //
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Format the machine state before an opcode runs as one trace line
+///
+/// Registers are named and ordered the way Octo's debugger shows them,
+/// `pc`, `i`, `v0` to `vf`, then the `delay` and `buzzer` timers, all
+/// written as Octo assignments so a trace from Octo and one from this
+/// emulator can be compared with a plain diff.
+///
+/// # Arguments
+///
+/// * `sys` - system about to run the opcode
+/// * `opcode` - opcode at the program counter
+pub fn octo_line(sys: &ChipSystem, opcode: u16) -> String {
+    let mut line = format!(
+        "pc := 0x{:04X} op := 0x{:04X} i := 0x{:04X}",
+        sys.registers.get_pc(), opcode, sys.registers.get_i()
+    );
+    for reg in 0..16 {
+        line.push_str(&format!(" v{:x} := 0x{:02X}", reg, sys.registers.get_gp(reg)));
+    }
+    line.push_str(&format!(" delay := {} buzzer := {}", sys.registers.get_d(), sys.registers.get_s()));
+    line
+}
+
+/// Writes an Octo compatible execution trace, one line per opcode,
+/// see `octo_line`
+pub struct OctoTrace<W: Write> {
+    /// Destination of the lines
+    out: W,
+    /// Number of lines written
+    lines: u64
+}
+
+impl<W: Write> OctoTrace<W> {
+    /// Start a trace
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - destination of the lines, usually a buffered file
+    pub fn init(out: W) -> Self {
+        OctoTrace { out, lines: 0 }
+    }
+
+    /// Get the number of lines written
+    pub fn get_lines(&self) -> u64 {
+        self.lines
+    }
+
+    /// Write the line for an opcode about to run
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system about to run the opcode
+    /// * `opcode` - opcode at the program counter
+    pub fn record(&mut self, sys: &ChipSystem, opcode: u16) -> io::Result<()> {
+        writeln!(self.out, "{}", octo_line(sys, opcode))?;
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Flush buffered lines to the destination
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// The registers an opcode can change, taken before it runs so the
+/// trace line can show what it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterSnapshot {
+    /// Program counter
+    pub pc: u16,
+    /// Index register
+    pub i: u16,
+    /// General purpose registers V0-VF
+    pub gp: [u8; 16],
+    /// Delay timer
+    pub delay: u8,
+    /// Sound timer
+    pub sound: u8
+}
+
+impl RegisterSnapshot {
+    /// Take the registers of a system
+    ///
+    /// # Arguments
+    ///
+    /// * `sys` - system to read
+    pub fn take(sys: &ChipSystem) -> Self {
+        let mut gp = [0; 16];
+        for (reg, value) in gp.iter_mut().enumerate() {
+            *value = sys.registers.get_gp(reg);
+        }
+        RegisterSnapshot {
+            pc: sys.registers.get_pc(),
+            i: sys.registers.get_i(),
+            gp,
+            delay: sys.registers.get_d(),
+            sound: sys.registers.get_s()
+        }
+    }
+
+    /// List the registers that differ from another snapshot as
+    /// `V3 00->05`, in the order Octo shows them
+    ///
+    /// # Arguments
+    ///
+    /// * `after` - registers after the opcode ran
+    pub fn changes(&self, after: &RegisterSnapshot) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.i != after.i {
+            changes.push(format!("I {:03x}->{:03x}", self.i, after.i));
+        }
+        for reg in 0..16 {
+            if self.gp[reg] != after.gp[reg] {
+                changes.push(format!("V{:X} {:02x}->{:02x}", reg, self.gp[reg], after.gp[reg]));
+            }
+        }
+        if self.delay != after.delay {
+            changes.push(format!("DT {:02x}->{:02x}", self.delay, after.delay));
+        }
+        if self.sound != after.sound {
+            changes.push(format!("ST {:02x}->{:02x}", self.sound, after.sound));
+        }
+        changes
+    }
+}
+
+/// Format an opcode that ran as one line of the instruction log, the
+/// address, opcode and mnemonic followed by the registers it changed,
+/// such as `204: 6305  LD V3, 0x05  V3 00->05`
+///
+/// # Arguments
+///
+/// * `before` - registers before the opcode ran
+/// * `sys` - system after the opcode ran
+/// * `opcode` - opcode that ran
+pub fn log_line(before: &RegisterSnapshot, sys: &ChipSystem, opcode: u16) -> String {
+    let mut line = format!("{:03x}: {:04x}  {}", before.pc, opcode, disasm::mnemonic(opcode));
+    let changes = before.changes(&RegisterSnapshot::take(sys));
+    if !changes.is_empty() {
+        line.push_str(&format!("  {}", changes.join(" ")));
+    }
+    line
+}
+
+/// A logger for the `log` crate writing one record per line, used to
+/// keep the instruction log written at trace level by
+/// `ChipSystem::ex_opcode`
+///
+/// Trace records are written as they are, records of other levels
+/// start with the level, such as `WARN: `.
+pub struct TraceLogger {
+    /// Destination of the records
+    out: Mutex<Box<dyn Write + Send>>,
+    /// Most detailed level written
+    level: LevelFilter
+}
+
+impl TraceLogger {
+    /// Install a logger as the logger of the `log` crate, which can
+    /// only be done once per process
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - destination of the records, usually a buffered file
+    ///   or stderr
+    /// * `level` - most detailed level written, `LevelFilter::Trace`
+    ///   for the instruction log
+    pub fn install(out: Box<dyn Write + Send>, level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(TraceLogger { out: Mutex::new(out), level }))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for TraceLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // A poisoned lock or a full disk is not worth stopping the game
+        if let Ok(mut out) = self.out.lock() {
+            let _ = match record.level() {
+                Level::Trace => writeln!(out, "{}", record.args()),
+                level => writeln!(out, "{}: {}", level, record.args())
+            };
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut out) = self.out.lock() {
+            let _ = out.flush();
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// Standard Library Modules //
+use std::fmt;
+
+// Local Modules Use //
+use super::memory::RomWarning;
+
+/// Result of normalizing a ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrimReport {
+    /// Size of the ROM before trimming
+    pub original: usize,
+    /// Zero bytes removed from the end
+    pub trimmed: usize,
+    /// Zero bytes added by aligning or padding
+    pub padded: usize,
+    /// Problems with the normalized ROM
+    pub warnings: Vec<RomWarning>
+}
+
+impl fmt::Display for TrimReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.original - self.trimmed + self.padded;
+        write!(
+            f, "{} bytes -> {} bytes ({} trailing zero bytes removed, {} padding bytes added)",
+            self.original, size, self.trimmed, self.padded
+        )
+    }
+}
+
+/// Strip the trailing zero padding from a ROM, then pad it back out to
+/// a multiple of `align` bytes or to at least `pad_to` bytes
+///
+/// Memory after a ROM is cleared when it is loaded, so trailing zeros
+/// never change how a ROM runs. Removing them gives the same file for
+/// every copy of a game however it was padded, so hashes of the files
+/// can be compared. The normalized ROM is warned about if it is empty
+/// or has an odd size.
+///
+/// # Arguments
+///
+/// * `rom` - rom contents
+/// * `align` - pad the size to a multiple of this, 0 or 1 to not align
+/// * `pad_to` - pad to at least this many bytes
+pub fn normalize(rom: &[u8], align: usize, pad_to: Option<usize>) -> (Vec<u8>, TrimReport) {
+    let end = rom.iter().rposition(|byte| *byte != 0).map_or(0, |last| last + 1);
+    let mut out = rom[..end].to_vec();
+    if align > 1 && !out.len().is_multiple_of(align) {
+        out.resize(out.len().next_multiple_of(align), 0);
+    }
+    if let Some(size) = pad_to {
+        if out.len() < size {
+            out.resize(size, 0);
+        }
+    }
+    let mut warnings = Vec::new();
+    if out.is_empty() {
+        warnings.push(RomWarning::Empty);
+    } else if out.len() % 2 == 1 {
+        warnings.push(RomWarning::OddSize(out.len()));
+    }
+    let report = TrimReport {
+        original: rom.len(),
+        trimmed: rom.len() - end,
+        padded: out.len() - end,
+        warnings
+    };
+    (out, report)
+}
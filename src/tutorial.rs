@@ -0,0 +1,240 @@
+// Standard Library Modules //
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+
+// Local Modules Use //
+use super::ChipSystem;
+use super::assembler;
+use super::explain::explain_opcode;
+use super::export::{display_to_text, TextStyle};
+use super::teach::{render_execute, CycleView};
+
+/// Source of the bundled teaching ROM, assembled when the tutorial
+/// starts so it can be read alongside the notes
+pub const TUTORIAL_SOURCE: &str = "; Draw a face, wait half a second and move it to the right
+    CLS
+    LD V0, 20
+    LD V1, 12
+    LD I, face
+    DRW V0, V1, 6
+    LD V2, 30
+    LD DT, V2
+wait:
+    LD V3, DT
+    SE V3, 0
+    JP wait
+    DRW V0, V1, 6
+    ADD V0, 16
+    DRW V0, V1, 6
+done:
+    JP done
+face:
+    DB 0b00111100, 0b01000010, 0b10100101
+    DB 0b10000001, 0b10111101, 0b01111110
+";
+
+/// Notes for the bundled teaching ROM
+pub const TUTORIAL_NOTES: &str = "# Notes for the bundled tutorial ROM, see TUTORIAL_SOURCE
+0x200 Programs start at 0x200, the memory below was used by the original
+      interpreter. CLS clears the screen so nothing is left from before.
+0x202 The V registers hold the program's numbers. V0 will be the column
+      the face is drawn at, counted from the left edge.
+0x204 V1 is the row, counted down from the top. The screen is 64 pixels
+      wide and 32 tall.
+0x206 I is the register that points into memory, here at the 6 bytes of
+      the face sprite stored after the code.
+0x208 DRW draws the 6 rows of the sprite at I at column V0, row V1. Each
+      byte is a row and each 1 bit a lit pixel. Drawing flips pixels, so
+      VF is set when a lit pixel is turned off, which games use to spot
+      collisions.
+0x20a The delay timer counts down 60 times a second. Loading 30 into it
+      gives a wait of half a second, first the value goes into V2...
+0x20c ...then into the timer, it only takes values from a V register.
+0x20e Reading the timer back shows how much of the wait is left.
+0x210 SE skips the next instruction when V3 equals 0, that is when the
+      wait is over.
+0x212 Until then this jump goes back to read the timer again. A program
+      waiting in a loop like this is how games keep a steady speed.
+0x214 Drawing the same sprite in the same place flips its pixels back,
+      which erases it.
+0x216 ADD moves the column 16 pixels to the right.
+0x218 Drawing again shows the face in its new place, erase and redraw is
+      how everything moves on a CHIP-8 screen.
+0x21a A jump to itself ends the program, the original interpreter had no
+      instruction to stop so games wait forever like this.
+";
+
+/// Explanations for instructions of a ROM, keyed by address
+///
+/// Annotation files have one note per address, the address in hex
+/// followed by the text. Lines starting with whitespace continue the
+/// note above them, blank lines and lines starting with `#` are
+/// ignored.
+///
+/// ```text
+/// # Notes for pong.ch8
+/// 0x200 Clear the screen before the game starts.
+/// 0x202 V0 holds the score of the left player,
+///       it starts at zero.
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    /// Note text by address
+    notes: BTreeMap<u16, String>
+}
+
+impl Annotations {
+    /// Parse the text of an annotation file
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - contents of the annotation file
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut notes: BTreeMap<u16, String> = BTreeMap::new();
+        let mut current: Option<u16> = None;
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                match current.and_then(|addr| notes.get_mut(&addr)) {
+                    Some(note) => {
+                        note.push(' ');
+                        note.push_str(line.trim());
+                    },
+                    None => return Err(format!("line {}: continuation without a note above it", index + 1))
+                }
+                continue;
+            }
+            let (addr, note) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let addr = addr.strip_prefix("0x").or_else(|| addr.strip_prefix("0X")).unwrap_or(addr);
+            let addr = match u16::from_str_radix(addr, 16) {
+                Ok(addr) if addr < 0x1000 => addr,
+                _ => return Err(format!("line {}: expected an address such as 0x200, found '{}'", index + 1, line))
+            };
+            if notes.insert(addr, note.trim().to_string()).is_some() {
+                return Err(format!("line {}: second note for 0x{:03x}", index + 1, addr));
+            }
+            current = Some(addr);
+        }
+        Ok(Annotations { notes })
+    }
+
+    /// Read and parse an annotation file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - file to read
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Annotations::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Get the note for an address, None if it has none
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - address of the instruction
+    pub fn get(&self, addr: u16) -> Option<&str> {
+        self.notes.get(&addr).map(|note| note.as_str())
+    }
+
+    /// Get the number of notes
+    pub fn get_count(&self) -> usize {
+        self.notes.len()
+    }
+}
+
+/// Assemble the bundled teaching ROM, returning it with its notes
+pub fn bundled() -> (Vec<u8>, Annotations) {
+    let rom = assembler::assemble(TUTORIAL_SOURCE).expect("the bundled tutorial assembles");
+    let notes = Annotations::parse(TUTORIAL_NOTES).expect("the bundled tutorial notes parse");
+    (rom, notes)
+}
+
+/// Wrap text into lines of at most `width` characters, each starting
+/// with `indent`
+fn wrap(text: &str, width: usize, indent: &str) -> String {
+    let mut out = String::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            out.push_str(&format!("{}{}\n", indent, line));
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        out.push_str(&format!("{}{}\n", indent, line));
+    }
+    out
+}
+
+/// Step through a program one instruction at a time, showing the note
+/// for each annotated instruction and what every instruction changed
+///
+/// Enter runs the next instruction, `c` runs on to the next annotated
+/// one, `d` draws the display and `q` quits. The walkthrough ends when
+/// the program exits.
+///
+/// # Arguments
+///
+/// * `sys` - system with the ROM loaded
+/// * `notes` - notes for the ROM
+/// * `input` - where commands are read from
+/// * `output` - where the walkthrough is written to
+pub fn run_tutorial<R: BufRead, W: Write>(sys: &mut ChipSystem, notes: &Annotations,
+                                          mut input: R, mut output: W) -> io::Result<()> {
+    let mut running = false;
+    loop {
+        let pc = sys.registers.get_pc();
+        let opcode = sys.get_next_opcode();
+        let note = notes.get(pc);
+        if note.is_some() || !running {
+            running = false;
+            writeln!(output, "0x{:03x}  {:04x}  {}", pc, opcode, explain_opcode(opcode, sys))?;
+            if let Some(note) = note {
+                write!(output, "{}", wrap(note, 68, "    "))?;
+            }
+            loop {
+                write!(output, "-- enter: step, c: next note, d: display, q: quit -- ")?;
+                output.flush()?;
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    return Ok(());
+                }
+                match line.trim() {
+                    "" => break,
+                    "c" => {
+                        running = true;
+                        break;
+                    },
+                    "d" => write!(output, "{}", display_to_text(&sys.display, &TextStyle::default()))?,
+                    "q" => return Ok(()),
+                    other => writeln!(output, "unknown command '{}'", other)?
+                }
+            }
+        }
+        match CycleView::run(sys) {
+            Ok(view) if !running => write!(output, "{}", render_execute(&view))?,
+            Ok(_) => {},
+            Err(e) => {
+                writeln!(output, "The program stopped: {}", e)?;
+                return Ok(());
+            }
+        }
+        // Timers count down once per frame, call them a frame per step
+        // so waits like the bundled ROM's finish while stepping
+        sys.registers.decr_d();
+        sys.registers.decr_s();
+        if sys.get_exit().is_some() {
+            writeln!(output, "The program finished")?;
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/// Instruction set the system runs, extensions of later interpreters
+/// are only decoded when picked so plain CHIP-8 programs that happen to
+/// contain their opcodes behave as they did on the original
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    /// The original COSMAC VIP instruction set
+    #[default]
+    Chip8,
+    /// SUPER-CHIP 1.1: 128x64 hi-res mode (00FE/00FF), scrolling
+    /// (00CN/00FB/00FC), 16x16 sprites (DXY0) and the RPL user flags
+    /// (FX75/FX85)
+    Schip,
+    /// Octo's XO-CHIP, SUPER-CHIP with two display planes picked by
+    /// FN01, register ranges (5XY2/5XY3), scrolling up (00DN) and the
+    /// audio pattern, run it with extended memory for `F000 nnnn`
+    XoChip
+}
+
+/// Every variant with its name, as given to `--variant`
+pub const VARIANTS: [(Variant, &str); 3] = [
+    (Variant::Chip8, "chip8"),
+    (Variant::Schip, "schip"),
+    (Variant::XoChip, "xochip")
+];
+
+impl Variant {
+    /// Name of the variant
+    pub fn name(&self) -> &'static str {
+        VARIANTS.iter()
+            .find(|(variant, _)| variant == self)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    /// Find a variant by its name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the variant
+    pub fn from_name(name: &str) -> Option<Variant> {
+        VARIANTS.iter()
+            .find(|(_, variant_name)| *variant_name == name)
+            .map(|(variant, _)| *variant)
+    }
+
+    /// Returns true if the SUPER-CHIP opcodes are decoded
+    pub fn has_schip(&self) -> bool {
+        *self != Variant::Chip8
+    }
+
+    /// Returns true if the XO-CHIP opcodes are decoded
+    pub fn has_xochip(&self) -> bool {
+        *self == Variant::XoChip
+    }
+}
@@ -0,0 +1,202 @@
+// Standard Library Modules //
+use std::fmt;
+
+/// What a watchpoint watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchTarget {
+    /// General purpose register V0-VF
+    Register(u8),
+    /// The I register
+    I,
+    /// A byte of memory
+    Memory(u16)
+}
+
+/// When a watchpoint is hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchCondition {
+    /// Any write that changes the value
+    Changes,
+    /// A write that makes the value equal this, from something else
+    Equals(u16)
+}
+
+/// Pauses the debugger when a register or a byte of memory is written,
+/// see `ChipSystem::add_watchpoint`
+///
+/// Written `v3`, `i` or an address such as `0x300` to stop on any
+/// change, with `=value` added to stop once it becomes that value, such
+/// as `v3=5` or `0x300=0xff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    /// Register or address watched
+    pub target: WatchTarget,
+    /// When to stop
+    pub condition: WatchCondition
+}
+
+impl Watchpoint {
+    /// Parse a watchpoint such as `v3`, `i=0x300` or `0x2f0=1`
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - watchpoint to parse
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let number = |text: &str| -> Result<u16, String> {
+            let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16),
+                None => text.parse::<u16>()
+            };
+            parsed.map_err(|_| format!("invalid number '{}'", text))
+        };
+        let (target, value) = match text.trim().split_once('=') {
+            Some((target, value)) => (target.trim(), Some(number(value.trim())?)),
+            None => (text.trim(), None)
+        };
+        let lower = target.to_ascii_lowercase();
+        let target = match lower.strip_prefix('v') {
+            Some(reg) if reg.len() == 1 => WatchTarget::Register(u8::from_str_radix(reg, 16).map_err(|_| format!("no register {}", target))?),
+            _ if lower == "i" => WatchTarget::I,
+            _ => WatchTarget::Memory(number(target).map_err(|_| format!("expected V0-VF, I or an address, found '{}'", target))?)
+        };
+        let limit = match target {
+            WatchTarget::Register(_) | WatchTarget::Memory(_) => 0xFF,
+            WatchTarget::I => 0xFFFF
+        };
+        let condition = match value {
+            Some(value) if value > limit => return Err(format!("{} does not fit in {}", value, text)),
+            Some(value) => WatchCondition::Equals(value),
+            None => WatchCondition::Changes
+        };
+        Ok(Watchpoint { target, condition })
+    }
+
+    /// Returns true if a write hits the watchpoint
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - value before the write
+    /// * `new` - value written
+    pub fn is_hit(&self, old: u16, new: u16) -> bool {
+        match self.condition {
+            WatchCondition::Changes => old != new,
+            WatchCondition::Equals(value) => new == value && old != value
+        }
+    }
+}
+
+impl fmt::Display for WatchTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchTarget::Register(reg) => write!(f, "V{:X}", reg),
+            WatchTarget::I => write!(f, "I"),
+            WatchTarget::Memory(addr) => write!(f, "0x{:03x}", addr)
+        }
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.condition {
+            WatchCondition::Changes => write!(f, "{}", self.target),
+            WatchCondition::Equals(value) => write!(f, "{}=0x{:02x}", self.target, value)
+        }
+    }
+}
+
+/// A write that hit a watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Watchpoint hit
+    pub watchpoint: Watchpoint,
+    /// Value before the write
+    pub old: u16,
+    /// Value written
+    pub new: u16
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "watchpoint {}: {} {:02x} -> {:02x}", self.watchpoint, self.watchpoint.target, self.old, self.new)
+    }
+}
+
+/// Watchpoints on the registers or the memory holding them, checked on
+/// every write
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    /// Watchpoints set
+    points: Vec<Watchpoint>,
+    /// Hits not yet taken
+    hits: Vec<WatchHit>
+}
+
+impl Watchpoints {
+    /// Initialize with no watchpoints
+    pub fn init() -> Self {
+        Watchpoints::default()
+    }
+
+    /// Returns true if nothing is watched
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Get the watchpoints set
+    pub fn get_points(&self) -> &[Watchpoint] {
+        &self.points
+    }
+
+    /// Set a watchpoint, setting one twice has no effect
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - watchpoint to set
+    pub fn add(&mut self, point: Watchpoint) {
+        if !self.points.contains(&point) {
+            self.points.push(point);
+        }
+    }
+
+    /// Remove a watchpoint, returning false if it was not set
+    ///
+    /// # Arguments
+    ///
+    /// * `point` - watchpoint to remove
+    pub fn remove(&mut self, point: &Watchpoint) -> bool {
+        let before = self.points.len();
+        self.points.retain(|set| set != point);
+        self.points.len() != before
+    }
+
+    /// Remove every watchpoint and pending hit
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.hits.clear();
+    }
+
+    /// Check a write against the watchpoints
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - register or address written
+    /// * `old` - value before the write
+    /// * `new` - value written
+    pub fn check(&mut self, target: WatchTarget, old: u16, new: u16) {
+        for point in self.points.iter().filter(|point| point.target == target) {
+            if point.is_hit(old, new) {
+                self.hits.push(WatchHit { watchpoint: *point, old, new });
+            }
+        }
+    }
+
+    /// Returns true if a watchpoint was hit since hits were last taken
+    pub fn has_hits(&self) -> bool {
+        !self.hits.is_empty()
+    }
+
+    /// Take the hits since the last call, oldest first
+    pub fn take_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.hits)
+    }
+}